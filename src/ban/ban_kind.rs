@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+use crate::error::error_config::ErrorConfig;
+
+///
+/// Kinds of bans that a server applies
+/// to every connection, not only to a channel.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum BanKind {
+    K, // user@host mask, local to the server;
+    G, // user@host mask, propagated across the network;
+    Z, // ip address or block, checked when the connection is accepted.
+}
+
+impl BanKind {
+    ///
+    /// returns true if the ban must be
+    /// shared with the rest of the network
+    ///
+    pub fn is_global(&self) -> bool {
+        *self == BanKind::G
+    }
+
+    ///
+    /// text used when closing the link
+    /// of a banned connection
+    ///
+    pub fn description(&self) -> &str {
+        match self {
+            BanKind::K => "K-lined",
+            BanKind::G => "G-lined",
+            BanKind::Z => "Z-lined",
+        }
+    }
+}
+
+impl FromStr for BanKind {
+    type Err = ErrorConfig;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "K" => Ok(BanKind::K),
+            "G" => Ok(BanKind::G),
+            "Z" => Ok(BanKind::Z),
+            _ => Err(ErrorConfig::InvalidValue(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for BanKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::BanKind;
+
+    #[test]
+    fn display_for_ban_kind() {
+        assert_eq!(format!("{}", BanKind::G), "G");
+    }
+
+    #[test]
+    fn ban_kind_from_lowercase() {
+        assert_eq!(BanKind::from_str("z"), Ok(BanKind::Z));
+    }
+
+    #[test]
+    fn only_g_lines_are_global() {
+        assert!(BanKind::G.is_global());
+        assert!(!BanKind::K.is_global());
+    }
+}
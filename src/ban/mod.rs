@@ -0,0 +1,495 @@
+pub mod ban_kind;
+
+use std::{fs, io::ErrorKind, net::IpAddr, str::FromStr};
+
+use crate::{
+    config::cidr::Cidr,
+    error::{error_config::ErrorConfig, error_server::ErrorServer},
+    utils::mask_matches,
+};
+
+use self::ban_kind::BanKind;
+
+const COLON_CHAR: char = ':';
+const AT_CHAR: char = '@';
+const PERMANENT: u64 = 0;
+
+///
+/// struct that represents a ban applied by the
+/// server to every connection that matches its mask
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ServerBan {
+    pub kind: BanKind,
+    pub mask: String,
+    pub reason: String,
+    pub setter: String,
+    pub set_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+impl ServerBan {
+    ///
+    /// creates a ban that starts at `set_at`. A duration
+    /// of zero seconds makes the ban permanent. Returns None
+    /// if the ban would expire after the end of the time.
+    ///
+    pub fn new(
+        kind: BanKind,
+        mask: &str,
+        reason: &str,
+        setter: &str,
+        set_at: u64,
+        duration: u64,
+    ) -> Option<Self> {
+        Some(ServerBan {
+            kind,
+            mask: normalize_mask(kind, mask),
+            reason: reason.to_string(),
+            setter: setter.to_string(),
+            set_at,
+            expires_at: match duration {
+                PERMANENT => None,
+                d => Some(set_at.checked_add(d)?),
+            },
+        })
+    }
+
+    ///
+    /// returns true if both bans have the same kind, mask, reason
+    /// and expiry, no matter who set them or when
+    ///
+    pub fn same_as(&self, other: &ServerBan) -> bool {
+        self.kind == other.kind
+            && self.mask.eq_ignore_ascii_case(&other.mask)
+            && self.reason == other.reason
+            && self.expires_at == other.expires_at
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expires_at, Some(e) if e <= now)
+    }
+
+    ///
+    /// returns the seconds left until the ban expires,
+    /// zero when the ban is permanent
+    ///
+    pub fn remaining(&self, now: u64) -> u64 {
+        match self.expires_at {
+            Some(e) => e.saturating_sub(now).max(1),
+            None => PERMANENT,
+        }
+    }
+
+    ///
+    /// returns true if a user connected from the address, with
+    /// the given username and hostname, is affected by the ban
+    ///
+    pub fn matches(&self, username: &str, hostname: &str, ip: Option<IpAddr>) -> bool {
+        match self.kind {
+            BanKind::Z => ip.is_some_and(|ip| self.matches_ip(&ip)),
+            BanKind::K | BanKind::G => {
+                mask_matches(&self.mask, &format!("{username}{AT_CHAR}{hostname}"))
+                    || ip.is_some_and(|ip| {
+                        mask_matches(&self.mask, &format!("{username}{AT_CHAR}{ip}"))
+                    })
+            }
+        }
+    }
+
+    ///
+    /// builds the ERROR message sent to a
+    /// connection closed because of the ban
+    ///
+    pub fn closing_message(&self, target: &str) -> String {
+        format!(
+            "ERROR {COLON_CHAR}Closing Link: {target} ({}: {})",
+            self.kind.description(),
+            self.reason
+        )
+    }
+
+    fn matches_ip(&self, ip: &IpAddr) -> bool {
+        match Cidr::from_str(&self.mask) {
+            Ok(cidr) => cidr.contains(ip),
+            Err(_) => mask_matches(&self.mask, &ip.to_string()),
+        }
+    }
+}
+
+///
+/// converts the ban into the line in which it is persisted:
+/// `<kind> <mask> <set_at> <expires_at|0> <setter> :<reason>`
+///
+impl std::fmt::Display for ServerBan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {COLON_CHAR}{}",
+            self.kind,
+            self.mask,
+            self.set_at,
+            self.expires_at.unwrap_or(PERMANENT),
+            self.setter,
+            self.reason
+        )
+    }
+}
+
+impl FromStr for ServerBan {
+    type Err = ErrorConfig;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ErrorConfig::InvalidValue("ban".to_string());
+        // the mask of a Z-line may hold colons, so the fields
+        // are taken before looking for the reason
+        let mut fields = vec![];
+        let mut rest = s;
+        for _ in 0..5 {
+            let field = rest.trim_start();
+            let end = field.find(char::is_whitespace).ok_or_else(invalid)?;
+            fields.push(&field[..end]);
+            rest = &field[end..];
+        }
+        let reason = rest
+            .trim_start()
+            .strip_prefix(COLON_CHAR)
+            .ok_or_else(invalid)?;
+        let number = |s: &str| {
+            s.parse::<u64>()
+                .map_err(|_| ErrorConfig::InvalidValue("ban".to_string()))
+        };
+        let expires_at = number(fields[3])?;
+        Ok(ServerBan {
+            kind: BanKind::from_str(fields[0])?,
+            mask: fields[1].to_string(),
+            reason: reason.to_string(),
+            setter: fields[4].to_string(),
+            set_at: number(fields[2])?,
+            expires_at: (expires_at != PERMANENT).then_some(expires_at),
+        })
+    }
+}
+
+///
+/// Struct that stores the bans of the server. When it is created
+/// with a file, every change is written back to it, so the bans
+/// survive a restart.
+///
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct BanList {
+    bans: Vec<ServerBan>,
+    path: Option<String>,
+}
+
+impl BanList {
+    ///
+    /// creates an empty ban list,
+    /// kept only in memory
+    ///
+    pub fn new() -> Self {
+        BanList::default()
+    }
+
+    ///
+    /// Reads the bans persisted on the file. If the file doesn't
+    /// exist yet, the list starts empty and creates it on the first change.
+    ///
+    pub fn load(path: &str) -> Result<Self, ErrorConfig> {
+        let mut list = BanList {
+            bans: vec![],
+            path: Some(path.to_string()),
+        };
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(list),
+            Err(e) => return Err(e.into()),
+        };
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match ServerBan::from_str(line) {
+                Ok(ban) => list.bans.push(ban),
+                Err(_) => return Err(ErrorConfig::MalformedLine(number + 1)),
+            }
+        }
+        Ok(list)
+    }
+
+    ///
+    /// Adds the ban, replacing the one with the same kind and mask.
+    ///
+    /// # Returns
+    /// false if the same ban was already stored, even by another
+    /// setter, so the caller knows it must not be announced again.
+    pub fn add(&mut self, ban: ServerBan) -> Result<bool, ErrorServer> {
+        if self.bans.iter().any(|b| b.same_as(&ban)) {
+            return Ok(false);
+        }
+        self.bans
+            .retain(|b| !(b.kind == ban.kind && b.mask.eq_ignore_ascii_case(&ban.mask)));
+        self.bans.push(ban);
+        self.save()?;
+        Ok(true)
+    }
+
+    ///
+    /// removes the ban with the kind and mask,
+    /// returns false if there wasn't one
+    ///
+    pub fn remove(&mut self, kind: BanKind, mask: &str) -> Result<bool, ErrorServer> {
+        let mask = normalize_mask(kind, mask);
+        let amount = self.bans.len();
+        self.bans
+            .retain(|b| !(b.kind == kind && b.mask.eq_ignore_ascii_case(&mask)));
+        if amount == self.bans.len() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    ///
+    /// returns the bans of the kind
+    /// that haven't expired
+    ///
+    pub fn list(&self, kind: BanKind, now: u64) -> Vec<ServerBan> {
+        self.bans
+            .iter()
+            .filter(|b| b.kind == kind && !b.is_expired(now))
+            .cloned()
+            .collect()
+    }
+
+    ///
+    /// returns the K-line or G-line that affects the user, if any
+    ///
+    pub fn find_user_ban(
+        &self,
+        username: &str,
+        hostname: &str,
+        ip: Option<IpAddr>,
+        now: u64,
+    ) -> Option<ServerBan> {
+        self.bans
+            .iter()
+            .filter(|b| b.kind != BanKind::Z && !b.is_expired(now))
+            .find(|b| b.matches(username, hostname, ip))
+            .cloned()
+    }
+
+    ///
+    /// returns the Z-line that affects the address, if any
+    ///
+    pub fn find_ip_ban(&self, ip: IpAddr, now: u64) -> Option<ServerBan> {
+        self.bans
+            .iter()
+            .filter(|b| b.kind == BanKind::Z && !b.is_expired(now))
+            .find(|b| b.matches("", "", Some(ip)))
+            .cloned()
+    }
+
+    ///
+    /// forgets the bans that already expired
+    ///
+    pub fn expire(&mut self, now: u64) -> Result<(), ErrorServer> {
+        let amount = self.bans.len();
+        self.bans.retain(|b| !b.is_expired(now));
+        if amount != self.bans.len() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), ErrorServer> {
+        if let Some(path) = &self.path {
+            let content: String = self.bans.iter().map(|b| format!("{b}\n")).collect();
+            fs::write(path, content)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// returns true if the parameter is written as a duration,
+/// digits followed by an optional unit, even one too long
+///
+pub fn is_duration(duration: &str) -> bool {
+    split_duration(duration)
+        .is_some_and(|(amount, _)| !amount.is_empty() && amount.chars().all(|c| c.is_ascii_digit()))
+}
+
+///
+/// Converts a ban duration into seconds. A plain number is read as
+/// minutes, otherwise the suffix `s`, `m`, `h`, `d` or `w` gives the unit.
+/// Zero means a permanent ban. Returns None if it is not a duration or
+/// it doesn't fit in the seconds.
+///
+pub fn parse_duration(duration: &str) -> Option<u64> {
+    let (amount, seconds) = split_duration(duration)?;
+    amount.parse::<u64>().ok()?.checked_mul(seconds)
+}
+
+// Splits the duration in its amount and the seconds of its unit.
+fn split_duration(duration: &str) -> Option<(&str, u64)> {
+    let (amount, unit) = match duration.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&duration[..i], c),
+        Some(_) => (duration, 'm'),
+        None => return None,
+    };
+    let seconds = match unit.to_ascii_lowercase() {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Some((amount, seconds))
+}
+
+// A user mask without user part bans every user of the host.
+fn normalize_mask(kind: BanKind, mask: &str) -> String {
+    if kind != BanKind::Z && !mask.contains(AT_CHAR) {
+        return format!("*{AT_CHAR}{mask}");
+    }
+    mask.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, net::IpAddr, str::FromStr};
+
+    use super::{ban_kind::BanKind, is_duration, parse_duration, BanList, ServerBan};
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(IpAddr::from_str(s).unwrap())
+    }
+
+    #[test]
+    fn k_line_matches_user_and_host() {
+        let ban = ServerBan::new(BanKind::K, "bad*@*.example.com", "spam", "oper", 0, 0).unwrap();
+        assert!(ban.matches("badguy", "host.example.com", None));
+        assert!(!ban.matches("goodguy", "host.example.com", None));
+    }
+
+    #[test]
+    fn mask_without_user_bans_the_whole_host() {
+        let ban = ServerBan::new(BanKind::G, "10.0.0.1", "spam", "oper", 0, 0).unwrap();
+        assert_eq!(ban.mask, "*@10.0.0.1");
+        assert!(ban.matches("anyone", "unknown", ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn z_line_accepts_cidr_and_wildcards() {
+        let cidr = ServerBan::new(BanKind::Z, "10.0.0.0/8", "spam", "oper", 0, 0).unwrap();
+        let wildcard = ServerBan::new(BanKind::Z, "192.168.*", "spam", "oper", 0, 0).unwrap();
+        assert!(cidr.matches("", "", ip("10.1.2.3")));
+        assert!(wildcard.matches("", "", ip("192.168.0.7")));
+        assert!(!wildcard.matches("", "", ip("10.1.2.3")));
+    }
+
+    #[test]
+    fn ban_expires_after_its_duration() {
+        let ban = ServerBan::new(BanKind::K, "*@host", "spam", "oper", 100, 60).unwrap();
+        assert!(!ban.is_expired(159));
+        assert!(ban.is_expired(160));
+        assert_eq!(ban.remaining(130), 30);
+    }
+
+    #[test]
+    fn closing_message_includes_reason() {
+        let ban = ServerBan::new(BanKind::K, "*@host", "spam", "oper", 0, 0).unwrap();
+        assert_eq!(
+            ban.closing_message("nick"),
+            "ERROR :Closing Link: nick (K-lined: spam)"
+        );
+    }
+
+    #[test]
+    fn ban_line_round_trip() {
+        let ban = ServerBan::new(BanKind::G, "*@host", "go away now", "oper", 100, 60).unwrap();
+        let line = ban.to_string();
+        assert_eq!(line, "G *@host 100 160 oper :go away now");
+        assert_eq!(ServerBan::from_str(&line), Ok(ban));
+    }
+
+    #[test]
+    fn list_ignores_expired_bans() {
+        let mut list = BanList::new();
+        list.add(ServerBan::new(BanKind::K, "*@a", "r", "o", 0, 10).unwrap())
+            .unwrap();
+        list.add(ServerBan::new(BanKind::K, "*@b", "r", "o", 0, 0).unwrap())
+            .unwrap();
+        assert_eq!(list.list(BanKind::K, 5).len(), 2);
+        assert_eq!(list.list(BanKind::K, 20).len(), 1);
+        assert!(list.find_user_ban("u", "a", None, 20).is_none());
+    }
+
+    #[test]
+    fn adding_the_same_ban_twice_is_not_new() {
+        let mut list = BanList::new();
+        let ban = ServerBan::new(BanKind::G, "*@a", "r", "o", 0, 0).unwrap();
+        assert!(list.add(ban.clone()).unwrap());
+        assert!(!list.add(ban).unwrap());
+    }
+
+    #[test]
+    fn same_ban_from_another_setter_keeps_the_first() {
+        let mut list = BanList::new();
+        let ban = ServerBan::new(BanKind::G, "*@a", "r", "o", 0, 0).unwrap();
+        assert!(list.add(ban.clone()).unwrap());
+        let again = ServerBan::new(BanKind::G, "*@A", "r", "other", 5, 0).unwrap();
+        assert!(!list.add(again).unwrap());
+        assert_eq!(list.list(BanKind::G, 0), vec![ban]);
+    }
+
+    #[test]
+    fn removes_ban_by_mask() {
+        let mut list = BanList::new();
+        list.add(ServerBan::new(BanKind::K, "*@a", "r", "o", 0, 0).unwrap())
+            .unwrap();
+        assert!(list.remove(BanKind::K, "a").unwrap());
+        assert!(!list.remove(BanKind::K, "a").unwrap());
+    }
+
+    #[test]
+    fn bans_are_persisted_on_the_file() {
+        let path = env::temp_dir().join(format!("irc_bans_{}.conf", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut list = BanList::load(path).unwrap();
+        list.add(ServerBan::new(BanKind::Z, "10.0.0.1", "r", "o", 0, 0).unwrap())
+            .unwrap();
+
+        let loaded = BanList::load(path).unwrap();
+        assert!(loaded.find_ip_ban(ip("10.0.0.1").unwrap(), 0).is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("10"), Some(600));
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("2h"), Some(7200));
+        assert_eq!(parse_duration("0"), Some(0));
+        assert_eq!(parse_duration("user@host"), None);
+        assert!(is_duration("99999999999999999w"));
+        assert_eq!(parse_duration("99999999999999999w"), None);
+        assert!(!is_duration("10.0.0.1"));
+    }
+
+    #[test]
+    fn ban_that_never_expires_is_rejected() {
+        let ban = ServerBan::new(BanKind::K, "*@host", "spam", "oper", 100, u64::MAX);
+        assert!(ban.is_none());
+    }
+
+    #[test]
+    fn ipv6_z_line_round_trip() {
+        let ban = ServerBan::new(BanKind::Z, "2001:db8::/32", "spam: again", "oper", 5, 0).unwrap();
+        let line = ban.to_string();
+        assert_eq!(line, "Z 2001:db8::/32 5 0 oper :spam: again");
+        assert_eq!(ServerBan::from_str(&line), Ok(ban));
+    }
+}
@@ -462,21 +462,20 @@ fn handle_msgs_from_tcp_chat(
                                 "Reply Join Add model -chanel:{},-users:{}",
                                 channel, users_in_channel
                             );
-                            let users_iter = users_in_channel.split('~');
-                            for user in users_iter {
-                                if user.contains("Cannot send to channel") {
-                                    model.remove_msg_from_channel(&channel.to_string());
-                                    // show_error_box(user);
-                                    break;
+                            if users_in_channel.contains("Cannot send to channel") {
+                                model.remove_msg_from_channel(&channel.to_string());
+                                // show_error_box(user);
+                            } else if users_in_channel.contains("Cannot join channel") {
+                                model.delete_channel(&channel.to_string());
+                            } else if !users_in_channel.contains("not channel operator") {
+                                // the members come after the prefix of their rank
+                                for user in users_in_channel.split_whitespace() {
+                                    let user = user.trim_start_matches(['~', '&', '@', '%', '+']);
+                                    model.add_user_to_channel(
+                                        &channel.to_string(),
+                                        user.to_string(),
+                                    );
                                 }
-                                if user.contains("Cannot join channel") {
-                                    model.delete_channel(&channel.to_string());
-                                    break;
-                                }
-                                if user.contains("not channel operator") {
-                                    break;
-                                }
-                                model.add_user_to_channel(&channel.to_string(), user.to_string());
                             }
                             model.remove_users_from_channel(
                                 &channel.to_string(),
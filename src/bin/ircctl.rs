@@ -0,0 +1,30 @@
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::process;
+
+// Sends one command to the control socket of a server and prints the
+// records of the response. Exits with 1 when the server answers ERR.
+fn main() -> std::io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: {} <control socket> <command> [arguments]", args[0]);
+        process::exit(2);
+    }
+    let mut stream = UnixStream::connect(&args[1])?;
+    stream.write_all(format!("{}\n", args[2..].join(" ")).as_bytes())?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line == "OK" {
+            return Ok(());
+        }
+        if let Some(reason) = line.strip_prefix("ERR ") {
+            eprintln!("{reason}");
+            process::exit(1);
+        }
+        println!("{line}");
+    }
+    eprintln!("connection closed before the response ended");
+    process::exit(1)
+}
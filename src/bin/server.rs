@@ -1,13 +1,26 @@
+use irc_project::config::ServerConfig;
 use irc_project::error::error_server::ErrorServer;
-use irc_project::server::init_server;
+use irc_project::server::init_server_with_config;
+use irc_project::server::shutdown::ShutdownKind;
 use std::env;
+use std::process::Command;
 
 fn main() -> Result<(), ErrorServer> {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
+    if args.len() != 3 && args.len() != 4 {
         return Err(ErrorServer::TcpFail);
     }
+    // optional configuration file
+    let config = match args.get(3) {
+        Some(path) => ServerConfig::load(path)?,
+        None => ServerConfig::default(),
+    };
     //println!("main() server initiation");
-    init_server(args[1].to_string(), args[2].to_string())?;
+    let request = init_server_with_config(args[1].to_string(), args[2].to_string(), config)?;
+    println!("Server stopped: {}", request.reason);
+    if request.kind == ShutdownKind::Restart {
+        // the new process binds the port once this one released it
+        Command::new(env::current_exe()?).args(&args[1..]).spawn()?;
+    }
     Ok(())
 }
@@ -10,12 +10,33 @@ pub enum ChannelFlag {
     L, // set the user limit to channel;
     B, // set a ban mask to keep users out;
     V, // give/take the ability to speak on a moderated channel;
-    K, // set a channel key (password).
+    K, // set a channel key (password);
+    E, // set a ban exception mask, that overrides the bans;
+    // set a mask allowed to join an invite-only channel, shown as `I`.
+    InviteException,
+    // refuse the KNOCKs of the users, shown as `K`.
+    NoKnock,
+    // limit the lines of each member, shown as `f`.
+    Flood,
+    // limit the joins to the channel, shown as `j`.
+    JoinThrottle,
+    // strip the colors of the messages, shown as `c`.
+    StripColors,
+    // refuse the CTCPs other than ACTION, shown as `C`.
+    NoCtcp,
 }
 
 impl std::fmt::Display for ChannelFlag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", format!("{:?}", self).to_lowercase())
+        match self {
+            ChannelFlag::InviteException => write!(f, "I"),
+            ChannelFlag::NoKnock => write!(f, "K"),
+            ChannelFlag::Flood => write!(f, "f"),
+            ChannelFlag::JoinThrottle => write!(f, "j"),
+            ChannelFlag::StripColors => write!(f, "c"),
+            ChannelFlag::NoCtcp => write!(f, "C"),
+            _ => write!(f, "{}", format!("{:?}", self).to_lowercase()),
+        }
     }
 }
 
@@ -32,6 +53,13 @@ mod test {
         assert_eq!(format!("{flag}"), "b");
     }
 
+    #[test]
+    fn display_for_invite_exception_is_uppercase() {
+        assert_eq!(ChannelFlag::InviteException.to_string(), "I");
+        assert_eq!(ChannelFlag::I.to_string(), "i");
+        assert_eq!(ChannelFlag::NoKnock.to_string(), "K");
+    }
+
     #[test]
     fn hash_for_flags() {
         let flag = ChannelFlag::B;
@@ -0,0 +1,220 @@
+use std::collections::VecDeque;
+
+// the codes that start a color, followed by its digits
+const COLOR: char = '\x03';
+const HEX_COLOR: char = '\x04';
+// bold, italics, underline and the rest of the formatting codes
+const FORMATTING: [char; 7] = ['\x02', '\x0f', '\x11', '\x16', '\x1d', '\x1e', '\x1f'];
+const CTCP_DELIMITER: char = '\x01';
+const CTCP_ACTION: &str = "\x01ACTION";
+
+///
+/// What the channel does with a member that sends
+/// more lines than its flood limit allows.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FloodAction {
+    /// the member is kicked out of the channel
+    Kick,
+    /// the member can't speak for the seconds of the limit
+    Mute,
+    /// the channel is moderated for the seconds of the limit
+    Moderate,
+}
+
+impl FloodAction {
+    fn parse(action: &str) -> Option<Self> {
+        match action.to_lowercase().as_str() {
+            "kick" => Some(FloodAction::Kick),
+            "mute" => Some(FloodAction::Mute),
+            "moderate" => Some(FloodAction::Moderate),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FloodAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+///
+/// An amount of events allowed in some seconds,
+/// given as `<amount>:<seconds>`.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RateLimit {
+    pub amount: usize,
+    pub seconds: u64,
+}
+
+impl RateLimit {
+    pub fn parse(rate: &str) -> Option<Self> {
+        let (amount, seconds) = rate.split_once(':')?;
+        let limit = RateLimit {
+            amount: amount.parse().ok()?,
+            seconds: seconds.parse().ok()?,
+        };
+        (limit.amount > 0 && limit.seconds > 0).then_some(limit)
+    }
+
+    ///
+    /// Records the event in the times of the last ones, forgetting
+    /// those older than the seconds of the limit. Returns false,
+    /// without recording it, if the event goes over the limit.
+    ///
+    pub fn record(&self, times: &mut VecDeque<u64>, now: u64) -> bool {
+        while times
+            .front()
+            .is_some_and(|time| time.saturating_add(self.seconds) <= now)
+        {
+            times.pop_front();
+        }
+        if times.len() >= self.amount {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+impl std::fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.amount, self.seconds)
+    }
+}
+
+///
+/// The lines each member can send to a channel, with the action
+/// taken against the flood, like `5:10:mute`. Without action
+/// the member is kicked.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FloodLimit {
+    pub rate: RateLimit,
+    pub action: FloodAction,
+}
+
+impl FloodLimit {
+    pub fn parse(limit: &str) -> Option<Self> {
+        let (rate, action) = match limit.rsplit_once(':') {
+            Some((rate, action)) if rate.contains(':') => (rate, FloodAction::parse(action)?),
+            _ => (limit, FloodAction::Kick),
+        };
+        Some(FloodLimit {
+            rate: RateLimit::parse(rate)?,
+            action,
+        })
+    }
+}
+
+impl std::fmt::Display for FloodLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.rate, self.action)
+    }
+}
+
+///
+/// returns the text without its colors
+/// nor its formatting codes
+///
+pub fn strip_colors(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            COLOR | HEX_COLOR => {
+                let (digits, is_digit): (usize, fn(&char) -> bool) = match c {
+                    COLOR => (2, char::is_ascii_digit),
+                    _ => (6, char::is_ascii_hexdigit),
+                };
+                if skip_while(&mut chars, digits, is_digit) > 0
+                    && chars.peek() == Some(&',')
+                    && chars.clone().nth(1).is_some_and(|c| is_digit(&c))
+                {
+                    chars.next();
+                    skip_while(&mut chars, digits, is_digit);
+                }
+            }
+            c if FORMATTING.contains(&c) => {}
+            c => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+// Skips up to the given amount of chars that hold,
+// returning how many were skipped.
+fn skip_while(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    most: usize,
+    holds: fn(&char) -> bool,
+) -> usize {
+    let mut skipped = 0;
+    while skipped < most && chars.next_if(holds).is_some() {
+        skipped += 1;
+    }
+    skipped
+}
+
+///
+/// returns true if the text is a CTCP,
+/// other than an ACTION (`/me`)
+///
+pub fn is_ctcp(text: &str) -> bool {
+    text.starts_with(CTCP_DELIMITER) && !text.starts_with(CTCP_ACTION)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use super::{is_ctcp, strip_colors, FloodAction, FloodLimit, RateLimit};
+
+    #[test]
+    fn limits_are_parsed_and_shown_back() {
+        let limit = FloodLimit::parse("5:10:mute").unwrap();
+        assert_eq!(limit.rate, RateLimit::parse("5:10").unwrap());
+        assert_eq!(limit.action, FloodAction::Mute);
+        assert_eq!(limit.to_string(), "5:10:mute");
+        assert_eq!(FloodLimit::parse("3:4").unwrap().to_string(), "3:4:kick");
+        assert_eq!(FloodLimit::parse("3:4:ban"), None);
+        assert_eq!(RateLimit::parse("0:10"), None);
+        assert_eq!(RateLimit::parse("ten"), None);
+    }
+
+    #[test]
+    fn rate_forgets_the_old_events() {
+        let limit = RateLimit::parse("2:10").unwrap();
+        let mut times = VecDeque::new();
+        assert!(limit.record(&mut times, 0));
+        assert!(limit.record(&mut times, 5));
+        assert!(!limit.record(&mut times, 9));
+        assert!(limit.record(&mut times, 10));
+        assert_eq!(times, VecDeque::from([5, 10]));
+    }
+
+    #[test]
+    fn huge_rate_never_forgets_the_events() {
+        let limit = RateLimit::parse("1:18446744073709551615").unwrap();
+        let mut times = VecDeque::new();
+        assert!(limit.record(&mut times, 10));
+        assert!(!limit.record(&mut times, 20));
+    }
+
+    #[test]
+    fn colors_and_formatting_are_stripped() {
+        assert_eq!(strip_colors("\x034,12red\x03 \x02bold\x02"), "red bold");
+        assert_eq!(strip_colors("\x0312,text"), ",text");
+        assert_eq!(strip_colors("\x04FF0000hex\x0f"), "hex");
+        assert_eq!(strip_colors("plain, text"), "plain, text");
+    }
+
+    #[test]
+    fn actions_are_not_ctcps() {
+        assert!(is_ctcp("\x01VERSION\x01"));
+        assert!(!is_ctcp("\x01ACTION waves\x01"));
+        assert!(!is_ctcp("hello"));
+    }
+}
@@ -0,0 +1,509 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    str::FromStr,
+};
+
+use crate::{
+    error::{error_config::ErrorConfig, error_server::ErrorServer},
+    utils::{format_iso8601, parse_iso8601, MESSAGE_LENGHT},
+};
+
+/// amount of messages each channel remembers, unless configured
+pub const DEFAULT_HISTORY_LENGTH: usize = 50;
+/// most messages answered by one CHATHISTORY
+pub const MAX_HISTORY_QUERY: usize = 100;
+
+const COLON_CHAR: char = ':';
+
+///
+/// A message sent to a channel, with the id
+/// and the time given by the server.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HistoryEntry {
+    pub msgid: String,
+    pub time: u64,
+    pub channel: String,
+    pub from: String,
+    pub text: String,
+}
+
+impl HistoryEntry {
+    ///
+    /// builds the PRIVMSG that replays the message, tagged with
+    /// its time and id when the client asked for them. The text
+    /// is cut so the tagged line still fits in a message
+    ///
+    pub fn to_message(&self, tags: &[String]) -> String {
+        let tags: Vec<String> = tags
+            .iter()
+            .map(|tag| match tag.as_str() {
+                "time" => format!("time={}", format_iso8601(self.time)),
+                "msgid" => format!("msgid={}", self.msgid),
+                batch => batch.to_string(),
+            })
+            .collect();
+        let prefix = match tags.is_empty() {
+            true => String::new(),
+            false => format!("@{} ", tags.join(";")),
+        };
+        let message = format!(
+            "{prefix}:{} PRIVMSG {} {COLON_CHAR}",
+            self.from, self.channel
+        );
+        let room = MESSAGE_LENGHT.saturating_sub(message.len());
+        message + truncate(&self.text, room)
+    }
+}
+
+// Returns the longest start of the text that takes
+// at most the given bytes, without splitting a char.
+fn truncate(text: &str, bytes: usize) -> &str {
+    let mut end = bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+///
+/// converts the entry into the line in which it is persisted:
+/// `<channel> <msgid> <time> <from> :<text>`
+///
+impl std::fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {COLON_CHAR}{}",
+            self.channel, self.msgid, self.time, self.from, self.text
+        )
+    }
+}
+
+impl FromStr for HistoryEntry {
+    type Err = ErrorConfig;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ErrorConfig::InvalidValue("history".to_string());
+        let (fields, text) = s.split_once(" :").ok_or_else(invalid)?;
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        match fields[..] {
+            [channel, msgid, time, from] => Ok(HistoryEntry {
+                msgid: msgid.to_string(),
+                time: time.parse().map_err(|_| invalid())?,
+                channel: channel.to_string(),
+                from: from.to_string(),
+                text: text.to_string(),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+///
+/// Message of the history a CHATHISTORY query is
+/// relative to: `*`, `msgid=<id>` or `timestamp=<date>`
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum HistoryReference {
+    Any,
+    MsgId(String),
+    Timestamp(u64),
+}
+
+impl FromStr for HistoryReference {
+    type Err = ErrorServer;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "*" {
+            return Ok(HistoryReference::Any);
+        }
+        match s.split_once('=') {
+            Some(("msgid", msgid)) => Ok(HistoryReference::MsgId(msgid.to_string())),
+            Some(("timestamp", date)) => match parse_iso8601(date) {
+                Some(time) => Ok(HistoryReference::Timestamp(time)),
+                None => Err(ErrorServer::BadQuery),
+            },
+            _ => Err(ErrorServer::BadQuery),
+        }
+    }
+}
+
+///
+/// Struct that keeps the last messages sent to each channel. When
+/// it is created with a file, every message is appended to it, so the
+/// history survives a restart. The file is rewritten with only the kept
+/// messages once it holds twice as many lines.
+///
+#[derive(Debug, Default)]
+pub struct ChannelHistory {
+    channels: HashMap<String, VecDeque<HistoryEntry>>,
+    length: usize,
+    path: Option<String>,
+    sequence: u64,
+    // lines written on the file since it was last rewritten
+    lines: usize,
+}
+
+impl ChannelHistory {
+    ///
+    /// creates an empty history that keeps the
+    /// given amount of messages of each channel
+    ///
+    pub fn new(length: usize) -> Self {
+        ChannelHistory {
+            length,
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// Reads the history persisted on the file, keeping the last messages
+    /// of each channel, and rewrites it with them. If the file doesn't
+    /// exist yet, the history starts empty and creates it on the first message.
+    ///
+    pub fn load(path: &str, length: usize) -> Result<Self, ErrorConfig> {
+        let mut history = ChannelHistory::new(length);
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                history.path = Some(path.to_string());
+                return Ok(history);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match HistoryEntry::from_str(line) {
+                Ok(entry) => history.push(entry),
+                Err(_) => return Err(ErrorConfig::MalformedLine(number + 1)),
+            }
+        }
+        // the ids go on from the ones already given
+        history.sequence = content.lines().count() as u64;
+        history.path = Some(path.to_string());
+        history.save()?;
+        Ok(history)
+    }
+
+    ///
+    /// keeps the message sent to the channel,
+    /// giving it an id, and returns it
+    ///
+    pub fn record(
+        &mut self,
+        channel: &str,
+        from: &str,
+        text: &str,
+        time: u64,
+    ) -> Result<Option<HistoryEntry>, ErrorServer> {
+        if self.length == 0 {
+            return Ok(None);
+        }
+        self.sequence += 1;
+        let entry = HistoryEntry {
+            msgid: format!("{time:x}{:04x}", self.sequence % 0x10000),
+            time,
+            channel: channel.to_string(),
+            from: from.to_string(),
+            text: text.to_string(),
+        };
+        self.push(entry.clone());
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{entry}")?;
+            self.lines += 1;
+        }
+        if self.lines > 2 * self.channels.values().map(VecDeque::len).sum::<usize>() {
+            self.save()?;
+        }
+        Ok(Some(entry))
+    }
+
+    ///
+    /// returns the last messages of the channel, only
+    /// those after the reference if it isn't `*`
+    ///
+    pub fn latest(
+        &self,
+        channel: &str,
+        reference: &HistoryReference,
+        limit: usize,
+    ) -> Vec<HistoryEntry> {
+        let entries = self.entries(channel);
+        let start = match reference {
+            HistoryReference::Any => 0,
+            reference => Self::after_index(&entries, reference).unwrap_or(entries.len()),
+        };
+        Self::last(&entries[start..], limit)
+    }
+
+    ///
+    /// returns the messages of the channel right before the reference
+    ///
+    pub fn before(
+        &self,
+        channel: &str,
+        reference: &HistoryReference,
+        limit: usize,
+    ) -> Vec<HistoryEntry> {
+        let entries = self.entries(channel);
+        let end = Self::before_index(&entries, reference).unwrap_or(0);
+        Self::last(&entries[..end], limit)
+    }
+
+    ///
+    /// returns the messages of the channel right after the reference
+    ///
+    pub fn after(
+        &self,
+        channel: &str,
+        reference: &HistoryReference,
+        limit: usize,
+    ) -> Vec<HistoryEntry> {
+        let entries = self.entries(channel);
+        let start = Self::after_index(&entries, reference).unwrap_or(entries.len());
+        entries[start..].iter().take(limit).cloned().collect()
+    }
+
+    ///
+    /// returns the messages of the channel between both references,
+    /// the ones closest to the first
+    ///
+    pub fn between(
+        &self,
+        channel: &str,
+        first: &HistoryReference,
+        second: &HistoryReference,
+        limit: usize,
+    ) -> Vec<HistoryEntry> {
+        let entries = self.entries(channel);
+        let bounds = (
+            Self::after_index(&entries, first),
+            Self::before_index(&entries, second),
+            Self::after_index(&entries, second),
+            Self::before_index(&entries, first),
+        );
+        match bounds {
+            (Some(start), Some(end), _, _) if start < end => {
+                entries[start..end].iter().take(limit).cloned().collect()
+            }
+            (_, _, Some(start), Some(end)) if start < end => {
+                Self::last(&entries[start..end], limit)
+            }
+            _ => vec![],
+        }
+    }
+
+    // Rewrites the file with only the kept messages.
+    fn save(&mut self) -> std::io::Result<()> {
+        if let Some(path) = &self.path {
+            let entries: Vec<&HistoryEntry> = self.channels.values().flatten().collect();
+            let content: String = entries.iter().map(|entry| format!("{entry}\n")).collect();
+            fs::write(path, content)?;
+            self.lines = entries.len();
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, entry: HistoryEntry) {
+        let entries = self.channels.entry(entry.channel.clone()).or_default();
+        entries.push_back(entry);
+        while entries.len() > self.length {
+            entries.pop_front();
+        }
+    }
+
+    fn entries(&self, channel: &str) -> Vec<HistoryEntry> {
+        match self.channels.get(channel) {
+            Some(entries) => entries.iter().cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    // The last messages of the slice, oldest first.
+    fn last(entries: &[HistoryEntry], limit: usize) -> Vec<HistoryEntry> {
+        entries[entries.len().saturating_sub(limit)..].to_vec()
+    }
+
+    // Position where the messages after the reference start.
+    fn after_index(entries: &[HistoryEntry], reference: &HistoryReference) -> Option<usize> {
+        match reference {
+            HistoryReference::Any => Some(0),
+            HistoryReference::MsgId(msgid) => entries
+                .iter()
+                .position(|entry| &entry.msgid == msgid)
+                .map(|i| i + 1),
+            HistoryReference::Timestamp(time) => {
+                Some(entries.iter().filter(|entry| entry.time <= *time).count())
+            }
+        }
+    }
+
+    // Position where the messages before the reference end.
+    fn before_index(entries: &[HistoryEntry], reference: &HistoryReference) -> Option<usize> {
+        match reference {
+            HistoryReference::Any => Some(entries.len()),
+            HistoryReference::MsgId(msgid) => {
+                entries.iter().position(|entry| &entry.msgid == msgid)
+            }
+            HistoryReference::Timestamp(time) => {
+                Some(entries.iter().filter(|entry| entry.time < *time).count())
+            }
+        }
+    }
+}
+
+///
+/// returns the ISUPPORT tokens that tell the users
+/// how to ask for the history of the channels
+///
+pub fn isupport_tokens() -> Vec<String> {
+    vec![
+        format!("CHATHISTORY={MAX_HISTORY_QUERY}"),
+        "MSGREFTYPES=msgid,timestamp".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, str::FromStr};
+
+    use super::{ChannelHistory, HistoryEntry, HistoryReference};
+
+    fn history() -> ChannelHistory {
+        let mut history = ChannelHistory::new(5);
+        for time in 1..=7 {
+            let text = format!("mensaje {time}");
+            history
+                .record("#canal", "ana!ana@host", &text, time * 10)
+                .unwrap();
+        }
+        history
+    }
+
+    fn texts(entries: Vec<HistoryEntry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.text).collect()
+    }
+
+    #[test]
+    fn history_keeps_the_last_messages() {
+        let history = history();
+        assert_eq!(
+            texts(history.latest("#canal", &HistoryReference::Any, 2)),
+            vec!["mensaje 6", "mensaje 7"]
+        );
+        assert_eq!(
+            history.latest("#canal", &HistoryReference::Any, 10).len(),
+            5
+        );
+        assert!(history
+            .latest("#otro", &HistoryReference::Any, 10)
+            .is_empty());
+    }
+
+    #[test]
+    fn messages_are_found_around_a_reference() {
+        let history = history();
+        let fourth = history.latest("#canal", &HistoryReference::Any, 4)[0].clone();
+        let msgid = HistoryReference::MsgId(fourth.msgid);
+        assert_eq!(
+            texts(history.before("#canal", &msgid, 10)),
+            vec!["mensaje 3"]
+        );
+        assert_eq!(
+            texts(history.after("#canal", &msgid, 2)),
+            vec!["mensaje 5", "mensaje 6"]
+        );
+        let time = HistoryReference::Timestamp(60);
+        assert_eq!(
+            texts(history.between("#canal", &msgid, &time, 10)),
+            vec!["mensaje 5"]
+        );
+        assert_eq!(
+            texts(history.between("#canal", &HistoryReference::Timestamp(70), &msgid, 1)),
+            vec!["mensaje 6"]
+        );
+        assert_eq!(
+            texts(history.latest("#canal", &time, 10)),
+            vec!["mensaje 7"]
+        );
+    }
+
+    #[test]
+    fn reference_is_parsed() {
+        assert_eq!(
+            HistoryReference::from_str("timestamp=1970-01-01T00:01:00.000Z"),
+            Ok(HistoryReference::Timestamp(60))
+        );
+        assert_eq!(
+            HistoryReference::from_str("msgid=abc"),
+            Ok(HistoryReference::MsgId("abc".to_string()))
+        );
+        assert!(HistoryReference::from_str("yesterday").is_err());
+    }
+
+    #[test]
+    fn replayed_message_carries_the_tags() {
+        let mut history = ChannelHistory::new(5);
+        let entry = history
+            .record("#canal", "ana!ana@host", "hola mundo", 60)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            entry.to_message(&[]),
+            ":ana!ana@host PRIVMSG #canal :hola mundo"
+        );
+        let tags = ["time".to_string(), "msgid".to_string()];
+        assert_eq!(
+            entry.to_message(&tags),
+            format!(
+                "@time=1970-01-01T00:01:00.000Z;msgid={} :ana!ana@host PRIVMSG #canal :hola mundo",
+                entry.msgid
+            )
+        );
+    }
+
+    #[test]
+    fn persisted_history_survives_a_restart() {
+        let path = env::temp_dir().join(format!("history_test_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut history = ChannelHistory::load(path, 2).unwrap();
+        for text in ["uno", "dos", "tres"] {
+            history.record("#canal", "ana!ana@host", text, 10).unwrap();
+        }
+        let history = ChannelHistory::load(path, 2).unwrap();
+        assert_eq!(
+            texts(history.latest("#canal", &HistoryReference::Any, 10)),
+            vec!["dos", "tres"]
+        );
+        assert_eq!(fs::read_to_string(path).unwrap().lines().count(), 2);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn persisted_history_stays_bounded() {
+        let path = env::temp_dir().join(format!("history_bound_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut history = ChannelHistory::load(path, 2).unwrap();
+        for time in 0..100 {
+            for channel in ["#uno", "#dos"] {
+                history
+                    .record(channel, "ana!ana@host", "hola", time)
+                    .unwrap();
+            }
+            assert!(fs::read_to_string(path).unwrap().lines().count() <= 8);
+        }
+        let history = ChannelHistory::load(path, 2).unwrap();
+        assert_eq!(history.latest("#dos", &HistoryReference::Any, 10).len(), 2);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+///
+/// Rank of a member of a channel, from the voiced
+/// users up to the owner of the channel.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum Rank {
+    Voice,
+    Halfop,
+    Op,
+    Admin,
+    Owner,
+}
+
+// ranks from the highest to the lowest, as advertised in PREFIX
+const RANKS: [Rank; 5] = [
+    Rank::Owner,
+    Rank::Admin,
+    Rank::Op,
+    Rank::Halfop,
+    Rank::Voice,
+];
+
+impl Rank {
+    ///
+    /// returns the prefix shown before the nickname
+    /// of the member in NAMES, WHO and WHOIS
+    ///
+    pub fn prefix(self) -> char {
+        match self {
+            Rank::Owner => '~',
+            Rank::Admin => '&',
+            Rank::Op => '@',
+            Rank::Halfop => '%',
+            Rank::Voice => '+',
+        }
+    }
+
+    ///
+    /// returns the mode that gives or takes the rank
+    ///
+    pub fn mode(self) -> char {
+        match self {
+            Rank::Owner => 'q',
+            Rank::Admin => 'a',
+            Rank::Op => 'o',
+            Rank::Halfop => 'h',
+            Rank::Voice => 'v',
+        }
+    }
+
+    pub fn from_prefix(prefix: char) -> Option<Rank> {
+        RANKS.into_iter().find(|rank| rank.prefix() == prefix)
+    }
+
+    pub fn from_mode(mode: char) -> Option<Rank> {
+        RANKS.into_iter().find(|rank| rank.mode() == mode)
+    }
+
+    ///
+    /// returns the rank a member needs to give or take this one:
+    /// halfops give voice, ops give halfop and op, and only the
+    /// owner gives admin and owner
+    ///
+    pub fn needed_to_set(self) -> Rank {
+        match self {
+            Rank::Voice => Rank::Halfop,
+            Rank::Halfop | Rank::Op => Rank::Op,
+            Rank::Admin | Rank::Owner => Rank::Owner,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+///
+/// Status of a member of a channel: the set of
+/// ranks it was given, kept as a bitset.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct MemberStatus(u8);
+
+impl MemberStatus {
+    pub fn with(rank: Rank) -> Self {
+        MemberStatus(rank.bit())
+    }
+
+    pub fn add(&mut self, rank: Rank) {
+        self.0 |= rank.bit();
+    }
+
+    pub fn remove(&mut self, rank: Rank) {
+        self.0 &= !rank.bit();
+    }
+
+    pub fn has(&self, rank: Rank) -> bool {
+        self.0 & rank.bit() != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    ///
+    /// returns the highest rank of the member, if any
+    ///
+    pub fn highest(&self) -> Option<Rank> {
+        RANKS.into_iter().find(|rank| self.has(*rank))
+    }
+
+    ///
+    /// returns the prefix of the highest rank,
+    /// or an empty string for a plain member
+    ///
+    pub fn prefix(&self) -> String {
+        self.highest().map(Rank::prefix).into_iter().collect()
+    }
+
+    ///
+    /// returns the prefixes of all the ranks, from the highest
+    ///
+    pub fn prefixes(&self) -> String {
+        RANKS
+            .into_iter()
+            .filter(|rank| self.has(*rank))
+            .map(Rank::prefix)
+            .collect()
+    }
+
+    ///
+    /// splits a member given as its prefixes followed
+    /// by its nickname, like `@+nick`
+    ///
+    pub fn parse(member: &str) -> (MemberStatus, &str) {
+        let nickname = member.trim_start_matches(|c| Rank::from_prefix(c).is_some());
+        let mut status = MemberStatus::default();
+        member[..member.len() - nickname.len()]
+            .chars()
+            .filter_map(Rank::from_prefix)
+            .for_each(|rank| status.add(rank));
+        (status, nickname)
+    }
+}
+
+///
+/// returns the PREFIX token of ISUPPORT, that
+/// maps each mode of a rank to its prefix
+///
+pub fn isupport_prefix() -> String {
+    let modes: String = RANKS.into_iter().map(Rank::mode).collect();
+    let prefixes: String = RANKS.into_iter().map(Rank::prefix).collect();
+    format!("PREFIX=({modes}){prefixes}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{isupport_prefix, MemberStatus, Rank};
+
+    #[test]
+    fn highest_rank_gives_the_prefix() {
+        let mut status = MemberStatus::with(Rank::Voice);
+        assert_eq!(status.prefix(), "+");
+        status.add(Rank::Op);
+        assert_eq!(status.highest(), Some(Rank::Op));
+        assert_eq!(status.prefix(), "@");
+        assert_eq!(status.prefixes(), "@+");
+        status.remove(Rank::Op);
+        status.remove(Rank::Voice);
+        assert!(status.is_empty());
+        assert_eq!(status.prefix(), "");
+    }
+
+    #[test]
+    fn member_is_parsed_from_its_prefixes() {
+        let (status, nickname) = MemberStatus::parse("~@+nick");
+        assert_eq!(nickname, "nick");
+        assert!(status.has(Rank::Owner) && status.has(Rank::Op) && status.has(Rank::Voice));
+        assert!(!status.has(Rank::Halfop));
+        assert_eq!(
+            MemberStatus::parse("nick"),
+            (MemberStatus::default(), "nick")
+        );
+    }
+
+    #[test]
+    fn prefix_token_lists_the_ranks_from_the_highest() {
+        assert_eq!(isupport_prefix(), "PREFIX=(qaohv)~&@%+");
+        assert_eq!(Rank::from_mode('h'), Some(Rank::Halfop));
+        assert_eq!(Rank::from_prefix('&'), Some(Rank::Admin));
+    }
+}
@@ -1,10 +1,26 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub mod channel_flag;
-
-use crate::{command::Command, error::error_channel::ErrorChannel, parser::message::Message};
-
-use self::channel_flag::ChannelFlag;
+pub mod flood;
+pub mod history;
+pub mod member_status;
+pub mod mode_parser;
+pub mod topic;
+
+use crate::{
+    command::{list, Command},
+    error::error_channel::ErrorChannel,
+    parser::message::Message,
+    utils::{mask_matches, unix_timestamp},
+};
+
+use self::{
+    channel_flag::ChannelFlag,
+    flood::{is_ctcp, strip_colors, FloodAction, FloodLimit, RateLimit},
+    member_status::{MemberStatus, Rank},
+    mode_parser::{mode_type, ModeChange, ModeType},
+    topic::{Topic, TOPIC_HISTORY},
+};
 
 ///
 ///
@@ -16,15 +32,27 @@ use self::channel_flag::ChannelFlag;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Channel {
     pub name: String,
-    topic: Option<String>,
-    clients: HashSet<String>,
-    channel_operators: HashSet<String>,
+    topic: Option<Topic>,
+    topic_history: VecDeque<Topic>,
+    members: HashMap<String, MemberStatus>,
     channel_flags: Vec<ChannelFlag>,
     user_limit: Option<usize>,
     bans: HashSet<String>,
+    ban_exceptions: HashSet<String>,
+    invite_exceptions: HashSet<String>,
     key: Option<String>,
-    invited: HashSet<String>,
-    moderated_users: HashSet<String>,
+    // the nicknames invited, with the time their invite expires
+    invited: HashMap<String, u64>,
+    created_at: u64,
+    flood: Option<FloodLimit>,
+    join_limit: Option<RateLimit>,
+    // the times of the last lines of each member, and of the last joins
+    lines: HashMap<String, VecDeque<u64>>,
+    joins: VecDeque<u64>,
+    // the members muted by the flood limit, until the given time
+    muted: HashMap<String, u64>,
+    // the time the moderation set by the flood limit ends
+    moderated_until: Option<u64>,
 }
 
 impl Channel {
@@ -35,24 +63,53 @@ impl Channel {
     /// * `channel_operator` : reference to a string containing the name of the channel operator
     ///
     pub fn new(name: String, channel_operator: String) -> Channel {
-        let mut clients: HashSet<String> = HashSet::new();
-        let mut channel_operators = HashSet::new();
-        channel_operators.insert(channel_operator.clone());
-        clients.insert(channel_operator);
+        let mut members = HashMap::new();
+        members.insert(channel_operator, MemberStatus::with(Rank::Op));
         Channel {
             name,
             topic: None,
-            clients,
-            channel_operators,
+            topic_history: VecDeque::new(),
+            members,
             channel_flags: Vec::new(),
             user_limit: None,
             bans: HashSet::new(),
+            ban_exceptions: HashSet::new(),
+            invite_exceptions: HashSet::new(),
             key: None,
-            invited: HashSet::new(),
-            moderated_users: HashSet::new(),
+            invited: HashMap::new(),
+            created_at: 0,
+            flood: None,
+            join_limit: None,
+            lines: HashMap::new(),
+            joins: VecDeque::new(),
+            muted: HashMap::new(),
+            moderated_until: None,
         }
     }
 
+    ///
+    /// creates a channel announced by another server. Its members
+    /// are added later with [`Channel::join_with_status`]
+    ///
+    pub fn from_burst(name: String, created_at: u64) -> Channel {
+        let mut channel = Channel::new(name, String::new());
+        channel.members.clear();
+        channel.created_at = created_at;
+        channel
+    }
+
+    ///
+    /// returns the timestamp of the creation of the channel,
+    /// the older one keeps its modes when two of them merge
+    ///
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn set_created_at(&mut self, created_at: u64) {
+        self.created_at = created_at;
+    }
+
     ///
     /// Returns true if channel's name is equal to name
     ///
@@ -81,6 +138,22 @@ impl Channel {
         false
     }
 
+    ///
+    /// returns true if the users can
+    /// KNOCK to ask for an invite
+    ///
+    pub fn accepts_knocks(&self) -> bool {
+        !self.channel_flags.contains(&ChannelFlag::NoKnock)
+    }
+
+    ///
+    /// returns true if the lines of the
+    /// members are limited, by +f
+    ///
+    pub fn is_flood_limited(&self) -> bool {
+        self.flood.is_some()
+    }
+
     pub fn is_no_message_from_outside(&self) -> bool {
         if self.channel_flags.contains(&ChannelFlag::N) {
             return true;
@@ -93,13 +166,10 @@ impl Channel {
         old_nickname: &str,
         new_nickname: &str,
     ) -> Result<bool, ErrorChannel> {
-        if self.remove_member(old_nickname) {
-            // ¿Que pasa si me banean y me ambio de nickname?
-            // Actualizar HashMaps de moderamiento y baneo.
-            // ¿Que pasa si no me invitan y me cambio de nickname al original?
-            // Si no existe el usuario, borrar como miembro y borrar de invitados y chanOp.
-            // ¿Que pasa si soy chanOp y me cambio de nickname al original?
-            self.add_member(new_nickname, &self.key.clone())?;
+        if let Some(status) = self.members.remove(old_nickname) {
+            // the bans were checked before accepting the new nickname,
+            // the member keeps its status
+            self.members.insert(new_nickname.to_string(), status);
             Ok(true)
         } else {
             Ok(false)
@@ -107,10 +177,13 @@ impl Channel {
     }
 
     ///
-    /// function that adds a new member to the channel
+    /// function that adds a new member to the channel. The client is
+    /// its nickname or its `nick!user@host` mask, that is checked
+    /// against the bans and the exceptions of the channel
     ///
     pub fn add_member(&mut self, client: &str, key: &Option<String>) -> Result<(), ErrorChannel> {
-        let users = self.clients.len();
+        let users = self.members.len();
+        let nickname = nickname_of(client);
 
         if !self.key_is_correct(key) {
             return Err(ErrorChannel::BadKey);
@@ -121,25 +194,109 @@ impl Channel {
                 return Err(ErrorChannel::FullChannel);
             }
         }
-        if self.bans.contains(client) {
+        if self.is_banned(client) {
             //println!("no joineo");
             return Err(ErrorChannel::BannedClient);
         }
-        if self.is_invite_only() && !self.invited.contains(client) {
+        if self.is_invite_only() && !self.is_invited(client) {
             return Err(ErrorChannel::ClientNotInvited);
         }
+        if let (Some(limit), false) = (self.join_limit, self.has_member(nickname)) {
+            if !limit.record(&mut self.joins, unix_timestamp()) {
+                return Err(ErrorChannel::JoinThrottled);
+            }
+        }
         //println!("joineo");
-        self.clients.insert(client.to_string());
+        self.members.entry(nickname.to_string()).or_default();
         Ok(())
     }
 
-    pub fn invite_member(&mut self, client: String) {
+    ///
+    /// returns true if the `nick!user@host` mask matches
+    /// a ban of the channel and none of its exceptions
+    ///
+    pub fn is_banned(&self, mask: &str) -> bool {
+        let mask = full_mask(mask);
+        self.bans.iter().any(|ban| mask_matches(ban, &mask))
+            && !self
+                .ban_exceptions
+                .iter()
+                .any(|exception| mask_matches(exception, &mask))
+    }
+
+    ///
+    /// returns false if a banned member without any status
+    /// tries to change its nickname, or to take one that is banned
+    ///
+    pub fn can_change_nick(&self, old_mask: &str, new_mask: &str) -> bool {
+        self.has_rank(nickname_of(old_mask), Rank::Voice)
+            || !(self.is_banned(old_mask) || self.is_banned(new_mask))
+    }
+
+    // A client joins an invite-only channel if its invite
+    // didn't expire or its mask matches an invite exception.
+    fn is_invited(&self, client: &str) -> bool {
+        let mask = full_mask(client);
+        self.has_invite(nickname_of(client), unix_timestamp())
+            || self
+                .invite_exceptions
+                .iter()
+                .any(|exception| mask_matches(exception, &mask))
+    }
+
+    ///
+    /// adds a member announced by another server,
+    /// where the restrictions to join were checked
+    ///
+    pub fn join_with_status(&mut self, client: &str, status: MemberStatus) {
+        self.members.insert(client.to_string(), status);
+    }
+
+    ///
+    /// removes the modes of the channel and the
+    /// status of its members, but not the members
+    ///
+    pub fn clear_modes(&mut self) {
+        self.channel_flags.clear();
+        self.user_limit = None;
+        self.bans.clear();
+        self.ban_exceptions.clear();
+        self.invite_exceptions.clear();
+        self.key = None;
+        self.invited.clear();
+        self.flood = None;
+        self.join_limit = None;
+        self.muted.clear();
+        self.moderated_until = None;
+        self.members
+            .values_mut()
+            .for_each(|status| *status = MemberStatus::default());
+    }
+
+    ///
+    /// invites the client until the given time, forgetting
+    /// the invites that expired. Only an invite-only
+    /// channel keeps its invites
+    ///
+    pub fn invite_member(&mut self, client: String, expires_at: u64) {
         if self.is_invite_only() {
-            self.invited.insert(client);
+            let now = unix_timestamp();
+            self.invited.retain(|_, expires| *expires > now);
+            self.invited.insert(client, expires_at);
         }
         //println!("invited: {:?}", self.invited);
     }
 
+    ///
+    /// returns true if the nickname was
+    /// invited and at the time it still holds
+    ///
+    pub fn has_invite(&self, nickname: &str, now: u64) -> bool {
+        self.invited
+            .get(nickname)
+            .is_some_and(|expires| *expires > now)
+    }
+
     pub fn remove_all_channel_flags(&mut self) {
         self.channel_flags = Vec::new();
     }
@@ -148,10 +305,7 @@ impl Channel {
     /// function that removes a member of the channel, if exists.
     ///
     pub fn remove_member(&mut self, client: &str) -> bool {
-        if self.has_member(client) {
-            return self.clients.remove(client);
-        }
-        false
+        self.members.remove(client).is_some()
     }
     ///
     /// function that checks if exists any participants.
@@ -161,25 +315,42 @@ impl Channel {
     }
 
     ///
-    /// returns a list with the channel characteristics
+    /// returns the name of the channel followed by its members,
+    /// from the highest rank, each with the prefix of its rank
     ///
     pub fn list_channel(&self) -> String {
-        let mut response = self.name.clone();
-        response.push_str(": ");
-        for i in &self.clients {
-            //println!("cliente en canal: {}", i);
-            response.push_str(i);
-            response.push('~');
-        }
-        response.pop();
-        response
+        let mut members: Vec<(&String, &MemberStatus)> = self.members.iter().collect();
+        members.sort_by(|(a, a_status), (b, b_status)| {
+            b_status.highest().cmp(&a_status.highest()).then(a.cmp(b))
+        });
+        let members: Vec<String> = members
+            .into_iter()
+            .map(|(member, status)| status.prefix() + member)
+            .collect();
+        format!("{}: {}", self.name, members.join(" "))
     }
 
     ///
     /// return all members in channel
     ///
     pub fn return_members(&self) -> Vec<String> {
-        self.clients.clone().into_iter().collect()
+        self.members.keys().cloned().collect()
+    }
+
+    ///
+    /// returns the status of the member, if it is in the channel
+    ///
+    pub fn member_status(&self, user: &str) -> Option<MemberStatus> {
+        self.members.get(user).copied()
+    }
+
+    ///
+    /// returns true if the member has the given rank or a higher one
+    ///
+    pub fn has_rank(&self, user: &str, rank: Rank) -> bool {
+        self.member_status(user)
+            .and_then(|status| status.highest())
+            .is_some_and(|highest| highest >= rank)
     }
 
     pub fn get_status_name(&self, user: &str) -> String {
@@ -193,24 +364,29 @@ impl Channel {
     }
 
     ///
-    /// returns the name of the channel operator
+    /// returns the names of the channel operators,
+    /// including the admins and the owners
     ///
     pub fn get_channel_operators(&self) -> HashSet<String> {
-        self.channel_operators.clone()
+        self.members
+            .keys()
+            .filter(|member| self.is_channel_operator(member))
+            .cloned()
+            .collect()
     }
     ///
-    /// returns true if the CO matches with user
+    /// returns true if the user is an operator of the
+    /// channel, or an admin or the owner
     ///
     pub fn is_channel_operator(&self, user: &str) -> bool {
-        //println!("Es chop {:?}? en {:?}", user, self.channel_operators);
-        self.channel_operators.contains(user)
+        self.has_rank(user, Rank::Op)
     }
     ///
     /// returns true if the channel has the given client
     /// or false in the other case
     ///
     pub fn has_member(&self, client: &str) -> bool {
-        self.clients.contains(client)
+        self.members.contains_key(client)
     }
 
     pub fn status(&self) -> &str {
@@ -224,7 +400,7 @@ impl Channel {
     }
 
     pub fn member_amount(&self) -> usize {
-        self.clients.len()
+        self.members.len()
     }
 
     /// returns channel_flags on a channel
@@ -242,33 +418,155 @@ impl Channel {
         string
     }
 
-    fn is_moderated_user(&self, user: &str) -> bool {
-        self.moderated_users.contains(user)
-    }
-
-    fn moderated_permission(&self, user: &str) -> bool {
-        if self.channel_flags.contains(&ChannelFlag::M) {
-            return self.is_moderated_user(user);
+    fn moderated_permission(&self, user: &str, now: u64) -> bool {
+        // the moderation set by the flood limit ends by itself
+        let moderated = match self.moderated_until {
+            Some(until) => now < until,
+            None => true,
+        };
+        if self.channel_flags.contains(&ChannelFlag::M) && moderated {
+            return self.has_rank(user, Rank::Voice);
         }
         true
     }
 
+    ///
+    /// returns true if the user, given by its nickname or its
+    /// `nick!user@host` mask, can send messages to the channel.
+    /// Banned users can't, unless they have any status, neither
+    /// can the members muted by the flood limit.
+    ///
     pub fn user_can_speak(&self, user: &str) -> bool {
-        if self.is_no_message_from_outside() && !self.has_member(user) {
+        let nickname = nickname_of(user);
+        let now = unix_timestamp();
+        if self.is_no_message_from_outside() && !self.has_member(nickname) {
             return false;
         }
-        self.moderated_permission(user)
+        if self.is_banned(user) && !self.has_rank(nickname, Rank::Voice) {
+            return false;
+        }
+        if self.muted.get(nickname).is_some_and(|until| now < *until) {
+            return false;
+        }
+        self.moderated_permission(nickname, now)
+    }
+
+    ///
+    /// returns the text as the channel delivers it, without
+    /// colors when it is +c, or None for a CTCP when it is +C
+    ///
+    pub fn filter_text(&self, text: &str) -> Option<String> {
+        if self.channel_flags.contains(&ChannelFlag::NoCtcp) && is_ctcp(text) {
+            return None;
+        }
+        match self.channel_flags.contains(&ChannelFlag::StripColors) {
+            true => Some(strip_colors(text)),
+            false => Some(text.to_string()),
+        }
     }
 
+    ///
+    /// Records a line sent by the user. Once the user goes over the
+    /// flood limit the action is taken against it, and returned.
+    /// The halfops and above are not limited.
+    ///
+    pub fn record_line(&mut self, user: &str, now: u64) -> Option<FloodAction> {
+        let limit = self.flood?;
+        let nickname = nickname_of(user);
+        if self.has_rank(nickname, Rank::Halfop) {
+            return None;
+        }
+        self.lines
+            .retain(|member, _| self.members.contains_key(member));
+        self.muted.retain(|_, until| now < *until);
+        let lines = self.lines.entry(nickname.to_string()).or_default();
+        if limit.rate.record(lines, now) {
+            return None;
+        }
+        self.lines.remove(nickname);
+        let until = now.saturating_add(limit.rate.seconds);
+        match limit.action {
+            FloodAction::Kick => {
+                self.members.remove(nickname);
+            }
+            FloodAction::Mute => {
+                self.muted.insert(nickname.to_string(), until);
+            }
+            FloodAction::Moderate => {
+                // a channel moderated by its operators stays moderated
+                if !self.channel_flags.contains(&ChannelFlag::M) {
+                    self.channel_flags.push(ChannelFlag::M);
+                    self.moderated_until = Some(until);
+                } else if self.moderated_until.is_some() {
+                    self.moderated_until = Some(until);
+                }
+            }
+        }
+        Some(limit.action)
+    }
+
+    ///
+    /// takes the +m set by the flood limit once its
+    /// time is over. Returns true if it was taken.
+    ///
+    pub fn lift_flood_moderation(&mut self, now: u64) -> bool {
+        match self.moderated_until {
+            Some(until) if until <= now => self.moderated_until = None,
+            _ => return false,
+        }
+        self.channel_flags.retain(|flag| *flag != ChannelFlag::M);
+        true
+    }
+
+    ///
+    /// returns true if the user can set the topic,
+    /// that needs halfop when the channel is +t
+    ///
     pub fn user_can_set_topic(&self, user: &str) -> bool {
         if self.channel_flags.contains(&ChannelFlag::T) {
-            return self.is_channel_operator(user);
+            return self.has_rank(user, Rank::Halfop);
         }
         true
     }
 
-    pub fn user_can_kick_others(&self, user: &str) -> bool {
-        self.is_channel_operator(user)
+    ///
+    /// returns true if the user can kick the target out of the
+    /// channel. Halfops only kick members below them, the
+    /// operators and above also kick their equals.
+    ///
+    pub fn user_can_kick(&self, user: &str, target: &str) -> bool {
+        self.has_rank(user, Rank::Halfop) && self.outranks(user, target)
+    }
+
+    ///
+    /// returns true if the user can apply the change of mode, like
+    /// `+o` or `-b`. The modes of the channel need halfop, and each
+    /// rank needs the one that gives it. A member can always take
+    /// its own status.
+    ///
+    pub fn user_can_change_mode(&self, user: &str, flag: &str, param: &Option<String>) -> bool {
+        let mut chars = flag.chars();
+        let (sign, mode) = (chars.next(), chars.next());
+        match mode.and_then(Rank::from_mode) {
+            Some(rank) => match param {
+                Some(target) if sign == Some('-') && target == user => true,
+                Some(target) => {
+                    self.has_rank(user, rank.needed_to_set()) && self.outranks(user, target)
+                }
+                None => self.has_rank(user, Rank::Halfop),
+            },
+            None => self.has_rank(user, Rank::Halfop),
+        }
+    }
+
+    // A member acts on another whose highest rank is lower, or
+    // equal when the member is an operator or above.
+    fn outranks(&self, user: &str, target: &str) -> bool {
+        let rank = |member| self.member_status(member).and_then(|s| s.highest());
+        match rank(user) {
+            Some(highest) if highest >= Rank::Op => rank(user) >= rank(target),
+            _ => rank(user) > rank(target),
+        }
     }
 
     pub fn key_is_correct(&self, key: &Option<String>) -> bool {
@@ -281,12 +579,51 @@ impl Channel {
         }
     }
 
-    pub fn set_topic(&mut self, topic: String) {
+    ///
+    /// sets the topic, keeping the last
+    /// ones in the history of the channel
+    ///
+    pub fn set_topic(&mut self, topic: Topic) {
+        self.topic_history.push_front(topic.clone());
+        self.topic_history.truncate(TOPIC_HISTORY);
         self.topic = Some(topic);
     }
 
+    ///
+    /// sets the topic announced by another server, if it
+    /// was set after the current one. Returns true if set.
+    ///
+    pub fn merge_topic(&mut self, topic: Topic) -> bool {
+        match &self.topic {
+            Some(current) if !topic.wins_over(current) => false,
+            _ => {
+                self.set_topic(topic);
+                true
+            }
+        }
+    }
+
     pub fn get_topic(&self) -> Option<String> {
-        self.topic.clone()
+        self.topic.as_ref().map(|topic| topic.text.clone())
+    }
+
+    pub fn topic_info(&self) -> Option<&Topic> {
+        self.topic.as_ref()
+    }
+
+    ///
+    /// returns the last topics of the channel, the newest first
+    ///
+    pub fn topic_history(&self) -> Vec<Topic> {
+        self.topic_history.iter().cloned().collect()
+    }
+
+    pub fn get_key(&self) -> Option<String> {
+        self.key.clone()
+    }
+
+    pub fn get_user_limit(&self) -> Option<usize> {
+        self.user_limit
     }
 
     pub fn modify_channel_flag(
@@ -295,21 +632,8 @@ impl Channel {
         params: Option<String>,
     ) -> Option<HashSet<String>> {
         match channel_flag {
-            "+o" => {
-                if let Some(s) = params {
-                    if !self.is_channel_operator(&s) {
-                        self.channel_operators.insert(s);
-                    }
-                }
-            }
-            "-o" => {
-                if self.channel_operators.len() > 1 {
-                    if let Some(s) = params {
-                        if self.is_channel_operator(&s) {
-                            self.channel_operators.remove(&s);
-                        }
-                    }
-                }
+            "+q" | "+a" | "+o" | "+h" | "+v" | "-q" | "-a" | "-o" | "-h" | "-v" => {
+                self.modify_status(channel_flag, params)
             }
             "+p" => {
                 let f = ChannelFlag::P;
@@ -342,7 +666,22 @@ impl Channel {
             "-i" => {
                 if let Some(i) = self.channel_flags.iter().position(|x| *x == ChannelFlag::I) {
                     self.channel_flags.remove(i);
-                    self.invited = HashSet::new();
+                    self.invited = HashMap::new();
+                }
+            }
+            "+K" => {
+                let f = ChannelFlag::NoKnock;
+                if !(self.channel_flags.contains(&f)) {
+                    self.channel_flags.push(f);
+                }
+            }
+            "-K" => {
+                let position = self
+                    .channel_flags
+                    .iter()
+                    .position(|x| *x == ChannelFlag::NoKnock);
+                if let Some(i) = position {
+                    self.channel_flags.remove(i);
                 }
             }
             "+t" => {
@@ -372,12 +711,62 @@ impl Channel {
                 if !(self.channel_flags.contains(&f)) {
                     self.channel_flags.push(f);
                 }
+                self.moderated_until = None;
             }
             "-m" => {
                 if let Some(i) = self.channel_flags.iter().position(|x| *x == ChannelFlag::M) {
                     self.channel_flags.remove(i);
                 }
-                self.moderated_users = HashSet::new();
+                self.moderated_until = None;
+            }
+            "+c" => {
+                let f = ChannelFlag::StripColors;
+                if !(self.channel_flags.contains(&f)) {
+                    self.channel_flags.push(f);
+                }
+            }
+            "-c" => {
+                self.channel_flags
+                    .retain(|x| *x != ChannelFlag::StripColors);
+            }
+            "+C" => {
+                let f = ChannelFlag::NoCtcp;
+                if !(self.channel_flags.contains(&f)) {
+                    self.channel_flags.push(f);
+                }
+            }
+            "-C" => {
+                self.channel_flags.retain(|x| *x != ChannelFlag::NoCtcp);
+            }
+            "+f" => {
+                if let Some(limit) = params.as_deref().and_then(FloodLimit::parse) {
+                    let f = ChannelFlag::Flood;
+                    if !(self.channel_flags.contains(&f)) {
+                        self.channel_flags.push(f);
+                    }
+                    self.flood = Some(limit);
+                    self.lines.clear();
+                }
+            }
+            "-f" => {
+                self.channel_flags.retain(|x| *x != ChannelFlag::Flood);
+                self.flood = None;
+                self.lines.clear();
+            }
+            "+j" => {
+                if let Some(limit) = params.as_deref().and_then(RateLimit::parse) {
+                    let f = ChannelFlag::JoinThrottle;
+                    if !(self.channel_flags.contains(&f)) {
+                        self.channel_flags.push(f);
+                    }
+                    self.join_limit = Some(limit);
+                }
+            }
+            "-j" => {
+                self.channel_flags
+                    .retain(|x| *x != ChannelFlag::JoinThrottle);
+                self.join_limit = None;
+                self.joins.clear();
             }
             "+l" => {
                 let f = ChannelFlag::L;
@@ -398,45 +787,12 @@ impl Channel {
                 }
                 self.user_limit = None;
             }
-            "+b" => {
-                println!("entro ban");
-                let f = ChannelFlag::B;
-                if !(self.channel_flags.contains(&f)) {
-                    self.channel_flags.push(f);
-                }
-                match params {
-                    Some(s) => {
-                        self.bans.insert(s);
-                        println!("baneo");
-                        println!("bans: {:?}", self.bans);
-                    }
-                    None => return Some(self.bans.clone()),
-                }
-            }
-            "-b" => {
-                if let Some(s) = params {
-                    self.bans.remove(&s);
-                    if self.bans.is_empty() {
-                        if let Some(i) =
-                            self.channel_flags.iter().position(|x| *x == ChannelFlag::B)
-                        {
-                            self.channel_flags.remove(i);
-                        }
-                    }
-                }
-            }
-            "+v" => {
-                if let Some(s) = params {
-                    self.moderated_users.insert(s);
-                }
-            }
-            "-v" => {
-                if let Some(s) = params {
-                    if self.is_moderated_user(&s) {
-                        self.moderated_users.remove(&s);
-                    }
-                }
-            }
+            "+b" => return self.modify_mask_list(ChannelFlag::B, true, params),
+            "-b" => return self.modify_mask_list(ChannelFlag::B, false, params),
+            "+e" => return self.modify_mask_list(ChannelFlag::E, true, params),
+            "-e" => return self.modify_mask_list(ChannelFlag::E, false, params),
+            "+I" => return self.modify_mask_list(ChannelFlag::InviteException, true, params),
+            "-I" => return self.modify_mask_list(ChannelFlag::InviteException, false, params),
             "+k" => {
                 let f = ChannelFlag::K;
                 if !(self.channel_flags.contains(&f)) {
@@ -457,209 +813,227 @@ impl Channel {
         None
     }
 
-    pub fn build_channel_msg(&self) -> Vec<String> {
-        if self.name.starts_with('#') {
-            let mut join_msg: Vec<Message> = self
-                .clients
-                .iter()
-                .map(|u| Self::build_join_msg(&self.name, u))
-                .collect();
-
-            let mut modes_msgs = vec![];
-            if let Some(chop) = join_msg.first() {
-                let chop = chop.prefix().unwrap();
-                modes_msgs.append(&mut Self::build_moderates_users_msg(
-                    &self.name,
-                    self.moderated_users
-                        .iter()
-                        .map(std::ops::Deref::deref)
-                        .collect::<Vec<&str>>(),
-                    &chop,
-                ));
-                modes_msgs.append(&mut Self::build_chops_msg(
-                    &self.name,
-                    self.channel_operators
-                        .iter()
-                        .map(std::ops::Deref::deref)
-                        .collect::<Vec<&str>>(),
-                    &chop,
-                ));
-                modes_msgs.append(&mut self.build_flags_msg(&chop));
-            }
-
-            join_msg.append(&mut modes_msgs);
-            let msg: Vec<String> = join_msg.iter().map(|msg| msg.to_string()).collect();
-            return msg;
-        }
-        vec![String::from("")]
-    }
-
-    fn build_join_msg(name: &str, nick: &str) -> Message {
-        let mut prefix = ":".to_string();
-        prefix.push_str(nick);
-        Message::new(Some(prefix), Command::Join, Some(vec![name.to_string()]))
-    }
-
-    fn build_chops_msg(name: &str, chops: Vec<&str>, initial_chop: &str) -> Vec<Message> {
-        let mut prefix = ":".to_string();
-        prefix.push_str(initial_chop);
-
-        let mut chops_msgs: Vec<Message> = chops
-            .iter()
-            .map(|u| {
-                Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![name.to_string(), "+o".to_string(), u.to_string()]),
-                )
-            })
-            .collect();
-
-        if !chops.contains(&initial_chop) {
-            chops_msgs.push(Message::new(
-                Some(prefix),
-                Command::Mode,
-                Some(vec![
-                    name.to_string(),
-                    "-o".to_string(),
-                    initial_chop.to_string(),
-                ]),
-            ));
+    ///
+    /// Applies the changes in order, and returns the ones that changed
+    /// the channel, with the masks of the lists completed. The changes
+    /// must have been checked with [`Channel::user_can_change_mode`].
+    ///
+    pub fn apply_modes(&mut self, changes: &[ModeChange]) -> Vec<ModeChange> {
+        let mut applied = vec![];
+        for change in changes.iter().filter(|change| !change.is_list_query()) {
+            let mut change = change.clone();
+            if mode_type(change.mode) == Some(ModeType::A) {
+                change.param = change.param.map(|mask| full_mask(&mask));
+            }
+            let before = self.clone();
+            self.modify_channel_flag(&change.flag(), change.param.clone());
+            if *self != before {
+                applied.push(change);
+            }
         }
+        applied
+    }
 
-        chops_msgs
+    // Gives or takes a rank to a member. The last operator
+    // of the channel keeps its op.
+    fn modify_status(&mut self, channel_flag: &str, member: Option<String>) {
+        let rank = match channel_flag.chars().nth(1).and_then(Rank::from_mode) {
+            Some(rank) => rank,
+            None => return,
+        };
+        let operators = self.members.values().filter(|s| s.has(Rank::Op)).count();
+        let status = match member.and_then(|member| self.members.get_mut(&member)) {
+            Some(status) => status,
+            None => return,
+        };
+        if channel_flag.starts_with('+') {
+            status.add(rank);
+        } else if rank != Rank::Op || operators > 1 {
+            status.remove(rank);
+        }
     }
 
-    fn build_moderates_users_msg(
-        name: &str,
-        moderated_users: Vec<&str>,
-        initial_chop: &str,
-    ) -> Vec<Message> {
-        let mut prefix = ":".to_string();
-        prefix.push_str(initial_chop);
+    // Adds or removes a mask of the bans or of the exceptions, keeping
+    // the flag while the list has any. Without a mask the list is
+    // returned, to be shown to the user.
+    fn modify_mask_list(
+        &mut self,
+        flag: ChannelFlag,
+        add: bool,
+        mask: Option<String>,
+    ) -> Option<HashSet<String>> {
+        let list = match flag {
+            ChannelFlag::E => &mut self.ban_exceptions,
+            ChannelFlag::InviteException => &mut self.invite_exceptions,
+            _ => &mut self.bans,
+        };
+        let mask = match (mask, add) {
+            (Some(mask), _) => full_mask(&mask),
+            (None, true) => return Some(list.clone()),
+            (None, false) => return None,
+        };
+        if add {
+            list.insert(mask);
+        } else {
+            list.remove(&mask);
+        }
+        let empty = list.is_empty();
+        match (empty, self.channel_flags.iter().position(|f| *f == flag)) {
+            (true, Some(i)) => {
+                self.channel_flags.remove(i);
+            }
+            (false, None) => self.channel_flags.push(flag),
+            _ => {}
+        }
+        None
+    }
 
-        let msgs: Vec<Message> = moderated_users
+    ///
+    /// Builds the messages that announce the channel to another server:
+    /// a SJOIN with its creation timestamp, its modes and its members,
+    /// each with the prefixes of all its ranks, followed by its topic.
+    ///
+    pub fn build_channel_msg(&self, servername: &str) -> Vec<String> {
+        if !self.name.starts_with('#') {
+            return vec![];
+        }
+        let mut parameters = vec![self.created_at.to_string(), self.name.clone()];
+        parameters.append(&mut self.build_modes());
+        let members: Vec<String> = self
+            .members
             .iter()
-            .map(|u| {
-                Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![name.to_string(), "+v".to_string(), u.to_string()]),
-                )
-            })
+            .map(|(member, status)| status.prefixes() + member)
             .collect();
-
+        parameters.push(format!(":{}", members.join(" ")));
+        let prefix = format!(":{servername}");
+        let mut msgs =
+            vec![Message::new(Some(prefix), Command::Sjoin, Some(parameters)).to_string()];
+        if let Some(msg) = self.build_topic_msg(servername) {
+            msgs.push(msg.to_string());
+        }
         msgs
     }
 
-    fn build_flags_msg(&self, chop: &str) -> Vec<Message> {
-        let mut p = ":".to_string();
-        p.push_str(chop);
-
-        let mut msg = vec![];
+    // The modes, as `+<flags>`, followed by their parameters.
+    fn build_modes(&self) -> Vec<String> {
+        let mut flags = String::from("+");
+        let mut parameters = vec![];
         for flag in &self.channel_flags {
-            let prefix = p.clone();
-            let mut new_msgs = match flag {
-                ChannelFlag::P => vec![Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![self.name.clone(), "+p".to_string()]),
-                )],
-                ChannelFlag::M => vec![Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![self.name.clone(), "+m".to_string()]),
-                )],
-                ChannelFlag::S => vec![Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![self.name.clone(), "+m".to_string()]),
-                )],
-                ChannelFlag::L => vec![Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![
-                        self.name.clone(),
-                        "+l".to_string(),
-                        self.user_limit.unwrap().to_string(),
-                    ]),
-                )],
-                ChannelFlag::I => vec![Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![self.name.clone(), "+i".to_string()]),
-                )],
-                ChannelFlag::B => self.build_banned_users_msgs(prefix),
-                ChannelFlag::T => self.build_topic_msg(&prefix),
-                ChannelFlag::K => vec![Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![
-                        self.name.clone(),
-                        "+k".to_string(),
-                        self.key.as_ref().unwrap().to_owned(),
-                    ]),
-                )],
-                ChannelFlag::N => vec![Message::new(
-                    Some(prefix.clone()),
-                    Command::Mode,
-                    Some(vec![self.name.clone(), "+n".to_string()]),
-                )],
-                _ => continue,
-            };
-            msg.append(&mut new_msgs);
-        }
-
-        msg
-    }
-
-    fn build_topic_msg(&self, prefix: &str) -> Vec<Message> {
-        let topic = match &self.topic {
-            Some(t) => t.to_owned(),
-            None => String::from("")
-        };
-        vec![Message::new(
-            Some(prefix.to_owned()),
-            Command::Topic,
+            match flag {
+                ChannelFlag::L => match self.user_limit {
+                    Some(limit) => parameters.push(limit.to_string()),
+                    None => continue,
+                },
+                ChannelFlag::K => match &self.key {
+                    Some(key) => parameters.push(key.clone()),
+                    None => continue,
+                },
+                ChannelFlag::Flood => match self.flood {
+                    Some(limit) => parameters.push(limit.to_string()),
+                    None => continue,
+                },
+                ChannelFlag::JoinThrottle => match self.join_limit {
+                    Some(limit) => parameters.push(limit.to_string()),
+                    None => continue,
+                },
+                ChannelFlag::B | ChannelFlag::E | ChannelFlag::InviteException => {
+                    let list = match flag {
+                        ChannelFlag::E => &self.ban_exceptions,
+                        ChannelFlag::InviteException => &self.invite_exceptions,
+                        _ => &self.bans,
+                    };
+                    for mask in list {
+                        flags.push_str(&flag.to_string());
+                        parameters.push(mask.clone());
+                    }
+                    continue;
+                }
+                ChannelFlag::O | ChannelFlag::V => continue,
+                _ => {}
+            }
+            flags.push_str(&flag.to_string());
+        }
+        let mut modes = vec![flags];
+        modes.append(&mut parameters);
+        modes
+    }
+
+    // The topic with who set it and when, so the
+    // other server keeps the one set last.
+    fn build_topic_msg(&self, servername: &str) -> Option<Message> {
+        let topic = self.topic.as_ref()?;
+        Some(Message::new(
+            Some(format!(":{servername}")),
+            Command::Tb,
             Some(vec![
                 self.name.clone(),
-                topic.to_string()
-            ]))]
+                topic.set_at.to_string(),
+                topic.setter.clone(),
+                format!(":{}", topic.text),
+            ]),
+        ))
     }
+}
 
-    fn build_banned_users_msgs(&self, prefix: String) -> Vec<Message> {
-        let mut msgs = vec![];
-        for ban in &self.bans {
-            msgs.push(Message::new(
-                Some(prefix.clone()),
-                Command::Mode,
-                Some(vec![self.name.clone(), "+b".to_string(), ban.clone()]),
-            ));
-        }
-        msgs
+///
+/// returns the ISUPPORT tokens that tell the users the kinds of
+/// channel, its modes, how to ask for its history and to list them
+///
+pub fn isupport_tokens() -> Vec<String> {
+    let mut tokens = vec![
+        member_status::isupport_prefix(),
+        "CHANTYPES=#&".to_string(),
+        mode_parser::isupport_chanmodes(),
+    ];
+    tokens.append(&mut history::isupport_tokens());
+    tokens.append(&mut list::isupport_tokens());
+    tokens
+}
+
+///
+/// completes a mask given as a nickname, or without host, so
+/// `nick` stands for `nick!*@*` and `nick!user` for `nick!user@*`
+///
+pub fn full_mask(mask: &str) -> String {
+    match (mask.contains('!'), mask.contains('@')) {
+        (true, true) => mask.to_string(),
+        (true, false) => format!("{mask}@*"),
+        (false, true) => format!("*!{mask}"),
+        (false, false) => format!("{mask}!*@*"),
     }
 }
 
+// The nickname of a client given by its nickname or its mask.
+fn nickname_of(client: &str) -> &str {
+    client.split('!').next().unwrap_or(client)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     #[test]
     fn new_channel() {
-        let mut set = HashSet::new();
-        set.insert(String::from("juan"));
-        let mut channel_operators = HashSet::new();
-        channel_operators.insert(String::from("juan"));
+        let mut members = HashMap::new();
+        members.insert(String::from("juan"), MemberStatus::with(Rank::Op));
         let expected = Channel {
             name: String::from("canal"),
             topic: None,
-            clients: set,
-            channel_operators,
+            topic_history: VecDeque::new(),
+            members,
             channel_flags: Vec::new(),
             user_limit: None,
             bans: HashSet::new(),
+            ban_exceptions: HashSet::new(),
+            invite_exceptions: HashSet::new(),
             key: None,
-            invited: HashSet::new(),
-            moderated_users: HashSet::new(),
+            invited: HashMap::new(),
+            created_at: 0,
+            flood: None,
+            join_limit: None,
+            lines: HashMap::new(),
+            joins: VecDeque::new(),
+            muted: HashMap::new(),
+            moderated_until: None,
         };
         let result = Channel::new("canal".to_string(), "juan".to_string());
 
@@ -675,22 +1049,28 @@ mod test {
 
     #[test]
     fn add_member() {
-        let mut set = HashSet::new();
-        set.insert(String::from("juan"));
-        set.insert(String::from("pedro"));
-        let mut channel_operators = HashSet::new();
-        channel_operators.insert(String::from("juan"));
+        let mut members = HashMap::new();
+        members.insert(String::from("juan"), MemberStatus::with(Rank::Op));
+        members.insert(String::from("pedro"), MemberStatus::default());
         let expected = Channel {
             name: String::from("canal"),
             topic: None,
-            clients: set,
-            channel_operators,
+            topic_history: VecDeque::new(),
+            members,
             channel_flags: Vec::new(),
             user_limit: None,
             bans: HashSet::new(),
+            ban_exceptions: HashSet::new(),
+            invite_exceptions: HashSet::new(),
             key: None,
-            invited: HashSet::new(),
-            moderated_users: HashSet::new(),
+            invited: HashMap::new(),
+            created_at: 0,
+            flood: None,
+            join_limit: None,
+            lines: HashMap::new(),
+            joins: VecDeque::new(),
+            muted: HashMap::new(),
+            moderated_until: None,
         };
         let mut result = Channel::new("canal".to_string(), "juan".to_string());
         result.add_member("pedro", &None).unwrap();
@@ -699,44 +1079,56 @@ mod test {
     }
     #[test]
     fn list_correctly() {
-        let mut set = HashSet::new();
-        set.insert(String::from("juan"));
-        let mut channel_operators = HashSet::new();
-        channel_operators.insert(String::from("juan"));
+        let mut members = HashMap::new();
+        members.insert(String::from("juan"), MemberStatus::with(Rank::Op));
         let channel = Channel {
             name: String::from("canal"),
             topic: None,
-            clients: set,
-            channel_operators,
+            topic_history: VecDeque::new(),
+            members,
             channel_flags: Vec::new(),
             user_limit: None,
             bans: HashSet::new(),
+            ban_exceptions: HashSet::new(),
+            invite_exceptions: HashSet::new(),
             key: None,
-            invited: HashSet::new(),
-            moderated_users: HashSet::new(),
+            invited: HashMap::new(),
+            created_at: 0,
+            flood: None,
+            join_limit: None,
+            lines: HashMap::new(),
+            joins: VecDeque::new(),
+            muted: HashMap::new(),
+            moderated_until: None,
         };
         let result = channel.list_channel();
-        let expected = "canal: juan".to_string();
+        let expected = "canal: @juan".to_string();
         assert_eq!(result, expected);
     }
 
     #[test]
     fn remove_member() {
-        let mut set = HashSet::new();
-        set.insert(String::from("juan"));
-        let mut channel_operators = HashSet::new();
-        channel_operators.insert(String::from("juan"));
+        let mut members = HashMap::new();
+        members.insert(String::from("juan"), MemberStatus::with(Rank::Op));
         let expected = Channel {
             name: String::from("canal"),
             topic: None,
-            clients: set,
-            channel_operators,
+            topic_history: VecDeque::new(),
+            members,
             channel_flags: Vec::new(),
             user_limit: None,
             bans: HashSet::new(),
+            ban_exceptions: HashSet::new(),
+            invite_exceptions: HashSet::new(),
             key: None,
-            invited: HashSet::new(),
-            moderated_users: HashSet::new(),
+            invited: HashMap::new(),
+            created_at: 0,
+            flood: None,
+            join_limit: None,
+            lines: HashMap::new(),
+            joins: VecDeque::new(),
+            muted: HashMap::new(),
+            moderated_until: None,
         };
         let mut result = Channel::new("canal".to_string(), "juan".to_string());
         result.add_member("pedro", &None).unwrap();
@@ -846,4 +1238,307 @@ mod test {
 
         assert!(channel.has_member("pepe"));
     }
+
+    #[test]
+    fn expired_invite_does_not_let_the_user_join() {
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channel.modify_channel_flag("+i", None);
+        channel.invite_member("juan".to_string(), unix_timestamp() - 1);
+        channel.invite_member("luis".to_string(), unix_timestamp() + 60);
+
+        assert_eq!(
+            channel.add_member("juan", &None),
+            Err(ErrorChannel::ClientNotInvited)
+        );
+        assert!(channel.add_member("luis", &None).is_ok());
+        channel.modify_channel_flag("+K", None);
+        assert!(!channel.accepts_knocks());
+        assert_eq!(channel.return_channel_flags_str(), " ,i - K - ");
+    }
+
+    #[test]
+    fn wildcard_ban_matches_any_nickname() {
+        let mut channel = Channel::new("canal".to_string(), "pedro".to_string());
+        channel.modify_channel_flag("+b", Some("*!*@badhost".to_string()));
+
+        assert_eq!(
+            channel.add_member("pepe!pepe@badhost", &None),
+            Err(ErrorChannel::BannedClient)
+        );
+        assert_eq!(
+            channel.add_member("otro!pepe@BADHOST", &None),
+            Err(ErrorChannel::BannedClient)
+        );
+        channel.add_member("juan!juan@goodhost", &None).unwrap();
+        assert!(channel.has_member("juan"));
+    }
+
+    #[test]
+    fn ban_exception_overrides_the_ban() {
+        let mut channel = Channel::new("canal".to_string(), "pedro".to_string());
+        channel.modify_channel_flag("+b", Some("*!*@*.badhost".to_string()));
+        channel.modify_channel_flag("+e", Some("*!juan@*".to_string()));
+
+        channel.add_member("juan!juan@a.badhost", &None).unwrap();
+        assert!(channel.is_banned("pepe!pepe@a.badhost"));
+        assert_eq!(
+            channel.modify_channel_flag("+e", None),
+            Some(HashSet::from(["*!juan@*".to_string()]))
+        );
+    }
+
+    #[test]
+    fn invite_exception_joins_invite_only_channel() {
+        let mut channel = Channel::new("canal".to_string(), "pedro".to_string());
+        channel.modify_channel_flag("+i", None);
+        channel.modify_channel_flag("+I", Some("*!*@trusted".to_string()));
+
+        channel.add_member("juan!juan@trusted", &None).unwrap();
+        assert_eq!(
+            channel.add_member("pepe!pepe@other", &None),
+            Err(ErrorChannel::ClientNotInvited)
+        );
+    }
+
+    #[test]
+    fn banned_member_cant_speak_nor_change_nick() {
+        let mut channel = Channel::new("canal".to_string(), "pedro".to_string());
+        channel.add_member("pepe!pepe@host", &None).unwrap();
+        channel.add_member("juan!juan@host", &None).unwrap();
+        channel.modify_channel_flag("+b", Some("*!*@host".to_string()));
+        channel.modify_channel_flag("+v", Some("juan".to_string()));
+
+        assert!(!channel.user_can_speak("pepe!pepe@host"));
+        assert!(!channel.can_change_nick("pepe!pepe@host", "otro!pepe@host"));
+        assert!(channel.user_can_speak("juan!juan@host"));
+        assert!(channel.can_change_nick("juan!juan@host", "otro!juan@host"));
+
+        channel.modify_channel_flag("-b", Some("*!*@host".to_string()));
+        channel.modify_channel_flag("+b", Some("otro".to_string()));
+        assert!(channel.user_can_speak("pepe!pepe@host"));
+        assert!(!channel.can_change_nick("pepe!pepe@host", "otro!pepe@host"));
+    }
+
+    #[test]
+    fn nick_change_keeps_the_status() {
+        let mut channel = Channel::new("canal".to_string(), "pedro".to_string());
+        channel.update_member("pedro", "pedro2").unwrap();
+
+        assert!(channel.has_member("pedro2"));
+        assert!(channel.is_channel_operator("pedro2"));
+        assert!(!channel.is_channel_operator("pedro"));
+    }
+
+    #[test]
+    fn channel_msg_carries_timestamp_modes_and_status() {
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        channel.set_created_at(5);
+        channel.modify_channel_flag("+t", None);
+        channel.modify_channel_flag("+l", Some("10".to_string()));
+        channel.modify_channel_flag("+I", Some("*!*@trusted".to_string()));
+        channel.set_topic(Topic::new("hola mundo", "pedro!pedro@host", 7));
+
+        assert_eq!(
+            channel.build_channel_msg("server"),
+            vec![
+                ":server SJOIN 5 #canal +tlI 10 *!*@trusted :@pedro",
+                ":server TB #canal 7 pedro!pedro@host :hola mundo"
+            ]
+        );
+    }
+
+    #[test]
+    fn topic_history_keeps_the_last_topics() {
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        for set_at in 0..15 {
+            channel.set_topic(Topic::new(&set_at.to_string(), "pedro!pedro@host", set_at));
+        }
+        let history = channel.topic_history();
+        assert_eq!(history.len(), TOPIC_HISTORY);
+        assert_eq!(history[0].set_at, 14);
+        assert_eq!(channel.get_topic(), Some("14".to_string()));
+
+        assert!(!channel.merge_topic(Topic::new("old", "juan!juan@host", 3)));
+        assert!(channel.merge_topic(Topic::new("new", "juan!juan@host", 20)));
+        assert_eq!(channel.topic_info().unwrap().setter, "juan!juan@host");
+    }
+
+    #[test]
+    fn cleared_channel_keeps_its_members() {
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        let mut status = MemberStatus::with(Rank::Op);
+        status.add(Rank::Voice);
+        channel.join_with_status("juan", status);
+        channel.modify_channel_flag("+k", Some("clave".to_string()));
+
+        channel.clear_modes();
+
+        assert!(channel.has_member("juan"));
+        assert!(!channel.is_channel_operator("pedro"));
+        assert_eq!(channel.get_key(), None);
+    }
+
+    #[test]
+    fn members_are_listed_with_the_prefix_of_their_rank() {
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        channel.add_member("ana", &None).unwrap();
+        channel.add_member("juan", &None).unwrap();
+        channel.add_member("zoe", &None).unwrap();
+        channel.modify_channel_flag("+v", Some("juan".to_string()));
+        channel.modify_channel_flag("+h", Some("juan".to_string()));
+        channel.modify_channel_flag("+q", Some("zoe".to_string()));
+
+        assert_eq!(channel.list_channel(), "#canal: ~zoe @pedro %juan ana");
+        assert!(channel.build_channel_msg("server")[0].contains("%+juan"));
+    }
+
+    #[test]
+    fn ranks_limit_kicks_modes_and_topic() {
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        channel.add_member("juan", &None).unwrap();
+        channel.add_member("ana", &None).unwrap();
+        channel.add_member("luis", &None).unwrap();
+        channel.modify_channel_flag("+h", Some("juan".to_string()));
+        channel.modify_channel_flag("+t", None);
+
+        assert!(channel.user_can_set_topic("juan"));
+        assert!(!channel.user_can_set_topic("ana"));
+        assert!(channel.user_can_kick("juan", "ana"));
+        assert!(!channel.user_can_kick("juan", "pedro"));
+        assert!(!channel.user_can_kick("ana", "luis"));
+        assert!(channel.user_can_kick("pedro", "juan"));
+
+        let target = Some("ana".to_string());
+        assert!(channel.user_can_change_mode("juan", "+v", &target));
+        assert!(!channel.user_can_change_mode("juan", "+o", &target));
+        assert!(channel.user_can_change_mode("pedro", "+o", &target));
+        assert!(!channel.user_can_change_mode("pedro", "+q", &target));
+        assert!(channel.user_can_change_mode("juan", "+m", &None));
+        assert!(!channel.user_can_change_mode("ana", "+m", &None));
+        let juan = Some("juan".to_string());
+        assert!(channel.user_can_change_mode("juan", "-h", &juan));
+        assert!(!channel.user_can_change_mode("ana", "-h", &juan));
+    }
+
+    #[test]
+    fn last_operator_keeps_its_op() {
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        channel.modify_channel_flag("-o", Some("pedro".to_string()));
+        assert!(channel.is_channel_operator("pedro"));
+
+        channel.add_member("juan", &None).unwrap();
+        channel.modify_channel_flag("+o", Some("juan".to_string()));
+        channel.modify_channel_flag("-o", Some("pedro".to_string()));
+        assert!(!channel.is_channel_operator("pedro"));
+        assert_eq!(channel.get_channel_operators().len(), 1);
+    }
+
+    #[test]
+    fn join_throttle_refuses_the_joins_over_its_limit() {
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channel.modify_channel_flag("+j", Some("2:60".to_string()));
+        channel.add_member("juan", &None).unwrap();
+        channel.add_member("luis", &None).unwrap();
+
+        assert_eq!(
+            channel.add_member("pepe", &None),
+            Err(ErrorChannel::JoinThrottled)
+        );
+        assert!(channel.add_member("juan", &None).is_ok());
+        channel.modify_channel_flag("-j", None);
+        assert!(channel.add_member("pepe", &None).is_ok());
+    }
+
+    #[test]
+    fn flood_limit_takes_its_action_against_the_flooder() {
+        let now = unix_timestamp();
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channel.add_member("juan", &None).unwrap();
+        channel.modify_channel_flag("+f", Some("2:10:mute".to_string()));
+        assert_eq!(channel.record_line("juan!juan@host", now), None);
+        assert_eq!(channel.record_line("juan!juan@host", now), None);
+        assert_eq!(channel.record_line("ana", now), None);
+
+        assert_eq!(
+            channel.record_line("juan!juan@host", now),
+            Some(FloodAction::Mute)
+        );
+        assert!(!channel.user_can_speak("juan"));
+        assert!(channel.user_can_speak("ana"));
+
+        channel.modify_channel_flag("+f", Some("1:10:kick".to_string()));
+        channel.add_member("luis", &None).unwrap();
+        channel.record_line("luis", now);
+        assert_eq!(channel.record_line("luis", now), Some(FloodAction::Kick));
+        assert!(!channel.has_member("luis"));
+    }
+
+    #[test]
+    fn flood_moderation_is_lifted_once_over() {
+        let now = unix_timestamp();
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channel.add_member("juan", &None).unwrap();
+        channel.modify_channel_flag("+f", Some("1:10:moderate".to_string()));
+        channel.record_line("juan", now);
+        assert_eq!(
+            channel.record_line("juan", now),
+            Some(FloodAction::Moderate)
+        );
+        assert!(!channel.user_can_speak("juan"));
+        assert!(!channel.lift_flood_moderation(now));
+
+        assert!(channel.lift_flood_moderation(now + 10));
+        assert!(!channel.return_channel_flags().contains(&ChannelFlag::M));
+    }
+
+    #[test]
+    fn huge_flood_limit_mutes_for_ever() {
+        let now = unix_timestamp();
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channel.add_member("juan", &None).unwrap();
+        channel.modify_channel_flag("+f", Some("1:18446744073709551615:mute".to_string()));
+        channel.record_line("juan", now);
+        assert_eq!(channel.record_line("juan", now), Some(FloodAction::Mute));
+        assert!(!channel.user_can_speak("juan"));
+
+        channel.modify_channel_flag("+j", Some("1:18446744073709551615".to_string()));
+        assert!(channel.add_member("luis", &None).is_ok());
+        assert_eq!(
+            channel.add_member("pepe", &None),
+            Err(ErrorChannel::JoinThrottled)
+        );
+    }
+
+    #[test]
+    fn colors_and_ctcps_are_filtered() {
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+        assert_eq!(
+            channel.filter_text("\x034hola\x03"),
+            Some("\x034hola\x03".to_string())
+        );
+        channel.modify_channel_flag("+c", None);
+        channel.modify_channel_flag("+C", None);
+
+        assert_eq!(
+            channel.filter_text("\x034hola\x03"),
+            Some("hola".to_string())
+        );
+        assert_eq!(channel.filter_text("\x01VERSION\x01"), None);
+        assert!(channel.filter_text("\x01ACTION saluda\x01").is_some());
+    }
+
+    #[test]
+    fn flood_modes_are_announced_with_their_limits() {
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        channel.set_created_at(5);
+        channel.modify_channel_flag("+f", Some("5:10:moderate".to_string()));
+        channel.modify_channel_flag("+j", Some("3:20".to_string()));
+        channel.modify_channel_flag("+c", None);
+        channel.modify_channel_flag("+f", Some("nope".to_string()));
+
+        assert_eq!(
+            channel.build_channel_msg("server"),
+            vec![":server SJOIN 5 #canal +fjc 5:10:moderate 3:20 :@pedro"]
+        );
+    }
 }
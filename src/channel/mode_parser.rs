@@ -0,0 +1,192 @@
+use super::member_status::Rank;
+
+///
+/// Kind of a mode of a channel, as told to the
+/// users in the CHANMODES token of ISUPPORT.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ModeType {
+    /// a list of masks, shown when given without parameter
+    A,
+    /// a setting that always takes a parameter
+    B,
+    /// a setting that takes a parameter only when it is set
+    C,
+    /// a flag that never takes a parameter
+    D,
+}
+
+// the modes of each type, in the order of CHANMODES
+const CHANMODES: [(ModeType, &str); 4] = [
+    (ModeType::A, "beI"),
+    (ModeType::B, "k"),
+    (ModeType::C, "fjl"),
+    (ModeType::D, "cCiKmnpst"),
+];
+
+///
+/// returns the type of the mode. The ranks of the
+/// members take a nickname, so they are of type B.
+///
+pub fn mode_type(mode: char) -> Option<ModeType> {
+    if Rank::from_mode(mode).is_some() {
+        return Some(ModeType::B);
+    }
+    CHANMODES
+        .iter()
+        .find(|(_, modes)| modes.contains(mode))
+        .map(|(mode_type, _)| *mode_type)
+}
+
+///
+/// returns true if the mode, when set or unset,
+/// takes the next parameter of the message
+///
+pub fn takes_parameter(add: bool, mode: char) -> bool {
+    match mode_type(mode) {
+        Some(ModeType::A | ModeType::B) => true,
+        Some(ModeType::C) => add,
+        _ => false,
+    }
+}
+
+///
+/// returns the CHANMODES token of ISUPPORT
+///
+pub fn isupport_chanmodes() -> String {
+    let types: Vec<&str> = CHANMODES.iter().map(|(_, modes)| *modes).collect();
+    format!("CHANMODES={}", types.join(","))
+}
+
+///
+/// A change of one mode of a channel, with its parameter.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ModeChange {
+    pub add: bool,
+    pub mode: char,
+    pub param: Option<String>,
+}
+
+impl ModeChange {
+    ///
+    /// returns the change as a flag, like `+o` or `-b`
+    ///
+    pub fn flag(&self) -> String {
+        let sign = if self.add { '+' } else { '-' };
+        format!("{sign}{}", self.mode)
+    }
+
+    ///
+    /// returns true if the change only asks for a list of masks
+    ///
+    pub fn is_list_query(&self) -> bool {
+        self.add && self.param.is_none() && mode_type(self.mode) == Some(ModeType::A)
+    }
+}
+
+///
+/// Parses a mode string like `+ov-b`, taking the parameters of the
+/// modes from the queue, in order. A change that lacks the parameter
+/// it needs is left out, except a list without mask, that is a query
+/// of the list. Returns the first unknown mode as error.
+///
+pub fn parse_modes(modes: &str, params: &[String]) -> Result<Vec<ModeChange>, char> {
+    let mut params = params.iter();
+    let mut add = true;
+    let mut changes = vec![];
+    for mode in modes.chars() {
+        match mode {
+            '+' => add = true,
+            '-' => add = false,
+            _ => {
+                let mode_type = mode_type(mode).ok_or(mode)?;
+                let param = match takes_parameter(add, mode) {
+                    true => params.next().cloned(),
+                    false => None,
+                };
+                let needs_param = Rank::from_mode(mode).is_some()
+                    || match mode_type {
+                        ModeType::A => !add,
+                        ModeType::B => add,
+                        ModeType::C | ModeType::D => false,
+                    };
+                if param.is_none() && needs_param {
+                    continue;
+                }
+                changes.push(ModeChange { add, mode, param });
+            }
+        }
+    }
+    Ok(changes)
+}
+
+///
+/// Builds the normalized mode string of the changes followed by
+/// their parameters, like `+ov-b alice bob *!*@host`
+///
+pub fn mode_line(changes: &[ModeChange]) -> Vec<String> {
+    let mut modes = String::new();
+    let mut params = vec![];
+    let mut sign = None;
+    for change in changes {
+        if sign != Some(change.add) {
+            modes.push(if change.add { '+' } else { '-' });
+            sign = Some(change.add);
+        }
+        modes.push(change.mode);
+        if let Some(param) = &change.param {
+            params.push(param.clone());
+        }
+    }
+    let mut line = vec![modes];
+    line.append(&mut params);
+    line
+}
+
+#[cfg(test)]
+mod test {
+    use super::{isupport_chanmodes, mode_line, parse_modes, ModeChange};
+
+    fn params(params: &[&str]) -> Vec<String> {
+        params.iter().map(|param| param.to_string()).collect()
+    }
+
+    #[test]
+    fn parameters_are_taken_in_order() {
+        let changes = parse_modes("+ov-b+l", &params(&["alice", "bob", "*!*@x", "5"])).unwrap();
+        let flags: Vec<String> = changes.iter().map(ModeChange::flag).collect();
+        assert_eq!(flags, vec!["+o", "+v", "-b", "+l"]);
+        assert_eq!(changes[2].param, Some("*!*@x".to_string()));
+        assert_eq!(changes[3].param, Some("5".to_string()));
+    }
+
+    #[test]
+    fn changes_without_their_parameter_are_left_out() {
+        let changes = parse_modes("-l+tko", &params(&["key"])).unwrap();
+        let flags: Vec<String> = changes.iter().map(ModeChange::flag).collect();
+        assert_eq!(flags, vec!["-l", "+t", "+k"]);
+
+        let changes = parse_modes("+b", &[]).unwrap();
+        assert!(changes[0].is_list_query());
+    }
+
+    #[test]
+    fn unknown_mode_is_err() {
+        assert_eq!(parse_modes("+tz", &[]), Err('z'));
+    }
+
+    #[test]
+    fn line_groups_the_changes_by_sign() {
+        let changes = parse_modes("+o+v-b-t", &params(&["alice", "bob", "*!*@x"])).unwrap();
+        assert_eq!(
+            mode_line(&changes),
+            params(&["+ov-bt", "alice", "bob", "*!*@x"])
+        );
+    }
+
+    #[test]
+    fn chanmodes_lists_the_modes_by_type() {
+        assert_eq!(isupport_chanmodes(), "CHANMODES=beI,k,fjl,cCiKmnpst");
+    }
+}
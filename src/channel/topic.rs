@@ -0,0 +1,49 @@
+/// amount of topics a channel remembers
+pub const TOPIC_HISTORY: usize = 10;
+
+///
+/// Topic of a channel, with the mask of
+/// the user that set it and when.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Topic {
+    pub text: String,
+    pub setter: String,
+    pub set_at: u64,
+}
+
+impl Topic {
+    pub fn new(text: &str, setter: &str, set_at: u64) -> Self {
+        Topic {
+            text: text.to_string(),
+            setter: setter.to_string(),
+            set_at,
+        }
+    }
+
+    ///
+    /// returns true if this topic replaces the other when two
+    /// servers merge a channel. The one set last wins, so both
+    /// servers keep the same.
+    ///
+    pub fn wins_over(&self, other: &Topic) -> bool {
+        (self.set_at, &self.text, &self.setter) > (other.set_at, &other.text, &other.setter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Topic;
+
+    #[test]
+    fn topic_set_last_wins() {
+        let old = Topic::new("old", "ana!ana@host", 10);
+        let new = Topic::new("new", "juan!juan@host", 20);
+        assert!(new.wins_over(&old));
+        assert!(!old.wins_over(&new));
+        assert!(!new.wins_over(&new));
+
+        let other = Topic::new("other", "juan!juan@host", 20);
+        assert!(other.wins_over(&new) != new.wins_over(&other));
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{config::ServerConfig, error::error_server::ErrorServer, reply::Reply};
+
+///
+/// struct that implements the ADMIN message, that answers
+/// with the administrative info of the server:
+/// `ADMIN [<server>]`
+///
+pub struct AdminMsg;
+
+impl AdminMsg {
+    ///
+    /// Answers with the location of the server and the email of
+    /// its administrator, the ones that are configured.
+    ///
+    pub fn response(
+        servername: &str,
+        config: &Arc<Mutex<ServerConfig>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let (location, email) = match config.lock() {
+            Ok(config) => (config.admin_location.clone(), config.admin_email.clone()),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        if location.is_none() && email.is_none() {
+            return Ok(vec![Reply::err_no_admin_info(servername)]);
+        }
+        let mut replies = vec![Reply::rpl_admin_me(servername)];
+        if let Some(location) = location {
+            replies.push(Reply::rpl_admin_loc1(&location));
+        }
+        if let Some(email) = email {
+            replies.push(Reply::rpl_admin_email(&email));
+        }
+        Ok(replies)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::config::ServerConfig;
+
+    use super::AdminMsg;
+
+    #[test]
+    fn answers_the_configured_info() {
+        let config = ServerConfig::from_str("admin_email = admin@example.com").unwrap();
+        let replies = AdminMsg::response("server1", &Arc::new(Mutex::new(config))).unwrap();
+        let replies: Vec<String> = replies.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            replies,
+            vec!["256 server1 :Administrative info", "259 :admin@example.com"]
+        );
+    }
+
+    #[test]
+    fn server_without_info_is_err_no_admin_info() {
+        let config = Arc::new(Mutex::new(ServerConfig::default()));
+        let replies = AdminMsg::response("server1", &config).unwrap();
+        assert_eq!(
+            replies[0].to_string(),
+            "423 server1 :No administrative info available"
+        );
+    }
+}
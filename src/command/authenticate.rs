@@ -0,0 +1,217 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    error::error_server::ErrorServer, parser::message::Message,
+    services::accounts::AccountRegistry, utils::decode_base64,
+};
+
+/// mechanisms offered with the sasl capability
+pub const SASL_MECHANISMS: &str = "PLAIN";
+
+const ABORT: &str = "*";
+const EMPTY: &str = "+";
+// a chunk this long is followed by more of the payload
+const CHUNK_LENGTH: usize = 400;
+// the longest payload accepted, in base64
+const PAYLOAD_LENGTH: usize = 4096;
+
+///
+/// State of the SASL authentication of a client
+/// that is registering: the chosen mechanism, the
+/// payload received so far and the account it logged in.
+///
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SaslSession {
+    mechanism: Option<String>,
+    payload: String,
+    account: Option<String>,
+}
+
+impl SaslSession {
+    pub fn new() -> Self {
+        SaslSession::default()
+    }
+
+    ///
+    /// returns the account the client logged in,
+    /// if the authentication succeeded
+    ///
+    pub fn account(&self) -> Option<String> {
+        self.account.clone()
+    }
+}
+
+///
+/// struct that implements the AUTHENTICATE message, with which a
+/// client that enabled the sasl capability logs in an account
+/// while it registers:
+///
+/// `AUTHENTICATE PLAIN`
+/// `AUTHENTICATE <base64 of authzid NUL authcid NUL password>`
+/// `AUTHENTICATE *` aborts the authentication
+///
+pub struct AuthenticateMsg {
+    data: String,
+}
+
+impl AuthenticateMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        match msg.get_param_from_msg(0) {
+            Some(data) => Ok(AuthenticateMsg { data }),
+            None => Err(ErrorServer::BadQuery),
+        }
+    }
+
+    ///
+    /// Advances the session with the data, answering the client with
+    /// the given nickname, `*` while it registers. Once the whole
+    /// payload arrives the credentials are checked with the accounts.
+    ///
+    pub fn response(
+        &self,
+        nickname: &str,
+        session: &mut SaslSession,
+        accounts: &Arc<Mutex<AccountRegistry>>,
+    ) -> Result<Vec<String>, ErrorServer> {
+        if session.account.is_some() {
+            return Ok(vec![format!(
+                "907 {nickname} :You have already authenticated using SASL"
+            )]);
+        }
+        if self.data == ABORT {
+            *session = SaslSession::new();
+            return Ok(vec![format!("906 {nickname} :SASL authentication aborted")]);
+        }
+        if session.mechanism.is_none() {
+            if !self.data.eq_ignore_ascii_case(SASL_MECHANISMS) {
+                return Ok(vec![
+                    format!("908 {nickname} {SASL_MECHANISMS} :are available SASL mechanisms"),
+                    Self::failed(nickname),
+                ]);
+            }
+            session.mechanism = Some(SASL_MECHANISMS.to_string());
+            return Ok(vec![format!("AUTHENTICATE {EMPTY}")]);
+        }
+        if self.data != EMPTY {
+            session.payload.push_str(&self.data);
+        }
+        if session.payload.len() > PAYLOAD_LENGTH {
+            *session = SaslSession::new();
+            return Ok(vec![Self::failed(nickname)]);
+        }
+        if self.data.len() == CHUNK_LENGTH {
+            return Ok(vec![]);
+        }
+        let credentials = decode_base64(&session.payload).and_then(|payload| plain(&payload));
+        *session = SaslSession::new();
+        let account = match (credentials, accounts.lock()) {
+            (Some((account, password)), Ok(accounts)) => accounts.authenticate(&account, &password),
+            (None, Ok(_)) => None,
+            (_, Err(_)) => return Err(ErrorServer::LockedResource),
+        };
+        match account {
+            Some(account) => {
+                let name = account.name;
+                session.account = Some(name.clone());
+                Ok(vec![
+                    format!("900 {nickname} {nickname} {name} :You are now logged in as {name}"),
+                    format!("903 {nickname} :SASL authentication successful"),
+                ])
+            }
+            None => Ok(vec![Self::failed(nickname)]),
+        }
+    }
+
+    fn failed(nickname: &str) -> String {
+        format!("904 {nickname} :SASL authentication failed")
+    }
+}
+
+// Splits the PLAIN payload into the account and the password.
+// The identity to act as, if given, must be the same account.
+fn plain(payload: &[u8]) -> Option<(String, String)> {
+    let payload = String::from_utf8(payload.to_vec()).ok()?;
+    match payload.split('\0').collect::<Vec<&str>>().as_slice() {
+        [authzid, authcid, password]
+            if authzid.is_empty() || authzid.eq_ignore_ascii_case(authcid) =>
+        {
+            Some((authcid.to_string(), password.to_string()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        parser::message::Message,
+        services::accounts::{Account, AccountRegistry},
+    };
+
+    use super::{AuthenticateMsg, SaslSession, CHUNK_LENGTH, PAYLOAD_LENGTH};
+
+    fn authenticate(
+        data: &str,
+        session: &mut SaslSession,
+        accounts: &Arc<Mutex<AccountRegistry>>,
+    ) -> Vec<String> {
+        let msg = Message::from_str(&format!("AUTHENTICATE {data}")).unwrap();
+        AuthenticateMsg::new(&msg)
+            .unwrap()
+            .response("*", session, accounts)
+            .unwrap()
+    }
+
+    fn accounts() -> Arc<Mutex<AccountRegistry>> {
+        let mut accounts = AccountRegistry::new();
+        let account = Account::new("ana", "secreta", 0).unwrap();
+        accounts.register(account).unwrap();
+        Arc::new(Mutex::new(accounts))
+    }
+
+    #[test]
+    fn plain_credentials_log_in_the_account() {
+        let (accounts, mut session) = (accounts(), SaslSession::new());
+        assert_eq!(
+            authenticate("PLAIN", &mut session, &accounts),
+            vec!["AUTHENTICATE +"]
+        );
+        let lines = authenticate("AGFuYQBzZWNyZXRh", &mut session, &accounts);
+        assert_eq!(lines[1], "903 * :SASL authentication successful");
+        assert_eq!(session.account(), Some("ana".to_string()));
+
+        let lines = authenticate("PLAIN", &mut session, &accounts);
+        assert!(lines[0].starts_with("907 "));
+    }
+
+    #[test]
+    fn wrong_password_or_mechanism_fails() {
+        let (accounts, mut session) = (accounts(), SaslSession::new());
+        let lines = authenticate("EXTERNAL", &mut session, &accounts);
+        assert_eq!(lines[1], "904 * :SASL authentication failed");
+
+        authenticate("PLAIN", &mut session, &accounts);
+        // "\0ana\0otra"
+        let lines = authenticate("AGFuYQBvdHJh", &mut session, &accounts);
+        assert_eq!(lines, vec!["904 * :SASL authentication failed"]);
+        assert_eq!(session.account(), None);
+    }
+
+    #[test]
+    fn payload_too_long_fails_and_restarts() {
+        let (accounts, mut session) = (accounts(), SaslSession::new());
+        authenticate("PLAIN", &mut session, &accounts);
+        let chunk = "A".repeat(CHUNK_LENGTH);
+        for _ in 0..PAYLOAD_LENGTH / CHUNK_LENGTH {
+            assert!(authenticate(&chunk, &mut session, &accounts).is_empty());
+        }
+        let lines = authenticate(&chunk, &mut session, &accounts);
+        assert_eq!(lines, vec!["904 * :SASL authentication failed"]);
+        assert_eq!(session, SaslSession::new());
+    }
+}
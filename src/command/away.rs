@@ -78,6 +78,15 @@ mod test {
             Ok(vec![])
         }
 
+        fn find_page(
+            &self,
+            after: Option<String>,
+            amount: usize,
+        ) -> Result<Vec<Connection>, ErrorServer> {
+            let _ = (after, amount);
+            Ok(vec![])
+        }
+
         fn search(&self, key: String) -> Result<Option<Connection>, ErrorServer> {
             let _ = key;
             Ok(None)
@@ -0,0 +1,143 @@
+use crate::{error::error_server::ErrorServer, parser::message::Message, reply::Reply};
+
+const START_CHAR: char = '+';
+const END_CHAR: char = '-';
+
+///
+/// type of the batch that groups the burst sent
+/// to a server when it links with this one
+///
+pub const NETBURST: &str = "netburst";
+
+///
+/// struct that implements the BATCH message, that groups
+/// the messages sent by a server, as the IRCv3 batches:
+///
+/// `:<server> BATCH +<reference> <type>`
+/// `:<server> BATCH -<reference>`
+///
+/// The messages in between belong to the batch.
+///
+#[derive(Debug)]
+pub struct BatchMsg {
+    server: String,
+    reference: String,
+    kind: Option<String>,
+    start: bool,
+}
+
+impl BatchMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let server = match msg.prefix() {
+            Some(server) => server,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        let param = msg.get_param_from_msg(0).unwrap_or_default();
+        let (start, reference) =
+            match (param.strip_prefix(START_CHAR), param.strip_prefix(END_CHAR)) {
+                (Some(reference), _) => (true, reference),
+                (_, Some(reference)) => (false, reference),
+                _ => return Err(ErrorServer::BadQuery),
+            };
+        if reference.is_empty() {
+            return Err(ErrorServer::BadQuery);
+        }
+        Ok(BatchMsg {
+            server,
+            reference: reference.to_string(),
+            kind: msg.get_param_from_msg(1),
+            start,
+        })
+    }
+
+    ///
+    /// The messages of the batch are applied as they arrive,
+    /// so nothing is left to do at its start or its end.
+    ///
+    pub fn response(&self) -> Result<Vec<Reply>, ErrorServer> {
+        Ok(vec![Reply::rpl_none()])
+    }
+
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    ///
+    /// returns true if the message starts
+    /// the batch, false if it ends it
+    ///
+    pub fn is_start(&self) -> bool {
+        self.start
+    }
+}
+
+///
+/// surrounds the messages with the start
+/// and the end of a batch sent by the server
+///
+pub fn wrap_in_batch(
+    servername: &str,
+    reference: &str,
+    kind: &str,
+    messages: Vec<String>,
+) -> Vec<String> {
+    let mut batch = vec![format!(
+        ":{servername} BATCH {START_CHAR}{reference} {kind}"
+    )];
+    batch.extend(messages);
+    batch.push(format!(":{servername} BATCH {END_CHAR}{reference}"));
+    batch
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::parser::message::Message;
+
+    use super::{wrap_in_batch, BatchMsg, NETBURST};
+
+    fn batch(msg: &str) -> Option<BatchMsg> {
+        BatchMsg::new(&Message::from_str(msg).unwrap()).ok()
+    }
+
+    #[test]
+    fn parses_start_and_end() {
+        let start = batch(":server BATCH +1a netburst").unwrap();
+        assert!(start.start);
+        assert_eq!(start.reference, "1a");
+        assert_eq!(start.kind, Some(NETBURST.to_string()));
+
+        let end = batch(":server BATCH -1a").unwrap();
+        assert!(!end.start);
+        assert_eq!(end.reference, "1a");
+    }
+
+    #[test]
+    fn reference_without_sign_is_err() {
+        assert!(batch(":server BATCH 1a netburst").is_none());
+        assert!(batch(":server BATCH +").is_none());
+        assert!(batch(":server BATCH").is_none());
+    }
+
+    #[test]
+    fn messages_are_wrapped_in_the_batch() {
+        let messages = vec![":server NICK bob".to_string()];
+        assert_eq!(
+            wrap_in_batch("server", "1a", NETBURST, messages),
+            vec![
+                ":server BATCH +1a netburst",
+                ":server NICK bob",
+                ":server BATCH -1a"
+            ]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{error::error_server::ErrorServer, parser::message::Message};
+
+use super::authenticate::SASL_MECHANISMS;
+
+/// capability with which the messages carry the account of their sender
+pub const ACCOUNT_TAG: &str = "account-tag";
+
+/// capabilities the client can enable with CAP REQ
+pub const SUPPORTED_CAPABILITIES: [&str; 6] = [
+    ACCOUNT_TAG,
+    "batch",
+    "draft/chathistory",
+    "message-tags",
+    "sasl",
+    "server-time",
+];
+
+const SASL: &str = "sasl";
+
+const REMOVE_CHAR: char = '-';
+
+///
+/// struct that implements the CAP message, with which the
+/// client negotiates the IRCv3 capabilities it understands:
+///
+/// `CAP LS [<version>]`
+/// `CAP LIST`
+/// `CAP REQ :<capability> [-<capability> ...]`
+/// `CAP END`
+///
+/// It can be sent both during the registration and after it.
+///
+pub struct CapMsg {
+    subcommand: String,
+    capabilities: Vec<String>,
+}
+
+impl CapMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let subcommand = match msg.get_param_from_msg(0) {
+            Some(subcommand) => subcommand.to_uppercase(),
+            None => return Err(ErrorServer::BadQuery),
+        };
+        let capabilities = match msg.parameters() {
+            Some(parameters) => parameters
+                .iter()
+                .skip(1)
+                .flat_map(|p| p.trim_start_matches(':').split_whitespace())
+                .map(str::to_string)
+                .collect(),
+            None => vec![],
+        };
+        Ok(CapMsg {
+            subcommand,
+            capabilities,
+        })
+    }
+
+    ///
+    /// returns true if the client ends the negotiation,
+    /// so its registration can be completed
+    ///
+    pub fn ends_negotiation(&self) -> bool {
+        self.subcommand == "END"
+    }
+
+    ///
+    /// Answers the subcommand for the client with the given nickname,
+    /// `*` while it registers. A REQ enables or disables the capabilities
+    /// all at once, or none of them if one is not supported.
+    ///
+    pub fn response(&self, nickname: &str, enabled: &mut Vec<String>) -> Vec<String> {
+        match self.subcommand.as_str() {
+            "LS" => {
+                let capabilities: Vec<String> = SUPPORTED_CAPABILITIES
+                    .iter()
+                    .map(|capability| match *capability {
+                        SASL => format!("{SASL}={SASL_MECHANISMS}"),
+                        capability => capability.to_string(),
+                    })
+                    .collect();
+                vec![format!("CAP {nickname} LS :{}", capabilities.join(" "))]
+            }
+            "LIST" => vec![format!("CAP {nickname} LIST :{}", enabled.join(" "))],
+            "REQ" => {
+                let requested = self.capabilities.join(" ");
+                let supported = self.capabilities.iter().all(|capability| {
+                    let name = capability.trim_start_matches(REMOVE_CHAR);
+                    SUPPORTED_CAPABILITIES.contains(&name)
+                });
+                if !supported || self.capabilities.is_empty() {
+                    return vec![format!("CAP {nickname} NAK :{requested}")];
+                }
+                for capability in &self.capabilities {
+                    match capability.strip_prefix(REMOVE_CHAR) {
+                        Some(name) => enabled.retain(|c| c != name),
+                        None if !enabled.contains(capability) => enabled.push(capability.clone()),
+                        None => {}
+                    }
+                }
+                vec![format!("CAP {nickname} ACK :{requested}")]
+            }
+            "END" => vec![],
+            subcommand => vec![format!("410 {nickname} {subcommand} :Invalid CAP command")],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::parser::message::Message;
+
+    use super::CapMsg;
+
+    fn cap(line: &str, enabled: &mut Vec<String>) -> Vec<String> {
+        let msg = Message::from_str(line).unwrap();
+        CapMsg::new(&msg).unwrap().response("*", enabled)
+    }
+
+    #[test]
+    fn supported_capabilities_are_listed() {
+        let lines = cap("CAP LS 302", &mut vec![]);
+        assert_eq!(
+            lines,
+            vec![
+                "CAP * LS :account-tag batch draft/chathistory message-tags sasl=PLAIN server-time"
+            ]
+        );
+    }
+
+    #[test]
+    fn request_is_accepted_whole_or_not_at_all() {
+        let mut enabled = vec![];
+        let lines = cap("CAP REQ :server-time batch", &mut enabled);
+        assert_eq!(lines, vec!["CAP * ACK :server-time batch"]);
+        assert_eq!(enabled, vec!["server-time", "batch"]);
+
+        let lines = cap("CAP REQ :-batch away-notify", &mut enabled);
+        assert_eq!(lines, vec!["CAP * NAK :-batch away-notify"]);
+        assert_eq!(enabled, vec!["server-time", "batch"]);
+
+        cap("CAP REQ :-batch", &mut enabled);
+        assert_eq!(
+            cap("CAP LIST", &mut enabled),
+            vec!["CAP * LIST :server-time"]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    channel::{
+        history::{ChannelHistory, HistoryEntry, HistoryReference, MAX_HISTORY_QUERY},
+        Channel,
+    },
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    user::User,
+    utils::{unix_timestamp, write_messages_to},
+};
+
+use super::batch::wrap_in_batch;
+
+/// type of the batch that groups the messages of a history
+pub const CHATHISTORY: &str = "chathistory";
+
+///
+/// struct that implements the IRCv3 CHATHISTORY message, with which
+/// a member of a channel asks for the messages it missed:
+///
+/// `CHATHISTORY LATEST <channel> <* | reference> <limit>`
+/// `CHATHISTORY BEFORE <channel> <reference> <limit>`
+/// `CHATHISTORY AFTER <channel> <reference> <limit>`
+/// `CHATHISTORY BETWEEN <channel> <reference> <reference> <limit>`
+///
+/// where a reference is `msgid=<id>` or `timestamp=<date>`.
+///
+pub struct ChatHistoryMsg {
+    subcommand: String,
+    parameters: Vec<String>,
+}
+
+impl ChatHistoryMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let mut parameters = msg.parameters().unwrap_or_default().into_iter();
+        Ok(ChatHistoryMsg {
+            subcommand: parameters.next().unwrap_or_default().to_uppercase(),
+            parameters: parameters.collect(),
+        })
+    }
+
+    ///
+    /// Returns the messages asked for in a batch, or the FAIL
+    /// that tells why they could not be retrieved. Only the
+    /// members of the channel can read its history.
+    ///
+    pub fn response(
+        &self,
+        user: &User,
+        channel_sender: &ClientChannel<String, Channel>,
+        history: &Arc<Mutex<ChannelHistory>>,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let references = match self.subcommand.as_str() {
+            "LATEST" | "BEFORE" | "AFTER" => 1,
+            "BETWEEN" => 2,
+            _ => return Ok(vec![self.fail("UNKNOWN_COMMAND", ":Unknown command")]),
+        };
+        if self.parameters.len() < references + 2 {
+            return Ok(vec![
+                self.fail("NEED_MORE_PARAMS", ":Insufficient parameters")
+            ]);
+        }
+        let target = &self.parameters[0];
+        let member = match channel_sender.search(target.clone())? {
+            Some(channel) => channel.has_member(user.nickname().unwrap_or_default()),
+            None => false,
+        };
+        if !member {
+            let text = format!("{target} :Messages could not be retrieved");
+            return Ok(vec![self.fail("INVALID_TARGET", &text)]);
+        }
+        let parsed: Result<Vec<HistoryReference>, ErrorServer> = self.parameters[1..=references]
+            .iter()
+            .map(|reference| HistoryReference::from_str(reference))
+            .collect();
+        let limit = self.parameters[references + 1].parse::<usize>();
+        let (references, limit) = match (parsed, limit) {
+            (Ok(references), Ok(limit)) if self.valid(&references) => (references, limit),
+            _ => return Ok(vec![self.fail("INVALID_PARAMS", ":Invalid parameters")]),
+        };
+        let limit = limit.clamp(1, MAX_HISTORY_QUERY);
+        let entries = match history.lock() {
+            Ok(history) => match self.subcommand.as_str() {
+                "LATEST" => history.latest(target, &references[0], limit),
+                "BEFORE" => history.before(target, &references[0], limit),
+                "AFTER" => history.after(target, &references[0], limit),
+                _ => history.between(target, &references[0], &references[1], limit),
+            },
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        Ok(history_batch(user, target, &entries))
+    }
+
+    // Only LATEST takes `*` as reference.
+    fn valid(&self, references: &[HistoryReference]) -> bool {
+        self.subcommand == "LATEST" || !references.contains(&HistoryReference::Any)
+    }
+
+    // The standard reply that tells why the query failed.
+    fn fail(&self, code: &str, description: &str) -> String {
+        format!("FAIL CHATHISTORY {code} {} {description}", self.subcommand)
+    }
+}
+
+///
+/// Builds the messages of the history as the user can read them: in a
+/// batch and tagged with their time and id, if it enabled those capabilities
+///
+pub fn history_batch(user: &User, target: &str, entries: &[HistoryEntry]) -> Vec<String> {
+    let reference = format!("{:x}", unix_timestamp());
+    let mut tags = vec![];
+    if user.has_capability("batch") {
+        tags.push(format!("batch={reference}"));
+    }
+    if user.has_capability("server-time") {
+        tags.push("time".to_string());
+    }
+    if user.has_capability("message-tags") {
+        tags.push("msgid".to_string());
+    }
+    let messages = entries
+        .iter()
+        .map(|entry| entry.to_message(&tags))
+        .collect();
+    match user.has_capability("batch") {
+        true => wrap_in_batch(
+            user.servername().unwrap_or_default(),
+            &reference,
+            &format!("{CHATHISTORY} {target}"),
+            messages,
+        ),
+        false => messages,
+    }
+}
+
+///
+/// Sends the last messages of the channels the user just joined,
+/// if it asked for the history with the `draft/chathistory` capability
+///
+pub fn replay_on_join(
+    user: &User,
+    channels: &[String],
+    nick_sender: &ClientChannel<String, Connection>,
+    channel_sender: &ClientChannel<String, Channel>,
+    history: &Arc<Mutex<ChannelHistory>>,
+) -> Result<(), ErrorServer> {
+    let nickname = user.nickname().unwrap_or_default();
+    if !user.has_capability("draft/chathistory") {
+        return Ok(());
+    }
+    let mut connection = match nick_sender.search(nickname.to_string())? {
+        Some(connection) if connection.is_local() => connection,
+        _ => return Ok(()),
+    };
+    for name in channels {
+        match channel_sender.search(name.clone())? {
+            Some(channel) if channel.has_member(nickname) => {}
+            _ => continue,
+        }
+        let entries = match history.lock() {
+            Ok(history) => history.latest(name, &HistoryReference::Any, MAX_HISTORY_QUERY),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        if !entries.is_empty() {
+            let lines = history_batch(user, name, &entries);
+            write_messages_to(&mut lines.iter(), &mut connection)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        channel::{
+            history::{ChannelHistory, HistoryReference},
+            Channel,
+        },
+        database::RepositoryHandler,
+        parser::message::Message,
+        repository::traits::operations::Operations,
+        user::User,
+        utils::{write_message_to, MESSAGE_LENGHT},
+    };
+
+    use super::{history_batch, ChatHistoryMsg};
+
+    fn history() -> Arc<Mutex<ChannelHistory>> {
+        let mut history = ChannelHistory::new(10);
+        for (time, text) in [(60, "hola"), (120, "chau")] {
+            history
+                .record("#canal", "ana!ana@host", text, time)
+                .unwrap();
+        }
+        Arc::new(Mutex::new(history))
+    }
+
+    fn query(line: &str, user: &User) -> Vec<String> {
+        let channels = RepositoryHandler::new().unwrap();
+        let channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channels
+            .get_channels()
+            .add("#canal".to_string(), channel)
+            .unwrap();
+        let msg = Message::from_str(line).unwrap();
+        ChatHistoryMsg::new(&msg)
+            .unwrap()
+            .response(user, &channels.get_channels(), &history())
+            .unwrap()
+    }
+
+    #[test]
+    fn history_is_sent_in_a_batch_with_tags() {
+        let mut user = User::new("ana", "ana", "host", "server", "Ana", "");
+        user.set_capabilities(vec!["batch".to_string(), "server-time".to_string()]);
+        let lines = query(":ana CHATHISTORY LATEST #canal * 1", &user);
+        assert_eq!(lines.len(), 3);
+        assert!(
+            lines[0].starts_with(":server BATCH +") && lines[0].ends_with(" chathistory #canal")
+        );
+        assert!(lines[1].starts_with("@batch="));
+        assert!(
+            lines[1].ends_with(";time=1970-01-01T00:02:00.000Z :ana!ana@host PRIVMSG #canal :chau")
+        );
+    }
+
+    #[test]
+    fn plain_client_gets_the_messages_alone() {
+        let user = User::new("ana", "ana", "host", "server", "Ana", "");
+        let lines = query(
+            ":ana CHATHISTORY BEFORE #canal timestamp=1970-01-01T00:02:00.000Z 10",
+            &user,
+        );
+        assert_eq!(lines, vec![":ana!ana@host PRIVMSG #canal :hola"]);
+    }
+
+    #[test]
+    fn long_messages_are_cut_to_fit_with_their_tags() {
+        let mut user = User::new("ana", "ana", "host", "server", "Ana", "");
+        user.set_capabilities(vec![
+            "batch".to_string(),
+            "server-time".to_string(),
+            "message-tags".to_string(),
+        ]);
+        let mut history = ChannelHistory::new(10);
+        let text = "ñ".repeat(480);
+        history.record("#canal", "ana!ana@host", &text, 60).unwrap();
+        let entries = history.latest("#canal", &HistoryReference::Any, 1);
+        let lines = history_batch(&user, "#canal", &entries);
+        assert!(lines[1].starts_with("@batch=") && lines[1].contains(" :ñ"));
+        assert!(lines[1].len() <= MESSAGE_LENGHT);
+        for line in lines {
+            let mut sent = vec![];
+            write_message_to(&line, &mut sent).unwrap();
+            assert_eq!(sent.len(), MESSAGE_LENGHT);
+        }
+    }
+
+    #[test]
+    fn invalid_queries_fail() {
+        let user = User::new("ana", "ana", "host", "server", "Ana", "");
+        let fails = [
+            (":ana CHATHISTORY LATEST #canal *", "NEED_MORE_PARAMS"),
+            (":ana CHATHISTORY AFTER #canal * 10", "INVALID_PARAMS"),
+            (":ana CHATHISTORY LATEST #otro * 10", "INVALID_TARGET"),
+            (":ana CHATHISTORY AROUND #canal * 10", "UNKNOWN_COMMAND"),
+        ];
+        for (line, code) in fails {
+            let lines = query(line, &user);
+            assert!(lines[0].starts_with(&format!("FAIL CHATHISTORY {code}")));
+        }
+        let outsider = User::new("juan", "juan", "host", "server", "Juan", "");
+        let lines = query(":juan CHATHISTORY LATEST #canal * 10", &outsider);
+        assert!(lines[0].starts_with("FAIL CHATHISTORY INVALID_TARGET"));
+    }
+}
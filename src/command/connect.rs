@@ -0,0 +1,267 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    config::ServerConfig,
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    server_comunication::{
+        info_sender::send_to_servers, spanning_tree::SpanningTree, uid::to_uids,
+    },
+    utils::write_message_to,
+};
+
+///
+/// struct that implements the CONNECT message, that
+/// links a server with one of its configured links:
+///
+/// `CONNECT <target server> [port] [remote server]`
+///
+/// With a remote server, the message is routed to it
+/// and that server is the one that connects.
+///
+pub struct ConnectMsg {
+    from: String,
+    target: Option<String>,
+    port: Option<Result<u16, String>>,
+    remote: Option<String>,
+    msg: Message,
+}
+
+impl ConnectMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let from = match msg.prefix() {
+            Some(from) => from,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        Ok(ConnectMsg {
+            from,
+            target: msg.get_param_from_msg(0),
+            port: msg
+                .get_param_from_msg(1)
+                .map(|port| port.parse().map_err(|_| port)),
+            remote: msg.get_param_from_msg(2),
+            msg: msg.clone(),
+        })
+    }
+
+    ///
+    /// Connects this server with the target, or forwards the message
+    /// to the remote server. The given function opens the link with
+    /// the address of the target.
+    ///
+    pub fn response<F>(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        config: &Arc<Mutex<ServerConfig>>,
+        connect: F,
+    ) -> Result<Vec<Reply>, ErrorServer>
+    where
+        F: FnOnce(&str) -> Result<(), ErrorServer>,
+    {
+        let target = match (&self.target, &self.port) {
+            (Some(target), None | Some(Ok(_))) => target.clone(),
+            _ => {
+                return Ok(vec![Reply::err_need_more_params(
+                    None,
+                    vec!["CONNECT".to_string()],
+                )])
+            }
+        };
+        let oper = match nick_sender.search(self.from.clone())? {
+            Some(oper) if oper.is_op_connection() => oper,
+            _ => return Ok(vec![Reply::err_no_privileges(None)]),
+        };
+        let linked = match spanning_tree.lock() {
+            Ok(st) => match &self.remote {
+                Some(remote) if *remote != st.get_root_name() => {
+                    if !st.contains(remote.clone()) {
+                        return Ok(vec![Reply::err_no_such_server(None, vec![remote.clone()])]);
+                    }
+                    let origin = oper.get_servername().unwrap_or_default().to_string();
+                    send_to_servers(&st, &self.msg, std::slice::from_ref(remote), &origin)?;
+                    return Ok(vec![Reply::rpl_none()]);
+                }
+                _ => st.is_linked_to(&target),
+            },
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let address = match config.lock() {
+            Ok(config) => config.find_link(&target).map(|link| link.address.clone()),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let address = match address {
+            Some(address) => address,
+            None => return Ok(vec![Reply::err_no_such_server(None, vec![target])]),
+        };
+        if linked {
+            let text = format!("*** {target} is already linked");
+            self.notice(&oper, nick_sender, spanning_tree, &text)?;
+            return Ok(vec![Reply::rpl_none()]);
+        }
+        let address = match (&self.port, address.rsplit_once(':')) {
+            (Some(Ok(port)), Some((host, _))) => format!("{host}:{port}"),
+            _ => address,
+        };
+        if let Err(e) = connect(&address) {
+            let text = format!("*** Connect to {target} at {address} failed ({e:?})");
+            self.notice(&oper, nick_sender, spanning_tree, &text)?;
+        }
+        Ok(vec![Reply::rpl_none()])
+    }
+
+    // Tells the operator how the CONNECT ended. An operator of
+    // another server gets the NOTICE through the spanning tree.
+    fn notice(
+        &self,
+        oper: &Connection,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        text: &str,
+    ) -> Result<(), ErrorServer> {
+        let st = match spanning_tree.lock() {
+            Ok(st) => st,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let root = st.get_root_name();
+        let notice = format!(":{root} NOTICE {} :{text}", self.from);
+        match oper.get_servername() {
+            // the operator may be gone already
+            _ if oper.is_local() => {
+                let _ = write_message_to(&notice, &mut oper.clone());
+            }
+            Some(servername) => {
+                let notice = to_uids(&notice, nick_sender);
+                send_to_servers(&st, &notice, &[servername.to_string()], &root)?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::TcpStream,
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        command::fixtures::{add_user, link_server, spanning_tree},
+        config::ServerConfig,
+        database::RepositoryHandler,
+        error::error_server::ErrorServer,
+        parser::message::Message,
+        reply::{code::Code, Reply},
+        server_comunication::spanning_tree::SpanningTree,
+        utils::read_frame_from,
+    };
+
+    use super::ConnectMsg;
+
+    // Runs the message and returns the replies and the address connected to.
+    fn connect(msg: &str, nickname: &str, oper: bool) -> (Vec<Reply>, Option<String>) {
+        let (replies, address, _) = connect_in(msg, nickname, oper, &spanning_tree());
+        (replies, address)
+    }
+
+    // Runs the message in the given network, and returns the replies,
+    // the address connected to and the other end of the user's stream.
+    fn connect_in(
+        msg: &str,
+        nickname: &str,
+        oper: bool,
+        st: &Arc<Mutex<SpanningTree>>,
+    ) -> (Vec<Reply>, Option<String>, TcpStream) {
+        let nicks = RepositoryHandler::new().unwrap();
+        let other_end = add_user(&nicks, nickname, oper);
+        let config = ServerConfig::from_str("link = server2 127.0.0.1:8081").unwrap();
+        let mut address = None;
+        let replies = ConnectMsg::new(&Message::from_str(msg).unwrap())
+            .unwrap()
+            .response(
+                &nicks.get_channels(),
+                st,
+                &Arc::new(Mutex::new(config)),
+                |to| {
+                    address = Some(to.to_string());
+                    Err(ErrorServer::TcpFail)
+                },
+            )
+            .unwrap();
+        nicks.stop().unwrap();
+        (replies, address, other_end)
+    }
+
+    #[test]
+    fn oper_connects_to_the_configured_address() {
+        let (replies, address) = connect(":oper CONNECT server2", "oper", true);
+        assert_eq!(replies, vec![Reply::rpl_none()]);
+        assert_eq!(address, Some("127.0.0.1:8081".to_string()));
+    }
+
+    #[test]
+    fn port_replaces_the_configured_one() {
+        let (_, address) = connect(":oper CONNECT server2 9000 server", "oper", true);
+        assert_eq!(address, Some("127.0.0.1:9000".to_string()));
+    }
+
+    #[test]
+    fn unknown_link_is_no_such_server() {
+        let (replies, address) = connect(":oper CONNECT server9", "oper", true);
+        assert_eq!(replies[0].code(), Code::ErrNosuchserver);
+        assert!(address.is_none());
+    }
+
+    #[test]
+    fn unknown_remote_server_is_no_such_server() {
+        let (replies, address) = connect(":oper CONNECT server2 8081 server9", "oper", true);
+        assert_eq!(replies[0].code(), Code::ErrNosuchserver);
+        assert!(address.is_none());
+    }
+
+    #[test]
+    fn user_cant_connect() {
+        let (replies, address) = connect(":user CONNECT server2", "user", false);
+        assert_eq!(replies[0].code(), Code::ErrNoprivileges);
+        assert!(address.is_none());
+    }
+
+    #[test]
+    fn invalid_port_needs_more_params() {
+        let (replies, _) = connect(":oper CONNECT server2 port", "oper", true);
+        assert_eq!(replies[0].code(), Code::ErrNeedmoreparams);
+    }
+
+    #[test]
+    fn failed_connect_is_told_to_the_oper() {
+        let (replies, _, mut other_end) =
+            connect_in(":oper CONNECT server2", "oper", true, &spanning_tree());
+        assert_eq!(replies, vec![Reply::rpl_none()]);
+        assert_eq!(
+            read_frame_from(&mut other_end).unwrap(),
+            ":server NOTICE oper :*** Connect to server2 at 127.0.0.1:8081 failed (TcpFail)"
+        );
+    }
+
+    #[test]
+    fn linked_server_is_told_to_the_oper() {
+        let st = spanning_tree();
+        let _link = link_server(&st, "server2");
+        let (replies, address, mut other_end) =
+            connect_in(":oper CONNECT server2", "oper", true, &st);
+        assert_eq!(replies, vec![Reply::rpl_none()]);
+        assert!(address.is_none());
+        assert_eq!(
+            read_frame_from(&mut other_end).unwrap(),
+            ":server NOTICE oper :*** server2 is already linked"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    server::shutdown::{ShutdownHandle, ShutdownRequest},
+    server_comunication::spanning_tree::SpanningTree,
+};
+
+use super::Command;
+
+///
+/// struct that implements the DIE and RESTART
+/// messages, that stop the server the
+/// operator is connected to
+///
+pub struct DieMsg {
+    from: String,
+    restart: bool,
+}
+
+impl DieMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let from = match msg.prefix() {
+            Some(u) => u,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        Ok(DieMsg {
+            from,
+            restart: msg.is_command(Command::Restart),
+        })
+    }
+
+    ///
+    /// Requests the shutdown of the server. Only the operators
+    /// connected to this server can stop it.
+    ///
+    pub fn response(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        shutdown: &ShutdownHandle,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let servername = match spanning_tree.lock() {
+            Ok(st) => st.get_root_name(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let allowed = match nick_sender.search(self.from.clone())? {
+            Some(c) => c.is_op_connection() && c.is_on_server(&servername),
+            None => false,
+        };
+        if !allowed {
+            return Ok(vec![Reply::err_no_privileges(None)]);
+        }
+        let request = match self.restart {
+            true => ShutdownRequest::restart(&format!("Server restarting by {}", self.from)),
+            false => ShutdownRequest::die(&format!("Server terminating by {}", self.from)),
+        };
+        shutdown.request(request)?;
+        Ok(vec![Reply::rpl_none()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        command::fixtures::{add_user, spanning_tree},
+        database::RepositoryHandler,
+        parser::message::Message,
+        reply::code::Code,
+        server::shutdown::{ShutdownHandle, ShutdownKind},
+    };
+
+    use super::DieMsg;
+
+    #[test]
+    fn operator_restarts_the_server() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let _other_end = add_user(&nicks, "nick", true);
+        let shutdown = ShutdownHandle::new();
+        let msg = Message::from_str(":nick RESTART").unwrap();
+        DieMsg::new(&msg)
+            .unwrap()
+            .response(&nicks.get_channels(), &spanning_tree(), &shutdown)
+            .unwrap();
+        assert_eq!(shutdown.wait().unwrap().kind, ShutdownKind::Restart);
+        nicks.stop().unwrap();
+    }
+
+    #[test]
+    fn user_cant_stop_the_server() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let _other_end = add_user(&nicks, "nick", false);
+        let shutdown = ShutdownHandle::new();
+        let msg = Message::from_str(":nick DIE").unwrap();
+        let replies = DieMsg::new(&msg)
+            .unwrap()
+            .response(&nicks.get_channels(), &spanning_tree(), &shutdown)
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::ErrNoprivileges);
+        assert!(!shutdown.is_requested());
+        nicks.stop().unwrap();
+    }
+}
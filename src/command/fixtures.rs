@@ -0,0 +1,65 @@
+use std::{
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    database::RepositoryHandler,
+    repository::{connection::Connection, traits::operations::Operations},
+    server_comunication::{
+        server::Server,
+        server_connection::ConnectionServer,
+        spanning_tree::{node::Node, SpanningTree},
+    },
+    user::User,
+};
+
+///
+/// returns a network with only this server, named "server".
+///
+pub fn spanning_tree() -> Arc<Mutex<SpanningTree>> {
+    let root = Node::new(Server::new("server".to_string(), None));
+    Arc::new(Mutex::new(SpanningTree::new(root, vec![])))
+}
+
+///
+/// adds a user of this server to the repository and returns
+/// the other end of its stream, which must be kept while the
+/// test writes to the user.
+///
+#[must_use]
+pub fn add_user(
+    nicks: &RepositoryHandler<String, Connection>,
+    nickname: &str,
+    oper: bool,
+) -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let user = User::new(nickname, "user", "host", "server", "real", "");
+    let mut connection = Connection::new(stream, user);
+    if oper {
+        connection.get_op_privileges();
+    }
+    nicks
+        .get_channels()
+        .add(nickname.to_string(), connection)
+        .unwrap();
+    listener.accept().unwrap().0
+}
+
+///
+/// links a server to the root of the network and returns the
+/// other end of its stream, which must be kept while the test
+/// writes to the link.
+///
+#[must_use]
+pub fn link_server(spanning_tree: &Arc<Mutex<SpanningTree>>, servername: &str) -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    spanning_tree
+        .lock()
+        .unwrap()
+        .set_connection(servername.to_string(), ConnectionServer::new(stream))
+        .unwrap();
+    listener.accept().unwrap().0
+}
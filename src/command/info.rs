@@ -0,0 +1,50 @@
+use crate::{
+    reply::Reply,
+    server::state::ServerState,
+    utils::{format_utc, unix_timestamp},
+};
+
+///
+/// struct that implements the INFO message, that answers
+/// with the version of the server and since when it runs:
+/// `INFO [<server>]`
+///
+pub struct InfoMsg;
+
+impl InfoMsg {
+    pub fn response(state: &ServerState) -> Vec<Reply> {
+        let uptime = unix_timestamp().saturating_sub(state.started);
+        let lines = [
+            format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            format!("Birth Date: {}", format_utc(state.started)),
+            format!(
+                "On-line for {} days, {:02}:{:02}:{:02}",
+                uptime / 86400,
+                uptime / 3600 % 24,
+                uptime / 60 % 60,
+                uptime % 60
+            ),
+        ];
+        let mut replies: Vec<Reply> = lines.iter().map(|line| Reply::rpl_info(line)).collect();
+        replies.push(Reply::rpl_end_of_info());
+        replies
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{config::ServerConfig, reply::code::Code, server::state::ServerState};
+
+    use super::InfoMsg;
+
+    #[test]
+    fn ends_with_end_of_info() {
+        let state = ServerState::new(ServerConfig::default()).unwrap();
+        let replies = InfoMsg::response(&state);
+        assert_eq!(replies[0].code(), Code::RplyInfo);
+        assert!(replies[2]
+            .to_string()
+            .starts_with("371 :On-line for 0 days, 00:00:0"));
+        assert_eq!(replies[3].to_string(), "374 :End of INFO list");
+    }
+}
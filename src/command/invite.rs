@@ -9,8 +9,12 @@ use crate::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
+    utils::unix_timestamp,
 };
 
+/// seconds an invite lets the user join, unless configured
+pub const DEFAULT_INVITE_EXPIRY: u64 = 3600;
+
 ///
 /// struct that implements the invite
 /// msg according to the irc protocol
@@ -52,13 +56,14 @@ impl InviteMsg {
     /// function that is responsible for
     /// responding to the invitation message
     /// given by a user who wants to invite
-    /// another to a channel within the server
-    ///
+    /// another to a channel within the server.
+    /// The invite expires after the given seconds
     ///
     pub fn response(
         &mut self,
         nick_sender: ClientChannel<String, Connection>,
         channel_sender: ClientChannel<String, Channel>,
+        expiry: u64,
     ) -> Result<Vec<Reply>, ErrorServer> {
         let mut replies = vec![];
         if let (true, true) = (self.channel.is_empty(), self.user.is_empty()) {
@@ -100,7 +105,9 @@ impl InviteMsg {
                     )]);
                 } // Reply ERR_CHANOPRIVSNEEDED
 
-                channel.invite_member(self.to.clone());
+                // a huge expiry makes the invite last forever
+                let expires_at = unix_timestamp().saturating_add(expiry);
+                channel.invite_member(self.to.clone(), expires_at);
                 channel_sender.update(channel.name.clone(), channel)?;
 
                 //RPL_AWAY
@@ -116,6 +123,30 @@ impl InviteMsg {
         }
         Ok(replies)
     }
+
+    ///
+    /// answers an INVITE without parameters with the
+    /// channels the user was invited to and can still join
+    ///
+    pub fn invite_list(
+        user: &str,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let now = unix_timestamp();
+        let mut channels: Vec<String> = channel_sender
+            .find_all()?
+            .iter()
+            .filter(|channel| channel.has_invite(user, now))
+            .map(|channel| channel.get_name())
+            .collect();
+        channels.sort();
+        let mut replies: Vec<Reply> = channels
+            .iter()
+            .map(|channel| Reply::rpl_invited_to(channel))
+            .collect();
+        replies.push(Reply::rpl_end_invited_to());
+        Ok(replies)
+    }
 }
 
 ///
@@ -132,10 +163,17 @@ pub fn send_message_to(msg: &str, client: &mut dyn Write) -> Result<(), ErrorSer
 
 #[cfg(test)]
 mod test {
+    use crate::channel::Channel;
     use crate::command::invite::{send_message_to, InviteMsg};
     use crate::command::Command;
+    use crate::database::RepositoryHandler;
     use crate::parser::message::Message;
+    use crate::repository::connection::Connection;
+    use crate::repository::traits::operations::Operations;
+    use crate::user::User;
+    use crate::utils::unix_timestamp;
     use std::io::Write;
+    use std::str::FromStr;
 
     struct MockClient {
         msg: String,
@@ -184,6 +222,76 @@ mod test {
         assert_eq!(msg.to, "pepe".to_string());
     }
 
+    #[test]
+    fn invites_are_listed_until_they_expire() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let juan = User::new("juan", "juan", "host", "server", "Juan", "");
+        nicks
+            .get_channels()
+            .add("juan".to_string(), Connection::connection_away_server(juan))
+            .unwrap();
+        for name in ["#canal", "#otro"] {
+            let mut channel = Channel::new(name.to_string(), "ana".to_string());
+            channel.modify_channel_flag("+i", None);
+            channels
+                .get_channels()
+                .add(name.to_string(), channel)
+                .unwrap();
+        }
+        for (line, expiry) in [
+            (":ana INVITE juan #otro", 60),
+            (":ana INVITE juan #canal", 0),
+        ] {
+            InviteMsg::new(Message::from_str(line).unwrap())
+                .unwrap()
+                .response(nicks.get_channels(), channels.get_channels(), expiry)
+                .unwrap();
+        }
+
+        let replies = InviteMsg::invite_list("juan", &channels.get_channels()).unwrap();
+        let lines: Vec<String> = replies.iter().map(|reply| reply.to_string()).collect();
+        assert_eq!(lines, vec!["336 #otro", "337 :End of /INVITE list"]);
+        let channel = channels
+            .get_channels()
+            .search("#otro".to_string())
+            .unwrap()
+            .unwrap();
+        assert!(!channel.has_invite("juan", unix_timestamp() + 60));
+        nicks.stop().unwrap();
+        channels.stop().unwrap();
+    }
+
+    #[test]
+    fn huge_expiry_never_expires() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let juan = User::new("juan", "juan", "host", "server", "Juan", "");
+        nicks
+            .get_channels()
+            .add("juan".to_string(), Connection::connection_away_server(juan))
+            .unwrap();
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channel.modify_channel_flag("+i", None);
+        channels
+            .get_channels()
+            .add("#canal".to_string(), channel)
+            .unwrap();
+        InviteMsg::new(Message::from_str(":ana INVITE juan #canal").unwrap())
+            .unwrap()
+            .response(nicks.get_channels(), channels.get_channels(), u64::MAX)
+            .unwrap();
+
+        let channel = channels
+            .get_channels()
+            .search("#canal".to_string())
+            .unwrap()
+            .unwrap();
+        assert!(channel.has_invite("juan", u64::MAX - 1));
+        nicks.stop().unwrap();
+        channels.stop().unwrap();
+    }
+
     #[test]
     fn send_message_to_client() {
         let mut client = MockClient {
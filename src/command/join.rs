@@ -1,12 +1,14 @@
 use crate::{
     channel::Channel,
-    command::topic::TopicMsg,
+    command::{topic::TopicMsg, Command},
     error::{error_channel::ErrorChannel, error_server::ErrorServer},
     parser::message::Message,
     reply::Reply,
     repository::{
-        repository_channel::client_channel::ClientChannel, traits::operations::Operations,
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
     },
+    utils::unix_timestamp,
 };
 
 pub const COMMA_U8: u8 = b',';
@@ -44,6 +46,50 @@ impl JoinMsg {
         })
     }
 
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    ///
+    /// keeps only the channels for which the given
+    /// function returns true, with their keys. The
+    /// empty keys left at the end are dropped
+    ///
+    pub fn retain_channels<F: FnMut(&str) -> bool>(&mut self, mut f: F) {
+        let keys = self.keys.take().unwrap_or_default();
+        let mut kept_keys = vec![];
+        let mut kept = vec![];
+        for (i, channel) in self.channels.drain(..).enumerate() {
+            if f(&channel) {
+                if let Some(key) = keys.get(i) {
+                    kept_keys.push(key.clone());
+                }
+                kept.push(channel);
+            }
+        }
+        while kept_keys.last().is_some_and(|key| key.is_empty()) {
+            kept_keys.pop();
+        }
+        self.channels = kept;
+        self.keys = (!kept_keys.is_empty()).then_some(kept_keys);
+    }
+
+    ///
+    /// returns the JOIN of the channels the
+    /// message has, to send it to the other servers
+    ///
+    pub fn message(&self) -> Message {
+        let mut parameters = vec![self.channels.join(",")];
+        if let Some(keys) = &self.keys {
+            parameters.push(keys.join(","));
+        }
+        Message::new(
+            Some(format!(":{}", self.user)),
+            Command::Join,
+            Some(parameters),
+        )
+    }
+
     ///
     /// function that responds to the private
     /// message sent by the user and
@@ -51,6 +97,7 @@ impl JoinMsg {
     ///
     pub fn response(
         &self,
+        nick_sender: &ClientChannel<String, Connection>,
         channel_sender: ClientChannel<String, Channel>,
     ) -> Result<Vec<Reply>, ErrorServer> {
         if self.channels.is_empty() {
@@ -59,6 +106,11 @@ impl JoinMsg {
                 vec!["JOIN".to_string()],
             )]);
         }
+        // the bans of the channels are matched against the whole mask
+        let mask = match nick_sender.search(self.user.clone())? {
+            Some(connection) => connection.get_mask(),
+            None => self.user.clone(),
+        };
         let mut replies = vec![];
         let iter_keys = match &self.keys {
             Some(keys) => keys
@@ -77,7 +129,7 @@ impl JoinMsg {
         for (ch, key) in iter {
             //Busco para saber si existe
             match channel_sender.search(ch.to_owned())? {
-                Some(mut c) => match c.add_member(&self.user, key) {
+                Some(mut c) => match c.add_member(&mask, key) {
                     Ok(_) => channel_sender.update(c.name.clone(), c.clone()),
                     Err(ErrorChannel::BadKey) => {
                         replies.push(Reply::err_bad_chan_key(vec![ch.clone()]));
@@ -95,13 +147,39 @@ impl JoinMsg {
                         replies.push(Reply::err_invite_only_chan(ch.clone()));
                         continue;
                     }
+                    Err(ErrorChannel::JoinThrottled) => {
+                        replies.push(Reply::err_unavail_resource(ch));
+                        continue;
+                    }
                 },
-                None => channel_sender.add(ch.clone(), Channel::new(ch.clone(), self.user.clone())),
+                None => {
+                    let mut channel = Channel::new(ch.clone(), self.user.clone());
+                    channel.set_created_at(unix_timestamp());
+                    channel_sender.add(ch.clone(), channel)
+                }
             }?;
+        }
+        Ok(replies)
+    }
 
+    ///
+    /// answers the topic of each channel the user is in,
+    /// once the join was completed
+    ///
+    pub fn topic_replies(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let mut replies = vec![];
+        for ch in &self.channels {
+            match channel_sender.search(ch.clone())? {
+                Some(channel) if channel.has_member(&self.user) => {}
+                _ => continue,
+            }
             //RPL_TOPIC
             let mut msg = TopicMsg::new_join(self.user.clone(), ch.clone())?;
-            replies.append(&mut msg.response(channel_sender.clone())?);
+            replies.append(&mut msg.response(nick_sender, channel_sender.clone())?);
         }
         Ok(replies)
     }
@@ -143,10 +221,28 @@ impl JoinMsg {
 
 #[cfg(test)]
 mod test {
+    use std::str::FromStr;
+
     use crate::command::join::JoinMsg;
     use crate::command::Command;
     use crate::parser::message::Message;
 
+    #[test]
+    fn channels_are_left_out_with_their_keys() {
+        let msg = Message::from_str(":ana JOIN #a,#b,#c uno,dos").unwrap();
+        let mut join = JoinMsg::new(&msg).unwrap();
+        join.retain_channels(|channel| channel != "#a");
+        assert_eq!(join.channels(), ["#b", "#c"]);
+        assert_eq!(join.message().to_string(), ":ana JOIN #b,#c dos");
+
+        let msg = Message::from_str(":ana JOIN #a,#b,#c uno,,tres").unwrap();
+        let mut join = JoinMsg::new(&msg).unwrap();
+        join.retain_channels(|channel| channel != "#c");
+        assert_eq!(join.message().to_string(), ":ana JOIN #a,#b uno");
+        join.retain_channels(|channel| channel != "#a");
+        assert_eq!(join.message().to_string(), ":ana JOIN #b");
+    }
+
     //TODO: Testear fuerte -> esta bien parseado? que se espera ?
     #[test]
     fn get_key_from_msg() {
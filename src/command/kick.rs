@@ -67,7 +67,7 @@ impl KickMsg {
             )]);
         }
 
-        if !channel.user_can_kick_others(&self.user) {
+        if !channel.user_can_kick(&self.user, user_to_kick) {
             return Ok(vec![Reply::err_chan_o_privs_needed(
                 None,
                 vec![self.user.clone()],
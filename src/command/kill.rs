@@ -14,8 +14,7 @@ use crate::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
-    server_comunication::spanning_tree::SpanningTree,
-    utils::write_message_to,
+    server_comunication::{info_sender::send_to_all_servers, spanning_tree::SpanningTree},
 };
 
 pub struct Kill {
@@ -56,12 +55,9 @@ impl Kill {
                             }
                         }
                         // mando un kill a todos los servers de los que tengo conn
-                        let msg = Message::new(None, super::Command::Kill, Some(vec![self.user]));
-                        for server in st.get_servers() {
-                            if let Some(mut s_conn) = server.get_connection() {
-                                write_message_to(&msg.to_string(), &mut s_conn)?;
-                            }
-                        }
+                        let target = conn.get_uid().unwrap_or(&self.user).to_string();
+                        let msg = Message::new(None, super::Command::Kill, Some(vec![target]));
+                        send_to_all_servers(&st, msg, &st.get_root_name())?;
                     }
                 }
                 Err(_) => return Err(ErrorServer::LockedResource),
@@ -0,0 +1,308 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    channel::Channel,
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    utils::{unix_timestamp, write_message_to},
+};
+
+/// seconds a user waits between two KNOCKs
+pub const KNOCK_USER_DELAY: u64 = 300;
+/// seconds between two KNOCKs on the same channel
+pub const KNOCK_CHANNEL_DELAY: u64 = 60;
+
+const DEFAULT_KNOCK_MESSAGE: &str = "has asked for an invite";
+
+///
+/// Remembers when each user and each channel knocked last,
+/// to refuse the KNOCKs that come too often.
+///
+#[derive(Debug, Default)]
+pub struct KnockLimiter {
+    users: HashMap<String, u64>,
+    channels: HashMap<String, u64>,
+}
+
+impl KnockLimiter {
+    pub fn new() -> Self {
+        KnockLimiter::default()
+    }
+
+    ///
+    /// Records the KNOCK of the user on the channel, unless one
+    /// of them knocked too recently.
+    ///
+    /// # Returns
+    /// the limit reached, `user` or `channel`, if the KNOCK is refused.
+    pub fn allow(&mut self, user: &str, channel: &str, now: u64) -> Option<&'static str> {
+        self.users
+            .retain(|_, knocked| now < *knocked + KNOCK_USER_DELAY);
+        self.channels
+            .retain(|_, knocked| now < *knocked + KNOCK_CHANNEL_DELAY);
+        let (user, channel) = (user.to_lowercase(), channel.to_lowercase());
+        if self.users.contains_key(&user) {
+            return Some("user");
+        }
+        if self.channels.contains_key(&channel) {
+            return Some("channel");
+        }
+        self.users.insert(user, now);
+        self.channels.insert(channel, now);
+        None
+    }
+}
+
+///
+/// struct that implements the KNOCK message, with which a user
+/// asks the operators of an invite-only channel for an invite:
+///
+/// `KNOCK <channel> [<message>]`
+///
+pub struct KnockMsg {
+    user: String,
+    channel: String,
+    message: String,
+    forward: Option<Message>,
+}
+
+impl KnockMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let user = match msg.prefix() {
+            Some(u) => u,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        let channel = match msg.get_param_from_msg(0) {
+            Some(channel) => channel,
+            None => return Err(ErrorServer::BadQuery),
+        };
+        let message = msg
+            .get_param_from_msg(1)
+            .map(|message| {
+                message
+                    .strip_prefix(':')
+                    .unwrap_or(&message)
+                    .trim()
+                    .to_string()
+            })
+            .filter(|message| !message.is_empty())
+            .unwrap_or(DEFAULT_KNOCK_MESSAGE.to_string());
+        Ok(KnockMsg {
+            user,
+            channel,
+            message,
+            forward: None,
+        })
+    }
+
+    ///
+    /// returns the KNOCK to send to the other servers,
+    /// so they tell it to their operators of the channel
+    ///
+    pub fn forward(&self) -> Option<Message> {
+        self.forward.clone()
+    }
+
+    ///
+    /// Answers the KNOCK of a local user. Only a user out of an
+    /// invite-only channel that accepts KNOCKs and doesn't ban it
+    /// can knock, and no more often than the limiter allows.
+    ///
+    pub fn response(
+        &mut self,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        limiter: &Arc<Mutex<KnockLimiter>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let channel = match channel_sender.search(self.channel.clone())? {
+            Some(channel) => channel,
+            None => {
+                return Ok(vec![Reply::err_no_such_channel(
+                    None,
+                    vec![self.channel.clone()],
+                )])
+            }
+        };
+        if channel.has_member(&self.user) {
+            return Ok(vec![Reply::err_knock_on_chan(&self.channel)]);
+        }
+        if !channel.is_invite_only() {
+            return Ok(vec![Reply::err_chan_open(&self.channel)]);
+        }
+        if !channel.accepts_knocks() {
+            let reason = "Channel has disabled KNOCK";
+            return Ok(vec![Reply::err_cannot_knock(&self.channel, reason)]);
+        }
+        let mask = match nick_sender.search(self.user.clone())? {
+            Some(connection) => connection.get_mask(),
+            None => return Err(ErrorServer::UnreachableClient),
+        };
+        if channel.is_banned(&mask) {
+            let reason = "You are banned";
+            return Ok(vec![Reply::err_cannot_knock(&self.channel, reason)]);
+        }
+        let limit = match limiter.lock() {
+            Ok(mut limiter) => limiter.allow(&self.user, &self.channel, unix_timestamp()),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        if let Some(limit) = limit {
+            return Ok(vec![Reply::err_too_many_knock(&self.channel, limit)]);
+        }
+        self.notify_operators(nick_sender, channel_sender)?;
+        let forward = format!(":{} KNOCK {} :{}", self.user, self.channel, self.message);
+        self.forward = Some(Message::from_str(&forward)?);
+        Ok(vec![Reply::rpl_knock_delivered(&self.channel)])
+    }
+
+    ///
+    /// tells the KNOCK to the operators of
+    /// the channel connected to this server
+    ///
+    pub fn notify_operators(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<(), ErrorServer> {
+        let channel = match channel_sender.search(self.channel.clone())? {
+            Some(channel) => channel,
+            None => return Ok(()),
+        };
+        let mask = match nick_sender.search(self.user.clone())? {
+            Some(connection) => connection.get_mask(),
+            None => self.user.clone(),
+        };
+        let reply = Reply::rpl_knock(&self.channel, &mask, &self.message);
+        for operator in channel.get_channel_operators() {
+            if let Some(mut connection) = nick_sender.search(operator)? {
+                if connection.is_local() {
+                    write_message_to(&reply, &mut connection)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Read,
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        channel::Channel,
+        command::fixtures::add_user,
+        database::RepositoryHandler,
+        parser::message::Message,
+        repository::{connection::Connection, traits::operations::Operations},
+        user::User,
+    };
+
+    use super::{KnockLimiter, KnockMsg, KNOCK_CHANNEL_DELAY, KNOCK_USER_DELAY};
+
+    #[test]
+    fn knocks_are_limited_by_user_and_by_channel() {
+        let mut limiter = KnockLimiter::new();
+        assert_eq!(limiter.allow("juan", "#canal", 0), None);
+        assert_eq!(limiter.allow("JUAN", "#otro", 10), Some("user"));
+        assert_eq!(limiter.allow("luis", "#Canal", 10), Some("channel"));
+        assert_eq!(limiter.allow("luis", "#canal", KNOCK_CHANNEL_DELAY), None);
+        assert_eq!(limiter.allow("juan", "#otro", KNOCK_USER_DELAY), None);
+    }
+
+    #[test]
+    fn operators_are_told_of_the_knock() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let mut operator = add_user(&nicks, "ana", false);
+        let juan = User::new("juan", "juan", "host", "server", "Juan", "");
+        nicks
+            .get_channels()
+            .add("juan".to_string(), Connection::connection_away_server(juan))
+            .unwrap();
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channel.modify_channel_flag("+i", None);
+        channels
+            .get_channels()
+            .add("#canal".to_string(), channel)
+            .unwrap();
+        let limiter = Arc::new(Mutex::new(KnockLimiter::new()));
+
+        let msg = Message::from_str(":juan KNOCK #canal :let me in").unwrap();
+        let mut knock = KnockMsg::new(&msg).unwrap();
+        let replies = knock
+            .response(&nicks.get_channels(), &channels.get_channels(), &limiter)
+            .unwrap();
+        assert_eq!(
+            replies[0].to_string(),
+            "711 #canal :Your KNOCK has been delivered"
+        );
+        assert!(knock.forward().is_some());
+        let mut line = [0; 512];
+        operator.read_exact(&mut line).unwrap();
+        let line = String::from_utf8_lossy(&line);
+        assert_eq!(line.trim(), "710 #canal juan!juan@host :let me in");
+
+        let mut again = KnockMsg::new(&msg).unwrap();
+        let replies = again
+            .response(&nicks.get_channels(), &channels.get_channels(), &limiter)
+            .unwrap();
+        assert_eq!(replies[0].to_string(), "712 #canal :Too many KNOCKs (user)");
+        nicks.stop().unwrap();
+        channels.stop().unwrap();
+    }
+
+    #[test]
+    fn knock_is_refused_on_open_or_closed_channels() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let mut open = Channel::new("#abierto".to_string(), "ana".to_string());
+        let mut closed = Channel::new("#cerrado".to_string(), "ana".to_string());
+        closed.modify_channel_flag("+i", None);
+        closed.modify_channel_flag("+K", None);
+        open.modify_channel_flag("+n", None);
+        channels
+            .get_channels()
+            .add("#abierto".to_string(), open)
+            .unwrap();
+        channels
+            .get_channels()
+            .add("#cerrado".to_string(), closed)
+            .unwrap();
+        let limiter = Arc::new(Mutex::new(KnockLimiter::new()));
+        let knock = |line: &str| {
+            let msg = Message::from_str(line).unwrap();
+            KnockMsg::new(&msg)
+                .unwrap()
+                .response(&nicks.get_channels(), &channels.get_channels(), &limiter)
+                .unwrap()[0]
+                .to_string()
+        };
+
+        assert_eq!(
+            knock(":juan KNOCK #abierto"),
+            "713 #abierto :Channel is open"
+        );
+        assert_eq!(
+            knock(":juan KNOCK #cerrado"),
+            "480 :Cannot knock on #cerrado (Channel has disabled KNOCK)"
+        );
+        assert_eq!(
+            knock(":ana KNOCK #cerrado"),
+            "714 #cerrado :You are already on that channel"
+        );
+        nicks.stop().unwrap();
+        channels.stop().unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    error::error_server::ErrorServer, parser::message::Message, reply::Reply,
+    server_comunication::spanning_tree::SpanningTree, utils::mask_matches,
+};
+
+///
+/// struct that implements the LINKS message:
+/// `LINKS [[remote server] <server mask>]`
+///
+/// Every server knows the whole network, so
+/// the remote server is answered by this one.
+///
+pub struct LinksMsg {
+    mask: Option<String>,
+}
+
+impl LinksMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let mask = match msg.parameters() {
+            Some(parameters) => parameters.last().cloned(),
+            None => None,
+        };
+        Ok(LinksMsg { mask })
+    }
+
+    ///
+    /// Lists the servers that match the mask, with the
+    /// server they are reached through and their hopcount.
+    ///
+    pub fn response(
+        &self,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let mask = self.mask.clone().unwrap_or_else(|| "*".to_string());
+        let hopcounts = match spanning_tree.lock() {
+            Ok(st) => st.get_hopcounts(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let mut replies: Vec<Reply> = hopcounts
+            .into_iter()
+            .filter(|(server, _, _)| mask_matches(&mask, server))
+            .map(|(server, uplink, hopcount)| Reply::rpl_links(server, uplink, hopcount))
+            .collect();
+        replies.push(Reply::rpl_end_of_links(mask));
+        Ok(replies)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        parser::message::Message,
+        server_comunication::{
+            server::Server,
+            spanning_tree::{edge::Edge, node::Node, SpanningTree},
+        },
+    };
+
+    use super::LinksMsg;
+
+    fn links(msg: &str) -> Vec<String> {
+        let node1 = Node::new(Server::new("server1".to_string(), None));
+        let node2 = Node::new(Server::new("server2".to_string(), None));
+        let node3 = Node::new(Server::new("hub".to_string(), None));
+        let edges = vec![
+            Edge::new(node1.clone(), node2.clone(), 1),
+            Edge::new(node2, node3, 1),
+        ];
+        let st = Arc::new(Mutex::new(SpanningTree::new(node1, edges)));
+        LinksMsg::new(&Message::from_str(msg).unwrap())
+            .unwrap()
+            .response(&st)
+            .unwrap()
+            .iter()
+            .map(|r| r.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lists_every_server() {
+        assert_eq!(
+            links(":alice LINKS"),
+            vec![
+                "364 server1 server1 :0 server1",
+                "364 server2 server1 :1 server2",
+                "364 hub server2 :2 hub",
+                "365 * :End of /LINKS list",
+            ]
+        );
+    }
+
+    #[test]
+    fn mask_filters_the_servers() {
+        assert_eq!(
+            links(":alice LINKS server1 serv*2"),
+            vec![
+                "364 server2 server1 :1 server2",
+                "365 serv*2 :End of /LINKS list",
+            ]
+        );
+    }
+}
@@ -6,10 +6,109 @@ use crate::{
     repository::{
         repository_channel::client_channel::ClientChannel, traits::operations::Operations,
     },
+    utils::{mask_matches, unix_timestamp},
 };
 
 pub const COMMA_U8: u8 = b',';
 
+// channels asked to the repository at a time, so a long
+// listing doesn't keep it from answering other queries
+const LIST_PAGE: usize = 50;
+const SECONDS_PER_MINUTE: u64 = 60;
+
+///
+/// returns the ISUPPORT tokens that tell the
+/// users the filters accepted by LIST
+///
+pub fn isupport_tokens() -> Vec<String> {
+    vec!["ELIST=CMNTU".to_string(), "SAFELIST".to_string()]
+}
+
+///
+/// Condition a channel must meet to be listed, given as one
+/// of the comma separated parameters of LIST. The ages are
+/// in minutes, as the ELIST extensions define them.
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum ListFilter {
+    /// a name or a mask of names, `#rust*`
+    Name(String),
+    /// a mask of names to leave out, `!#rust*`
+    NotName(String),
+    /// more users than the amount, `>n`
+    MoreUsers(usize),
+    /// fewer users than the amount, `<n`
+    FewerUsers(usize),
+    /// a mask of the topic, `T:*rust*`
+    Topic(String),
+    /// created less than the minutes ago, `C<n`
+    CreatedWithin(u64),
+    /// created more than the minutes ago, `C>n`
+    CreatedBefore(u64),
+    /// topic set less than the minutes ago, `T<n`
+    TopicWithin(u64),
+    /// topic set more than the minutes ago, `T>n`
+    TopicBefore(u64),
+}
+
+impl ListFilter {
+    ///
+    /// parses a parameter of LIST, returns
+    /// None if the amount of a filter is invalid
+    ///
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut chars = value.chars();
+        let filter = match (chars.next()?, chars.next()) {
+            ('>', _) => ListFilter::MoreUsers(value[1..].parse().ok()?),
+            ('<', _) => ListFilter::FewerUsers(value[1..].parse().ok()?),
+            ('!', _) => ListFilter::NotName(value[1..].to_string()),
+            ('T' | 't', Some(':')) => ListFilter::Topic(value[2..].to_string()),
+            ('C' | 'c', Some('<')) => ListFilter::CreatedWithin(value[2..].parse().ok()?),
+            ('C' | 'c', Some('>')) => ListFilter::CreatedBefore(value[2..].parse().ok()?),
+            ('T' | 't', Some('<')) => ListFilter::TopicWithin(value[2..].parse().ok()?),
+            ('T' | 't', Some('>')) => ListFilter::TopicBefore(value[2..].parse().ok()?),
+            _ => ListFilter::Name(value.to_string()),
+        };
+        Some(filter)
+    }
+
+    ///
+    /// returns true if the channel, as the user sees it,
+    /// meets the condition at the given time
+    ///
+    pub fn matches(&self, channel: &Channel, user: &str, now: u64) -> bool {
+        let name = channel.get_status_name(user);
+        let topic = match channel.is_priv() && !channel.has_member(user) {
+            true => None,
+            false => channel.topic_info(),
+        };
+        let minutes_since = |time: u64| now.saturating_sub(time) / SECONDS_PER_MINUTE;
+        match self {
+            ListFilter::Name(mask) => mask_matches(mask, &name),
+            ListFilter::NotName(mask) => !mask_matches(mask, &name),
+            ListFilter::MoreUsers(amount) => channel.member_amount() > *amount,
+            ListFilter::FewerUsers(amount) => channel.member_amount() < *amount,
+            ListFilter::Topic(mask) => topic.is_some_and(|t| mask_matches(mask, &t.text)),
+            ListFilter::CreatedWithin(minutes) => minutes_since(channel.created_at()) < *minutes,
+            ListFilter::CreatedBefore(minutes) => minutes_since(channel.created_at()) > *minutes,
+            ListFilter::TopicWithin(minutes) => {
+                topic.is_some_and(|t| minutes_since(t.set_at) < *minutes)
+            }
+            ListFilter::TopicBefore(minutes) => {
+                topic.is_some_and(|t| minutes_since(t.set_at) > *minutes)
+            }
+        }
+    }
+
+    // Names without wildcards are looked up instead of matched.
+    fn exact_name(&self) -> Option<&str> {
+        match self {
+            ListFilter::Name(name) if !name.contains(['*', '?']) => Some(name),
+            _ => None,
+        }
+    }
+}
+
 ///
 /// struct that implements a list message
 /// according to what is established by the
@@ -18,7 +117,7 @@ pub const COMMA_U8: u8 = b',';
 ///
 pub struct ListMsg {
     user: String,
-    channels: Option<Vec<String>>,
+    filters: Vec<ListFilter>,
 }
 
 impl ListMsg {
@@ -33,7 +132,7 @@ impl ListMsg {
         };
         Ok(ListMsg {
             user,
-            channels: Self::get_channels_from_msg(&msg),
+            filters: Self::get_filters_from_msg(&msg),
         })
     }
 
@@ -46,16 +145,23 @@ impl ListMsg {
         &self,
         channel_sender: ClientChannel<String, Channel>,
     ) -> Result<Vec<Reply>, ErrorServer> {
-        //Itera por los canales y crea un vector de canales
-        let (mut list_channels, is_list_all) = match &self.channels {
-            None => (channel_sender.find_all()?, true),
-            Some(channels) => (Self::find_channels(channels, &channel_sender)?, false),
+        let names: Option<Vec<String>> = match self.filters.is_empty() {
+            true => None,
+            false => self
+                .filters
+                .iter()
+                .map(|filter| filter.exact_name().map(|name| name.to_string()))
+                .collect(),
         };
-
-        list_channels = self.filter_channels(list_channels, &self.user);
-
         //Creo el response
-        let response = self.create_response(list_channels, is_list_all);
+        let response = match names {
+            Some(names) => {
+                let list_channels = Self::find_channels(&names, &channel_sender)?;
+                let list_channels = self.filter_channels(list_channels, &self.user);
+                self.create_response(list_channels, false)
+            }
+            None => self.list_by_pages(&channel_sender)?,
+        };
         // Se esta buscando así mismo.
         let mut replies = vec![Reply::rply_list_start(None)];
 
@@ -70,6 +176,42 @@ impl ListMsg {
         Ok(replies)
     }
 
+    // Goes through every channel, a page at a time,
+    // keeping the ones that meet the filters.
+    fn list_by_pages(
+        &self,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let now = unix_timestamp();
+        let mut response = vec![];
+        let mut after = None;
+        loop {
+            let page = channel_sender.find_page(after, LIST_PAGE)?;
+            let last_page = page.len() < LIST_PAGE;
+            after = page.last().map(|channel| channel.get_name());
+            let listed: Vec<Channel> = page
+                .into_iter()
+                .filter(|channel| self.is_listed(channel, now))
+                .collect();
+            let listed = self.filter_channels(listed, &self.user);
+            response.append(&mut self.create_response(listed, true));
+            if last_page {
+                return Ok(response);
+            }
+        }
+    }
+
+    // A channel is listed if it matches any of the names given,
+    // when there are some, and every other filter.
+    fn is_listed(&self, channel: &Channel, now: u64) -> bool {
+        let (names, others): (Vec<&ListFilter>, Vec<&ListFilter>) = self
+            .filters
+            .iter()
+            .partition(|filter| matches!(filter, ListFilter::Name(_)));
+        let matches = |filter: &&ListFilter| filter.matches(channel, &self.user, now);
+        (names.is_empty() || names.iter().any(matches)) && others.iter().all(matches)
+    }
+
     fn filter_channels(&self, channels: Vec<Channel>, user: &str) -> Vec<Channel> {
         let mut vec = Vec::new();
         for ch in channels {
@@ -111,24 +253,21 @@ impl ListMsg {
 
     ///
     /// function that is responsible
-    /// for parsing the channels contained
-    /// in the list message
+    /// for parsing the channels and the
+    /// filters contained in the list message
     ///
-    fn get_channels_from_msg(msg: &Message) -> Option<Vec<String>> {
-        //Parsear los canales
-        let channels = msg.get_param_from_msg(0)?;
-
-        let split = channels.trim().split(COMMA_U8 as char);
-        let mut list_channels = vec![];
-
-        for value in split {
-            //Validar si comienzan con & y #.
-            if !value.is_empty() && (value.contains('&') || value.contains('#')) {
-                list_channels.push(value.to_string());
-            }
-        }
+    fn get_filters_from_msg(msg: &Message) -> Vec<ListFilter> {
+        let filters = match msg.get_param_from_msg(0) {
+            Some(filters) => filters,
+            None => return vec![],
+        };
 
-        Some(list_channels)
+        filters
+            .trim()
+            .split(COMMA_U8 as char)
+            .filter(|value| !value.is_empty())
+            .filter_map(ListFilter::parse)
+            .collect()
     }
 
     fn info_all_channels(&self, channel: &Channel) -> String {
@@ -158,10 +297,74 @@ impl ListMsg {
 
 #[cfg(test)]
 mod test {
-    use crate::channel::Channel;
-    use crate::command::list::ListMsg;
+    use std::str::FromStr;
+
+    use crate::channel::{topic::Topic, Channel};
+    use crate::command::list::{ListFilter, ListMsg, LIST_PAGE};
     use crate::command::Command;
+    use crate::database::RepositoryHandler;
     use crate::parser::message::Message;
+    use crate::repository::traits::operations::Operations;
+    use crate::utils::unix_timestamp;
+
+    fn list(parameters: &str, channels: &RepositoryHandler<String, Channel>) -> Vec<String> {
+        let msg = Message::from_str(&format!(":ana LIST {parameters}")).unwrap();
+        let replies = ListMsg::new(msg)
+            .unwrap()
+            .response(channels.get_channels())
+            .unwrap();
+        // the first and the last replies are the start and the end
+        replies[1..replies.len() - 1]
+            .iter()
+            .map(|reply| reply.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn filters_are_parsed_from_the_parameters() {
+        let msg = Message::from_str(":ana LIST >2,<10,#ru*,!#rust-es,T:*rust*,C<5,T>3,>x").unwrap();
+        let filters = ListMsg::new(msg).unwrap().filters;
+        assert_eq!(
+            filters,
+            vec![
+                ListFilter::MoreUsers(2),
+                ListFilter::FewerUsers(10),
+                ListFilter::Name("#ru*".to_string()),
+                ListFilter::NotName("#rust-es".to_string()),
+                ListFilter::Topic("*rust*".to_string()),
+                ListFilter::CreatedWithin(5),
+                ListFilter::TopicBefore(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn channels_are_listed_by_pages_and_filtered() {
+        let channels = RepositoryHandler::new().unwrap();
+        let sender = channels.get_channels();
+        for number in 0..LIST_PAGE + 5 {
+            let name = format!("#canal{number:02}");
+            sender
+                .add(name.clone(), Channel::new(name, "ana".to_string()))
+                .unwrap();
+        }
+        let mut rust = Channel::new("#rust".to_string(), "ana".to_string());
+        rust.set_created_at(unix_timestamp());
+        rust.add_member("juan", &None).unwrap();
+        rust.set_topic(Topic::new("all about rust", "ana", unix_timestamp() - 600));
+        sender.add("#rust".to_string(), rust).unwrap();
+
+        assert_eq!(list("", &channels).len(), LIST_PAGE + 6);
+        assert_eq!(list("#canal5*", &channels).len(), 5);
+        assert_eq!(list("#canal0*,#canal1*,!#canal1?", &channels).len(), 10);
+        let lines = list(">1", &channels);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("#rust"));
+        assert_eq!(list("T:*RUST*,T>5", &channels), lines);
+        assert!(list("T<5", &channels).is_empty());
+        assert_eq!(list("C<5", &channels), lines);
+        channels.stop().unwrap();
+    }
 
     #[test]
     fn get_channel_from_msg() {
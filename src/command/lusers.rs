@@ -0,0 +1,165 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    channel::Channel,
+    error::error_server::ErrorServer,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    server::state::{ServerState, UserPeaks},
+    server_comunication::spanning_tree::SpanningTree,
+};
+
+///
+/// struct that implements the LUSERS message, that
+/// answers with the size of the network
+///
+pub struct LusersMsg;
+
+impl LusersMsg {
+    ///
+    /// Counts the users, operators, channels and servers.
+    /// The operators and channels are only shown if any.
+    ///
+    pub fn response(
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        state: &ServerState,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let (servername, servers, links) = match spanning_tree.lock() {
+            Ok(st) => (
+                st.get_root_name(),
+                st.get_number_of_nodes().max(1),
+                st.get_links().len(),
+            ),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let users = nick_sender.find_all()?;
+        let local = users.iter().filter(|c| c.is_on_server(&servername)).count();
+        let invisible = users.iter().filter(|c| c.is_invisible_connection()).count();
+        let opers = users.iter().filter(|c| c.is_op_connection()).count();
+        let channels = channel_sender.find_all()?.len();
+        let peaks = state.record_users(local, users.len())?;
+
+        let mut replies = vec![Reply::rpl_luser_client(
+            users.len() - invisible,
+            invisible,
+            servers,
+        )];
+        if opers > 0 {
+            replies.push(Reply::rpl_luser_op(opers));
+        }
+        if channels > 0 {
+            replies.push(Reply::rpl_luser_channels(channels));
+        }
+        replies.push(Reply::rpl_luser_me(local, links));
+        replies.push(Reply::rpl_local_users(local, peaks.local));
+        replies.push(Reply::rpl_global_users(users.len(), peaks.global));
+        Ok(replies)
+    }
+}
+
+///
+/// updates the peaks of users of the server
+/// with the ones registered now
+///
+pub fn record_user_peaks(
+    nick_sender: &ClientChannel<String, Connection>,
+    servername: &str,
+    state: &ServerState,
+) -> Result<UserPeaks, ErrorServer> {
+    let users = nick_sender.find_all()?;
+    let local = users.iter().filter(|c| c.is_on_server(servername)).count();
+    state.record_users(local, users.len())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        channel::Channel,
+        config::ServerConfig,
+        database::RepositoryHandler,
+        reply::code::Code,
+        repository::{connection::Connection, traits::operations::Operations},
+        server::state::ServerState,
+        server_comunication::{
+            server::Server,
+            spanning_tree::{edge::Edge, node::Node, SpanningTree},
+        },
+        user::User,
+    };
+
+    use super::LusersMsg;
+
+    fn add_user(nicks: &RepositoryHandler<String, Connection>, nickname: &str, server: &str) {
+        let user = User::new(nickname, nickname, "host", server, "real", "");
+        nicks
+            .get_channels()
+            .add(
+                nickname.to_string(),
+                Connection::connection_away_server(user),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn counts_the_network() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        add_user(&nicks, "alice", "server1");
+        add_user(&nicks, "bob", "server2");
+        channels
+            .get_channels()
+            .add(
+                "#c".to_string(),
+                Channel::new("#c".to_string(), "alice".to_string()),
+            )
+            .unwrap();
+        let node1 = Node::new(Server::new("server1".to_string(), None));
+        let node2 = Node::new(Server::new("server2".to_string(), None));
+        let st = SpanningTree::new(node1.clone(), vec![Edge::new(node1, node2, 1)]);
+        let state = ServerState::new(ServerConfig::default()).unwrap();
+
+        let replies = LusersMsg::response(
+            &nicks.get_channels(),
+            &channels.get_channels(),
+            &Arc::new(Mutex::new(st)),
+            &state,
+        )
+        .unwrap();
+        let replies: Vec<String> = replies.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            replies,
+            vec![
+                "251 :There are 2 users and 0 invisible on 2 servers",
+                "254 1 :channels formed",
+                "255 :I have 1 clients and 0 servers",
+                "265 1 1 :Current local users 1, max 1",
+                "266 2 2 :Current global users 2, max 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn lonely_server_counts_itself() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::<String, Channel>::new().unwrap();
+        let root = Node::new(Server::new("server1".to_string(), None));
+        let st = Arc::new(Mutex::new(SpanningTree::new(root, vec![])));
+        let state = ServerState::new(ServerConfig::default()).unwrap();
+
+        let replies =
+            LusersMsg::response(&nicks.get_channels(), &channels.get_channels(), &st, &state)
+                .unwrap();
+        assert_eq!(replies[0].code(), Code::RplyLuserClient);
+        assert_eq!(
+            replies[0].to_string(),
+            "251 :There are 0 users and 0 invisible on 1 servers"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    error::error_server::ErrorServer, reply::Reply,
+    server_comunication::spanning_tree::SpanningTree,
+};
+
+///
+/// struct that implements the MAP message, that draws
+/// the servers of the network as a tree, with the
+/// servers below the one they are reached through
+///
+pub struct MapMsg;
+
+impl MapMsg {
+    pub fn response(spanning_tree: &Arc<Mutex<SpanningTree>>) -> Result<Vec<Reply>, ErrorServer> {
+        let map = match spanning_tree.lock() {
+            Ok(st) => st.to_string(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let mut replies: Vec<Reply> = map.lines().map(Reply::rpl_map).collect();
+        replies.push(Reply::rpl_end_of_map());
+        Ok(replies)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::server_comunication::{
+        server::Server,
+        spanning_tree::{edge::Edge, node::Node, SpanningTree},
+    };
+
+    use super::MapMsg;
+
+    #[test]
+    fn draws_a_line_per_server() {
+        let node1 = Node::new(Server::new("server1".to_string(), None));
+        let node2 = Node::new(Server::new("server2".to_string(), None));
+        let st = SpanningTree::new(node1.clone(), vec![Edge::new(node1, node2, 1)]);
+        let replies: Vec<String> = MapMsg::response(&Arc::new(Mutex::new(st)))
+            .unwrap()
+            .iter()
+            .map(|r| r.to_string())
+            .collect();
+
+        assert_eq!(
+            replies,
+            vec!["015 :server1", "015 :`- server2", "017 :End of /MAP"]
+        );
+    }
+}
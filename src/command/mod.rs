@@ -1,25 +1,51 @@
+pub mod admin;
+pub mod authenticate;
 pub mod away;
+pub mod batch;
+pub mod cap;
+pub mod chathistory;
+pub mod connect;
 pub mod dcc_accept;
 pub mod dcc_chat;
 pub mod dcc_pause;
 pub mod dcc_resume;
 pub mod dcc_send;
+pub mod die;
+#[cfg(test)]
+pub mod fixtures;
+pub mod info;
 pub mod invite;
 pub mod join;
 pub mod kick;
 pub mod kill;
+pub mod knock;
+pub mod links;
 pub mod list;
+pub mod lusers;
+pub mod map;
 pub mod mode;
+pub mod motd;
 pub mod names;
 pub mod notice_msg;
 pub mod oper_msg;
 pub mod part;
+pub mod ping;
 pub mod private_msg;
 pub mod quit;
+pub mod rehash;
+pub mod reply_msg;
+pub mod server_ban;
 pub mod server_msg;
+pub mod server_query;
+pub mod sjoin;
 pub mod squit;
+pub mod stats;
+pub mod time;
 pub mod topic;
+pub mod topic_burst;
+pub mod unlink;
 pub mod user_msg;
+pub mod version;
 pub mod who;
 pub mod whois;
 use crate::error::error_command::ErrorCommand;
@@ -54,6 +80,40 @@ pub enum Command {
     Away,
     Server,
     Kill,
+    Kline,
+    Unkline,
+    Gline,
+    Ungline,
+    Zline,
+    Unzline,
+    Stats,
+    Die,
+    Restart,
+    Rehash,
+    Sjoin,
+    Unlink,
+    Connect,
+    Links,
+    Map,
+    Lusers,
+    Batch,
+    Ping,
+    Pong,
+    Version,
+    Time,
+    Admin,
+    Info,
+    Motd,
+    Reply,
+    Tb,
+    Topichistory,
+    Cap,
+    Chathistory,
+    Chanserv,
+    Nickserv,
+    Account,
+    Authenticate,
+    Knock,
 }
 
 impl FromStr for Command {
@@ -81,6 +141,40 @@ impl FromStr for Command {
             "AWAY" => Ok(Command::Away),
             "SERVER" => Ok(Command::Server),
             "KILL" => Ok(Command::Kill),
+            "KLINE" => Ok(Command::Kline),
+            "UNKLINE" => Ok(Command::Unkline),
+            "GLINE" => Ok(Command::Gline),
+            "UNGLINE" => Ok(Command::Ungline),
+            "ZLINE" => Ok(Command::Zline),
+            "UNZLINE" => Ok(Command::Unzline),
+            "STATS" => Ok(Command::Stats),
+            "DIE" => Ok(Command::Die),
+            "RESTART" => Ok(Command::Restart),
+            "REHASH" => Ok(Command::Rehash),
+            "SJOIN" => Ok(Command::Sjoin),
+            "UNLINK" => Ok(Command::Unlink),
+            "CONNECT" => Ok(Command::Connect),
+            "LINKS" => Ok(Command::Links),
+            "MAP" => Ok(Command::Map),
+            "LUSERS" => Ok(Command::Lusers),
+            "BATCH" => Ok(Command::Batch),
+            "PING" => Ok(Command::Ping),
+            "PONG" => Ok(Command::Pong),
+            "VERSION" => Ok(Command::Version),
+            "TIME" => Ok(Command::Time),
+            "ADMIN" => Ok(Command::Admin),
+            "INFO" => Ok(Command::Info),
+            "MOTD" => Ok(Command::Motd),
+            "REPLY" => Ok(Command::Reply),
+            "TB" => Ok(Command::Tb),
+            "TOPICHISTORY" => Ok(Command::Topichistory),
+            "CAP" => Ok(Command::Cap),
+            "CHATHISTORY" => Ok(Command::Chathistory),
+            "CHANSERV" | "CS" => Ok(Command::Chanserv),
+            "NICKSERV" | "NS" => Ok(Command::Nickserv),
+            "ACCOUNT" => Ok(Command::Account),
+            "AUTHENTICATE" => Ok(Command::Authenticate),
+            "KNOCK" => Ok(Command::Knock),
             _ => Err(ErrorCommand::UnknownCommand),
         }
     }
@@ -236,4 +330,105 @@ mod test {
         let command = Command::from_str("SERVER");
         assert_eq!(command, Ok(Command::Server));
     }
+
+    #[test]
+    fn command_from_gline() {
+        let command = Command::from_str("GLINE");
+        assert_eq!(command, Ok(Command::Gline));
+    }
+
+    #[test]
+    fn command_from_unzline() {
+        let command = Command::from_str("UNZLINE");
+        assert_eq!(command, Ok(Command::Unzline));
+    }
+
+    #[test]
+    fn command_from_restart() {
+        let command = Command::from_str("RESTART");
+        assert_eq!(command, Ok(Command::Restart));
+    }
+
+    #[test]
+    fn command_from_rehash() {
+        let command = Command::from_str("REHASH");
+        assert_eq!(command, Ok(Command::Rehash));
+    }
+
+    #[test]
+    fn command_from_sjoin() {
+        let command = Command::from_str("SJOIN");
+        assert_eq!(command, Ok(Command::Sjoin));
+    }
+
+    #[test]
+    fn command_from_unlink() {
+        let command = Command::from_str("UNLINK");
+        assert_eq!(command, Ok(Command::Unlink));
+    }
+
+    #[test]
+    fn command_from_connect() {
+        let command = Command::from_str("CONNECT");
+        assert_eq!(command, Ok(Command::Connect));
+    }
+
+    #[test]
+    fn command_from_topology_queries() {
+        assert_eq!(Command::from_str("LINKS"), Ok(Command::Links));
+        assert_eq!(Command::from_str("MAP"), Ok(Command::Map));
+        assert_eq!(Command::from_str("LUSERS"), Ok(Command::Lusers));
+    }
+
+    #[test]
+    fn command_from_batch() {
+        let command = Command::from_str("BATCH");
+        assert_eq!(command, Ok(Command::Batch));
+    }
+
+    #[test]
+    fn command_from_ping_and_pong() {
+        assert_eq!(Command::from_str("PING"), Ok(Command::Ping));
+        assert_eq!(Command::from_str("PONG"), Ok(Command::Pong));
+    }
+
+    #[test]
+    fn command_from_server_queries() {
+        assert_eq!(Command::from_str("VERSION"), Ok(Command::Version));
+        assert_eq!(Command::from_str("TIME"), Ok(Command::Time));
+        assert_eq!(Command::from_str("ADMIN"), Ok(Command::Admin));
+        assert_eq!(Command::from_str("INFO"), Ok(Command::Info));
+        assert_eq!(Command::from_str("MOTD"), Ok(Command::Motd));
+        assert_eq!(Command::from_str("REPLY"), Ok(Command::Reply));
+    }
+
+    #[test]
+    fn command_from_topic_burst_and_history() {
+        assert_eq!(Command::from_str("TB"), Ok(Command::Tb));
+        assert_eq!(Command::from_str("TOPICHISTORY"), Ok(Command::Topichistory));
+    }
+
+    #[test]
+    fn command_from_cap_and_chathistory() {
+        assert_eq!(Command::from_str("CAP"), Ok(Command::Cap));
+        assert_eq!(Command::from_str("CHATHISTORY"), Ok(Command::Chathistory));
+    }
+
+    #[test]
+    fn command_from_chanserv_and_its_alias() {
+        assert_eq!(Command::from_str("CHANSERV"), Ok(Command::Chanserv));
+        assert_eq!(Command::from_str("cs"), Ok(Command::Chanserv));
+    }
+
+    #[test]
+    fn command_from_nickserv_and_its_alias() {
+        assert_eq!(Command::from_str("NICKSERV"), Ok(Command::Nickserv));
+        assert_eq!(Command::from_str("ns"), Ok(Command::Nickserv));
+        assert_eq!(Command::from_str("AUTHENTICATE"), Ok(Command::Authenticate));
+    }
+
+    #[test]
+    fn command_from_knock() {
+        assert_eq!(Command::from_str("knock"), Ok(Command::Knock));
+    }
 }
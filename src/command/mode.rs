@@ -1,7 +1,9 @@
-use std::collections::HashSet;
-
 use crate::{
-    channel::Channel,
+    channel::{
+        mode_parser::{mode_line, parse_modes, ModeChange},
+        Channel,
+    },
+    command::Command,
     error::error_server::ErrorServer,
     parser::message::Message,
     reply::Reply,
@@ -9,47 +11,70 @@ use crate::{
     repository::{
         repository_channel::client_channel::ClientChannel, traits::operations::Operations,
     },
+    utils::write_message_to,
 };
 
 pub const COMMA_U8: u8 = b',';
 
 ///
-/// struct that implements a names message
+/// struct that implements a mode message
 /// according to what is established by the
-/// IRC protocol
+/// IRC protocol. A channel takes several
+/// modes at once, like `MODE #c +ov-b alice bob *!*@x`
 ///
 ///
 pub struct Mode {
+    msg: Message,
     user: String,
     channel: Option<String>,
     nick: Option<String>,
     flag: Option<String>,
-    param: Option<String>,
+    params: Vec<String>,
+    forward: Option<Message>,
 }
 
 impl Mode {
     ///
     /// function that creates a
-    /// new names message
+    /// new mode message
     ///
     pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
         let user = match msg.prefix() {
             Some(u) => u,
             None => return Err(ErrorServer::UnknownCommand),
         };
+        let params = msg
+            .parameters()
+            .unwrap_or_default()
+            .into_iter()
+            .skip(2)
+            .map(|param| param.trim_start_matches(':').trim().to_string())
+            .collect();
         Ok(Mode {
+            msg: msg.clone(),
             user,
             channel: Self::get_channel_from_msg(msg),
             nick: Self::get_nick_from_msg(msg),
             flag: msg.get_param_from_msg(1),
-            param: msg.get_param_from_msg(2),
+            params,
+            forward: None,
         })
     }
 
     ///
-    /// function that responds to the names
+    /// returns the MODE that has to be sent to the other
+    /// servers: the changes applied to the channel, or
+    /// the modes of the user
+    ///
+    pub fn forward(&self) -> Option<Message> {
+        self.forward.clone()
+    }
+
+    ///
+    /// function that responds to the mode
     /// message sent by the user and
-    /// acts accordingly
+    /// acts accordingly. The changes of a
+    /// channel are applied all or none.
     ///
     pub fn response(
         &mut self,
@@ -58,7 +83,6 @@ impl Mode {
     ) -> Result<Vec<Reply>, ErrorServer> {
         //Busca el prefijo
         //Obtiene el nickname
-        let response: Option<HashSet<String>>;
         let mut replies = vec![];
 
         if let (None, None) = (&self.channel, &self.nick) {
@@ -69,34 +93,39 @@ impl Mode {
         }
 
         if let Some(ch) = &self.channel {
-            match channel_sender.search(ch.to_string())? {
-                Some(mut c) => {
-                    if c.is_channel_operator(&self.user) {
-                        match &self.flag {
-                            Some(f) => {
-                                response = c.modify_channel_flag(f, self.param.clone());
-                            }
-                            None => {
-                                let vec = vec![ch.to_string(), c.return_channel_flags_str()];
-                                return Ok(vec![Reply::rply_modes(vec)]);
-                            }
-                        }
-                        channel_sender.update(c.name.clone(), c.clone())?;
-                    } else {
-                        return Ok(vec![Reply::err_chan_o_privs_needed(
-                            None,
-                            vec![c.name.clone()],
-                        )]);
-                    }
-                }
+            let mut c = match channel_sender.search(ch.to_string())? {
+                Some(c) => c,
                 None => return Ok(vec![Reply::err_no_such_channel(None, vec![ch.to_owned()])]),
-            }
-
-            if let Some(r) = response {
-                for i in r {
-                    replies.push(Reply::rpl_ban_list(vec![ch.to_string(), i]));
+            };
+            let flag = match &self.flag {
+                Some(f) => f,
+                None if c.is_channel_operator(&self.user) => {
+                    let vec = vec![ch.to_string(), c.return_channel_flags_str()];
+                    return Ok(vec![Reply::rply_modes(vec)]);
                 }
-                replies.push(Reply::rpl_end_ban_list(vec![ch.to_string()]));
+                None => "",
+            };
+            let changes = match parse_modes(flag, &self.params) {
+                Ok(changes) => changes,
+                Err(mode) => return Ok(vec![Reply::err_unknown_mode(mode)]),
+            };
+            let allowed = !flag.is_empty()
+                && changes.iter().all(|change| {
+                    c.user_can_change_mode(&self.user, &change.flag(), &change.param)
+                });
+            if !allowed {
+                return Ok(vec![Reply::err_chan_o_privs_needed(
+                    None,
+                    vec![c.name.clone()],
+                )]);
+            }
+            let applied = c.apply_modes(&changes);
+            if !applied.is_empty() {
+                channel_sender.update(c.name.clone(), c.clone())?;
+                self.broadcast(&c, &applied, &nick_sender)?;
+            }
+            for change in changes.iter().filter(|change| change.is_list_query()) {
+                replies.append(&mut Self::list_replies(&mut c, change.mode));
             }
         }
 
@@ -114,6 +143,7 @@ impl Mode {
                 Some(f) => {
                     client.modify_connecion_flag(f);
                     nick_sender.update(self.user.clone(), client.clone())?;
+                    self.forward = Some(self.msg.clone());
                 }
                 None => {
                     let vec = vec![s.to_string(), client.return_connection_flags_str()];
@@ -125,6 +155,58 @@ impl Mode {
         Ok(replies)
     }
 
+    // Writes the MODE with the changes that were applied to the
+    // members of the channel in this server, and keeps it to be
+    // sent to the other servers.
+    fn broadcast(
+        &mut self,
+        c: &Channel,
+        applied: &[ModeChange],
+        nick_sender: &ClientChannel<String, Connection>,
+    ) -> Result<(), ErrorServer> {
+        let mut parameters = vec![c.name.clone()];
+        parameters.append(&mut mode_line(applied));
+        let from = match nick_sender.search(self.user.clone())? {
+            Some(connection) => connection.get_mask(),
+            None => self.user.clone(),
+        };
+        let line = build_mode(&from, &parameters);
+        for member in c.return_members() {
+            if let Some(mut connection) = nick_sender.search(member)? {
+                if connection.is_local() {
+                    let _ = write_message_to(&line, &mut connection);
+                }
+            }
+        }
+        self.forward = Some(build_mode(&self.user, &parameters));
+        Ok(())
+    }
+
+    // The entries of the list of masks asked for, followed by its end.
+    fn list_replies(c: &mut Channel, mode: char) -> Vec<Reply> {
+        let list = c
+            .modify_channel_flag(&format!("+{mode}"), None)
+            .unwrap_or_default();
+        let mut replies: Vec<Reply> = list
+            .into_iter()
+            .map(|mask| {
+                let params = vec![c.name.clone(), mask];
+                match mode {
+                    'e' => Reply::rpl_except_list(params),
+                    'I' => Reply::rpl_invite_list(params),
+                    _ => Reply::rpl_ban_list(params),
+                }
+            })
+            .collect();
+        let params = vec![c.name.clone()];
+        replies.push(match mode {
+            'e' => Reply::rpl_end_except_list(params),
+            'I' => Reply::rpl_end_invite_list(params),
+            _ => Reply::rpl_end_ban_list(params),
+        });
+        replies
+    }
+
     fn get_channel_from_msg(msg: &Message) -> Option<String> {
         if let Some(ch) = msg.get_param_from_msg(0) {
             if ch.starts_with('#') || ch.starts_with('&') {
@@ -145,11 +227,29 @@ impl Mode {
     }
 }
 
+///
+/// builds the MODE sent by the given
+/// prefix with the given parameters
+///
+pub fn build_mode(prefix: &str, parameters: &[String]) -> Message {
+    Message::new(
+        Some(format!(":{prefix}")),
+        Command::Mode,
+        Some(parameters.to_vec()),
+    )
+}
+
 #[cfg(test)]
 mod test {
+    use std::str::FromStr;
+
+    use crate::channel::Channel;
     use crate::command::mode::Mode;
     use crate::command::Command;
+    use crate::database::RepositoryHandler;
     use crate::parser::message::Message;
+    use crate::reply::code::Code;
+    use crate::repository::traits::operations::Operations;
 
     #[test]
     fn get_nick_from_msg() {
@@ -223,4 +323,117 @@ mod test {
 
         assert_eq!(result, Some("&ch1".to_string()));
     }
+
+    #[test]
+    fn exception_lists_are_answered() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let channel = Channel::new("#c".to_string(), "pedro".to_string());
+        channels
+            .get_channels()
+            .add("#c".to_string(), channel)
+            .unwrap();
+        let mode = |line: &str| {
+            let msg = Message::from_str(line).unwrap();
+            let replies = Mode::new(&msg)
+                .unwrap()
+                .response(nicks.get_channels(), channels.get_channels())
+                .unwrap();
+            replies
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert!(mode(":pedro MODE #c +e *!*@host").is_empty());
+        assert_eq!(mode(":pedro MODE #c +e"), vec!["348 #c *!*@host", "349 #c"]);
+        assert_eq!(mode(":pedro MODE #c +I"), vec!["347 #c"]);
+    }
+
+    #[test]
+    fn halfop_can_voice_but_not_op() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let mut channel = Channel::new("#c".to_string(), "pedro".to_string());
+        channel.add_member("juan", &None).unwrap();
+        channel.add_member("ana", &None).unwrap();
+        channel.modify_channel_flag("+h", Some("juan".to_string()));
+        channels
+            .get_channels()
+            .add("#c".to_string(), channel)
+            .unwrap();
+        let mode = |line: &str| {
+            let msg = Message::from_str(line).unwrap();
+            Mode::new(&msg)
+                .unwrap()
+                .response(nicks.get_channels(), channels.get_channels())
+                .unwrap()
+        };
+
+        assert!(mode(":juan MODE #c +v ana").is_empty());
+        assert_eq!(
+            mode(":juan MODE #c +o ana")[0].code(),
+            Code::ErrChanOPrivsNeeded
+        );
+        let channel = channels.get_channels().search("#c".to_string()).unwrap();
+        assert_eq!(channel.unwrap().list_channel(), "#c: @pedro %juan +ana");
+    }
+
+    #[test]
+    fn several_modes_are_applied_at_once() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let mut channel = Channel::new("#c".to_string(), "pedro".to_string());
+        channel.add_member("juan", &None).unwrap();
+        channel.add_member("ana", &None).unwrap();
+        channel.modify_channel_flag("+b", Some("*!*@x".to_string()));
+        channels
+            .get_channels()
+            .add("#c".to_string(), channel)
+            .unwrap();
+
+        let msg = Message::from_str(":pedro MODE #c +tov-b+t juan ana *!*@x").unwrap();
+        let mut mode = Mode::new(&msg).unwrap();
+        let replies = mode
+            .response(nicks.get_channels(), channels.get_channels())
+            .unwrap();
+        assert!(replies.is_empty());
+        assert_eq!(
+            mode.forward().unwrap().to_string(),
+            ":pedro MODE #c +tov-b juan ana *!*@x"
+        );
+        let channel = channels.get_channels().search("#c".to_string()).unwrap();
+        let channel = channel.unwrap();
+        assert_eq!(channel.list_channel(), "#c: @juan @pedro +ana");
+        assert!(!channel.is_banned("pepe!pepe@x"));
+    }
+
+    #[test]
+    fn changes_are_applied_all_or_none() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let mut channel = Channel::new("#c".to_string(), "pedro".to_string());
+        channel.add_member("juan", &None).unwrap();
+        channel.modify_channel_flag("+h", Some("juan".to_string()));
+        channels
+            .get_channels()
+            .add("#c".to_string(), channel)
+            .unwrap();
+        let mode = |line: &str| {
+            let msg = Message::from_str(line).unwrap();
+            let mut mode = Mode::new(&msg).unwrap();
+            let replies = mode
+                .response(nicks.get_channels(), channels.get_channels())
+                .unwrap();
+            (replies, mode.forward())
+        };
+
+        let (replies, forward) = mode(":juan MODE #c +mo juan");
+        assert_eq!(replies[0].code(), Code::ErrChanOPrivsNeeded);
+        assert!(forward.is_none());
+        let (replies, _) = mode(":pedro MODE #c +mz");
+        assert_eq!(replies[0].code(), Code::ErrUnknownMode);
+        let channel = channels.get_channels().search("#c".to_string()).unwrap();
+        assert!(channel.unwrap().return_channel_flags().is_empty());
+    }
 }
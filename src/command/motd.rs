@@ -0,0 +1,79 @@
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use crate::{config::ServerConfig, error::error_server::ErrorServer, reply::Reply};
+
+///
+/// struct that implements the MOTD message, that answers
+/// with the message of the day of the server:
+/// `MOTD [<server>]`
+///
+pub struct MotdMsg;
+
+impl MotdMsg {
+    ///
+    /// Reads the configured file, so a new message of the day is
+    /// shown without restarting the server. A missing or unreadable
+    /// file is answered with ERR_NOMOTD.
+    ///
+    pub fn response(
+        servername: &str,
+        config: &Arc<Mutex<ServerConfig>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let path = match config.lock() {
+            Ok(config) => config.motd_file.clone(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let motd = match path.map(fs::read_to_string) {
+            Some(Ok(motd)) => motd,
+            _ => return Ok(vec![Reply::err_no_motd()]),
+        };
+        let mut replies = vec![Reply::rpl_motd_start(servername)];
+        replies.extend(motd.lines().map(Reply::rpl_motd));
+        replies.push(Reply::rpl_end_of_motd());
+        Ok(replies)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env, fs, process,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::config::ServerConfig;
+
+    use super::MotdMsg;
+
+    #[test]
+    fn answers_each_line_of_the_file() {
+        let path = env::temp_dir().join(format!("motd-{}.txt", process::id()));
+        fs::write(&path, "Welcome\nBe nice\n").unwrap();
+        let config = ServerConfig {
+            motd_file: Some(path.to_string_lossy().to_string()),
+            ..ServerConfig::default()
+        };
+        let replies = MotdMsg::response("server1", &Arc::new(Mutex::new(config))).unwrap();
+        let replies: Vec<String> = replies.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            replies,
+            vec![
+                "375 :- server1 Message of the day - ",
+                "372 :- Welcome",
+                "372 :- Be nice",
+                "376 :End of MOTD command",
+            ]
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn server_without_motd_is_err_no_motd() {
+        let config = Arc::new(Mutex::new(ServerConfig::default()));
+        let replies = MotdMsg::response("server1", &config).unwrap();
+        assert_eq!(replies[0].to_string(), "422 :MOTD File is missing");
+    }
+}
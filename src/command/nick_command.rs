@@ -1,7 +1,4 @@
-use std::{
-    io::Write,
-    sync::{Arc, Mutex},
-};
+use std::sync::{Arc, Mutex};
 
 use crate::{
     channel::Channel,
@@ -13,7 +10,8 @@ use crate::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
-    server_comunication::spanning_tree::SpanningTree,
+    server_comunication::{info_sender::send_to_all_servers, spanning_tree::SpanningTree},
+    services::is_service,
     user::builder::UserBuilder,
 };
 
@@ -82,6 +80,12 @@ impl NickCommand {
             )]);
         }
 
+        if c.is_local() {
+            if let Some(reply) = self.check_bans(&c, &channel_sender)? {
+                return Ok(vec![reply]);
+            }
+        }
+
         if !Self::update_nickname(
             self.from.clone(),
             self.new_nickname.clone(),
@@ -98,27 +102,17 @@ impl NickCommand {
         Self::update_channels(&self.from, &self.new_nickname.clone(), &channel_sender)?;
 
         if let Some(connection) = nick_sender.search(self.new_nickname.clone())? {
+            // the old nickname is gone, the id still names the user
+            let mut prefix = ":".to_string();
+            prefix.push_str(connection.get_uid().unwrap_or(&self.from));
+            let msg = Message::new(
+                Some(prefix),
+                super::Command::Nick,
+                Some(vec![self.new_nickname.clone()]),
+            );
             match spanning_tree.lock() {
                 Ok(st) => {
-                    if let Some(excluded) = st.look_for_nearest_connection(
-                        connection.get_servername().unwrap().to_string(),
-                    ) {
-                        let servers = st.get_servers();
-                        for server in servers {
-                            if server != excluded {
-                                if let Some(mut connection) = server.get_connection() {
-                                    let mut prefix = ":".to_string();
-                                    prefix.push_str(self.from.clone().as_str());
-                                    let msg = Message::new(
-                                        Some(prefix),
-                                        super::Command::Nick,
-                                        Some(vec![self.new_nickname.clone()]),
-                                    );
-                                    connection.write_all(msg.to_string().as_bytes())?;
-                                }
-                            }
-                        }
-                    }
+                    send_to_all_servers(&st, msg, connection.get_servername().unwrap_or_default())?
                 }
                 Err(_) => return Err(ErrorServer::LockedResource),
             }
@@ -137,6 +131,29 @@ impl NickCommand {
         Err(Reply::err_no_nickname_given(None))
     }
 
+    // A local user can't change its nickname while banned in one of its
+    // channels, nor take a nickname banned there. The servers of the
+    // remote users checked it before announcing the change.
+    fn check_bans(
+        &self,
+        connection: &Connection,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<Option<Reply>, ErrorServer> {
+        let old_mask = connection.get_mask();
+        let mut user = connection.get_user();
+        user.set_nickname(&self.new_nickname);
+        let new_mask = user.mask();
+        for channel in channel_sender.find_all()? {
+            if channel.has_member(&self.from) && !channel.can_change_nick(&old_mask, &new_mask) {
+                return Ok(Some(Reply::err_ban_nick_change(
+                    &self.from,
+                    &channel.get_name(),
+                )));
+            }
+        }
+        Ok(None)
+    }
+
     fn update_channels(
         old_nickname: &str,
         new_nickname: &str,
@@ -167,6 +184,6 @@ impl NickCommand {
         nick_sender: &ClientChannel<String, Connection>,
     ) -> Result<bool, ErrorServer> {
         let result = nick_sender.search(nickname.to_owned())?;
-        Ok(result.is_some())
+        Ok(result.is_some() || is_service(nickname))
     }
 }
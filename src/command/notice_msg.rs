@@ -1,7 +1,4 @@
-use std::{
-    io::Write,
-    sync::{Arc, Mutex},
-};
+use std::sync::{Arc, Mutex};
 
 use crate::{
     channel::Channel,
@@ -12,7 +9,9 @@ use crate::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
-    server_comunication::spanning_tree::SpanningTree,
+    server_comunication::{
+        info_sender::send_to_servers, spanning_tree::SpanningTree, uid::to_uids,
+    },
     utils::write_message_to,
 };
 
@@ -123,7 +122,7 @@ impl NoticeMsg {
             return Ok(());
         }
         match nick_sender.search(receiver)? {
-            Some(mut c) => self.send_message_to(&mut c, channel)?,
+            Some(mut c) => self.send_message_to(&mut c, channel, nick_sender)?,
             None => return Ok(()),
         }
 
@@ -147,7 +146,7 @@ impl NoticeMsg {
         let result = nick_sender.find_all()?;
         for k in result.iter() {
             if k.get_nickname().contains(user.as_str()) {
-                self.send_message_to(&mut k.clone(), None)?;
+                self.send_message_to(&mut k.clone(), None, nick_sender)?;
             }
         }
         Ok(())
@@ -161,6 +160,7 @@ impl NoticeMsg {
         &self,
         client: &mut Connection,
         channel: Option<String>,
+        nick_sender: &ClientChannel<String, Connection>,
     ) -> Result<(), ErrorServer> {
         let mut message = String::new();
         if let Some(c) = channel {
@@ -184,13 +184,9 @@ impl NoticeMsg {
                         crate::command::Command::Notice,
                         Some(vec![client.get_nickname(), self.msg.clone()]),
                     );
-                    if let Some(server) = st.look_for_nearest_connection(servername.to_string()) {
-                        if let Some(mut connection) = server.get_connection() {
-                            if let Err(_e) = connection.write_all(msg.to_string().as_bytes()) {
-                                return Err(ErrorServer::UnreachableClient);
-                            }
-                        }
-                    }
+                    let root = st.get_root_name();
+                    let msg = to_uids(&msg.to_string(), nick_sender);
+                    send_to_servers(&st, &msg, &[servername.to_string()], &root)?;
                 } else if let Err(_e) = write_message_to(&message, client) {
                     return Err(ErrorServer::UnreachableClient);
                 }
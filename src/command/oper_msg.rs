@@ -1,4 +1,7 @@
+use std::sync::{Arc, Mutex};
+
 use crate::{
+    config::ServerConfig,
     error::error_server::ErrorServer,
     parser::message::Message,
     reply::Reply,
@@ -33,11 +36,13 @@ impl OperMsg {
     /// the message is now a
     /// server operator and
     /// returns a bool with the
-    /// result
+    /// result. The operators of the
+    /// configuration are accepted too
     ///
     pub fn response(
         &self,
         nick_sender: ClientChannel<String, Connection>,
+        config: &Arc<Mutex<ServerConfig>>,
     ) -> Result<Vec<Reply>, ErrorServer> {
         if self.from.is_empty() || self.password.is_empty() {
             return Ok(vec![Reply::err_need_more_params(
@@ -55,6 +60,15 @@ impl OperMsg {
                 let user = connection.get_user();
                 let password;
                 if let Some(_c) = connection.see_if_clonable() {
+                    let configured = match config.lock() {
+                        Ok(config) => config.is_oper(&self.user, &self.password),
+                        Err(_) => return Err(ErrorServer::LockedResource),
+                    };
+                    if configured {
+                        connection.get_op_privileges();
+                        nick_sender.update(self.from.clone(), connection)?;
+                        return Ok(vec![Reply::rpl_you_are_oper(None)]);
+                    }
                     if user.password().is_none() {
                         return Ok(vec![Reply::err_no_oper_host(None)]);
                     }
@@ -92,3 +106,35 @@ fn get_user_and_pass_from_msg(msg: &Message) -> (String, String) {
         None => (String::new(), String::new()),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        command::fixtures::add_user, config::ServerConfig, database::RepositoryHandler,
+        parser::message::Message, reply::code::Code, repository::traits::operations::Operations,
+    };
+
+    use super::OperMsg;
+
+    #[test]
+    fn configured_operator_is_accepted() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let _other_end = add_user(&nicks, "nick", false);
+        let config = ServerConfig::from_str("oper = admin secret").unwrap();
+        let msg = Message::from_str(":nick OPER admin secret").unwrap();
+
+        let replies = OperMsg::new(&msg)
+            .unwrap()
+            .response(nicks.get_channels(), &Arc::new(Mutex::new(config)))
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::RplYoureoper);
+        let connection = nicks.get_channels().search("nick".to_string()).unwrap();
+        assert!(connection.unwrap().is_op_connection());
+        nicks.stop().unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{error::error_server::ErrorServer, parser::message::Message};
+
+///
+/// struct that implements the PING and PONG messages, that
+/// neighbour servers exchange to measure the lag of their link:
+///
+/// `:<server> PING <server> :<token>`
+/// `:<server> PONG <server> :<token>`
+///
+/// The PONG carries the token of the PING it answers.
+///
+#[derive(Debug)]
+pub struct PingMsg {
+    server: String,
+    token: String,
+}
+
+impl PingMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let server = match msg.prefix() {
+            Some(server) => server,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        let token = match msg.get_param_from_msg(1) {
+            Some(token) => token.trim_start_matches(':').trim().to_string(),
+            None => return Err(ErrorServer::BadQuery),
+        };
+        Ok(PingMsg { server, token })
+    }
+
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    ///
+    /// builds the PONG sent by the
+    /// server to answer the PING
+    ///
+    pub fn pong(&self, servername: &str) -> String {
+        format!(":{servername} PONG {servername} :{}", self.token)
+    }
+}
+
+///
+/// builds the PING sent by the server
+/// with the given token
+///
+pub fn build_ping(servername: &str, token: &str) -> String {
+    format!(":{servername} PING {servername} :{token}")
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::parser::message::Message;
+
+    use super::{build_ping, PingMsg};
+
+    #[test]
+    fn pong_answers_with_the_token_of_the_ping() {
+        let ping = Message::from_str(&build_ping("server1", "7")).unwrap();
+        let ping = PingMsg::new(&ping).unwrap();
+        assert_eq!(ping.server(), "server1");
+        assert_eq!(ping.pong("server2"), ":server2 PONG server2 :7");
+
+        let pong = Message::from_str(&ping.pong("server2")).unwrap();
+        assert_eq!(PingMsg::new(&pong).unwrap().token(), "7");
+    }
+
+    #[test]
+    fn ping_without_token_is_err() {
+        let msg = Message::from_str(":server1 PING server1").unwrap();
+        assert!(PingMsg::new(&msg).is_err());
+    }
+}
@@ -1,5 +1,8 @@
+use std::sync::{Arc, Mutex};
+
 use crate::{
-    channel::Channel,
+    channel::{flood::FloodAction, history::ChannelHistory, Channel},
+    command::{mode::build_mode, Command},
     error::error_server::ErrorServer,
     parser::message::Message,
     reply::Reply,
@@ -7,9 +10,11 @@ use crate::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
-    utils::write_message_to,
+    utils::{unix_timestamp, write_message_to},
 };
 
+use super::cap::ACCOUNT_TAG;
+
 ///
 /// struct that implements a private message
 /// according to what is established by the
@@ -20,6 +25,7 @@ pub struct PrivMsg {
     from: String,
     msg: String,
     to: String,
+    account: Option<String>,
 }
 
 impl PrivMsg {
@@ -39,6 +45,7 @@ impl PrivMsg {
                 from: from.clone(),
                 to: to.clone(),
                 msg: Self::get_msg_from_privmsg(&msg)?,
+                account: None,
             })
         }
         Ok(msgs)
@@ -47,12 +54,17 @@ impl PrivMsg {
     ///
     /// function that responds to the private
     /// message sent by the user and
-    /// acts accordingly
+    /// acts accordingly. The messages sent
+    /// to a channel are kept in its history.
+    /// The flood actions are announced with
+    /// the name of this server.
     ///
     pub fn response(
-        self,
+        mut self,
         nick_sender: &ClientChannel<String, Connection>,
         channel_sender: &ClientChannel<String, Channel>,
+        history: &Arc<Mutex<ChannelHistory>>,
+        servername: &str,
     ) -> Result<Vec<Reply>, ErrorServer> {
         //ERR_NONICKNAMEGIVEN
         if self.to.is_empty() {
@@ -65,7 +77,10 @@ impl PrivMsg {
         }
 
         let oper = match nick_sender.search(self.from.clone())? {
-            Some(oper) => oper.is_op_connection(),
+            Some(oper) => {
+                self.account = oper.get_account().map(str::to_string);
+                oper.is_op_connection()
+            }
             None => return Err(ErrorServer::UnreachableClient),
         };
 
@@ -87,7 +102,9 @@ impl PrivMsg {
 
         if msg_receiver.starts_with('&') || msg_receiver.starts_with('#') {
             let mut r = match channel_sender.search(msg_receiver.clone())? {
-                Some(c) => self.send_to_channel(c, nick_sender)?,
+                Some(c) => {
+                    self.send_to_channel(c, nick_sender, channel_sender, history, servername)?
+                }
                 None => vec![Reply::err_no_such_nickname(None, vec![msg_receiver])],
             };
             replies.append(&mut r);
@@ -97,23 +114,109 @@ impl PrivMsg {
         Ok(replies)
     }
 
+    // Delivers the message to the members of the channel, as its modes
+    // allow. Every server that gets the message applies the same flood
+    // limit, so the channel stays the same in all of them.
     fn send_to_channel(
         &self,
-        c: Channel,
+        mut c: Channel,
         nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        history: &Arc<Mutex<ChannelHistory>>,
+        servername: &str,
     ) -> Result<Vec<Reply>, ErrorServer> {
         let mut replies = vec![Reply::rpl_none()];
-        if c.user_can_speak(&self.from) {
-            for member in c.return_members() {
-                replies.push(self.process_receiver(nick_sender, member, Some(c.name.clone()))?);
-            }
-        } else {
+        // the bans of the channel are matched against the whole mask
+        let from = match nick_sender.search(self.from.clone())? {
+            Some(connection) => connection.get_mask(),
+            None => self.from.clone(),
+        };
+        let now = unix_timestamp();
+        if c.lift_flood_moderation(now) {
+            channel_sender.update(c.name.clone(), c.clone())?;
+            let parameters = [c.name.clone(), "-m".to_string()];
+            Self::announce(&c, &build_mode(servername, &parameters), nick_sender)?;
+        }
+        let text = self.msg.strip_prefix(':').unwrap_or(&self.msg);
+        let text = match c.filter_text(text) {
+            Some(text) if c.user_can_speak(&from) => text,
             //ERR_CANTSENDTOCHANNEL
+            _ => return Ok(vec![Reply::err_cant_send_to_channel(vec![c.get_name()])]),
+        };
+        let action = c.record_line(&from, now);
+        if c.is_flood_limited() {
+            // the channel keeps the lines recorded
+            channel_sender.update(c.name.clone(), c.clone())?;
+        }
+        if let Some(action) = action {
+            self.announce_flood_action(&c, action, servername, nick_sender)?;
             return Ok(vec![Reply::err_cant_send_to_channel(vec![c.get_name()])]);
         }
+        match history.lock() {
+            Ok(mut history) => history.record(&c.name, &from, &text, now)?,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let delivered = PrivMsg {
+            from: self.from.clone(),
+            msg: format!(":{text}"),
+            to: self.to.clone(),
+            account: self.account.clone(),
+        };
+        for member in c.return_members() {
+            replies.push(delivered.process_receiver(nick_sender, member, Some(c.name.clone()))?);
+        }
         Ok(replies)
     }
 
+    // Tells the local members of the channel that the sender was
+    // kicked, or that the channel was moderated, for flooding it.
+    // A muted sender is only refused its message.
+    fn announce_flood_action(
+        &self,
+        c: &Channel,
+        action: FloodAction,
+        servername: &str,
+        nick_sender: &ClientChannel<String, Connection>,
+    ) -> Result<(), ErrorServer> {
+        let line = match action {
+            FloodAction::Kick => Message::new(
+                Some(format!(":{servername}")),
+                Command::Kick,
+                Some(vec![
+                    c.name.clone(),
+                    self.from.clone(),
+                    ":Channel flood (+f)".to_string(),
+                ]),
+            ),
+            FloodAction::Moderate => build_mode(servername, &[c.name.clone(), "+m".to_string()]),
+            FloodAction::Mute => return Ok(()),
+        };
+        if let (FloodAction::Kick, Some(mut connection)) =
+            (action, nick_sender.search(self.from.clone())?)
+        {
+            if connection.is_local() {
+                let _ = write_message_to(&line, &mut connection);
+            }
+        }
+        Self::announce(c, &line, nick_sender)
+    }
+
+    // Writes the line to the members of the channel in this server.
+    fn announce(
+        c: &Channel,
+        line: &Message,
+        nick_sender: &ClientChannel<String, Connection>,
+    ) -> Result<(), ErrorServer> {
+        for member in c.return_members() {
+            if let Some(mut connection) = nick_sender.search(member)? {
+                if connection.is_local() {
+                    let _ = write_message_to(line, &mut connection);
+                }
+            }
+        }
+        Ok(())
+    }
+
     ///
     /// function that is responsible for
     /// processing  who receives the given
@@ -199,8 +302,11 @@ impl PrivMsg {
         client: &mut Connection,
         channel: Option<String>,
     ) -> Result<Reply, ErrorServer> {
-        let message = self.create_message(channel);
+        let mut message = self.create_message(channel);
         //println!("Cree el mensaje {message}");
+        if let (Some(account), true) = (&self.account, client.has_capability(ACCOUNT_TAG)) {
+            message = format!("@account={account} {message}");
+        }
 
         if let Err(_e) = write_message_to(&message, client) {
             //Si es error puede que sea porque somos el receptor o porque no tenemos conexion directa con el usuario.
@@ -0,0 +1,105 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    server::state::ServerState,
+    server_comunication::spanning_tree::SpanningTree,
+};
+
+///
+/// struct that implements the REHASH
+/// message, that reloads the configuration
+/// of the server the operator is connected to
+///
+pub struct RehashMsg {
+    from: String,
+}
+
+impl RehashMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        match msg.prefix() {
+            Some(from) => Ok(RehashMsg { from }),
+            None => Err(ErrorServer::UnknownCommand),
+        }
+    }
+
+    ///
+    /// Reloads the configuration and answers with the settings
+    /// that changed. An invalid configuration is not applied.
+    ///
+    pub fn response(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        state: &ServerState,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let servername = match spanning_tree.lock() {
+            Ok(st) => st.get_root_name(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let allowed = match nick_sender.search(self.from.clone())? {
+            Some(c) => c.is_op_connection() && c.is_on_server(&servername),
+            None => false,
+        };
+        if !allowed {
+            return Ok(vec![Reply::err_no_privileges(None)]);
+        }
+        let file = state
+            .config_path()
+            .unwrap_or_else(|| "configuration".to_string());
+        match state.rehash() {
+            Ok(changes) => Ok(vec![Reply::rpl_rehashing(file, &changes)]),
+            Err(ErrorServer::LockedResource) => Err(ErrorServer::LockedResource),
+            Err(_) => Ok(vec![Reply::err_file_error("rehash", file)]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        command::fixtures::{add_user, spanning_tree},
+        config::ServerConfig,
+        database::RepositoryHandler,
+        parser::message::Message,
+        reply::code::Code,
+        server::state::ServerState,
+    };
+
+    use super::RehashMsg;
+
+    #[test]
+    fn failed_rehash_is_a_file_error() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let _other_end = add_user(&nicks, "oper", true);
+        let state = ServerState::new(ServerConfig::default()).unwrap();
+        let msg = Message::from_str(":oper REHASH").unwrap();
+        let replies = RehashMsg::new(&msg)
+            .unwrap()
+            .response(&nicks.get_channels(), &spanning_tree(), &state)
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::ErrFileError);
+        nicks.stop().unwrap();
+    }
+
+    #[test]
+    fn user_cant_rehash() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let state = ServerState::new(ServerConfig::default()).unwrap();
+        let msg = Message::from_str(":user REHASH").unwrap();
+        let replies = RehashMsg::new(&msg)
+            .unwrap()
+            .response(&nicks.get_channels(), &spanning_tree(), &state)
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::ErrNoprivileges);
+        nicks.stop().unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    server_comunication::{info_sender::send_to_servers, spanning_tree::SpanningTree},
+    utils::write_message_to,
+};
+
+///
+/// struct that implements the REPLY message, that carries
+/// the reply of a server to a user of another server:
+///
+/// `:<server> REPLY <nickname> :<reply>`
+///
+#[derive(Debug)]
+pub struct ReplyMsg {
+    server: String,
+    nickname: String,
+    reply: String,
+}
+
+impl ReplyMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let server = match msg.prefix() {
+            Some(server) => server,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        let (nickname, reply) = match (msg.get_param_from_msg(0), msg.get_param_from_msg(1)) {
+            (Some(nickname), Some(reply)) => (nickname, reply),
+            _ => return Err(ErrorServer::BadQuery),
+        };
+        let reply = reply.strip_prefix(':').unwrap_or(&reply).trim().to_string();
+        Ok(ReplyMsg {
+            server,
+            nickname,
+            reply,
+        })
+    }
+
+    pub fn reply(&self) -> &str {
+        &self.reply
+    }
+
+    ///
+    /// Writes the reply to the user, prefixed with the server that
+    /// answered, or sends it on toward the server of the user.
+    ///
+    pub fn response(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let mut connection = match nick_sender.search(self.nickname.clone())? {
+            Some(connection) => connection,
+            None => return Ok(vec![Reply::rpl_none()]),
+        };
+        if connection.is_local() {
+            let reply = format!(":{} {}", self.server, self.reply);
+            write_message_to(&reply, &mut connection)?;
+        } else if let Some(user_server) = connection.get_servername() {
+            let st = match spanning_tree.lock() {
+                Ok(st) => st,
+                Err(_) => return Err(ErrorServer::LockedResource),
+            };
+            let target = connection.get_uid().unwrap_or(&self.nickname);
+            let relayed = build_reply(&self.server, target, &self.reply);
+            send_to_servers(&st, &relayed, &[user_server.to_string()], &self.server)?;
+        }
+        Ok(vec![Reply::rpl_none()])
+    }
+}
+
+///
+/// builds the REPLY that carries the reply of
+/// the server to the user of another server
+///
+pub fn build_reply(servername: &str, nickname: &str, reply: &str) -> String {
+    format!(":{servername} REPLY {nickname} :{reply}")
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{parser::message::Message, reply::Reply};
+
+    use super::{build_reply, ReplyMsg};
+
+    #[test]
+    fn reply_is_carried_whole() {
+        let reply = Reply::rpl_time("server2", "Thursday, 1 January 1970 -- 00:00:00 UTC");
+        let msg = build_reply("server2", "alice", &reply.to_string());
+        let msg = ReplyMsg::new(&Message::from_str(&msg).unwrap()).unwrap();
+        assert_eq!(
+            msg.reply(),
+            "391 server2 :Thursday, 1 January 1970 -- 00:00:00 UTC"
+        );
+    }
+
+    #[test]
+    fn reply_without_text_is_err() {
+        let msg = Message::from_str(":server2 REPLY alice").unwrap();
+        assert!(ReplyMsg::new(&msg).is_err());
+    }
+}
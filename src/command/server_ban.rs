@@ -0,0 +1,321 @@
+use std::{
+    net::Shutdown,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    ban::{ban_kind::BanKind, is_duration, parse_duration, BanList, ServerBan},
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    server_comunication::spanning_tree::SpanningTree,
+    utils::{unix_timestamp, write_message_to},
+};
+
+use super::Command;
+
+const DEFAULT_REASON: &str = "No reason";
+
+///
+/// struct that implements the KLINE, GLINE and ZLINE
+/// messages, and the ones that remove them:
+/// `<command> [duration] <mask> :<reason>`
+/// `<uncommand> <mask>`
+///
+pub struct ServerBanMsg {
+    from: String,
+    oper: bool,
+    kind: BanKind,
+    remove: bool,
+    mask: Option<String>,
+    // None when the duration given doesn't fit in the seconds
+    duration: Option<u64>,
+    reason: String,
+    changed: bool,
+}
+
+impl ServerBanMsg {
+    pub fn new(msg: &Message, oper: bool) -> Result<Self, ErrorServer> {
+        let from = match msg.prefix() {
+            Some(u) => u,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        let (kind, remove) = match msg.command() {
+            Command::Kline => (BanKind::K, false),
+            Command::Unkline => (BanKind::K, true),
+            Command::Gline => (BanKind::G, false),
+            Command::Ungline => (BanKind::G, true),
+            Command::Zline => (BanKind::Z, false),
+            Command::Unzline => (BanKind::Z, true),
+            _ => return Err(ErrorServer::UnexpectedCommand),
+        };
+        let mut parameters = msg.parameters().unwrap_or_default().into_iter();
+        let mut mask = parameters.next();
+        let mut duration = Some(0);
+        if !remove && mask.as_deref().is_some_and(is_duration) {
+            duration = mask.as_deref().and_then(parse_duration);
+            mask = parameters.next();
+        }
+        let reason = match parameters.next() {
+            Some(r) => r.trim_start_matches(':').trim().to_string(),
+            None => DEFAULT_REASON.to_string(),
+        };
+        Ok(ServerBanMsg {
+            from,
+            oper,
+            kind,
+            remove,
+            mask,
+            duration,
+            reason,
+            changed: false,
+        })
+    }
+
+    ///
+    /// Adds or removes the ban. When a ban is added, the connections
+    /// of this server that match it are closed.
+    ///
+    pub fn response(
+        &mut self,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        bans: &Arc<Mutex<BanList>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        if !self.oper {
+            return Ok(vec![Reply::err_no_privileges(None)]);
+        }
+        let mask = match &self.mask {
+            Some(m) => m.clone(),
+            None => {
+                return Ok(vec![Reply::err_need_more_params(
+                    None,
+                    vec![format!("{}LINE", self.kind)],
+                )])
+            }
+        };
+        let now = unix_timestamp();
+        let ban = self.duration.and_then(|duration| {
+            ServerBan::new(self.kind, &mask, &self.reason, &self.from, now, duration)
+        });
+        let ban = match ban {
+            Some(ban) => ban,
+            None => {
+                let notice = format!("*** Invalid duration for the {}-line", self.kind);
+                self.notice(nick_sender, spanning_tree, &notice)?;
+                return Ok(vec![Reply::rpl_none()]);
+            }
+        };
+        match bans.lock() {
+            Ok(mut list) => {
+                list.expire(now)?;
+                self.changed = match self.remove {
+                    true => list.remove(self.kind, &mask)?,
+                    false => list.add(ban.clone())?,
+                };
+            }
+            Err(_) => return Err(ErrorServer::LockedResource),
+        }
+        if self.changed && !self.remove {
+            let servername = match spanning_tree.lock() {
+                Ok(st) => st.get_root_name(),
+                Err(_) => return Err(ErrorServer::LockedResource),
+            };
+            Self::disconnect_matching(&ban, nick_sender, &servername)?;
+        }
+        Ok(vec![Reply::rpl_none()])
+    }
+
+    ///
+    /// returns true if the message changed a ban
+    /// that the rest of the network must know
+    ///
+    pub fn must_be_propagated(&self) -> bool {
+        self.changed && self.kind.is_global()
+    }
+
+    // Tells the operator that sent the message why it was refused.
+    fn notice(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        text: &str,
+    ) -> Result<(), ErrorServer> {
+        let servername = match spanning_tree.lock() {
+            Ok(st) => st.get_root_name(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        if let Some(mut connection) = nick_sender.search(self.from.clone())? {
+            if connection.is_local() {
+                let notice = format!(":{servername} NOTICE {} :{text}", self.from);
+                write_message_to(&notice, &mut connection)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Closes the connections of the users of this server affected by the ban.
+    // Their threads notice the closed socket and inform the QUIT.
+    fn disconnect_matching(
+        ban: &ServerBan,
+        nick_sender: &ClientChannel<String, Connection>,
+        servername: &str,
+    ) -> Result<(), ErrorServer> {
+        for connection in nick_sender.find_all()? {
+            if !connection.is_on_server(servername) {
+                continue;
+            }
+            if let Some(mut stream) = connection.see_if_clonable() {
+                let ip = stream.peer_addr().ok().map(|addr| addr.ip());
+                let user = connection.get_user();
+                let username = user.username().unwrap_or_default();
+                let hostname = user.hostname().unwrap_or_default();
+                if ban.matches(username, hostname, ip) {
+                    // a dead socket must not keep the rest connected
+                    let error = ban.closing_message(&connection.get_nickname());
+                    let _ = write_message_to(&error, &mut stream);
+                    let _ = stream.shutdown(Shutdown::Both);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// builds the GLINE messages that share the active
+/// G-lines with a server joining the network
+///
+pub fn build_gline_msgs(bans: &BanList, servername: &str, now: u64) -> Vec<String> {
+    bans.list(BanKind::G, now)
+        .iter()
+        .map(|ban| {
+            let duration = match ban.remaining(now) {
+                0 => "0".to_string(),
+                seconds => format!("{seconds}s"),
+            };
+            format!(
+                ":{servername} GLINE {duration} {} :{}",
+                ban.mask, ban.reason
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        ban::{ban_kind::BanKind, BanList, ServerBan},
+        command::fixtures::spanning_tree,
+        database::RepositoryHandler,
+        parser::message::Message,
+        reply::code::Code,
+    };
+
+    use super::{build_gline_msgs, ServerBanMsg};
+
+    #[test]
+    fn parses_duration_mask_and_reason() {
+        let msg = Message::from_str(":oper KLINE 10 bad@host :go away").unwrap();
+        let kline = ServerBanMsg::new(&msg, true).unwrap();
+        assert_eq!(kline.kind, BanKind::K);
+        assert_eq!(kline.duration, Some(600));
+        assert_eq!(kline.mask, Some("bad@host".to_string()));
+        assert_eq!(kline.reason, "go away");
+    }
+
+    #[test]
+    fn duration_is_optional() {
+        let msg = Message::from_str(":oper ZLINE 10.0.0.0/8").unwrap();
+        let zline = ServerBanMsg::new(&msg, true).unwrap();
+        assert_eq!(zline.duration, Some(0));
+        assert_eq!(zline.mask, Some("10.0.0.0/8".to_string()));
+    }
+
+    #[test]
+    fn ban_too_long_is_refused() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let bans = Arc::new(Mutex::new(BanList::new()));
+        let msg = Message::from_str(":oper KLINE 99999999999999999w bad@host :x").unwrap();
+        let mut kline = ServerBanMsg::new(&msg, true).unwrap();
+        assert_eq!(kline.duration, None);
+        assert_eq!(kline.mask, Some("bad@host".to_string()));
+
+        kline
+            .response(&nicks.get_channels(), &spanning_tree(), &bans)
+            .unwrap();
+        assert!(bans.lock().unwrap().list(BanKind::K, 0).is_empty());
+        assert!(!kline.must_be_propagated());
+        nicks.stop().unwrap();
+    }
+
+    #[test]
+    fn only_operators_can_ban() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let bans = Arc::new(Mutex::new(BanList::new()));
+        let msg = Message::from_str(":user GLINE *@host :spam").unwrap();
+        let mut gline = ServerBanMsg::new(&msg, false).unwrap();
+        let replies = gline
+            .response(&nicks.get_channels(), &spanning_tree(), &bans)
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::ErrNoprivileges);
+        assert!(bans.lock().unwrap().list(BanKind::G, 0).is_empty());
+    }
+
+    #[test]
+    fn new_gline_must_be_propagated_once() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let bans = Arc::new(Mutex::new(BanList::new()));
+        let msg = Message::from_str(":oper GLINE *@host :spam").unwrap();
+
+        let mut gline = ServerBanMsg::new(&msg, true).unwrap();
+        gline
+            .response(&nicks.get_channels(), &spanning_tree(), &bans)
+            .unwrap();
+        assert!(gline.must_be_propagated());
+
+        let mut repeated = ServerBanMsg::new(&msg, true).unwrap();
+        repeated
+            .response(&nicks.get_channels(), &spanning_tree(), &bans)
+            .unwrap();
+        assert!(!repeated.must_be_propagated());
+    }
+
+    #[test]
+    fn unkline_removes_the_ban() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let bans = Arc::new(Mutex::new(BanList::new()));
+        bans.lock()
+            .unwrap()
+            .add(ServerBan::new(BanKind::K, "*@host", "r", "o", 0, 0).unwrap())
+            .unwrap();
+        let msg = Message::from_str(":oper UNKLINE host").unwrap();
+        let mut unkline = ServerBanMsg::new(&msg, true).unwrap();
+        unkline
+            .response(&nicks.get_channels(), &spanning_tree(), &bans)
+            .unwrap();
+        assert!(bans.lock().unwrap().list(BanKind::K, 0).is_empty());
+    }
+
+    #[test]
+    fn burst_contains_only_glines() {
+        let mut bans = BanList::new();
+        bans.add(ServerBan::new(BanKind::G, "*@a", "spam", "o", 0, 0).unwrap())
+            .unwrap();
+        bans.add(ServerBan::new(BanKind::K, "*@b", "spam", "o", 0, 0).unwrap())
+            .unwrap();
+        assert_eq!(
+            build_gline_msgs(&bans, "server", 0),
+            vec![":server GLINE 0 *@a :spam".to_string()]
+        );
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
     server_comunication::{
         info_sender::send_to_all_servers,
         server::Server,
-        spanning_tree::{edge::Edge, node::Node, SpanningTree},
+        spanning_tree::{edge::Edge, node::Node, SpanningTree, LINK_COST},
     },
 };
 
@@ -35,21 +35,22 @@ impl ServerMsg {
         self,
         spanning_tree: Arc<Mutex<SpanningTree>>,
     ) -> Result<Vec<Reply>, ErrorServer> {
-        let mut replies = vec![];
         match spanning_tree.lock() {
             Ok(mut st) => {
                 let server_to_connect = st.search(self.from.clone());
                 match server_to_connect {
                     Some(actual_server) => {
+                        // a known link was already announced, forwarding it again would loop
+                        if st.has_link(&self.from, &self.servername) {
+                            return Ok(vec![Reply::err_already_registered(None)]);
+                        }
                         let new_server = Server::new(self.servername.clone(), None);
-                        if st.search(self.servername.clone()).is_none() {
-                            //Si no lo tiene, lo agrego sin conexión
-                            let node = Node::new(new_server.clone());
-                            st.add_edge(Edge::new(Node::new(actual_server), node, self.hopcount));
+                        if !st.contains(self.servername.clone()) {
                             println!("NEW SERVER KNOWN: {:?}", new_server.servername);
-                        } else {
-                            replies.push(Reply::err_already_registered(None));
                         }
+                        // a server already known gets a redundant link
+                        let node = Node::new(new_server);
+                        st.add_edge(Edge::new(Node::new(actual_server), node, LINK_COST));
                         let mut prefix = String::from(":");
                         prefix.push_str(&self.from);
                         let message = Message::new(
@@ -66,7 +67,7 @@ impl ServerMsg {
             Err(_) => return Err(ErrorServer::LockedResource),
         }
 
-        Ok(replies)
+        Ok(vec![])
     }
 }
 
@@ -0,0 +1,223 @@
+use std::{
+    slice,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::{code::Code, Reply},
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    server_comunication::{info_sender::send_to_servers, spanning_tree::SpanningTree},
+    utils::mask_matches,
+};
+
+use super::reply_msg::build_reply;
+
+///
+/// Query about a server, like VERSION or MOTD, that may
+/// name the server that has to answer it:
+///
+/// `<command> [<server>]`
+///
+/// A query for another server is sent to it through the spanning
+/// tree, and the replies come back to the user in REPLY messages.
+///
+pub struct ServerQuery {
+    msg: Message,
+    nickname: String,
+    target: Option<String>,
+}
+
+impl ServerQuery {
+    ///
+    /// takes the target server from the
+    /// parameter at the given position
+    ///
+    pub fn new(msg: &Message, position: usize) -> Result<Self, ErrorServer> {
+        let nickname = match msg.prefix() {
+            Some(nickname) => nickname,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        Ok(ServerQuery {
+            msg: msg.clone(),
+            nickname,
+            target: msg.get_param_from_msg(position),
+        })
+    }
+
+    ///
+    /// Answers the query when it has no target or the target matches
+    /// this server. Otherwise it is forwarded to the first server that
+    /// matches the target, answering ERR_NOSUCHSERVER if none does.
+    ///
+    pub fn response<F>(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        answer: F,
+    ) -> Result<Vec<Reply>, ErrorServer>
+    where
+        F: FnOnce(&str) -> Result<Vec<Reply>, ErrorServer>,
+    {
+        let servername = match spanning_tree.lock() {
+            Ok(st) => st.get_root_name(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let target = match &self.target {
+            Some(target) if !mask_matches(target, &servername) => target,
+            _ => {
+                let replies = answer(&servername)?;
+                return self.relay(replies, &servername, nick_sender, spanning_tree);
+            }
+        };
+        let origin = match nick_sender.search(self.nickname.clone())? {
+            Some(connection) => connection
+                .get_servername()
+                .unwrap_or(&servername)
+                .to_string(),
+            None => servername.clone(),
+        };
+        let server = match spanning_tree.lock() {
+            Ok(st) => {
+                let server = st
+                    .get_servers()
+                    .into_iter()
+                    .map(|server| server.servername)
+                    .find(|name| mask_matches(target, name));
+                if let Some(server) = &server {
+                    send_to_servers(&st, &self.msg, slice::from_ref(server), &origin)?;
+                }
+                server
+            }
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        match server {
+            Some(_) => Ok(vec![Reply::rpl_none()]),
+            None => {
+                let replies = vec![Reply::err_no_such_server(None, vec![target.clone()])];
+                self.relay(replies, &servername, nick_sender, spanning_tree)
+            }
+        }
+    }
+
+    // The replies go straight to a local user. For a user of another
+    // server they are sent inside REPLY messages toward its server.
+    fn relay(
+        &self,
+        replies: Vec<Reply>,
+        servername: &str,
+        nick_sender: &ClientChannel<String, Connection>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let (user_server, target) = match nick_sender.search(self.nickname.clone())? {
+            Some(connection) if !connection.is_local() => (
+                connection.get_servername().map(str::to_string),
+                connection.get_uid().unwrap_or(&self.nickname).to_string(),
+            ),
+            _ => (None, self.nickname.clone()),
+        };
+        let user_server = match user_server {
+            Some(user_server) if user_server != servername => user_server,
+            _ => return Ok(replies),
+        };
+        let st = match spanning_tree.lock() {
+            Ok(st) => st,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        for reply in replies.iter().filter(|r| r.code() != Code::RplyNone) {
+            let relayed = build_reply(servername, &target, &reply.to_string());
+            send_to_servers(&st, &relayed, slice::from_ref(&user_server), servername)?;
+        }
+        Ok(vec![Reply::rpl_none()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        database::RepositoryHandler,
+        error::error_server::ErrorServer,
+        parser::message::Message,
+        reply::{code::Code, Reply},
+        repository::{connection::Connection, traits::operations::Operations},
+        server_comunication::{
+            server::Server,
+            spanning_tree::{edge::Edge, node::Node, SpanningTree},
+        },
+        user::User,
+    };
+
+    use super::ServerQuery;
+
+    fn tree() -> Arc<Mutex<SpanningTree>> {
+        let node1 = Node::new(Server::new("server1".to_string(), None));
+        let node2 = Node::new(Server::new("server2".to_string(), None));
+        let st = SpanningTree::new(node1.clone(), vec![Edge::new(node1, node2, 1)]);
+        Arc::new(Mutex::new(st))
+    }
+
+    fn answer(servername: &str) -> Result<Vec<Reply>, ErrorServer> {
+        Ok(vec![Reply::rpl_time(servername, "now")])
+    }
+
+    #[test]
+    fn query_for_this_server_is_answered() {
+        let nicks = RepositoryHandler::<String, Connection>::new().unwrap();
+        let st = tree();
+        for line in [":alice TIME", ":alice TIME server1", ":alice TIME *1"] {
+            let msg = Message::from_str(line).unwrap();
+            let query = ServerQuery::new(&msg, 0).unwrap();
+            let replies = query.response(&nicks.get_channels(), &st, answer).unwrap();
+            assert_eq!(replies[0].to_string(), "391 server1 :now");
+        }
+    }
+
+    #[test]
+    fn query_for_another_server_is_forwarded() {
+        let nicks = RepositoryHandler::<String, Connection>::new().unwrap();
+        let msg = Message::from_str(":alice TIME server2").unwrap();
+        let query = ServerQuery::new(&msg, 0).unwrap();
+        let replies = query
+            .response(&nicks.get_channels(), &tree(), |_| {
+                panic!("the query is not for this server")
+            })
+            .unwrap();
+        assert_eq!(replies, vec![Reply::rpl_none()]);
+    }
+
+    #[test]
+    fn query_for_unknown_server_is_err_no_such_server() {
+        let nicks = RepositoryHandler::<String, Connection>::new().unwrap();
+        let msg = Message::from_str(":alice STATS l server9").unwrap();
+        let query = ServerQuery::new(&msg, 1).unwrap();
+        let replies = query
+            .response(&nicks.get_channels(), &tree(), answer)
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::ErrNosuchserver);
+    }
+
+    #[test]
+    fn replies_for_a_remote_user_are_relayed() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let user = User::new("bob", "bob", "host", "server2", "real", "");
+        nicks
+            .get_channels()
+            .add("bob".to_string(), Connection::connection_away_server(user))
+            .unwrap();
+        let msg = Message::from_str(":bob TIME server1").unwrap();
+        let query = ServerQuery::new(&msg, 0).unwrap();
+        let replies = query
+            .response(&nicks.get_channels(), &tree(), answer)
+            .unwrap();
+        assert_eq!(replies, vec![Reply::rpl_none()]);
+    }
+}
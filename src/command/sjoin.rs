@@ -0,0 +1,278 @@
+use std::cmp::Ordering;
+
+use crate::{
+    channel::{member_status::MemberStatus, mode_parser::takes_parameter, Channel},
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        repository_channel::client_channel::ClientChannel, traits::operations::Operations,
+    },
+};
+
+///
+/// struct that implements the SJOIN message, sent by a server
+/// when it links to announce each of its channels:
+///
+/// `:<server> SJOIN <timestamp> <channel> <modes> [parameters] :<members>`
+///
+/// where each member starts with the prefixes of its ranks, like `@`
+/// for the operators and `+` for the voiced users.
+///
+pub struct SjoinMsg {
+    created_at: u64,
+    channel: String,
+    modes: Vec<(char, Option<String>)>,
+    members: Vec<Member>,
+}
+
+struct Member {
+    nickname: String,
+    status: MemberStatus,
+}
+
+impl SjoinMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let mut parameters = match msg.parameters() {
+            Some(parameters) if parameters.len() >= 4 => parameters,
+            _ => return Err(ErrorServer::BadQuery),
+        };
+        let members = parameters.pop().unwrap_or_default();
+        let mut parameters = parameters.into_iter();
+        let created_at = match parameters.next().map(|ts| ts.parse()) {
+            Some(Ok(created_at)) => created_at,
+            _ => return Err(ErrorServer::BadQuery),
+        };
+        let channel = parameters.next().unwrap_or_default();
+        let flags = parameters.next().unwrap_or_default();
+        let modes = flags
+            .chars()
+            .filter(|mode| *mode != '+')
+            .map(|mode| match takes_parameter(true, mode) {
+                true => (mode, parameters.next()),
+                false => (mode, None),
+            })
+            .collect();
+        Ok(SjoinMsg {
+            created_at,
+            channel,
+            modes,
+            members: members
+                .trim_start_matches(':')
+                .split_whitespace()
+                .map(Member::from)
+                .collect(),
+        })
+    }
+
+    ///
+    /// keeps only the members for which the given
+    /// function returns true
+    ///
+    pub fn retain_members<F: FnMut(&str) -> bool>(&mut self, mut f: F) {
+        self.members.retain(|member| f(&member.nickname));
+    }
+
+    ///
+    /// Merges the announced channel with the one of this server.
+    /// The older channel keeps its modes and the status of its
+    /// members, the newer one loses them. With equal timestamps
+    /// both are merged.
+    ///
+    pub fn response(
+        &self,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        // none of its members is still there
+        if self.members.is_empty() {
+            return Ok(vec![Reply::rpl_none()]);
+        }
+        let (mut channel, ordering) = match channel_sender.search(self.channel.clone())? {
+            Some(channel) => {
+                let ordering = self.created_at.cmp(&channel.created_at());
+                (channel, ordering)
+            }
+            None => (
+                Channel::from_burst(self.channel.clone(), self.created_at),
+                Ordering::Equal,
+            ),
+        };
+        if ordering == Ordering::Less {
+            channel.clear_modes();
+            channel.set_created_at(self.created_at);
+        }
+        let keeps_status = ordering != Ordering::Greater;
+        if keeps_status {
+            for (mode, parameter) in &self.modes {
+                if Self::must_apply(&channel, *mode, parameter, ordering) {
+                    channel.modify_channel_flag(&format!("+{mode}"), parameter.clone());
+                }
+            }
+        }
+        for member in &self.members {
+            let status = match keeps_status {
+                true => member.status,
+                false => MemberStatus::default(),
+            };
+            channel.join_with_status(&member.nickname, status);
+        }
+        channel_sender.update(self.channel.clone(), channel)?;
+        Ok(vec![Reply::rpl_none()])
+    }
+
+    // When both channels are merged, the greatest key and limit
+    // are kept, so both servers get the same result.
+    fn must_apply(
+        channel: &Channel,
+        mode: char,
+        parameter: &Option<String>,
+        ordering: Ordering,
+    ) -> bool {
+        if ordering != Ordering::Equal {
+            return true;
+        }
+        match (mode, parameter) {
+            ('k', Some(key)) => channel.get_key().is_none_or(|current| *key > current),
+            ('l', Some(limit)) => match (limit.parse::<usize>(), channel.get_user_limit()) {
+                (Ok(limit), Some(current)) => limit > current,
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+}
+
+impl From<&str> for Member {
+    fn from(member: &str) -> Self {
+        let (status, nickname) = MemberStatus::parse(member);
+        Member {
+            nickname: nickname.to_string(),
+            status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        channel::{member_status::Rank, Channel},
+        database::RepositoryHandler,
+        parser::message::Message,
+        repository::{
+            repository_channel::client_channel::ClientChannel, traits::operations::Operations,
+        },
+    };
+
+    use super::SjoinMsg;
+
+    fn local_channel(channels: &ClientChannel<String, Channel>, created_at: u64) {
+        let mut channel = Channel::new("#c".to_string(), "local".to_string());
+        channel.set_created_at(created_at);
+        channel.modify_channel_flag("+k", Some("bbb".to_string()));
+        channels.add("#c".to_string(), channel).unwrap();
+    }
+
+    fn sjoin(created_at: u64, channels: &ClientChannel<String, Channel>) -> Channel {
+        let msg = format!(":server2 SJOIN {created_at} #c +tk aaa :@remote +voiced");
+        SjoinMsg::new(&Message::from_str(&msg).unwrap())
+            .unwrap()
+            .response(channels)
+            .unwrap();
+        channels.search("#c".to_string()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn unknown_channel_is_created() {
+        let channels = RepositoryHandler::new().unwrap();
+        let channel = sjoin(10, &channels.get_channels());
+
+        assert_eq!(channel.created_at(), 10);
+        assert!(channel.is_channel_operator("remote"));
+        assert!(channel.has_member("voiced"));
+        assert_eq!(channel.get_key(), Some("aaa".to_string()));
+    }
+
+    #[test]
+    fn every_rank_of_a_member_is_kept() {
+        let channels = RepositoryHandler::new().unwrap();
+        let msg = Message::from_str(":server2 SJOIN 10 #c + :~@owner %+helper").unwrap();
+        SjoinMsg::new(&msg)
+            .unwrap()
+            .response(&channels.get_channels())
+            .unwrap();
+
+        let channel = channels.get_channels().search("#c".to_string()).unwrap();
+        let channel = channel.unwrap();
+        assert_eq!(channel.list_channel(), "#c: ~owner %helper");
+        assert!(channel.member_status("helper").unwrap().has(Rank::Voice));
+    }
+
+    #[test]
+    fn older_channel_wins_the_modes() {
+        let channels = RepositoryHandler::new().unwrap();
+        local_channel(&channels.get_channels(), 20);
+        let channel = sjoin(10, &channels.get_channels());
+
+        assert_eq!(channel.created_at(), 10);
+        assert!(channel.is_channel_operator("remote"));
+        assert!(!channel.is_channel_operator("local"));
+        assert!(channel.has_member("local"));
+        assert_eq!(channel.get_key(), Some("aaa".to_string()));
+    }
+
+    #[test]
+    fn newer_channel_loses_the_modes() {
+        let channels = RepositoryHandler::new().unwrap();
+        local_channel(&channels.get_channels(), 10);
+        let channel = sjoin(20, &channels.get_channels());
+
+        assert_eq!(channel.created_at(), 10);
+        assert!(channel.is_channel_operator("local"));
+        assert!(!channel.is_channel_operator("remote"));
+        assert!(channel.has_member("remote"));
+        assert_eq!(channel.get_key(), Some("bbb".to_string()));
+    }
+
+    #[test]
+    fn channels_of_equal_timestamp_are_merged() {
+        let channels = RepositoryHandler::new().unwrap();
+        local_channel(&channels.get_channels(), 10);
+        let channel = sjoin(10, &channels.get_channels());
+
+        assert!(channel.is_channel_operator("local"));
+        assert!(channel.is_channel_operator("remote"));
+        assert_eq!(channel.get_key(), Some("bbb".to_string()));
+    }
+
+    #[test]
+    fn discarded_members_dont_join() {
+        let channels = RepositoryHandler::new().unwrap();
+        let msg = Message::from_str(":server2 SJOIN 10 #c + :@remote other").unwrap();
+        let mut sjoin = SjoinMsg::new(&msg).unwrap();
+        sjoin.retain_members(|nickname| nickname == "other");
+        sjoin.response(&channels.get_channels()).unwrap();
+
+        let channel = channels.get_channels().search("#c".to_string()).unwrap();
+        assert!(!channel.unwrap().has_member("remote"));
+    }
+
+    #[test]
+    fn channel_without_members_is_ignored() {
+        let channels = RepositoryHandler::new().unwrap();
+        let msg = Message::from_str(":server2 SJOIN 10 #c + :@remote").unwrap();
+        let mut sjoin = SjoinMsg::new(&msg).unwrap();
+        sjoin.retain_members(|_| false);
+        sjoin.response(&channels.get_channels()).unwrap();
+
+        let channel = channels.get_channels().search("#c".to_string()).unwrap();
+        assert!(channel.is_none());
+    }
+
+    #[test]
+    fn sjoin_without_members_is_err() {
+        let msg = Message::from_str(":server2 SJOIN 10 #c").unwrap();
+        assert!(SjoinMsg::new(&msg).is_err());
+    }
+}
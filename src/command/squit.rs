@@ -1,6 +1,5 @@
 use crate::{
     channel::Channel,
-    command::quit::QuitMsg,
     error::error_server::ErrorServer,
     parser::message::Message,
     reply::Reply,
@@ -8,14 +7,11 @@ use crate::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
-    server_comunication::spanning_tree::{edge::Edge, SpanningTree},
+    server_comunication::{netsplit::quit_lost_users, spanning_tree::SpanningTree},
     utils::write_message_to,
 };
 
-use std::{
-    str::FromStr,
-    sync::{Arc, Mutex, MutexGuard},
-};
+use std::sync::{Arc, Mutex};
 
 ///
 /// struct that implements the
@@ -56,18 +52,39 @@ impl SquitMsg {
         channel_sender: &ClientChannel<String, Channel>,
         spanning_tree: &Arc<Mutex<SpanningTree>>,
     ) -> Result<Vec<Reply>, ErrorServer> {
-        let operator = match nick_sender.search(self.oper.to_string())? {
-            Some(u) => u,
-            None => return Err(ErrorServer::UnreachableClient),
+        let actual_server = match spanning_tree.lock() {
+            Ok(st) => {
+                if !st.contains(self.server_to_delete.clone()) {
+                    return Ok(vec![Reply::err_no_such_server(
+                        None,
+                        vec![self.server_to_delete.to_string()],
+                    )]);
+                }
+                // a server that shuts down sends the SQUIT with its own prefix
+                match st.contains(self.oper.clone()) {
+                    true => Some(self.oper.clone()),
+                    false => None,
+                }
+            }
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let actual_server = match actual_server {
+            Some(server) => server,
+            None => match nick_sender.search(self.oper.to_string())? {
+                Some(operator) => match operator.get_servername() {
+                    Some(servername) => servername.to_string(),
+                    None => {
+                        return Ok(vec![Reply::err_no_such_server(
+                            None,
+                            vec![self.server_to_delete.to_string()],
+                        )])
+                    }
+                },
+                None => return Err(ErrorServer::UnreachableClient),
+            },
         };
 
-        let actual_server = operator.get_servername().unwrap().to_string();
-
-        let lost_servers = self.get_lost_servers(spanning_tree);
-
-        println!("SERVERS PERDIDOS {:?}", lost_servers);
-
-        match spanning_tree.lock() {
+        let lost_servers = match spanning_tree.lock() {
             Ok(mut st) => {
                 let mut msg = String::from(":");
                 msg.push_str(&self.oper);
@@ -78,7 +95,9 @@ impl SquitMsg {
                             msg.push_str(&st.get_root().server.servername);
                             msg.push(' ');
                             msg.push_str(&self._msg);
-                            if let Some(origin) = st.look_for_nearest_connection(actual_server) {
+                            if let Some(origin) =
+                                st.look_for_nearest_connection(actual_server.clone())
+                            {
                                 if origin.servername != self.server_to_delete
                                     && write_message_to(&msg, &mut s).is_err()
                                 {
@@ -94,31 +113,13 @@ impl SquitMsg {
                         )])
                     }
                 };
-
-                for edge in self.get_lost_edges(&st) {
-                    println!(
-                        "ELIMINANDO {}->{}",
-                        edge.source.get_element().servername,
-                        edge.destination.get_element().servername
-                    );
-                    st.delete_edge(
-                        edge.source.get_element().servername,
-                        edge.destination.get_element().servername,
-                    );
-                }
+                st.split(&self.server_to_delete)
             }
             Err(_) => return Err(ErrorServer::LockedResource),
-        }
+        };
 
-        self.get_quit_messages(lost_servers, nick_sender)?
-            .iter()
-            .filter_map(|msg| QuitMsg::new(msg).ok())
-            .for_each(|quit| {
-                println!("Elimino usuario {:?}", quit.user);
-                if quit.response(nick_sender, channel_sender).is_err() {
-                    println!("error squit - when try to quit user from channel");
-                };
-            });
+        let reason = format!("{actual_server} {}", self.server_to_delete);
+        quit_lost_users(&lost_servers, &reason, nick_sender, channel_sender)?;
 
         Ok(vec![Reply::rpl_none()])
     }
@@ -153,88 +154,6 @@ impl SquitMsg {
         Err(ErrorServer::ServerClosed)
     }
 
-    fn create_quit_message(&self, nickname: &str) -> Result<Message, ErrorServer> {
-        let mut msg = String::from(":");
-        msg.push_str(nickname);
-        msg.push_str(" QUIT :Server quit");
-
-        Ok(Message::from_str(&msg)?)
-    }
-
-    fn get_quit_messages(
-        &self,
-        lost_server: Vec<String>,
-        nick_sender: &ClientChannel<String, Connection>,
-    ) -> Result<Vec<Message>, ErrorServer> {
-        let mut quit_msgs = vec![];
-        for connection in nick_sender.find_all()? {
-            if lost_server.contains(&connection.get_servername().unwrap().to_string()) {
-                quit_msgs.push(self.create_quit_message(&connection.get_nickname())?);
-            }
-        }
-        Ok(quit_msgs)
-    }
-
-    fn get_lost_servers(&self, spanning_tree: &Arc<Mutex<SpanningTree>>) -> Vec<String> {
-        let mut servernames = vec![self.server_to_delete.clone()];
-        let edges = match spanning_tree.lock() {
-            Ok(st) => self.get_lost_edges(&st),
-            Err(_) => vec![],
-        };
-        let minimum_cost = edges
-            .iter()
-            .filter(|e| {
-                e.source.get_element().servername == self.server_to_delete
-                    || e.destination.get_element().servername == self.server_to_delete
-            })
-            .min_by(|e1, e2| e1.cost.cmp(&e2.cost))
-            .unwrap()
-            .cost;
-
-        for e in edges {
-            if e.cost != minimum_cost {
-                if !servernames.contains(&e.source.get_element().servername) {
-                    servernames.push(e.source.get_element().servername.clone())
-                }
-                if !servernames.contains(&e.destination.get_element().servername) {
-                    servernames.push(e.destination.get_element().servername.clone())
-                }
-            }
-        }
-        //servernames.sort();
-        //servernames.dedup();
-
-        servernames
-    }
-
-    fn get_lost_edges(&self, st: &MutexGuard<SpanningTree>) -> Vec<Edge> {
-        let minimum_cost = st
-            .get_edges()
-            .into_iter()
-            .filter(|e| {
-                e.source.get_element().servername == self.server_to_delete
-                    || e.destination.get_element().servername == self.server_to_delete
-            })
-            .min_by(|e1, e2| e1.cost.cmp(&e2.cost))
-            .unwrap()
-            .cost;
-
-        println!("COSTO MINIMO {}", minimum_cost);
-
-        let edges: Vec<Edge> = st
-            .get_edges()
-            .into_iter()
-            .filter(|e| {
-                //Nos fijamos los que forman conexiones directas con el server
-                //a eliminar y los que cumplan con el costo minimo para llegar hacia el.
-                e.cost > minimum_cost
-                    || e.source.get_element().servername == self.server_to_delete
-                    || e.destination.get_element().servername == self.server_to_delete
-            })
-            .collect();
-        edges
-    }
-
     pub fn get_operator(
         &self,
         nick_sender: &ClientChannel<String, Connection>,
@@ -245,3 +164,52 @@ impl SquitMsg {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        database::RepositoryHandler,
+        parser::message::Message,
+        reply::code::Code,
+        repository::{connection::Connection, traits::operations::Operations},
+        server_comunication::{
+            server::Server,
+            spanning_tree::{edge::Edge, node::Node, SpanningTree},
+        },
+        user::User,
+    };
+
+    use super::SquitMsg;
+
+    #[test]
+    fn operator_without_server_gets_an_error_reply() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let oper = User::new("oper", "oper", "host", "", "Oper", "");
+        nicks
+            .get_channels()
+            .add("oper".to_string(), Connection::connection_away_server(oper))
+            .unwrap();
+        let server1 = Node::new(Server::new("server1".to_string(), None));
+        let server2 = Node::new(Server::new("server2".to_string(), None));
+        let st = SpanningTree::new(server1.clone(), vec![Edge::new(server1, server2, 1)]);
+
+        let msg = Message::from_str(":oper SQUIT server2 :bye").unwrap();
+        let replies = SquitMsg::new(&msg)
+            .unwrap()
+            .response(
+                &nicks.get_channels(),
+                &channels.get_channels(),
+                &Arc::new(Mutex::new(st)),
+            )
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::ErrNosuchserver);
+        nicks.stop().unwrap();
+        channels.stop().unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::{
+    ban::{ban_kind::BanKind, BanList},
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    server_comunication::spanning_tree::SpanningTree,
+    utils::unix_timestamp,
+};
+
+// query that lists the links with the neighbour servers
+const LINKS_QUERY: &str = "l";
+
+///
+/// struct that implements the STATS message:
+/// `STATS <query>`
+///
+pub struct StatsMsg {
+    query: Option<String>,
+}
+
+impl StatsMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        Ok(StatsMsg {
+            query: msg.get_param_from_msg(0),
+        })
+    }
+
+    ///
+    /// Answers the query. The `k`, `g` and `z` queries list
+    /// the server bans, and the `l` query the health of the
+    /// links with the neighbour servers. They are reserved
+    /// to operators.
+    ///
+    pub fn response(
+        &self,
+        oper: bool,
+        bans: &Arc<Mutex<BanList>>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let query = match &self.query {
+            Some(q) => q.clone(),
+            None => {
+                return Ok(vec![Reply::err_need_more_params(
+                    None,
+                    vec!["STATS".to_string()],
+                )])
+            }
+        };
+        let mut replies = vec![];
+        if let Ok(kind) = BanKind::from_str(&query) {
+            if !oper {
+                return Ok(vec![Reply::err_no_privileges(None)]);
+            }
+            replies.append(&mut Self::ban_replies(kind, bans)?);
+        }
+        if query == LINKS_QUERY {
+            if !oper {
+                return Ok(vec![Reply::err_no_privileges(None)]);
+            }
+            replies.append(&mut Self::link_replies(spanning_tree)?);
+        }
+        replies.push(Reply::rpl_end_of_stats(query));
+        Ok(replies)
+    }
+
+    // One line per link: the bytes waiting to be sent, the messages
+    // and kilobytes sent and received, the seconds it has been open
+    // and the lag measured with the last PING.
+    fn link_replies(spanning_tree: &Arc<Mutex<SpanningTree>>) -> Result<Vec<Reply>, ErrorServer> {
+        let links = match spanning_tree.lock() {
+            Ok(st) => st.get_links(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let now = Instant::now();
+        let mut replies = vec![];
+        for link in links {
+            let health = match link.get_connection() {
+                Some(connection) => connection.health(),
+                None => continue,
+            };
+            let health = match health.lock() {
+                Ok(health) => health,
+                Err(_) => return Err(ErrorServer::LockedResource),
+            };
+            let lag = match health.lag() {
+                Some(lag) => format!(":lag {}ms", lag.as_millis()),
+                None => ":lag unknown".to_string(),
+            };
+            replies.push(Reply::rpl_stats_link_info(vec![
+                link.servername.clone(),
+                health.sendq().to_string(),
+                health.sent().messages.to_string(),
+                (health.sent().bytes / 1024).to_string(),
+                health.received().messages.to_string(),
+                (health.received().bytes / 1024).to_string(),
+                health.open_for(now).as_secs().to_string(),
+                lag,
+            ]));
+        }
+        Ok(replies)
+    }
+
+    fn ban_replies(kind: BanKind, bans: &Arc<Mutex<BanList>>) -> Result<Vec<Reply>, ErrorServer> {
+        let now = unix_timestamp();
+        let list = match bans.lock() {
+            Ok(list) => list.list(kind, now),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        Ok(list
+            .into_iter()
+            .map(|ban| {
+                Reply::rpl_stats_kline(vec![
+                    ban.kind.to_string(),
+                    ban.mask.clone(),
+                    ban.remaining(now).to_string(),
+                    ban.setter.clone(),
+                    format!(":{}", ban.reason),
+                ])
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        ban::{ban_kind::BanKind, BanList, ServerBan},
+        command::fixtures::{link_server, spanning_tree},
+        parser::message::Message,
+        reply::code::Code,
+        utils::write_message_to,
+    };
+
+    use super::StatsMsg;
+
+    fn bans() -> Arc<Mutex<BanList>> {
+        let mut list = BanList::new();
+        list.add(ServerBan::new(BanKind::K, "*@a", "spam", "oper", 0, 0).unwrap())
+            .unwrap();
+        Arc::new(Mutex::new(list))
+    }
+
+    #[test]
+    fn stats_k_lists_klines() {
+        let msg = Message::from_str(":oper STATS k").unwrap();
+        let replies = StatsMsg::new(&msg)
+            .unwrap()
+            .response(true, &bans(), &spanning_tree())
+            .unwrap();
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0].code(), Code::RplyStatsKLine);
+        assert_eq!(replies[1].code(), Code::RplyEndOfStats);
+    }
+
+    #[test]
+    fn stats_k_needs_operator() {
+        let msg = Message::from_str(":user STATS k").unwrap();
+        let replies = StatsMsg::new(&msg)
+            .unwrap()
+            .response(false, &bans(), &spanning_tree())
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::ErrNoprivileges);
+    }
+
+    #[test]
+    fn stats_without_query_is_err() {
+        let msg = Message::from_str(":user STATS").unwrap();
+        let replies = StatsMsg::new(&msg)
+            .unwrap()
+            .response(true, &bans(), &spanning_tree())
+            .unwrap();
+        assert_eq!(replies[0].code(), Code::ErrNeedmoreparams);
+    }
+
+    #[test]
+    fn stats_l_lists_the_links() {
+        let st = spanning_tree();
+        let _link = link_server(&st, "server2");
+        let server2 = st.lock().unwrap().search("server2".to_string()).unwrap();
+        let mut connection = server2.get_connection().unwrap();
+        write_message_to(&":server PING server :1", &mut connection).unwrap();
+
+        let msg = Message::from_str(":oper STATS l").unwrap();
+        let stats = StatsMsg::new(&msg).unwrap();
+        let replies = stats.response(true, &bans(), &st).unwrap();
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0].code(), Code::RplyStatsLinkInfo);
+        // nothing waiting and one message sent
+        assert!(replies[0].to_string().contains("server2 0 1 0 0 0"));
+
+        let replies = stats.response(false, &bans(), &st).unwrap();
+        assert_eq!(replies[0].code(), Code::ErrNoprivileges);
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{
+    reply::Reply,
+    utils::{format_utc, unix_timestamp},
+};
+
+///
+/// struct that implements the TIME message, that
+/// answers with the current time of the server:
+/// `TIME [<server>]`
+///
+pub struct TimeMsg;
+
+impl TimeMsg {
+    pub fn response(servername: &str) -> Vec<Reply> {
+        vec![Reply::rpl_time(servername, &format_utc(unix_timestamp()))]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::reply::code::Code;
+
+    use super::TimeMsg;
+
+    #[test]
+    fn answers_the_time_in_utc() {
+        let replies = TimeMsg::response("server1");
+        assert_eq!(replies[0].code(), Code::RplyTime);
+        assert!(replies[0].to_string().starts_with("391 server1 :"));
+        assert!(replies[0].to_string().ends_with(" UTC"));
+    }
+}
@@ -1,33 +1,47 @@
 use crate::{
-    channel::Channel,
+    channel::{topic::Topic, Channel},
+    command::Command,
     error::error_server::ErrorServer,
     parser::message::Message,
     reply::Reply,
     repository::{
-        repository_channel::client_channel::ClientChannel, traits::operations::Operations,
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
     },
+    utils::{unix_timestamp, write_message_to},
 };
 
+///
+/// struct that implements the TOPIC message, that
+/// shows or sets the topic of a channel:
+///
+/// `TOPIC <channel> [:<topic>]`
+///
 pub struct TopicMsg {
     user: String,
     channel: Option<String>,
     topic: Option<String>,
+    forward: Option<Message>,
 }
 
 impl TopicMsg {
     ///
     /// function that creates a
-    /// new names message
+    /// new topic message
     ///
     pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
         let user = match msg.prefix() {
             Some(u) => u,
             None => return Err(ErrorServer::UnknownCommand),
         };
+        let topic = msg
+            .get_param_from_msg(1)
+            .map(|topic| topic.strip_prefix(':').unwrap_or(&topic).trim().to_string());
         Ok(TopicMsg {
             user,
             channel: msg.get_param_from_msg(0),
-            topic: msg.get_param_from_msg(1),
+            topic,
+            forward: None,
         })
     }
 
@@ -36,33 +50,32 @@ impl TopicMsg {
             user,
             channel: Some(channel),
             topic: None,
+            forward: None,
         })
     }
+
+    ///
+    /// returns the TOPIC to send to the other
+    /// servers, if the topic was changed
     ///
-    /// function that responds to the names
+    pub fn forward(&self) -> Option<Message> {
+        self.forward.clone()
+    }
+
+    ///
+    /// function that responds to the topic
     /// message sent by the user and
-    /// acts accordingly
+    /// acts accordingly. A new topic is
+    /// told to the members of the channel.
     ///
     pub fn response(
         &mut self,
+        nick_sender: &ClientChannel<String, Connection>,
         channel_sender: ClientChannel<String, Channel>,
     ) -> Result<Vec<Reply>, ErrorServer> {
-        let mut channel = match &self.channel {
-            None => {
-                return Ok(vec![Reply::err_need_more_params(
-                    None,
-                    vec!["TOPIC".to_string()],
-                )]);
-            }
-            Some(name) => match channel_sender.search(name.to_owned())? {
-                Some(ch) => ch,
-                None => {
-                    return Ok(vec![Reply::err_no_such_channel(
-                        None,
-                        vec![name.to_string()],
-                    )]);
-                }
-            },
+        let mut channel = match self.search_channel(&channel_sender)? {
+            Ok(channel) => channel,
+            Err(reply) => return Ok(vec![reply]),
         };
         if !channel.has_member(&self.user) {
             return Ok(vec![Reply::err_not_on_channel(
@@ -71,25 +84,193 @@ impl TopicMsg {
             )]);
         }
 
-        let reply = match &self.topic {
-            None => match channel.get_topic() {
-                Some(t) => Reply::rpl_topic(channel.name.clone(), t),
-                None => Reply::rpl_no_topic(channel.name.clone()),
-            },
-            Some(t) => {
-                if !channel.user_can_set_topic(&self.user) {
-                    return Ok(vec![Reply::err_chan_o_privs_needed(
-                        None,
-                        vec![self.user.clone()],
-                    )]);
-                }
-
-                channel.set_topic(t.to_string());
-                channel_sender.update(channel.name.clone(), channel)?;
-                Reply::rpl_none()
+        let text = match &self.topic {
+            None => return Ok(Self::topic_replies(&channel.name, channel.topic_info())),
+            Some(text) => text.clone(),
+        };
+        if !channel.user_can_set_topic(&self.user) {
+            return Ok(vec![Reply::err_chan_o_privs_needed(
+                None,
+                vec![self.user.clone()],
+            )]);
+        }
+        let setter = match nick_sender.search(self.user.clone())? {
+            Some(connection) => connection.get_mask(),
+            None => self.user.clone(),
+        };
+        channel.set_topic(Topic::new(&text, &setter, unix_timestamp()));
+        channel_sender.update(channel.name.clone(), channel.clone())?;
+
+        broadcast_topic(&channel, &setter, &text, nick_sender)?;
+        self.forward = Some(build_topic(&self.user, &channel.name, &text));
+        Ok(vec![Reply::rpl_none()])
+    }
+
+    ///
+    /// answers the last topics of the channel, the newest
+    /// first, each with who set it and when. Only the
+    /// operators of the server can ask for them.
+    ///
+    pub fn history(
+        &self,
+        oper: bool,
+        channel_sender: ClientChannel<String, Channel>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        if !oper {
+            return Ok(vec![Reply::err_no_privileges(None)]);
+        }
+        let channel = match self.search_channel(&channel_sender)? {
+            Ok(channel) => channel,
+            Err(reply) => return Ok(vec![reply]),
+        };
+        let history = channel.topic_history();
+        if history.is_empty() {
+            return Ok(vec![Reply::rpl_no_topic(channel.name)]);
+        }
+        Ok(history
+            .iter()
+            .flat_map(|topic| Self::topic_replies(&channel.name, Some(topic)))
+            .collect())
+    }
+
+    // The channel named in the message, or the reply
+    // to give when it is missing or does not exist.
+    fn search_channel(
+        &self,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<Result<Channel, Reply>, ErrorServer> {
+        let name = match &self.channel {
+            Some(name) => name,
+            None => {
+                return Ok(Err(Reply::err_need_more_params(
+                    None,
+                    vec!["TOPIC".to_string()],
+                )))
             }
         };
+        match channel_sender.search(name.to_owned())? {
+            Some(channel) => Ok(Ok(channel)),
+            None => Ok(Err(Reply::err_no_such_channel(None, vec![name.clone()]))),
+        }
+    }
+
+    // RPL_TOPIC followed by RPL_TOPICWHOTIME, or RPL_NOTOPIC.
+    fn topic_replies(channel: &str, topic: Option<&Topic>) -> Vec<Reply> {
+        match topic {
+            Some(topic) => vec![
+                Reply::rpl_topic(channel.to_string(), format!(":{}", topic.text)),
+                Reply::rpl_topic_who_time(channel, &topic.setter, topic.set_at),
+            ],
+            None => vec![Reply::rpl_no_topic(channel.to_string())],
+        }
+    }
+}
+
+///
+/// writes the new topic of the channel to its local
+/// members, as sent by the user that set it
+///
+pub fn broadcast_topic(
+    channel: &Channel,
+    setter: &str,
+    text: &str,
+    nick_sender: &ClientChannel<String, Connection>,
+) -> Result<(), ErrorServer> {
+    let line = build_topic(setter, &channel.name, text);
+    for member in channel.return_members() {
+        if let Some(mut connection) = nick_sender.search(member)? {
+            if connection.is_local() {
+                let _ = write_message_to(&line, &mut connection);
+            }
+        }
+    }
+    Ok(())
+}
+
+// The TOPIC that sets the text, sent by the given prefix.
+fn build_topic(prefix: &str, channel: &str, text: &str) -> Message {
+    Message::new(
+        Some(format!(":{prefix}")),
+        Command::Topic,
+        Some(vec![channel.to_string(), format!(":{text}")]),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        channel::{topic::Topic, Channel},
+        database::RepositoryHandler,
+        parser::message::Message,
+        reply::{code::Code, Reply},
+        repository::traits::operations::Operations,
+    };
+
+    use super::TopicMsg;
+
+    #[test]
+    fn topic_is_set_with_its_setter_and_forwarded() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        channels
+            .get_channels()
+            .add("#canal".to_string(), channel)
+            .unwrap();
+
+        let msg = Message::from_str(":pedro TOPIC #canal :hola mundo").unwrap();
+        let mut topic = TopicMsg::new(&msg).unwrap();
+        let replies = topic
+            .response(&nicks.get_channels(), channels.get_channels())
+            .unwrap();
+        assert_eq!(replies, vec![Reply::rpl_none()]);
+        assert_eq!(
+            topic.forward().unwrap().to_string(),
+            ":pedro TOPIC #canal :hola mundo"
+        );
+
+        let msg = Message::from_str(":pedro TOPIC #canal").unwrap();
+        let mut query = TopicMsg::new(&msg).unwrap();
+        let replies = query
+            .response(&nicks.get_channels(), channels.get_channels())
+            .unwrap();
+        assert_eq!(replies[0].to_string(), "332 #canal :hola mundo");
+        assert_eq!(replies[1].code(), Code::RplyTopicWhoTime);
+        assert!(query.forward().is_none());
+    }
+
+    #[test]
+    fn history_is_only_for_operators() {
+        let channels = RepositoryHandler::new().unwrap();
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        channel.set_topic(Topic::new("primero", "pedro!pedro@host", 1));
+        channel.set_topic(Topic::new("segundo", "juan!juan@host", 2));
+        channels
+            .get_channels()
+            .add("#canal".to_string(), channel)
+            .unwrap();
+
+        let msg = Message::from_str(":ana TOPICHISTORY #canal").unwrap();
+        let history = TopicMsg::new(&msg).unwrap();
+        let replies = history.history(false, channels.get_channels()).unwrap();
+        assert_eq!(replies, vec![Reply::err_no_privileges(None)]);
 
-        Ok(vec![reply])
+        let replies: Vec<String> = history
+            .history(true, channels.get_channels())
+            .unwrap()
+            .iter()
+            .map(Reply::to_string)
+            .collect();
+        assert_eq!(
+            replies,
+            vec![
+                "332 #canal :segundo",
+                "333 #canal juan!juan@host 2",
+                "332 #canal :primero",
+                "333 #canal pedro!pedro@host 1"
+            ]
+        );
     }
 }
@@ -0,0 +1,114 @@
+use crate::{
+    channel::{topic::Topic, Channel},
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+};
+
+use super::topic::broadcast_topic;
+
+///
+/// struct that implements the TB message, sent by a server
+/// when it links to announce the topic of each of its channels:
+///
+/// `:<server> TB <channel> <set_at> <setter> :<topic>`
+///
+/// The channel keeps the topic that was set last.
+///
+pub struct TopicBurstMsg {
+    channel: String,
+    topic: Topic,
+}
+
+impl TopicBurstMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let parameters = match msg.parameters() {
+            Some(parameters) if parameters.len() >= 4 => parameters,
+            _ => return Err(ErrorServer::BadQuery),
+        };
+        let set_at = match parameters[1].parse() {
+            Ok(set_at) => set_at,
+            Err(_) => return Err(ErrorServer::BadQuery),
+        };
+        let text = parameters[3].strip_prefix(':').unwrap_or(&parameters[3]);
+        Ok(TopicBurstMsg {
+            channel: parameters[0].clone(),
+            topic: Topic::new(text.trim(), &parameters[2], set_at),
+        })
+    }
+
+    ///
+    /// Sets the topic if it is newer than the one of the
+    /// channel, telling the change to its local members.
+    ///
+    pub fn response(
+        &self,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let mut channel = match channel_sender.search(self.channel.clone())? {
+            Some(channel) => channel,
+            None => return Ok(vec![Reply::rpl_none()]),
+        };
+        if channel.merge_topic(self.topic.clone()) {
+            channel_sender.update(self.channel.clone(), channel.clone())?;
+            broadcast_topic(&channel, &self.topic.setter, &self.topic.text, nick_sender)?;
+        }
+        Ok(vec![Reply::rpl_none()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        channel::{topic::Topic, Channel},
+        database::RepositoryHandler,
+        parser::message::Message,
+        repository::traits::operations::Operations,
+    };
+
+    use super::TopicBurstMsg;
+
+    #[test]
+    fn newer_topic_replaces_the_current_one() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let mut channel = Channel::new("#canal".to_string(), "pedro".to_string());
+        channel.set_topic(Topic::new("hola", "pedro!pedro@host", 10));
+        channels
+            .get_channels()
+            .add("#canal".to_string(), channel)
+            .unwrap();
+
+        for line in [
+            ":server2 TB #canal 5 juan!juan@host :viejo",
+            ":server2 TB #canal 20 ana!ana@host :nuevo tema",
+        ] {
+            let msg = TopicBurstMsg::new(&Message::from_str(line).unwrap()).unwrap();
+            msg.response(&nicks.get_channels(), &channels.get_channels())
+                .unwrap();
+        }
+
+        let channel = channels
+            .get_channels()
+            .search("#canal".to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            channel.topic_info(),
+            Some(&Topic::new("nuevo tema", "ana!ana@host", 20))
+        );
+    }
+
+    #[test]
+    fn burst_without_setter_is_err() {
+        let msg = Message::from_str(":server2 TB #canal 20 :tema").unwrap();
+        assert!(TopicBurstMsg::new(&msg).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    channel::Channel,
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{connection::Connection, repository_channel::client_channel::ClientChannel},
+    server_comunication::{
+        info_sender::send_to_all_servers, netsplit::quit_lost_users, spanning_tree::SpanningTree,
+    },
+};
+
+///
+/// struct that implements the UNLINK message, sent by a server
+/// when the link with one of its neighbours drops:
+///
+/// `:<server> UNLINK <neighbour> :<reason>`
+///
+/// Every server removes the link. The servers that can't be
+/// reached anymore leave the network with their users, unless
+/// a link on standby keeps them joined.
+///
+pub struct UnlinkMsg {
+    server: String,
+    neighbour: String,
+    reason: String,
+    msg: Message,
+}
+
+impl UnlinkMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let server = match msg.prefix() {
+            Some(server) => server,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        let neighbour = match msg.get_param_from_msg(0) {
+            Some(neighbour) => neighbour,
+            None => return Err(ErrorServer::BadQuery),
+        };
+        let reason = match msg.get_param_from_msg(1) {
+            Some(reason) => reason.trim_start_matches(':').to_string(),
+            None => format!("{server} {neighbour}"),
+        };
+        Ok(UnlinkMsg {
+            server,
+            neighbour,
+            reason,
+            msg: msg.clone(),
+        })
+    }
+
+    ///
+    /// Removes the link and the servers lost with it, and
+    /// informs the rest of the network. A link already removed
+    /// is ignored, so the message doesn't go around.
+    ///
+    pub fn response(
+        self,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let lost_servers = match spanning_tree.lock() {
+            Ok(mut st) => match st.remove_link(&self.server, &self.neighbour) {
+                Some(lost_servers) => {
+                    send_to_all_servers(&st, self.msg, &self.server)?;
+                    lost_servers
+                }
+                None => return Ok(vec![Reply::rpl_none()]),
+            },
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        quit_lost_users(&lost_servers, &self.reason, nick_sender, channel_sender)?;
+        Ok(vec![Reply::rpl_none()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        database::RepositoryHandler,
+        parser::message::Message,
+        repository::{connection::Connection, traits::operations::Operations},
+        server_comunication::{
+            server::Server,
+            spanning_tree::{edge::Edge, node::Node, SpanningTree},
+        },
+        user::User,
+    };
+
+    use super::UnlinkMsg;
+
+    fn node(servername: &str) -> Node {
+        Node::new(Server::new(servername.to_string(), None))
+    }
+
+    // server1 is linked to server2 and server3, which are linked too
+    fn mesh() -> Arc<Mutex<SpanningTree>> {
+        let edges = vec![
+            Edge::new(node("server1"), node("server2"), 1),
+            Edge::new(node("server1"), node("server3"), 1),
+            Edge::new(node("server2"), node("server3"), 1),
+        ];
+        Arc::new(Mutex::new(SpanningTree::new(node("server1"), edges)))
+    }
+
+    fn unlink(msg: &str, st: &Arc<Mutex<SpanningTree>>) -> RepositoryHandler<String, Connection> {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let user = User::new("bob", "bob", "host", "server2", "Bob", "");
+        nicks
+            .get_channels()
+            .add("bob".to_string(), Connection::connection_away_server(user))
+            .unwrap();
+        let msg = Message::from_str(msg).unwrap();
+        UnlinkMsg::new(&msg)
+            .unwrap()
+            .response(&nicks.get_channels(), &channels.get_channels(), st)
+            .unwrap();
+        nicks
+    }
+
+    #[test]
+    fn redundant_link_keeps_the_servers() {
+        let st = mesh();
+        let nicks = unlink(":server2 UNLINK server1 :gone", &st);
+
+        assert!(!st.lock().unwrap().has_link("server1", "server2"));
+        assert!(st.lock().unwrap().contains("server2".to_string()));
+        assert!(nicks
+            .get_channels()
+            .search("bob".to_string())
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn last_link_loses_the_server_and_its_users() {
+        let st = mesh();
+        unlink(":server3 UNLINK server2", &st);
+        let nicks = unlink(":server2 UNLINK server1", &st);
+
+        assert!(!st.lock().unwrap().contains("server2".to_string()));
+        assert!(nicks
+            .get_channels()
+            .search("bob".to_string())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn unlink_without_neighbour_is_err() {
+        let msg = Message::from_str(":server2 UNLINK").unwrap();
+        assert!(UnlinkMsg::new(&msg).is_err());
+    }
+}
@@ -1,29 +1,46 @@
 use std::{
-    io::Write,
     net::Shutdown,
+    slice,
     sync::{Arc, Mutex},
-    thread,
-    time::Duration,
 };
 
 use crate::{
+    channel::Channel,
+    error::error_server::ErrorServer,
     parser::message::Message,
     reply::Reply,
     repository::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
-    server_comunication::spanning_tree::SpanningTree,
+    server_comunication::{
+        info_sender::send_to_all_servers, netsplit::quit_users, spanning_tree::SpanningTree,
+    },
     user::User,
     utils::write_message_to,
 };
 
+const COLLISION_REASON: &str = "Nick collision";
+
+///
+/// result of a nick collision between the user that
+/// arrives from another server and the one already known
+///
+#[derive(Debug, PartialEq, Eq)]
+enum Collision {
+    KeepExisting,
+    KeepIncoming,
+    KillBoth,
+}
+
 pub struct UserMsg {
     username: String,
     hostname: String,
     servername: String,
     realname: String,
     nickname: String,
+    created_at: u64,
+    uid: String,
     msg: Message,
 }
 
@@ -35,75 +52,96 @@ impl UserMsg {
             servername: get_servername(msg.clone()),
             realname: get_realname(msg.clone()),
             nickname: get_nickname(msg.clone()),
+            created_at: get_created_at(msg.clone()),
+            uid: get_uid(msg.clone()),
             msg,
         }
     }
+
+    ///
+    /// Adds the user of another server. If the nickname is already
+    /// in use, the user with the older timestamp keeps it and the
+    /// other one is killed; with equal timestamps both are killed.
+    /// Every server resolves the collision the same way, so the
+    /// message goes on to the rest of the network unless it lost.
+    ///
     pub fn response(
         self,
-        nick_sender: ClientChannel<String, Connection>,
-        spanning_tree: Arc<Mutex<SpanningTree>>,
-    ) {
-        //falta hacer los checkeos de colisiones
-        let user = User::new(
-            self.nickname.as_str(),
-            self.username.as_str(),
-            self.hostname.as_str(),
-            self.servername.as_str(),
-            self.realname.as_str(),
-            "",
-        );
-
-        let connection = Connection::connection_away_server(user);
-
-        let added = nick_sender.add(self.nickname.clone(), connection).unwrap();
-        // spanning tree se lo pasa al resto del arbol
-        if added {
-            if let Ok(st) = spanning_tree.lock() {
-                if let Some(excluded) = st.look_for_nearest_connection(self.servername) {
-                    let servers = st.get_servers();
-                    for server in servers {
-                        if let Some(mut conn) = server.get_connection() {
-                            if server != excluded {
-                                write_message_to(&self.msg, &mut conn).unwrap();
-                            }
-                        }
-                    }
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+    ) -> Result<(), ErrorServer> {
+        let collision = match nick_sender.search(self.nickname.clone())? {
+            Some(existing) => {
+                let collision =
+                    resolve_collision(self.created_at, existing.get_user().created_at());
+                match collision {
+                    // its server kills it when our user arrives there
+                    Collision::KeepExisting => return Ok(()),
+                    _ => Self::kill_collided(existing, nick_sender, channel_sender)?,
                 }
+                Some(collision)
             }
-        } else {
-            //manda el kill a todos excepto el subarbol de donde viene el otro
-            if let Some(conn) = nick_sender.search(self.nickname.clone()).unwrap() {
-                nick_sender.delete(self.nickname.clone()).unwrap();
-                if let Ok(st) = spanning_tree.lock() {
-                    if let Some(servername) = conn.get_servername() {
-                        if *servername == st.get_root().server.servername {
-                            let user_reply =
-                                Reply::err_erroneus_nickname(None, vec![(self.nickname.clone())]);
-                            if let Some(mut tcp) = conn.see_if_clonable() {
-                                tcp.write_all(user_reply.to_string().as_bytes()).unwrap();
-                                thread::sleep(Duration::from_millis(200));
-                                tcp.shutdown(Shutdown::Both).unwrap();
-                            }
-                        }
-                    };
-                    if let Some(excluded) = st.look_for_nearest_connection(self.servername) {
-                        let servers = st.get_servers();
-                        for server in servers {
-                            if let Some(mut conn) = server.get_connection() {
-                                if server != excluded {
-                                    let msg = Message::new(
-                                        None,
-                                        super::Command::Kill,
-                                        Some(vec![self.nickname.clone()]),
-                                    );
-                                    write_message_to(&msg.to_string(), &mut conn).unwrap();
-                                }
-                            }
-                        }
-                    }
-                }
+            None => None,
+        };
+        if collision != Some(Collision::KillBoth) {
+            let mut user = User::new(
+                self.nickname.as_str(),
+                self.username.as_str(),
+                self.hostname.as_str(),
+                self.servername.as_str(),
+                self.realname.as_str(),
+                "",
+            );
+            user.set_created_at(self.created_at);
+            user.set_uid(&self.uid);
+            nick_sender.add(
+                self.nickname.clone(),
+                Connection::connection_away_server(user),
+            )?;
+        }
+        match spanning_tree.lock() {
+            Ok(st) => send_to_all_servers(&st, self.msg, &self.servername),
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
+    }
+
+    // Removes the user that lost the nickname from the network and,
+    // if it is connected to this server, closes its connection. It
+    // is removed first, so its handler doesn't announce a QUIT.
+    fn kill_collided(
+        mut connection: Connection,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+    ) -> Result<(), ErrorServer> {
+        let nickname = connection.get_nickname();
+        quit_users(
+            slice::from_ref(&nickname),
+            COLLISION_REASON,
+            nick_sender,
+            channel_sender,
+        )?;
+        if connection.is_local() {
+            let reply = Reply::err_nick_collision(None, vec![nickname]);
+            let _ = write_message_to(&reply, &mut connection);
+            if let Some(stream) = connection.see_if_clonable() {
+                let _ = stream.shutdown(Shutdown::Both);
             }
         }
+        Ok(())
+    }
+}
+
+// The user with the older timestamp keeps the nickname. Users
+// announced without one can't win a collision.
+fn resolve_collision(incoming: u64, existing: u64) -> Collision {
+    if incoming == 0 || existing == 0 {
+        return Collision::KillBoth;
+    }
+    match incoming.cmp(&existing) {
+        std::cmp::Ordering::Less => Collision::KeepIncoming,
+        std::cmp::Ordering::Greater => Collision::KeepExisting,
+        std::cmp::Ordering::Equal => Collision::KillBoth,
     }
 }
 
@@ -125,13 +163,36 @@ pub fn get_username(msg: Message) -> String {
 
 pub fn get_realname(msg: Message) -> String {
     if let Some(parameter) = msg.parameters() {
-        if let Some(realname) = parameter.get(3) {
+        // the timestamp and the id go before the realname, when they are sent
+        if let Some(realname) = parameter.get(parameter.len().max(4) - 1) {
             return realname.clone();
         }
     }
     " ".to_string()
 }
 
+pub fn get_created_at(msg: Message) -> u64 {
+    if let Some(parameter) = msg.parameters() {
+        if parameter.len() > 4 {
+            if let Some(created_at) = parameter.get(3) {
+                return created_at.parse().unwrap_or_default();
+            }
+        }
+    }
+    0
+}
+
+pub fn get_uid(msg: Message) -> String {
+    if let Some(parameter) = msg.parameters() {
+        if parameter.len() > 5 {
+            if let Some(uid) = parameter.get(4) {
+                return uid.clone();
+            }
+        }
+    }
+    String::new()
+}
+
 pub fn get_hostname(msg: Message) -> String {
     if let Some(parameter) = msg.parameters() {
         if let Some(hostname) = parameter.get(1) {
@@ -148,3 +209,97 @@ pub fn get_servername(msg: Message) -> String {
     }
     " ".to_string()
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        command::fixtures::spanning_tree,
+        database::RepositoryHandler,
+        parser::message::Message,
+        repository::{connection::Connection, traits::operations::Operations},
+        user::User,
+    };
+
+    use super::{resolve_collision, Collision, UserMsg};
+
+    fn remote_user(servername: &str, created_at: u64) -> Connection {
+        let mut user = User::new("alice", "alice", "host", servername, "real", "");
+        user.set_created_at(created_at);
+        Connection::connection_away_server(user)
+    }
+
+    #[test]
+    fn older_user_wins_the_collision() {
+        assert_eq!(resolve_collision(10, 20), Collision::KeepIncoming);
+        assert_eq!(resolve_collision(20, 10), Collision::KeepExisting);
+    }
+
+    #[test]
+    fn equal_or_unknown_timestamps_kill_both() {
+        assert_eq!(resolve_collision(10, 10), Collision::KillBoth);
+        assert_eq!(resolve_collision(0, 10), Collision::KillBoth);
+    }
+
+    #[test]
+    fn timestamp_is_read_before_the_realname() {
+        let msg = Message::from_str(":alice USER alice host server2 10 :Alice A").unwrap();
+        let user = UserMsg::new(msg);
+        assert_eq!(user.created_at, 10);
+        assert_eq!(user.realname.trim(), ":Alice A");
+    }
+
+    #[test]
+    fn uid_is_read_after_the_timestamp() {
+        let msg = Message::from_str(":alice USER alice host server2 10 0ABAAAAAA :Alice").unwrap();
+        let user = UserMsg::new(msg);
+        assert_eq!(user.created_at, 10);
+        assert_eq!(user.uid, "0ABAAAAAA");
+        assert_eq!(user.realname.trim(), ":Alice");
+    }
+
+    #[test]
+    fn older_incoming_user_replaces_the_existing_one() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        nicks
+            .get_channels()
+            .add("alice".to_string(), remote_user("server3", 20))
+            .unwrap();
+        let msg = Message::from_str(":alice USER alice host server2 10 :Alice").unwrap();
+
+        UserMsg::new(msg)
+            .response(
+                &nicks.get_channels(),
+                &channels.get_channels(),
+                &spanning_tree(),
+            )
+            .unwrap();
+
+        let alice = nicks.get_channels().search("alice".to_string()).unwrap();
+        assert_eq!(alice.unwrap().get_servername(), Some("server2"));
+    }
+
+    #[test]
+    fn collision_with_equal_timestamps_removes_both() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        nicks
+            .get_channels()
+            .add("alice".to_string(), remote_user("server3", 10))
+            .unwrap();
+        let msg = Message::from_str(":alice USER alice host server2 10 :Alice").unwrap();
+
+        UserMsg::new(msg)
+            .response(
+                &nicks.get_channels(),
+                &channels.get_channels(),
+                &spanning_tree(),
+            )
+            .unwrap();
+
+        let alice = nicks.get_channels().search("alice".to_string()).unwrap();
+        assert!(alice.is_none());
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{config::ServerConfig, error::error_server::ErrorServer, reply::Reply};
+
+///
+/// struct that implements the VERSION message, that
+/// answers with the version of the server:
+/// `VERSION [<server>]`
+///
+pub struct VersionMsg;
+
+impl VersionMsg {
+    ///
+    /// The comments tell whether the server
+    /// offers compression to its links.
+    ///
+    pub fn response(
+        servername: &str,
+        config: &Arc<Mutex<ServerConfig>>,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let compression = match config.lock() {
+            Ok(config) => config.link_compression,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let version = format!("{}-{}.", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        let comments = match compression {
+            true => "link compression on",
+            false => "link compression off",
+        };
+        Ok(vec![Reply::rpl_version(&version, servername, comments)])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::config::ServerConfig;
+
+    use super::VersionMsg;
+
+    #[test]
+    fn answers_the_version_of_the_package() {
+        let config = Arc::new(Mutex::new(ServerConfig::default()));
+        let replies = VersionMsg::response("server1", &config).unwrap();
+        assert_eq!(
+            replies[0].to_string(),
+            format!(
+                "351 irc_project-{}. server1 :link compression off",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+}
@@ -70,8 +70,7 @@ impl Who {
                 for ch in channel_sender.find_all()? {
                     if ch.name.contains(name) {
                         matches_channel = true;
-                        let str = ch.name.clone();
-                        msg.push(str);
+                        msg.append(&mut Self::channel_members(&ch, &nick_sender)?);
                     }
                 }
 
@@ -94,6 +93,26 @@ impl Who {
         Ok(replies)
     }
 
+    // One line for each member of the channel, with the
+    // prefix of its highest rank before its nickname.
+    fn channel_members(
+        ch: &Channel,
+        nick_sender: &ClientChannel<String, Connection>,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let mut members = vec![];
+        for member in ch.return_members() {
+            let status = ch.member_status(&member).unwrap_or_default();
+            let connection = match nick_sender.search(member)? {
+                Some(connection) => connection,
+                None => continue,
+            };
+            if let Some(response) = Self::make_response(connection) {
+                members.push(format!("{} {}{response}", ch.name, status.prefix()));
+            }
+        }
+        Ok(members)
+    }
+
     fn make_response(c: Connection) -> Option<String> {
         if c.is_invisible_connection() {
             return None;
@@ -164,11 +183,44 @@ pub fn is_operator_from_msg(msg: &Message) -> bool {
 
 #[cfg(test)]
 mod test {
+    use std::str::FromStr;
+
     use crate::command::Command;
+    use crate::database::RepositoryHandler;
     use crate::parser::message::Message;
+    use crate::user::User;
 
     use super::*;
 
+    #[test]
+    fn channel_members_are_shown_with_their_prefix() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let user = User::new("pedro", "pedro", "host", "server2", "Pedro", "");
+        nicks
+            .get_channels()
+            .add(
+                "pedro".to_string(),
+                Connection::connection_away_server(user),
+            )
+            .unwrap();
+        let channel = Channel::new("#c".to_string(), "pedro".to_string());
+        channels
+            .get_channels()
+            .add("#c".to_string(), channel)
+            .unwrap();
+
+        let msg = Message::from_str(":ana WHO #c").unwrap();
+        let replies = Who::new(msg)
+            .unwrap()
+            .response(nicks.get_channels(), channels.get_channels())
+            .unwrap();
+        assert_eq!(
+            replies[1],
+            Reply::rpl_who(vec!["#c @pedro: Pedro".to_string()])
+        );
+    }
+
     #[test]
     fn send_reply_endwho_is_ok() {
         //GIVEN
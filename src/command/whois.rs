@@ -90,18 +90,13 @@ impl Whois {
         Ok(replies)
     }
 
+    // The channel, after the prefix of the highest rank of the user.
     fn display_user(user: &str, ch: &Channel) -> String {
-        let mut st = String::new();
-        if ch.is_channel_operator(user) {
-            st.push_str(":{@");
-            st.push_str(&ch.name);
-            st.push('}');
-        } else {
-            st.push_str(":{+");
-            st.push_str(&ch.name);
-            st.push('}');
-        }
-        st
+        let prefix = ch
+            .member_status(user)
+            .map(|status| status.prefix())
+            .unwrap_or_default();
+        format!(":{{{prefix}{}}}", ch.name)
     }
 
     fn create_response(connection: Connection) -> String {
@@ -208,10 +203,23 @@ fn get_users_from_msg(msg: &Message) -> Option<Vec<String>> {
 
 #[cfg(test)]
 mod tests {
-    use crate::command::whois::get_users_from_msg;
+    use crate::channel::Channel;
+    use crate::command::whois::{get_users_from_msg, Whois};
     use crate::command::Command;
     use crate::parser::message::Message;
 
+    #[test]
+    fn channels_are_shown_with_the_prefix_of_the_user() {
+        let mut channel = Channel::new("#c".to_string(), "pedro".to_string());
+        channel.add_member("juan", &None).unwrap();
+        channel.add_member("ana", &None).unwrap();
+        channel.modify_channel_flag("+h", Some("juan".to_string()));
+
+        assert_eq!(Whois::display_user("pedro", &channel), ":{@#c}");
+        assert_eq!(Whois::display_user("juan", &channel), ":{%#c}");
+        assert_eq!(Whois::display_user("ana", &channel), ":{#c}");
+    }
+
     #[test]
     fn test_get_users_from_msg() {
         let prefix = ":papa".to_string();
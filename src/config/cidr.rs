@@ -0,0 +1,139 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use crate::error::error_config::ErrorConfig;
+
+const IPV4_BITS: u8 = 32;
+const IPV6_BITS: u8 = 128;
+
+///
+/// struct that represents a block of ip
+/// addresses written in CIDR notation
+/// (`10.0.0.0/8`). A plain address is
+/// taken as a block of a single host.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    ///
+    /// creates a block that only contains
+    /// the given address
+    ///
+    pub fn host(ip: IpAddr) -> Self {
+        let prefix = match ip {
+            IpAddr::V4(_) => IPV4_BITS,
+            IpAddr::V6(_) => IPV6_BITS,
+        };
+        Cidr {
+            network: ip,
+            prefix,
+        }
+    }
+
+    ///
+    /// returns true if the address
+    /// belongs to the block
+    ///
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = mask_u32(self.prefix);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = mask_u128(self.prefix);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn mask_u32(prefix: u8) -> u32 {
+    match prefix {
+        0 => 0,
+        p => u32::MAX << (IPV4_BITS - p),
+    }
+}
+
+fn mask_u128(prefix: u8) -> u128 {
+    match prefix {
+        0 => 0,
+        p => u128::MAX << (IPV6_BITS - p),
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ErrorConfig;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ErrorConfig::InvalidValue(s.to_string());
+        let (address, prefix) = match s.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let network = IpAddr::from_str(address).map_err(|_| invalid())?;
+        let max = Cidr::host(network).prefix;
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Cidr { network, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn block_contains_addresses_of_the_network() {
+        let cidr = Cidr::from_str("10.0.0.0/8").unwrap();
+        assert!(cidr.contains(&IpAddr::from_str("10.20.30.40").unwrap()));
+        assert!(!cidr.contains(&IpAddr::from_str("11.0.0.1").unwrap()));
+    }
+
+    #[test]
+    fn plain_address_is_a_single_host() {
+        let cidr = Cidr::from_str("192.168.0.7").unwrap();
+        assert!(cidr.contains(&IpAddr::from_str("192.168.0.7").unwrap()));
+        assert!(!cidr.contains(&IpAddr::from_str("192.168.0.8").unwrap()));
+    }
+
+    #[test]
+    fn zero_prefix_contains_everything() {
+        let cidr = Cidr::from_str("0.0.0.0/0").unwrap();
+        assert!(cidr.contains(&IpAddr::from_str("8.8.8.8").unwrap()));
+    }
+
+    #[test]
+    fn ipv6_blocks() {
+        let cidr = Cidr::from_str("2001:db8::/32").unwrap();
+        assert!(cidr.contains(&IpAddr::from_str("2001:db8::1").unwrap()));
+        assert!(!cidr.contains(&IpAddr::from_str("127.0.0.1").unwrap()));
+    }
+
+    #[test]
+    fn invalid_prefix_is_err() {
+        assert!(Cidr::from_str("10.0.0.0/33").is_err());
+        assert!(Cidr::from_str("not an ip").is_err());
+    }
+
+    #[test]
+    fn displays_correctly() {
+        let cidr = Cidr::from_str("10.1.0.0/16").unwrap();
+        assert_eq!(cidr.to_string(), "10.1.0.0/16");
+    }
+}
@@ -0,0 +1,470 @@
+pub mod cidr;
+
+use std::{fs, str::FromStr, time::Duration};
+
+use crate::{error::error_config::ErrorConfig, server_comunication::uid::is_server_id};
+
+use self::cidr::Cidr;
+
+const COMMENT_CHAR: char = '#';
+const ASSIGN_CHAR: char = '=';
+
+///
+/// Struct that holds the configuration of a server, read from a plain
+/// text file with one `key = value` entry per line. Lines starting
+/// with `#` are comments.
+///
+/// # Keys
+/// * `max_connections` - global cap of simultaneous connections.
+/// * `max_connections_per_ip` - simultaneous connections from one address.
+/// * `cidr_limit` - `<cidr> <amount>`, simultaneous connections from a block.
+///   Can be repeated.
+/// * `throttle` - `<connections> <seconds>`, connection attempts allowed
+///   from one address inside the time window.
+/// * `exempt` - `<cidr>` excluded from every admission control. Can be repeated.
+/// * `ban_file` - path of the file where the server bans are persisted.
+/// * `control_socket` - path of the unix socket used to administrate the server.
+///   Changing it takes effect after a restart.
+/// * `oper` - `<name> <password>`, credentials accepted by OPER. Can be repeated.
+/// * `motd_file` - path of the file with the message of the day.
+/// * `sid` - identifier of the server inside the network, a digit followed
+///   by two uppercase letters or digits. When missing it is derived from the
+///   servername. Changing it takes effect after a restart.
+/// * `link` - `<servername> <ip:port> [autoconnect]`, server that can be
+///   linked with CONNECT. With `autoconnect` the link is kept up by the
+///   server itself. Can be repeated.
+/// * `link_compression` - `on` or `off`, compresses the server links when
+///   the other end agrees to it in the handshake. Off by default.
+/// * `admin_location` - where the server is, answered by ADMIN.
+/// * `admin_email` - contact of the administrator, answered by ADMIN.
+/// * `history_length` - amount of messages each channel remembers for
+///   CHATHISTORY, zero turns the history off. Fifty by default.
+/// * `history_file` - path of the file where the history of the channels
+///   is persisted. Changes to the history take effect after a restart.
+/// * `chanserv_file` - path of the file where the channels registered with
+///   ChanServ are persisted. Changing it takes effect after a restart.
+/// * `nickserv_file` - path of the file where the accounts registered with
+///   NickServ are persisted. Changing it takes effect after a restart.
+/// * `nick_enforce_delay` - seconds an unidentified user can keep a registered
+///   nickname before it is changed. Sixty by default.
+/// * `invite_expiry` - seconds an INVITE lets the user join an invite-only
+///   channel. An hour by default.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ServerConfig {
+    pub limits: ConnectionLimits,
+    pub ban_file: Option<String>,
+    pub control_socket: Option<String>,
+    pub opers: Vec<(String, String)>,
+    pub motd_file: Option<String>,
+    pub sid: Option<String>,
+    pub links: Vec<LinkBlock>,
+    pub link_compression: bool,
+    pub admin_location: Option<String>,
+    pub admin_email: Option<String>,
+    pub history_length: Option<usize>,
+    pub history_file: Option<String>,
+    pub chanserv_file: Option<String>,
+    pub nickserv_file: Option<String>,
+    pub nick_enforce_delay: Option<u64>,
+    pub invite_expiry: Option<u64>,
+    /// file the configuration was read from, used to reload it.
+    pub path: Option<String>,
+}
+
+///
+/// Limits applied by the server when accepting new connections.
+/// Every limit is disabled when it is not configured.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ConnectionLimits {
+    pub max_connections: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    pub cidr_limits: Vec<(Cidr, usize)>,
+    pub throttle: Option<Throttle>,
+    pub exempt: Vec<Cidr>,
+}
+
+///
+/// Amount of connection attempts accepted from the same
+/// address inside a time window.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Throttle {
+    pub connections: usize,
+    pub window: Duration,
+}
+
+///
+/// Server this one can link with, and the
+/// address where it accepts connections.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LinkBlock {
+    pub servername: String,
+    pub address: String,
+    pub autoconnect: bool,
+}
+
+impl ServerConfig {
+    ///
+    /// Reads and parses the configuration file
+    /// found at the given path.
+    ///
+    pub fn load(path: &str) -> Result<Self, ErrorConfig> {
+        let content = fs::read_to_string(path)?;
+        let mut config = ServerConfig::from_str(&content)?;
+        config.validate()?;
+        config.path = Some(path.to_string());
+        Ok(config)
+    }
+
+    ///
+    /// returns the name of the settings
+    /// that differ in the other configuration
+    ///
+    pub fn changes(&self, other: &ServerConfig) -> Vec<String> {
+        let settings = [
+            ("limits", self.limits != other.limits),
+            ("ban_file", self.ban_file != other.ban_file),
+            (
+                "control_socket",
+                self.control_socket != other.control_socket,
+            ),
+            ("opers", self.opers != other.opers),
+            ("motd_file", self.motd_file != other.motd_file),
+            ("sid", self.sid != other.sid),
+            ("links", self.links != other.links),
+            (
+                "link_compression",
+                self.link_compression != other.link_compression,
+            ),
+            (
+                "admin",
+                self.admin_location != other.admin_location
+                    || self.admin_email != other.admin_email,
+            ),
+            (
+                "history",
+                self.history_length != other.history_length
+                    || self.history_file != other.history_file,
+            ),
+            ("chanserv_file", self.chanserv_file != other.chanserv_file),
+            (
+                "nickserv",
+                self.nickserv_file != other.nickserv_file
+                    || self.nick_enforce_delay != other.nick_enforce_delay,
+            ),
+            ("invite_expiry", self.invite_expiry != other.invite_expiry),
+        ];
+        settings
+            .iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    ///
+    /// returns true if the name and password
+    /// belong to a configured operator
+    ///
+    pub fn is_oper(&self, name: &str, password: &str) -> bool {
+        self.opers.iter().any(|(n, p)| n == name && p == password)
+    }
+
+    ///
+    /// returns the link configured
+    /// for the given server
+    ///
+    pub fn find_link(&self, servername: &str) -> Option<&LinkBlock> {
+        self.links.iter().find(|link| link.servername == servername)
+    }
+
+    // The files referenced by the configuration must be readable.
+    fn validate(&self) -> Result<(), ErrorConfig> {
+        if let Some(motd) = &self.motd_file {
+            fs::metadata(motd)?;
+        }
+        Ok(())
+    }
+
+    fn parse_entry(&mut self, key: &str, value: &str) -> Result<(), ErrorConfig> {
+        match key {
+            "max_connections" => self.limits.max_connections = Some(parse_number(key, value)?),
+            "max_connections_per_ip" => {
+                self.limits.max_connections_per_ip = Some(parse_number(key, value)?)
+            }
+            "cidr_limit" => {
+                let (cidr, amount) = split_pair(key, value)?;
+                self.limits
+                    .cidr_limits
+                    .push((Cidr::from_str(cidr)?, parse_number(key, amount)?));
+            }
+            "throttle" => {
+                let (connections, seconds) = split_pair(key, value)?;
+                self.limits.throttle = Some(Throttle {
+                    connections: parse_number(key, connections)?,
+                    window: Duration::from_secs(parse_number(key, seconds)? as u64),
+                });
+            }
+            "exempt" => self.limits.exempt.push(Cidr::from_str(value)?),
+            "ban_file" => self.ban_file = Some(value.to_string()),
+            "control_socket" => self.control_socket = Some(value.to_string()),
+            "oper" => {
+                let (name, password) = split_pair(key, value)?;
+                self.opers.push((name.to_string(), password.to_string()));
+            }
+            "motd_file" => self.motd_file = Some(value.to_string()),
+            "sid" if is_server_id(value) => self.sid = Some(value.to_string()),
+            "sid" => return Err(ErrorConfig::InvalidValue(key.to_string())),
+            "link" => self.links.push(parse_link(key, value)?),
+            "link_compression" => self.link_compression = parse_switch(key, value)?,
+            "admin_location" => self.admin_location = Some(value.to_string()),
+            "admin_email" => self.admin_email = Some(value.to_string()),
+            "history_length" => self.history_length = Some(parse_number(key, value)?),
+            "history_file" => self.history_file = Some(value.to_string()),
+            "chanserv_file" => self.chanserv_file = Some(value.to_string()),
+            "nickserv_file" => self.nickserv_file = Some(value.to_string()),
+            "nick_enforce_delay" => {
+                self.nick_enforce_delay = Some(parse_number(key, value)? as u64)
+            }
+            "invite_expiry" => self.invite_expiry = Some(parse_number(key, value)? as u64),
+            _ => return Err(ErrorConfig::UnknownKey(key.to_string())),
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ServerConfig {
+    type Err = ErrorConfig;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = ServerConfig::default();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(COMMENT_CHAR) {
+                continue;
+            }
+            match line.split_once(ASSIGN_CHAR) {
+                Some((key, value)) => config.parse_entry(key.trim(), value.trim())?,
+                None => return Err(ErrorConfig::MalformedLine(number + 1)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn parse_number(key: &str, value: &str) -> Result<usize, ErrorConfig> {
+    value
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| ErrorConfig::InvalidValue(key.to_string()))
+}
+
+fn parse_switch(key: &str, value: &str) -> Result<bool, ErrorConfig> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(ErrorConfig::InvalidValue(key.to_string())),
+    }
+}
+
+fn parse_link(key: &str, value: &str) -> Result<LinkBlock, ErrorConfig> {
+    let mut split = value.split_whitespace();
+    match (split.next(), split.next(), split.next(), split.next()) {
+        (Some(servername), Some(address), autoconnect, None)
+            if matches!(autoconnect, None | Some("autoconnect")) =>
+        {
+            Ok(LinkBlock {
+                servername: servername.to_string(),
+                address: address.to_string(),
+                autoconnect: autoconnect.is_some(),
+            })
+        }
+        _ => Err(ErrorConfig::InvalidValue(key.to_string())),
+    }
+}
+
+fn split_pair<'a>(key: &str, value: &'a str) -> Result<(&'a str, &'a str), ErrorConfig> {
+    let mut split = value.split_whitespace();
+    match (split.next(), split.next(), split.next()) {
+        (Some(first), Some(second), None) => Ok((first, second)),
+        _ => Err(ErrorConfig::InvalidValue(key.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_config_has_no_limits() {
+        let config = ServerConfig::from_str("").unwrap();
+        assert_eq!(config, ServerConfig::default());
+    }
+
+    #[test]
+    fn parses_connection_limits() {
+        let content = "# limits\n\
+                       max_connections = 100\n\
+                       max_connections_per_ip = 3\n\
+                       cidr_limit = 10.0.0.0/8 20\n\
+                       throttle = 4 60\n\
+                       exempt = 127.0.0.1\n\
+                       ban_file = bans.conf\n\
+                       control_socket = /run/ircd.sock\n\
+                       oper = admin secret\n";
+        let config = ServerConfig::from_str(content).unwrap();
+
+        assert_eq!(config.limits.max_connections, Some(100));
+        assert_eq!(config.limits.max_connections_per_ip, Some(3));
+        assert_eq!(
+            config.limits.cidr_limits,
+            vec![(Cidr::from_str("10.0.0.0/8").unwrap(), 20)]
+        );
+        assert_eq!(
+            config.limits.throttle,
+            Some(Throttle {
+                connections: 4,
+                window: Duration::from_secs(60)
+            })
+        );
+        assert_eq!(
+            config.limits.exempt,
+            vec![Cidr::from_str("127.0.0.1").unwrap()]
+        );
+        assert_eq!(config.ban_file, Some("bans.conf".to_string()));
+        assert_eq!(config.control_socket, Some("/run/ircd.sock".to_string()));
+        assert!(config.is_oper("admin", "secret"));
+        assert!(!config.is_oper("admin", "guess"));
+    }
+
+    #[test]
+    fn changes_lists_the_modified_settings() {
+        let old = ServerConfig::from_str("max_connections = 10").unwrap();
+        let new = ServerConfig::from_str("max_connections = 20\noper = a b").unwrap();
+        assert_eq!(old.changes(&new), vec!["limits", "opers"]);
+        assert!(new.changes(&new).is_empty());
+    }
+
+    #[test]
+    fn missing_motd_file_is_err() {
+        let config = ServerConfig::from_str("motd_file = /nonexistent/motd").unwrap();
+        assert_eq!(config.validate(), Err(ErrorConfig::UnreadableFile));
+    }
+
+    #[test]
+    fn unknown_key_is_err() {
+        let result = ServerConfig::from_str("colour = blue");
+        assert_eq!(result, Err(ErrorConfig::UnknownKey("colour".to_string())));
+    }
+
+    #[test]
+    fn line_without_value_is_err() {
+        let result = ServerConfig::from_str("\nmax_connections");
+        assert_eq!(result, Err(ErrorConfig::MalformedLine(2)));
+    }
+
+    #[test]
+    fn invalid_number_is_err() {
+        let result = ServerConfig::from_str("max_connections = many");
+        assert_eq!(
+            result,
+            Err(ErrorConfig::InvalidValue("max_connections".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_sid_is_err() {
+        assert_eq!(
+            ServerConfig::from_str("sid = 0A1").unwrap().sid,
+            Some("0A1".to_string())
+        );
+        let result = ServerConfig::from_str("sid = AB1");
+        assert_eq!(result, Err(ErrorConfig::InvalidValue("sid".to_string())));
+    }
+
+    #[test]
+    fn parses_links() {
+        let content = "link = srv2 127.0.0.1:8081\nlink = srv3 127.0.0.1:8082 autoconnect";
+        let config = ServerConfig::from_str(content).unwrap();
+
+        assert!(!config.find_link("srv2").unwrap().autoconnect);
+        assert_eq!(
+            config.find_link("srv3"),
+            Some(&LinkBlock {
+                servername: "srv3".to_string(),
+                address: "127.0.0.1:8082".to_string(),
+                autoconnect: true,
+            })
+        );
+        let result = ServerConfig::from_str("link = srv2 127.0.0.1:8081 always");
+        assert_eq!(result, Err(ErrorConfig::InvalidValue("link".to_string())));
+    }
+
+    #[test]
+    fn parses_link_compression() {
+        assert!(!ServerConfig::default().link_compression);
+        let config = ServerConfig::from_str("link_compression = on").unwrap();
+        assert!(config.link_compression);
+
+        let result = ServerConfig::from_str("link_compression = yes");
+        assert_eq!(
+            result,
+            Err(ErrorConfig::InvalidValue("link_compression".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_admin_info() {
+        let config = ServerConfig::from_str(
+            "admin_location = Buenos Aires, Argentina\nadmin_email = admin@example.com",
+        )
+        .unwrap();
+        assert_eq!(
+            config.admin_location.as_deref(),
+            Some("Buenos Aires, Argentina")
+        );
+        assert_eq!(config.admin_email.as_deref(), Some("admin@example.com"));
+        assert_eq!(config.changes(&ServerConfig::default()), vec!["admin"]);
+    }
+
+    #[test]
+    fn parses_history_settings() {
+        let config =
+            ServerConfig::from_str("history_length = 20\nhistory_file = history.log").unwrap();
+        assert_eq!(config.history_length, Some(20));
+        assert_eq!(config.history_file.as_deref(), Some("history.log"));
+        assert_eq!(config.changes(&ServerConfig::default()), vec!["history"]);
+    }
+
+    #[test]
+    fn parses_chanserv_file() {
+        let config = ServerConfig::from_str("chanserv_file = chanserv.db").unwrap();
+        assert_eq!(config.chanserv_file.as_deref(), Some("chanserv.db"));
+        assert_eq!(
+            config.changes(&ServerConfig::default()),
+            vec!["chanserv_file"]
+        );
+    }
+
+    #[test]
+    fn parses_nickserv_settings() {
+        let config =
+            ServerConfig::from_str("nickserv_file = nickserv.db\nnick_enforce_delay = 30").unwrap();
+        assert_eq!(config.nickserv_file.as_deref(), Some("nickserv.db"));
+        assert_eq!(config.nick_enforce_delay, Some(30));
+        assert_eq!(config.changes(&ServerConfig::default()), vec!["nickserv"]);
+        assert!(ServerConfig::from_str("nick_enforce_delay = soon").is_err());
+    }
+
+    #[test]
+    fn parses_invite_expiry() {
+        let config = ServerConfig::from_str("invite_expiry = 600").unwrap();
+        assert_eq!(config.invite_expiry, Some(600));
+        assert_eq!(
+            config.changes(&ServerConfig::default()),
+            vec!["invite_expiry"]
+        );
+    }
+}
@@ -25,7 +25,7 @@ pub struct RepositoryHandler<K, T> {
 
 impl<K, T> RepositoryHandler<K, T>
 where
-    K: Eq + PartialEq + Hash + Send + Clone + 'static,
+    K: Eq + PartialEq + Hash + Ord + Send + Clone + 'static,
     T: Clone + Send + 'static,
 {
     //type RunChannel = ClientChannel<K,T>;
@@ -53,6 +53,15 @@ where
         self.client_channel.clone()
     }
 
+    /// Stops the Repository and waits for its thread to finish.
+    pub fn stop(self) -> Result<(), ErrorServer> {
+        self.client_channel.stop()?;
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(_) => Err(ErrorServer::PoisonedThread),
+        }
+    }
+
     //
     // function that runs the repository
     //
@@ -94,6 +103,14 @@ mod test {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn stops_the_repository_thread() {
+        let repo: RepositoryHandler<String, String> = RepositoryHandler::new().unwrap();
+        let channel = repo.get_channels();
+        assert!(repo.stop().is_ok());
+        assert!(channel.search("pepe".to_string()).is_err());
+    }
+
     #[test]
     fn default_constructor() {
         let repo = RepositoryHandler::new().unwrap();
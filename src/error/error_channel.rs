@@ -11,6 +11,7 @@ pub enum ErrorChannel {
     FullChannel,
     ClientNotInvited,
     BadKey,
+    JoinThrottled,
 }
 
 #[cfg(test)]
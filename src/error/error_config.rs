@@ -0,0 +1,55 @@
+use std::fmt;
+
+///
+/// enum that implements the different errors
+/// that can appear while reading the server
+/// configuration file. In turn, it implements
+/// the trait Display
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ErrorConfig {
+    UnreadableFile,
+    MalformedLine(usize),
+    UnknownKey(String),
+    InvalidValue(String),
+}
+
+impl From<std::io::Error> for ErrorConfig {
+    fn from(_error: std::io::Error) -> Self {
+        ErrorConfig::UnreadableFile
+    }
+}
+
+impl fmt::Display for ErrorConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorConfig::UnreadableFile => write!(f, "Configuration file can't be read"),
+            ErrorConfig::MalformedLine(line) => write!(f, "Malformed line {line}"),
+            ErrorConfig::UnknownKey(key) => write!(f, "Unknown key {key}"),
+            ErrorConfig::InvalidValue(key) => write!(f, "Invalid value for {key}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ErrorConfig;
+
+    #[test]
+    fn debugs_correctly() {
+        let error = ErrorConfig::UnreadableFile;
+        assert_eq!(format!("{error:?}"), "UnreadableFile")
+    }
+
+    #[test]
+    fn displays_correctly_for_malformed_line() {
+        let error = ErrorConfig::MalformedLine(3);
+        assert_eq!(format!("{error}"), "Malformed line 3")
+    }
+
+    #[test]
+    fn displays_correctly_for_invalid_value() {
+        let error = ErrorConfig::InvalidValue("max_connections".to_string());
+        assert_eq!(format!("{error}"), "Invalid value for max_connections")
+    }
+}
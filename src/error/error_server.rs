@@ -1,3 +1,4 @@
+use crate::error::error_config::ErrorConfig;
 use crate::error::error_msg::ErrorMsg;
 use crate::error::error_user::ErrorUser;
 use std::sync::mpsc::{RecvError, SendError};
@@ -22,6 +23,7 @@ pub enum ErrorServer {
     UnexpectedCommand,
     BadQuery,
     DCCError,
+    InvalidConfig,
 }
 
 impl From<std::io::Error> for ErrorServer {
@@ -42,6 +44,12 @@ impl From<ErrorMsg> for ErrorServer {
     }
 }
 
+impl From<ErrorConfig> for ErrorServer {
+    fn from(_error: ErrorConfig) -> Self {
+        ErrorServer::InvalidConfig
+    }
+}
+
 impl<T> From<SendError<T>> for ErrorServer {
     fn from(_error: SendError<T>) -> Self {
         ErrorServer::ChannelError
@@ -66,7 +74,7 @@ mod test {
         sync::mpsc,
     };
 
-    use crate::error::{error_msg::ErrorMsg, error_user::ErrorUser};
+    use crate::error::{error_config::ErrorConfig, error_msg::ErrorMsg, error_user::ErrorUser};
 
     use super::ErrorServer;
 
@@ -109,6 +117,13 @@ mod test {
         assert_eq!(format!("{error_server:?}"), "UnknownCommand");
     }
 
+    #[test]
+    fn from_error_config() {
+        let error = ErrorConfig::UnreadableFile;
+        let error_server = ErrorServer::from(error);
+        assert_eq!(format!("{error_server:?}"), "InvalidConfig");
+    }
+
     #[test]
     fn from_error_send() {
         let (tx, _rx) = mpsc::channel::<String>();
@@ -1,6 +1,7 @@
 pub mod error_channel;
 pub mod error_client;
 pub mod error_command;
+pub mod error_config;
 pub mod error_msg;
 pub mod error_reply;
 pub mod error_server;
@@ -1,6 +1,8 @@
+pub mod ban;
 pub mod channel;
 pub mod client;
 pub mod command;
+pub mod config;
 pub mod database;
 pub mod dcc;
 pub mod error;
@@ -9,6 +11,7 @@ pub mod reply;
 pub mod repository;
 pub mod server;
 pub mod server_comunication;
+pub mod services;
 pub mod string_object;
 pub mod user;
 pub mod utils;
@@ -116,6 +116,21 @@ pub enum Code {
 
     RplyEndBanList = 368,
 
+    /// "<channel> <invitemask>"
+    RplyInviteList = 346,
+
+    /// "<channel> :End of channel invite list"
+    RplyEndInviteList = 347,
+
+    /// "<channel> <exceptionmask>"
+    RplyExceptList = 348,
+
+    /// "<channel> :End of channel exception list"
+    RplyEndExceptList = 349,
+
+    /// "<nick> <channel> :Cannot change nickname while banned on channel"
+    ErrBanNickChange = 435,
+
     RplyCantSendChannel = 404,
 
     /// <channel> :Cannot join channel (+k)
@@ -148,6 +163,124 @@ pub enum Code {
     /// ":Cant change mode for other users"
     ErrUsersDontMatch = 502,
 
+    /// "<type> <mask> <remaining seconds> <setter> :<reason>"
+    RplyStatsKLine = 216,
+
+    /// "<linkname> <sendq> <sent messages> <sent Kbytes> <received messages>
+    /// <received Kbytes> <time open> :<lag>"
+    RplyStatsLinkInfo = 211,
+
+    /// "<stats letter> :End of /STATS report"
+    RplyEndOfStats = 219,
+
+    /// "<config file> :Rehashing"
+    RplyRehashing = 382,
+
+    /// ":File error doing <file op> on <file>"
+    ErrFileError = 424,
+
+    /// "<server> <uplink> :<hopcount> <server info>"
+    RplyLinks = 364,
+
+    /// "<mask> :End of /LINKS list"
+    RplyEndOfLinks = 365,
+
+    /// ":<line of the tree of servers>"
+    RplyMap = 15,
+
+    /// ":End of /MAP"
+    RplyEndOfMap = 17,
+
+    /// ":There are <users> users and <invisible> invisible on <servers> servers"
+    RplyLuserClient = 251,
+
+    /// "<amount> :operator(s) online"
+    RplyLuserOp = 252,
+
+    /// "<amount> :channels formed"
+    RplyLuserChannels = 254,
+
+    /// ":I have <clients> clients and <servers> servers"
+    RplyLuserMe = 255,
+
+    /// "<current> <max> :Current local users <current>, max <max>"
+    RplyLocalUsers = 265,
+
+    /// "<current> <max> :Current global users <current>, max <max>"
+    RplyGlobalUsers = 266,
+
+    /// "<version>.<debuglevel> <server> :<comments>"
+    RplyVersion = 351,
+
+    /// "<server> :<local time of the server>"
+    RplyTime = 391,
+
+    /// "<server> :Administrative info"
+    RplyAdminMe = 256,
+
+    /// ":<location of the server>"
+    RplyAdminLoc1 = 257,
+
+    /// ":<email of the administrator>"
+    RplyAdminEmail = 259,
+
+    /// "<server> :No administrative info available"
+    ErrNoAdminInfo = 423,
+
+    /// ":<line of information about the server>"
+    RplyInfo = 371,
+
+    /// ":End of INFO list"
+    RplyEndOfInfo = 374,
+
+    /// ":- <server> Message of the day - "
+    RplyMotdStart = 375,
+
+    /// ":- <line of the message of the day>"
+    RplyMotd = 372,
+
+    /// ":End of MOTD command"
+    RplyEndOfMotd = 376,
+
+    /// ":MOTD File is missing"
+    ErrNoMotd = 422,
+
+    /// "<token> [<token> ...] :are supported by this server"
+    RplyISupport = 5,
+
+    /// "<char> :is unknown mode char to me"
+    ErrUnknownMode = 472,
+
+    /// "<channel> <nick> <setat>"
+    RplyTopicWhoTime = 333,
+
+    /// "<channel>"
+    RplyInvitedTo = 336,
+
+    /// ":End of /INVITE list"
+    RplyEndInvitedTo = 337,
+
+    /// "<channel> <nick>!<user>@<host> :<message>"
+    RplyKnock = 710,
+
+    /// "<channel> :Your KNOCK has been delivered"
+    RplyKnockDelivered = 711,
+
+    /// "<channel> :Too many KNOCKs (<user|channel>)"
+    ErrTooManyKnock = 712,
+
+    /// "<channel> :Channel is open"
+    ErrChanOpen = 713,
+
+    /// "<channel> :You are already on that channel"
+    ErrKnockOnChan = 714,
+
+    /// ":Cannot knock on <channel> (<reason>)"
+    ErrCannotKnock = 480,
+
+    /// "<channel> :Channel is temporarily unavailable (+j)"
+    ErrUnavailResource = 437,
+
     /// Dummy Reply
     RplyNone = 300,
 }
@@ -192,6 +325,11 @@ impl TryFrom<i32> for Code {
             x if x == Code::RplyEndWho as i32 => Ok(Code::RplyEndWho),
             x if x == Code::RplyBanList as i32 => Ok(Code::RplyBanList),
             x if x == Code::RplyEndBanList as i32 => Ok(Code::RplyEndBanList),
+            x if x == Code::RplyInviteList as i32 => Ok(Code::RplyInviteList),
+            x if x == Code::RplyEndInviteList as i32 => Ok(Code::RplyEndInviteList),
+            x if x == Code::RplyExceptList as i32 => Ok(Code::RplyExceptList),
+            x if x == Code::RplyEndExceptList as i32 => Ok(Code::RplyEndExceptList),
+            x if x == Code::ErrBanNickChange as i32 => Ok(Code::ErrBanNickChange),
             x if x == Code::RplyCantSendChannel as i32 => Ok(Code::RplyCantSendChannel),
             x if x == Code::RplyBadChannelKey as i32 => Ok(Code::RplyBadChannelKey),
             x if x == Code::RplyUnaway as i32 => Ok(Code::RplyUnaway),
@@ -204,6 +342,45 @@ impl TryFrom<i32> for Code {
             x if x == Code::RplyModes as i32 => Ok(Code::RplyModes),
             x if x == Code::ErrUsersDontMatch as i32 => Ok(Code::ErrUsersDontMatch),
             x if x == Code::ErrNoTextToSend as i32 => Ok(Code::ErrNoTextToSend),
+            x if x == Code::RplyStatsKLine as i32 => Ok(Code::RplyStatsKLine),
+            x if x == Code::RplyStatsLinkInfo as i32 => Ok(Code::RplyStatsLinkInfo),
+            x if x == Code::RplyEndOfStats as i32 => Ok(Code::RplyEndOfStats),
+            x if x == Code::RplyRehashing as i32 => Ok(Code::RplyRehashing),
+            x if x == Code::ErrFileError as i32 => Ok(Code::ErrFileError),
+            x if x == Code::RplyLinks as i32 => Ok(Code::RplyLinks),
+            x if x == Code::RplyEndOfLinks as i32 => Ok(Code::RplyEndOfLinks),
+            x if x == Code::RplyMap as i32 => Ok(Code::RplyMap),
+            x if x == Code::RplyEndOfMap as i32 => Ok(Code::RplyEndOfMap),
+            x if x == Code::RplyLuserClient as i32 => Ok(Code::RplyLuserClient),
+            x if x == Code::RplyLuserOp as i32 => Ok(Code::RplyLuserOp),
+            x if x == Code::RplyLuserChannels as i32 => Ok(Code::RplyLuserChannels),
+            x if x == Code::RplyLuserMe as i32 => Ok(Code::RplyLuserMe),
+            x if x == Code::RplyLocalUsers as i32 => Ok(Code::RplyLocalUsers),
+            x if x == Code::RplyGlobalUsers as i32 => Ok(Code::RplyGlobalUsers),
+            x if x == Code::RplyVersion as i32 => Ok(Code::RplyVersion),
+            x if x == Code::RplyISupport as i32 => Ok(Code::RplyISupport),
+            x if x == Code::ErrUnknownMode as i32 => Ok(Code::ErrUnknownMode),
+            x if x == Code::RplyTopicWhoTime as i32 => Ok(Code::RplyTopicWhoTime),
+            x if x == Code::RplyTime as i32 => Ok(Code::RplyTime),
+            x if x == Code::RplyAdminMe as i32 => Ok(Code::RplyAdminMe),
+            x if x == Code::RplyAdminLoc1 as i32 => Ok(Code::RplyAdminLoc1),
+            x if x == Code::RplyAdminEmail as i32 => Ok(Code::RplyAdminEmail),
+            x if x == Code::ErrNoAdminInfo as i32 => Ok(Code::ErrNoAdminInfo),
+            x if x == Code::RplyInfo as i32 => Ok(Code::RplyInfo),
+            x if x == Code::RplyEndOfInfo as i32 => Ok(Code::RplyEndOfInfo),
+            x if x == Code::RplyMotdStart as i32 => Ok(Code::RplyMotdStart),
+            x if x == Code::RplyMotd as i32 => Ok(Code::RplyMotd),
+            x if x == Code::RplyEndOfMotd as i32 => Ok(Code::RplyEndOfMotd),
+            x if x == Code::ErrNoMotd as i32 => Ok(Code::ErrNoMotd),
+            x if x == Code::RplyInvitedTo as i32 => Ok(Code::RplyInvitedTo),
+            x if x == Code::RplyEndInvitedTo as i32 => Ok(Code::RplyEndInvitedTo),
+            x if x == Code::RplyKnock as i32 => Ok(Code::RplyKnock),
+            x if x == Code::RplyKnockDelivered as i32 => Ok(Code::RplyKnockDelivered),
+            x if x == Code::ErrTooManyKnock as i32 => Ok(Code::ErrTooManyKnock),
+            x if x == Code::ErrChanOpen as i32 => Ok(Code::ErrChanOpen),
+            x if x == Code::ErrKnockOnChan as i32 => Ok(Code::ErrKnockOnChan),
+            x if x == Code::ErrCannotKnock as i32 => Ok(Code::ErrCannotKnock),
+            x if x == Code::ErrUnavailResource as i32 => Ok(Code::ErrUnavailResource),
             _ => Err(()),
         }
     }
@@ -216,7 +393,8 @@ impl TryFrom<i32> for Code {
 impl ToString for Code {
     fn to_string(&self) -> String {
         let x = *self as i32;
-        x.to_string()
+        // numerics always have three digits
+        format!("{x:03}")
     }
 }
 
@@ -317,4 +495,9 @@ mod test {
         let x = Code::ErrAlreadyregistred;
         assert_eq!(format!("{:?}", x), "ErrAlreadyregistred");
     }
+
+    #[test]
+    fn short_codes_have_three_digits() {
+        assert_eq!(Code::try_from(15).unwrap().to_string(), "015");
+    }
 }
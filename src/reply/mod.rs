@@ -362,6 +362,53 @@ impl Reply {
         }
     }
 
+    pub fn rpl_invite_list(params: Vec<String>) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyInviteList,
+            parameters: Some(params),
+        }
+    }
+
+    pub fn rpl_end_invite_list(params: Vec<String>) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyEndInviteList,
+            parameters: Some(params),
+        }
+    }
+
+    pub fn rpl_except_list(params: Vec<String>) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyExceptList,
+            parameters: Some(params),
+        }
+    }
+
+    pub fn rpl_end_except_list(params: Vec<String>) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyEndExceptList,
+            parameters: Some(params),
+        }
+    }
+
+    ///
+    /// creates an ERR_BANNICKCHANGE
+    ///
+    pub fn err_ban_nick_change(nickname: &str, channel: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrBanNickChange,
+            parameters: Some(vec![
+                nickname.to_string(),
+                channel.to_string(),
+                ":Cannot change nickname while banned on channel".to_string(),
+            ]),
+        }
+    }
+
     pub fn err_cant_send_to_channel(params: Vec<String>) -> Self {
         Self {
             prefix: None,
@@ -445,6 +492,432 @@ impl Reply {
             parameters: None,
         }
     }
+    pub fn rpl_stats_kline(params: Vec<String>) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyStatsKLine,
+            parameters: Some(params),
+        }
+    }
+    pub fn rpl_stats_link_info(params: Vec<String>) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyStatsLinkInfo,
+            parameters: Some(params),
+        }
+    }
+    pub fn rpl_end_of_stats(letter: String) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyEndOfStats,
+            parameters: Some(vec![letter, ":End of /STATS report".to_string()]),
+        }
+    }
+    pub fn rpl_rehashing(file: String, changes: &[String]) -> Self {
+        let changed = match changes.is_empty() {
+            true => "nothing changed".to_string(),
+            false => format!("changed {}", changes.join(", ")),
+        };
+        Self {
+            prefix: None,
+            code: Code::RplyRehashing,
+            parameters: Some(vec![file, format!(":Rehashing, {changed}")]),
+        }
+    }
+    pub fn err_file_error(operation: &str, file: String) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrFileError,
+            parameters: Some(vec![format!(":File error doing {operation} on {file}")]),
+        }
+    }
+    pub fn rpl_links(server: String, uplink: String, hopcount: usize) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyLinks,
+            parameters: Some(vec![
+                server.clone(),
+                uplink,
+                format!(":{hopcount} {server}"),
+            ]),
+        }
+    }
+    pub fn rpl_end_of_links(mask: String) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyEndOfLinks,
+            parameters: Some(vec![mask, ":End of /LINKS list".to_string()]),
+        }
+    }
+    pub fn rpl_map(line: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyMap,
+            parameters: Some(vec![format!(":{line}")]),
+        }
+    }
+    pub fn rpl_end_of_map() -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyEndOfMap,
+            parameters: Some(vec![":End of /MAP".to_string()]),
+        }
+    }
+    pub fn rpl_luser_client(users: usize, invisible: usize, servers: usize) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyLuserClient,
+            parameters: Some(vec![format!(
+                ":There are {users} users and {invisible} invisible on {servers} servers"
+            )]),
+        }
+    }
+    pub fn rpl_luser_op(opers: usize) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyLuserOp,
+            parameters: Some(vec![opers.to_string(), ":operator(s) online".to_string()]),
+        }
+    }
+    pub fn rpl_luser_channels(channels: usize) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyLuserChannels,
+            parameters: Some(vec![channels.to_string(), ":channels formed".to_string()]),
+        }
+    }
+    pub fn rpl_luser_me(clients: usize, servers: usize) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyLuserMe,
+            parameters: Some(vec![format!(
+                ":I have {clients} clients and {servers} servers"
+            )]),
+        }
+    }
+    pub fn rpl_local_users(current: usize, max: usize) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyLocalUsers,
+            parameters: Some(vec![
+                current.to_string(),
+                max.to_string(),
+                format!(":Current local users {current}, max {max}"),
+            ]),
+        }
+    }
+    pub fn rpl_global_users(current: usize, max: usize) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyGlobalUsers,
+            parameters: Some(vec![
+                current.to_string(),
+                max.to_string(),
+                format!(":Current global users {current}, max {max}"),
+            ]),
+        }
+    }
+
+    ///
+    /// creates an RPL_VERSION
+    ///
+    pub fn rpl_version(version: &str, server: &str, comments: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyVersion,
+            parameters: Some(vec![
+                version.to_string(),
+                server.to_string(),
+                format!(":{comments}"),
+            ]),
+        }
+    }
+
+    ///
+    /// creates an RPL_TIME
+    ///
+    pub fn rpl_time(server: &str, time: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyTime,
+            parameters: Some(vec![server.to_string(), format!(":{time}")]),
+        }
+    }
+
+    ///
+    /// creates an RPL_ADMINME
+    ///
+    pub fn rpl_admin_me(server: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyAdminMe,
+            parameters: Some(vec![server.to_string(), ":Administrative info".to_string()]),
+        }
+    }
+
+    ///
+    /// creates an RPL_ADMINLOC1
+    ///
+    pub fn rpl_admin_loc1(location: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyAdminLoc1,
+            parameters: Some(vec![format!(":{location}")]),
+        }
+    }
+
+    ///
+    /// creates an RPL_ADMINEMAIL
+    ///
+    pub fn rpl_admin_email(email: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyAdminEmail,
+            parameters: Some(vec![format!(":{email}")]),
+        }
+    }
+
+    ///
+    /// creates an ERR_NOADMININFO
+    ///
+    pub fn err_no_admin_info(server: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrNoAdminInfo,
+            parameters: Some(vec![
+                server.to_string(),
+                ":No administrative info available".to_string(),
+            ]),
+        }
+    }
+
+    ///
+    /// creates an RPL_INFO
+    ///
+    pub fn rpl_info(line: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyInfo,
+            parameters: Some(vec![format!(":{line}")]),
+        }
+    }
+
+    ///
+    /// creates an RPL_ENDOFINFO
+    ///
+    pub fn rpl_end_of_info() -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyEndOfInfo,
+            parameters: Some(vec![":End of INFO list".to_string()]),
+        }
+    }
+
+    ///
+    /// creates an RPL_MOTDSTART
+    ///
+    pub fn rpl_motd_start(server: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyMotdStart,
+            parameters: Some(vec![format!(":- {server} Message of the day - ")]),
+        }
+    }
+
+    ///
+    /// creates an RPL_MOTD
+    ///
+    pub fn rpl_motd(line: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyMotd,
+            parameters: Some(vec![format!(":- {line}")]),
+        }
+    }
+
+    ///
+    /// creates an RPL_ENDOFMOTD
+    ///
+    pub fn rpl_end_of_motd() -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyEndOfMotd,
+            parameters: Some(vec![":End of MOTD command".to_string()]),
+        }
+    }
+
+    ///
+    /// creates an ERR_NOMOTD
+    ///
+    pub fn err_no_motd() -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrNoMotd,
+            parameters: Some(vec![":MOTD File is missing".to_string()]),
+        }
+    }
+
+    ///
+    /// creates an RPL_ISUPPORT, that tells the
+    /// user the features of the server
+    ///
+    pub fn rpl_isupport(nickname: &str, tokens: &[String]) -> Self {
+        let mut parameters = vec![nickname.to_string()];
+        parameters.extend_from_slice(tokens);
+        parameters.push(":are supported by this server".to_string());
+        Self {
+            prefix: None,
+            code: Code::RplyISupport,
+            parameters: Some(parameters),
+        }
+    }
+
+    ///
+    /// creates an ERR_UNKNOWNMODE
+    ///
+    pub fn err_unknown_mode(mode: char) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrUnknownMode,
+            parameters: Some(vec![
+                mode.to_string(),
+                ":is unknown mode char to me".to_string(),
+            ]),
+        }
+    }
+
+    ///
+    /// creates a RPL_TOPICWHOTIME, that tells who
+    /// set the topic of the channel and when
+    ///
+    pub fn rpl_topic_who_time(channel: &str, setter: &str, set_at: u64) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyTopicWhoTime,
+            parameters: Some(vec![
+                channel.to_string(),
+                setter.to_string(),
+                set_at.to_string(),
+            ]),
+        }
+    }
+
+    ///
+    /// creates a RPL_INVITEDTO, that tells a channel
+    /// the user was invited to and can still join
+    ///
+    pub fn rpl_invited_to(channel: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyInvitedTo,
+            parameters: Some(vec![channel.to_string()]),
+        }
+    }
+
+    ///
+    /// creates the reply that ends the
+    /// list of channels the user was invited to
+    ///
+    pub fn rpl_end_invited_to() -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyEndInvitedTo,
+            parameters: Some(vec![":End of /INVITE list".to_string()]),
+        }
+    }
+
+    ///
+    /// creates a RPL_KNOCK, that tells the operators
+    /// of the channel who asked for an invite
+    ///
+    pub fn rpl_knock(channel: &str, mask: &str, message: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyKnock,
+            parameters: Some(vec![
+                channel.to_string(),
+                mask.to_string(),
+                format!(":{message}"),
+            ]),
+        }
+    }
+
+    ///
+    /// creates a RPL_KNOCKDLVR
+    ///
+    pub fn rpl_knock_delivered(channel: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::RplyKnockDelivered,
+            parameters: Some(vec![
+                channel.to_string(),
+                ":Your KNOCK has been delivered".to_string(),
+            ]),
+        }
+    }
+
+    ///
+    /// creates an ERR_TOOMANYKNOCK, the limit is
+    /// either the one of the user or of the channel
+    ///
+    pub fn err_too_many_knock(channel: &str, limit: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrTooManyKnock,
+            parameters: Some(vec![
+                channel.to_string(),
+                format!(":Too many KNOCKs ({limit})"),
+            ]),
+        }
+    }
+
+    ///
+    /// creates an ERR_CHANOPEN
+    ///
+    pub fn err_chan_open(channel: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrChanOpen,
+            parameters: Some(vec![channel.to_string(), ":Channel is open".to_string()]),
+        }
+    }
+
+    ///
+    /// creates an ERR_KNOCKONCHAN
+    ///
+    pub fn err_knock_on_chan(channel: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrKnockOnChan,
+            parameters: Some(vec![
+                channel.to_string(),
+                ":You are already on that channel".to_string(),
+            ]),
+        }
+    }
+
+    ///
+    /// creates an ERR_CANNOTKNOCK
+    ///
+    pub fn err_cannot_knock(channel: &str, reason: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrCannotKnock,
+            parameters: Some(vec![format!(":Cannot knock on {channel} ({reason})")]),
+        }
+    }
+
+    pub fn err_unavail_resource(channel: &str) -> Self {
+        Self {
+            prefix: None,
+            code: Code::ErrUnavailResource,
+            parameters: Some(vec![
+                channel.to_string(),
+                ":Channel is temporarily unavailable (+j)".to_string(),
+            ]),
+        }
+    }
+
     pub fn rpl_none() -> Self {
         Self {
             prefix: None,
@@ -719,6 +1192,23 @@ mod test {
         assert_eq!(code, reply.code as i32)
     }
 
+    #[test]
+    fn rpl_except_and_invite_lists() {
+        let reply = Reply::rpl_except_list(vec!["#c".to_string(), "*!*@host".to_string()]);
+        assert_eq!(reply.to_string(), "348 #c *!*@host");
+        let reply = Reply::rpl_end_invite_list(vec!["#c".to_string()]);
+        assert_eq!(347, reply.code as i32)
+    }
+
+    #[test]
+    fn err_ban_nick_change() {
+        let reply = Reply::err_ban_nick_change("pepe", "#c");
+        assert_eq!(
+            reply.to_string(),
+            "435 pepe #c :Cannot change nickname while banned on channel"
+        );
+    }
+
     #[test]
     fn err_cant_send_to_channel() {
         let reply = Reply::err_cant_send_to_channel(vec![" ".to_string()]);
@@ -834,4 +1324,46 @@ mod test {
         let result = rep1.eq(&rep2);
         assert!(result)
     }
+
+    #[test]
+    fn rpl_isupport_lists_the_tokens() {
+        let tokens = vec!["PREFIX=(ov)@+".to_string(), "CHANTYPES=#&".to_string()];
+        let reply = Reply::rpl_isupport("pepe", &tokens);
+        assert_eq!(
+            reply.to_string(),
+            "005 pepe PREFIX=(ov)@+ CHANTYPES=#& :are supported by this server"
+        );
+    }
+
+    #[test]
+    fn rpl_topic_who_time_tells_setter_and_time() {
+        let reply = Reply::rpl_topic_who_time("#canal", "pepe!pepe@host", 10);
+        assert_eq!(reply.to_string(), "333 #canal pepe!pepe@host 10");
+    }
+
+    #[test]
+    fn rpl_knock_tells_who_knocked() {
+        let reply = Reply::rpl_knock("#canal", "pepe!pepe@host", "let me in");
+        assert_eq!(reply.to_string(), "710 #canal pepe!pepe@host :let me in");
+    }
+
+    #[test]
+    fn err_cannot_knock_tells_the_reason() {
+        let reply = Reply::err_cannot_knock("#canal", "Channel has disabled KNOCK");
+        assert_eq!(reply.code as i32, 480);
+        assert_eq!(
+            reply.to_string(),
+            "480 :Cannot knock on #canal (Channel has disabled KNOCK)"
+        );
+    }
+
+    #[test]
+    fn err_unavail_resource_names_the_channel() {
+        let reply = Reply::err_unavail_resource("#canal");
+        assert_eq!(reply.code as i32, 437);
+        assert_eq!(
+            reply.to_string(),
+            "437 #canal :Channel is temporarily unavailable (+j)"
+        );
+    }
 }
@@ -141,7 +141,9 @@ pub fn make_reply_format(reply: Reply) -> String {
             result.push_str(parameters.concat().as_str());
             result.push_str(":Cannot join channel (+l)");
         }
-        super::code::Code::RplyBanList => {
+        super::code::Code::RplyBanList
+        | super::code::Code::RplyInviteList
+        | super::code::Code::RplyExceptList => {
             result.push(' ');
             result.push_str(parameters.concat().as_str());
         }
@@ -149,6 +151,18 @@ pub fn make_reply_format(reply: Reply) -> String {
             result.push_str(parameters.concat().as_str());
             result.push_str(" :End of channel ban list");
         }
+        super::code::Code::RplyEndInviteList => {
+            result.push_str(parameters.concat().as_str());
+            result.push_str(" :End of channel invite list");
+        }
+        super::code::Code::RplyEndExceptList => {
+            result.push_str(parameters.concat().as_str());
+            result.push_str(" :End of channel exception list");
+        }
+        super::code::Code::ErrBanNickChange => {
+            result.push_str(&parameters.join(" "));
+            result.push_str(" :Cannot change nickname while banned on channel");
+        }
         super::code::Code::RplyCantSendChannel => {
             result.push_str(parameters.concat().as_str());
             result.push_str(" :Cannot send to channel");
@@ -182,6 +196,55 @@ pub fn make_reply_format(reply: Reply) -> String {
         super::code::Code::ErrNoTextToSend => {
             result.push_str(":No text to send");
         }
+        super::code::Code::RplyStatsKLine => {
+            result.push_str(&parameters.join(" "));
+        }
+        super::code::Code::RplyStatsLinkInfo => {
+            result.push_str(&parameters.join(" "));
+        }
+        super::code::Code::RplyEndOfStats => {
+            result.push_str(&parameters.join(" "));
+        }
+        super::code::Code::RplyRehashing => {
+            result.push_str(&parameters.join(" "));
+        }
+        super::code::Code::ErrFileError
+        | super::code::Code::RplyLinks
+        | super::code::Code::RplyEndOfLinks
+        | super::code::Code::RplyMap
+        | super::code::Code::RplyEndOfMap
+        | super::code::Code::RplyLuserClient
+        | super::code::Code::RplyLuserOp
+        | super::code::Code::RplyLuserChannels
+        | super::code::Code::RplyLuserMe
+        | super::code::Code::RplyLocalUsers
+        | super::code::Code::RplyGlobalUsers
+        | super::code::Code::RplyVersion
+        | super::code::Code::RplyTime
+        | super::code::Code::RplyAdminMe
+        | super::code::Code::RplyAdminLoc1
+        | super::code::Code::RplyAdminEmail
+        | super::code::Code::ErrNoAdminInfo
+        | super::code::Code::RplyInfo
+        | super::code::Code::RplyEndOfInfo
+        | super::code::Code::RplyMotdStart
+        | super::code::Code::RplyMotd
+        | super::code::Code::RplyEndOfMotd
+        | super::code::Code::ErrNoMotd
+        | super::code::Code::RplyISupport
+        | super::code::Code::ErrUnknownMode
+        | super::code::Code::RplyTopicWhoTime
+        | super::code::Code::RplyInvitedTo
+        | super::code::Code::RplyEndInvitedTo
+        | super::code::Code::RplyKnock
+        | super::code::Code::RplyKnockDelivered
+        | super::code::Code::ErrTooManyKnock
+        | super::code::Code::ErrChanOpen
+        | super::code::Code::ErrKnockOnChan
+        | super::code::Code::ErrCannotKnock
+        | super::code::Code::ErrUnavailResource => {
+            result.push_str(&parameters.join(" "));
+        }
         super::code::Code::RplyNone => {}
     };
     result
@@ -67,6 +67,10 @@ impl Connection {
         self.user.realname()
     }
 
+    pub fn get_mask(&self) -> String {
+        self.user.mask()
+    }
+
     pub fn modify_connecion_flag(&mut self, flag: &str) {
         self.user.modify_user_flag(flag)
     }
@@ -86,10 +90,34 @@ impl Connection {
         self.user.set_nickname(new_nick);
     }
 
+    pub fn get_uid(&self) -> Option<&str> {
+        self.user.uid()
+    }
+
     pub fn get_user(&self) -> User {
         self.user.clone()
     }
 
+    pub fn get_account(&self) -> Option<&str> {
+        self.user.account()
+    }
+
+    pub fn set_account(&mut self, account: Option<String>) {
+        self.user.set_account(account);
+    }
+
+    ///
+    /// returns true if the client of the
+    /// connection enabled the capability
+    ///
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.user.has_capability(capability)
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Vec<String>) {
+        self.user.set_capabilities(capabilities);
+    }
+
     pub fn get_op_privileges(&mut self) {
         self.user.modify_user_flag("+o");
     }
@@ -110,6 +138,14 @@ impl Connection {
         self.away_msg = msg
     }
 
+    ///
+    /// returns true if the user is connected
+    /// to this server
+    ///
+    pub fn is_local(&self) -> bool {
+        self.stream.is_some()
+    }
+
     pub fn is_on_server(&self, servername: &str) -> bool {
         self.get_servername().eq(&Some(servername))
     }
@@ -1,6 +1,7 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     hash::Hash,
+    ops::Bound,
     sync::{Arc, RwLock},
 };
 
@@ -11,10 +12,13 @@ use crate::repository::traits::operations::Operations;
 /// Struct that implements a key-value pair collection of two generic values
 /// as needed by the DAO programming pattern represented
 /// with the [`Operations`] trait.
+/// The keys are also kept in order, so the pages are served
+/// without sorting them.
 ///
 #[derive(Debug)]
 pub struct HashMapDao<K, T> {
     values: Arc<RwLock<HashMap<K, T>>>,
+    keys: Arc<RwLock<BTreeSet<K>>>,
 }
 
 impl<K, T> HashMapDao<K, T> {
@@ -29,6 +33,7 @@ impl<K, T> HashMapDao<K, T> {
     pub fn new() -> Self {
         HashMapDao {
             values: Arc::new(RwLock::new(HashMap::new())),
+            keys: Arc::new(RwLock::new(BTreeSet::new())),
         }
     }
 }
@@ -47,7 +52,7 @@ impl<K, T> Default for HashMapDao<K, T> {
 ///
 impl<K, T> Operations<K, T> for HashMapDao<K, T>
 where
-    K: Eq + PartialEq + Hash,
+    K: Eq + PartialEq + Hash + Ord + Clone,
     T: Clone,
 {
     fn search(&self, key: K) -> Result<Option<T>, ErrorServer> {
@@ -74,6 +79,7 @@ where
                 Ok(c) => c,
                 Err(_) => return Err(ErrorServer::PoisonedThread),
             };
+            self.index(key.clone())?;
             x.insert(key, value);
         }
 
@@ -96,6 +102,10 @@ where
                 Err(_e) => return Err(ErrorServer::PoisonedThread),
             };
 
+            match self.keys.write() {
+                Ok(mut keys) => keys.remove(&key),
+                Err(_e) => return Err(ErrorServer::PoisonedThread),
+            };
             x.remove(&key);
         }
         Ok(true)
@@ -107,6 +117,7 @@ where
             Err(_e) => return Err(ErrorServer::PoisonedThread),
         };
 
+        self.index(key.clone())?;
         x.insert(key, value);
         Ok(true)
     }
@@ -123,6 +134,40 @@ where
         }
         Ok(list)
     }
+
+    fn find_page(&self, after: Option<K>, amount: usize) -> Result<Vec<T>, ErrorServer> {
+        let x = match self.values.read() {
+            Ok(c) => c,
+            Err(_e) => return Err(ErrorServer::PoisonedThread),
+        };
+
+        let keys = match self.keys.read() {
+            Ok(c) => c,
+            Err(_e) => return Err(ErrorServer::PoisonedThread),
+        };
+        let start = match &after {
+            Some(after) => Bound::Excluded(after),
+            None => Bound::Unbounded,
+        };
+        Ok(keys
+            .range((start, Bound::Unbounded))
+            .take(amount)
+            .filter_map(|key| x.get(key).cloned())
+            .collect())
+    }
+}
+
+impl<K: Ord, T> HashMapDao<K, T> {
+    // Adds the key to the ordered ones.
+    fn index(&self, key: K) -> Result<(), ErrorServer> {
+        match self.keys.write() {
+            Ok(mut keys) => {
+                keys.insert(key);
+                Ok(())
+            }
+            Err(_e) => Err(ErrorServer::PoisonedThread),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -200,4 +245,36 @@ mod test {
         assert!(expected.iter().all(|item| result.contains(item)));
         Ok(())
     }
+
+    #[test]
+    fn find_values_by_pages() -> Result<(), ErrorServer> {
+        let repo: HashMapDao<String, String> = HashMapDao::new();
+        repo.add(String::from("user3"), String::from("test3"))?;
+        repo.add(String::from("user1"), String::from("test1"))?;
+        repo.add(String::from("user2"), String::from("test2"))?;
+
+        assert_eq!(repo.find_page(None, 2)?, vec!["test1", "test2"]);
+        assert_eq!(
+            repo.find_page(Some(String::from("user2")), 2)?,
+            vec!["test3"]
+        );
+        assert_eq!(
+            repo.find_page(Some(String::from("user15")), 5)?,
+            vec!["test2", "test3"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pages_follow_the_changes() -> Result<(), ErrorServer> {
+        let repo: HashMapDao<String, String> = HashMapDao::new();
+        repo.add(String::from("user1"), String::from("test1"))?;
+        repo.add(String::from("user2"), String::from("test2"))?;
+        repo.delete(String::from("user1"))?;
+        repo.update(String::from("user0"), String::from("test0"))?;
+        repo.update(String::from("user2"), String::from("new2"))?;
+
+        assert_eq!(repo.find_page(None, 5)?, vec!["test0", "new2"]);
+        Ok(())
+    }
 }
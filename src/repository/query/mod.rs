@@ -8,6 +8,8 @@ pub enum QueryOption {
     Update,
     Add,
     FindAll,
+    FindPage(usize),
+    Stop,
 }
 /// Enum representing the type of response for each CRUD operation made through a [`Query`] entity.
 /// Each variant contains different types of entities with which it responds.
@@ -18,6 +20,8 @@ pub enum QueryAnswer<T> {
     Update(bool),
     Add(bool),
     FindAll(Vec<T>),
+    FindPage(Vec<T>),
+    Stop,
 }
 
 ///
@@ -107,6 +111,21 @@ impl<T, R> Query<T, R> {
         Query::new(QueryOption::FindAll, None, None)
     }
 
+    ///
+    /// returns a new query of the type find_page, that asks
+    /// for up to amount values after the given key
+    ///
+    pub fn find_page(after: Option<T>, amount: usize) -> Self {
+        Query::new(QueryOption::FindPage(amount), after, None)
+    }
+
+    ///
+    /// returns a new query that ends the repository
+    ///
+    pub fn stop() -> Self {
+        Query::new(QueryOption::Stop, None, None)
+    }
+
     ///
     /// Fuction that validates that a response of the database it's coherent and handle.
     /// it as a [`Result`].
@@ -129,7 +148,9 @@ impl<T, R> Query<T, R> {
             QueryAnswer::Add(_) => self.option == QueryOption::Add,
             QueryAnswer::Delete(_) => self.option == QueryOption::Delete,
             QueryAnswer::FindAll(_) => self.option == QueryOption::FindAll,
+            QueryAnswer::FindPage(_) => matches!(self.option, QueryOption::FindPage(_)),
             QueryAnswer::Update(_) => self.option == QueryOption::Update,
+            QueryAnswer::Stop => self.option == QueryOption::Stop,
         };
 
         if result {
@@ -206,6 +227,15 @@ mod test {
         assert_eq!(result, expected)
     }
 
+    #[test]
+    fn query_find_page() {
+        let result: Query<String, String> = Query::find_page(Some("key".to_string()), 10);
+        assert_eq!(result.get_option(), &QueryOption::FindPage(10));
+        assert!(result
+            .validate_response(QueryAnswer::FindPage(vec![]))
+            .is_ok());
+    }
+
     #[test]
     fn get_an_option_from_query() {
         let q: Query<String, String> =
@@ -59,6 +59,7 @@ impl<K, T> Repository<K, T> {
     /// Function that contains the principal loop of
     /// the repository, in charge of handling a received [`Query`] and  
     /// sending the [`QueryAnswer`] through the [`ClientChannel`].
+    /// The loop ends when a stop query is received.
     ///
     pub fn run(&self) -> Result<(), ErrorServer> {
         //loop principal que recibe las instrucciones
@@ -67,7 +68,11 @@ impl<K, T> Repository<K, T> {
                 Ok(c) => self.handle_received_query(c)?,
                 Err(_) => continue,
             };
+            let stop = matches!(response, QueryAnswer::Stop);
             self.repo_channel.send(response)?;
+            if stop {
+                return Ok(());
+            }
         }
     }
 
@@ -78,6 +83,8 @@ impl<K, T> Repository<K, T> {
             QueryOption::Delete => self.delete(query),
             QueryOption::Update => self.update(query),
             QueryOption::FindAll => self.find_all(),
+            QueryOption::FindPage(amount) => self.find_page(query, amount),
+            QueryOption::Stop => Ok(QueryAnswer::Stop),
         }
     }
 
@@ -121,6 +128,11 @@ impl<K, T> Repository<K, T> {
         let result = self.dao.find_all()?;
         Ok(QueryAnswer::FindAll(result))
     }
+
+    fn find_page(&self, query: Query<K, T>, amount: usize) -> Result<QueryAnswer<T>, ErrorServer> {
+        let result = self.dao.find_page(query.get_arguments().0, amount)?;
+        Ok(QueryAnswer::FindPage(result))
+    }
 }
 
 #[cfg(test)]
@@ -232,6 +244,19 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn stop_ends_the_repository() {
+        let (tx, rx) = mpsc::channel::<ClientChannel<String, String>>();
+        let handle = thread::spawn(move || {
+            let repo: Repository<String, String> = Repository::new(Box::new(HashMapDao::new()));
+            tx.send(repo.get_client_channel()).expect("error");
+            repo.run()
+        });
+        let ch = rx.recv().unwrap();
+        ch.stop().unwrap();
+        assert!(handle.join().unwrap().is_ok());
+    }
+
     #[test]
     fn see_coordination_between_threads() -> Result<(), Box<dyn Error>> {
         let ch = initilize_mock_repo();
@@ -53,6 +53,17 @@ impl<T, Y> ClientChannel<T, Y> {
             Err(_) => Err(ErrorServer::BadQuery),
         }
     }
+
+    ///
+    /// Asks the repository to finish. Once it is stopped,
+    /// every operation sent through the channel fails.
+    ///
+    pub fn stop(&self) -> Result<(), ErrorServer> {
+        match self.send_and_receive(Query::stop())? {
+            QueryAnswer::Stop => Ok(()),
+            _ => Err(ErrorServer::BadQuery),
+        }
+    }
 }
 
 impl<T, Y> Operations<T, Y> for ClientChannel<T, Y> {
@@ -77,6 +88,13 @@ impl<T, Y> Operations<T, Y> for ClientChannel<T, Y> {
             Err(ErrorServer::BadQuery)
         }
     }
+    fn find_page(&self, after: Option<T>, amount: usize) -> Result<Vec<Y>, ErrorServer> {
+        if let QueryAnswer::FindPage(r) = self.send_and_receive(Query::find_page(after, amount))? {
+            Ok(r)
+        } else {
+            Err(ErrorServer::BadQuery)
+        }
+    }
     fn search(&self, key: T) -> Result<Option<Y>, ErrorServer> {
         if let QueryAnswer::Search(r) = self.send_and_receive(Query::search(key))? {
             Ok(r)
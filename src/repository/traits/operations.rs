@@ -15,4 +15,7 @@ pub trait Operations<K, T> {
     fn update(&self, key: K, value: T) -> Result<bool, ErrorServer>;
     /// find all the values contained in the "database"
     fn find_all(&self) -> Result<Vec<T>, ErrorServer>;
+    /// find, in the order of their keys, up to amount
+    /// values whose key goes after the given one
+    fn find_page(&self, after: Option<K>, amount: usize) -> Result<Vec<T>, ErrorServer>;
 }
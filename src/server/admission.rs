@@ -0,0 +1,360 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::config::ConnectionLimits;
+
+// Amount of tracked addresses from which the throttle history
+// starts to be swept of addresses without recent attempts.
+const HISTORY_SWEEP_THRESHOLD: usize = 1024;
+
+///
+/// Reasons why a new connection can be refused
+/// by the [`AdmissionControl`].
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Rejection {
+    ServerFull,
+    TooManyFromHost,
+    TooManyFromNetwork,
+    Throttled,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::ServerFull => write!(f, "Server is full"),
+            Rejection::TooManyFromHost => write!(f, "Too many connections from your host"),
+            Rejection::TooManyFromNetwork => write!(f, "Too many connections from your network"),
+            Rejection::Throttled => write!(f, "Reconnecting too fast, throttled"),
+        }
+    }
+}
+
+///
+/// Struct in charge of deciding whether the [`crate::server::MainServer`]
+/// accepts a new connection. It counts the open connections globally,
+/// per address and per configured block, and remembers the recent
+/// connection attempts of each address to throttle reconnect storms.
+///
+#[derive(Debug)]
+pub struct AdmissionControl {
+    limits: ConnectionLimits,
+    total: usize,
+    active: HashMap<IpAddr, usize>,
+    attempts: HashMap<IpAddr, VecDeque<Instant>>,
+}
+
+impl AdmissionControl {
+    ///
+    /// creates an admission control
+    /// without open connections
+    ///
+    pub fn new(limits: ConnectionLimits) -> Self {
+        AdmissionControl {
+            limits,
+            total: 0,
+            active: HashMap::new(),
+            attempts: HashMap::new(),
+        }
+    }
+
+    ///
+    /// replaces the limits, connections already
+    /// admitted are kept
+    ///
+    pub fn set_limits(&mut self, limits: ConnectionLimits) {
+        self.limits = limits;
+    }
+
+    ///
+    /// returns the amount of open connections
+    ///
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    ///
+    /// Registers a connection attempt from the address and decides if it is accepted.
+    ///
+    /// # Returns
+    /// If every configured limit allows it, the connection is counted as open
+    /// until [`AdmissionControl::release`] is called. Otherwise returns the
+    /// [`Rejection`] reason.
+    pub fn admit(&mut self, ip: IpAddr, now: Instant) -> Result<(), Rejection> {
+        if !self.is_exempt(&ip) {
+            self.check_throttle(ip, now)?;
+            self.check_limits(&ip)?;
+        }
+        self.total += 1;
+        *self.active.entry(ip).or_insert(0) += 1;
+        Ok(())
+    }
+
+    ///
+    /// forgets an open connection
+    /// of the address
+    ///
+    pub fn release(&mut self, ip: IpAddr) {
+        if let Some(amount) = self.active.get_mut(&ip) {
+            *amount -= 1;
+            if *amount == 0 {
+                self.active.remove(&ip);
+            }
+            self.total = self.total.saturating_sub(1);
+        }
+    }
+
+    fn is_exempt(&self, ip: &IpAddr) -> bool {
+        self.limits.exempt.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    fn check_limits(&self, ip: &IpAddr) -> Result<(), Rejection> {
+        if let Some(max) = self.limits.max_connections {
+            if self.total >= max {
+                return Err(Rejection::ServerFull);
+            }
+        }
+        if let Some(max) = self.limits.max_connections_per_ip {
+            if self.active.get(ip).copied().unwrap_or(0) >= max {
+                return Err(Rejection::TooManyFromHost);
+            }
+        }
+        for (cidr, max) in &self.limits.cidr_limits {
+            if cidr.contains(ip) {
+                let open: usize = self
+                    .active
+                    .iter()
+                    .filter(|(address, _)| cidr.contains(address))
+                    .map(|(_, amount)| amount)
+                    .sum();
+                if open >= *max {
+                    return Err(Rejection::TooManyFromNetwork);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Every attempt is remembered, even the rejected ones, so a client
+    // reconnecting in a loop stays throttled until it slows down.
+    fn check_throttle(&mut self, ip: IpAddr, now: Instant) -> Result<(), Rejection> {
+        let throttle = match self.limits.throttle {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        if self.attempts.len() > HISTORY_SWEEP_THRESHOLD {
+            self.attempts.retain(|_, attempts| {
+                attempts
+                    .back()
+                    .is_some_and(|last| now.duration_since(*last) < throttle.window)
+            });
+        }
+        let attempts = self.attempts.entry(ip).or_default();
+        while let Some(first) = attempts.front() {
+            if now.duration_since(*first) < throttle.window {
+                break;
+            }
+            attempts.pop_front();
+        }
+        attempts.push_back(now);
+        if attempts.len() > throttle.connections {
+            return Err(Rejection::Throttled);
+        }
+        Ok(())
+    }
+}
+
+///
+/// Struct that represents a connection admitted by an [`AdmissionControl`].
+/// The connection is released when the ticket is dropped, so it can be
+/// moved into the thread that serves the connection.
+///
+pub struct AdmissionTicket {
+    ip: IpAddr,
+    control: Arc<Mutex<AdmissionControl>>,
+}
+
+impl AdmissionTicket {
+    ///
+    /// asks the shared admission control for a new connection
+    /// from the address
+    ///
+    pub fn request(
+        control: &Arc<Mutex<AdmissionControl>>,
+        ip: IpAddr,
+    ) -> Result<AdmissionTicket, Rejection> {
+        // A poisoned control must not leave the server unreachable,
+        // its counters are still consistent between calls.
+        let mut c = control
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        c.admit(ip, Instant::now())?;
+        Ok(AdmissionTicket {
+            ip,
+            control: control.clone(),
+        })
+    }
+}
+
+impl Drop for AdmissionTicket {
+    fn drop(&mut self) {
+        let mut control = self
+            .control
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        control.release(self.ip);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{str::FromStr, time::Duration};
+
+    use crate::config::{cidr::Cidr, Throttle};
+
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn without_limits_everything_is_admitted() {
+        let mut control = AdmissionControl::new(ConnectionLimits::default());
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(control.admit(ip("10.0.0.1"), now).is_ok());
+        }
+        assert_eq!(control.total(), 100);
+    }
+
+    #[test]
+    fn global_cap_rejects_when_full() {
+        let limits = ConnectionLimits {
+            max_connections: Some(2),
+            ..Default::default()
+        };
+        let mut control = AdmissionControl::new(limits);
+        let now = Instant::now();
+        control.admit(ip("10.0.0.1"), now).unwrap();
+        control.admit(ip("10.0.0.2"), now).unwrap();
+        assert_eq!(
+            control.admit(ip("10.0.0.3"), now),
+            Err(Rejection::ServerFull)
+        );
+        control.release(ip("10.0.0.1"));
+        assert!(control.admit(ip("10.0.0.3"), now).is_ok());
+    }
+
+    #[test]
+    fn per_ip_limit_only_affects_that_address() {
+        let limits = ConnectionLimits {
+            max_connections_per_ip: Some(1),
+            ..Default::default()
+        };
+        let mut control = AdmissionControl::new(limits);
+        let now = Instant::now();
+        control.admit(ip("10.0.0.1"), now).unwrap();
+        assert_eq!(
+            control.admit(ip("10.0.0.1"), now),
+            Err(Rejection::TooManyFromHost)
+        );
+        assert!(control.admit(ip("10.0.0.2"), now).is_ok());
+    }
+
+    #[test]
+    fn cidr_limit_counts_the_whole_block() {
+        let limits = ConnectionLimits {
+            cidr_limits: vec![(Cidr::from_str("10.0.0.0/24").unwrap(), 2)],
+            ..Default::default()
+        };
+        let mut control = AdmissionControl::new(limits);
+        let now = Instant::now();
+        control.admit(ip("10.0.0.1"), now).unwrap();
+        control.admit(ip("10.0.0.2"), now).unwrap();
+        assert_eq!(
+            control.admit(ip("10.0.0.3"), now),
+            Err(Rejection::TooManyFromNetwork)
+        );
+        assert!(control.admit(ip("10.0.1.1"), now).is_ok());
+    }
+
+    #[test]
+    fn throttle_rejects_fast_reconnections() {
+        let limits = ConnectionLimits {
+            throttle: Some(Throttle {
+                connections: 2,
+                window: Duration::from_secs(10),
+            }),
+            ..Default::default()
+        };
+        let mut control = AdmissionControl::new(limits);
+        let now = Instant::now();
+        control.admit(ip("10.0.0.1"), now).unwrap();
+        control.release(ip("10.0.0.1"));
+        control.admit(ip("10.0.0.1"), now).unwrap();
+        control.release(ip("10.0.0.1"));
+        assert_eq!(
+            control.admit(ip("10.0.0.1"), now),
+            Err(Rejection::Throttled)
+        );
+        let later = now + Duration::from_secs(11);
+        assert!(control.admit(ip("10.0.0.1"), later).is_ok());
+    }
+
+    #[test]
+    fn exempt_addresses_skip_the_limits() {
+        let limits = ConnectionLimits {
+            max_connections_per_ip: Some(1),
+            exempt: vec![Cidr::from_str("127.0.0.1").unwrap()],
+            ..Default::default()
+        };
+        let mut control = AdmissionControl::new(limits);
+        let now = Instant::now();
+        control.admit(ip("127.0.0.1"), now).unwrap();
+        assert!(control.admit(ip("127.0.0.1"), now).is_ok());
+    }
+
+    #[test]
+    fn dropping_a_ticket_releases_the_connection() {
+        let control = Arc::new(Mutex::new(AdmissionControl::new(
+            ConnectionLimits::default(),
+        )));
+        let ticket = AdmissionTicket::request(&control, ip("10.0.0.1")).unwrap();
+        assert_eq!(control.lock().unwrap().total(), 1);
+        drop(ticket);
+        assert_eq!(control.lock().unwrap().total(), 0);
+    }
+
+    #[test]
+    fn poisoned_control_keeps_admitting() {
+        let control = Arc::new(Mutex::new(AdmissionControl::new(
+            ConnectionLimits::default(),
+        )));
+        let poisoner = control.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poisons the control");
+        })
+        .join();
+        assert!(control.is_poisoned());
+
+        let ticket = AdmissionTicket::request(&control, ip("10.0.0.1")).unwrap();
+        drop(ticket);
+        let total = control.lock().unwrap_or_else(|p| p.into_inner()).total();
+        assert_eq!(total, 0);
+    }
+
+    #[test]
+    fn rejection_displays_reason() {
+        assert_eq!(
+            Rejection::TooManyFromHost.to_string(),
+            "Too many connections from your host"
+        );
+    }
+}
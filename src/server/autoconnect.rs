@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::LinkBlock,
+    server_comunication::{spanning_tree::SpanningTree, ServerComunicationHandler},
+};
+
+use super::state::ServerState;
+
+// time between two checks of the configured links
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// wait after the first failed attempt, doubled after each one
+const FIRST_RETRY: Duration = Duration::from_secs(5);
+const MAX_RETRY: Duration = Duration::from_secs(300);
+
+///
+/// Keeps the attempts made to bring up the links with autoconnect.
+/// While a link stays down, the wait between two attempts doubles
+/// up to a limit. Once the link is up the wait starts over.
+///
+#[derive(Debug, Default)]
+pub struct Autoconnect {
+    retries: HashMap<String, Retry>,
+}
+
+#[derive(Debug)]
+struct Retry {
+    wait: Duration,
+    next_attempt: Instant,
+}
+
+impl Autoconnect {
+    pub fn new() -> Self {
+        Autoconnect::default()
+    }
+
+    ///
+    /// returns the links with autoconnect that are down
+    /// and whose next attempt is due. The links that are
+    /// up forget their previous attempts.
+    ///
+    pub fn due<F: Fn(&str) -> bool>(
+        &mut self,
+        links: &[LinkBlock],
+        is_linked: F,
+        now: Instant,
+    ) -> Vec<LinkBlock> {
+        let mut due = vec![];
+        for link in links.iter().filter(|link| link.autoconnect) {
+            if is_linked(&link.servername) {
+                self.retries.remove(&link.servername);
+                continue;
+            }
+            match self.retries.get(&link.servername) {
+                Some(retry) if retry.next_attempt > now => {}
+                _ => due.push(link.clone()),
+            }
+        }
+        due
+    }
+
+    ///
+    /// records an attempt to link with the server,
+    /// delaying the next one
+    ///
+    pub fn attempted(&mut self, servername: &str, now: Instant) {
+        let wait = match self.retries.get(servername) {
+            Some(retry) => (retry.wait * 2).min(MAX_RETRY),
+            None => FIRST_RETRY,
+        };
+        self.retries.insert(
+            servername.to_string(),
+            Retry {
+                wait,
+                next_attempt: now + wait,
+            },
+        );
+    }
+}
+
+///
+/// Starts a thread that links the server with the configured links
+/// that have autoconnect, and links them again when they drop. The
+/// links are read from the configuration on every check, so a rehash
+/// applies to them. The thread ends when the server shuts down.
+///
+pub fn handle_autoconnect(
+    server_comunication: ServerComunicationHandler,
+    spanning_tree: Arc<Mutex<SpanningTree>>,
+    state: ServerState,
+) {
+    thread::spawn(move || {
+        let mut autoconnect = Autoconnect::new();
+        while !state.shutdown.is_requested() {
+            let links = match state.config.lock() {
+                Ok(config) => config.links.clone(),
+                Err(_) => return,
+            };
+            let now = Instant::now();
+            let due = match spanning_tree.lock() {
+                Ok(st) => autoconnect.due(&links, |servername| st.is_linked_to(servername), now),
+                Err(_) => return,
+            };
+            for link in due {
+                autoconnect.attempted(&link.servername, now);
+                if let Err(e) = server_comunication.connect_to(&link.address) {
+                    println!("autoconnect to {} failed: {e:?}", link.servername);
+                }
+            }
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::config::LinkBlock;
+
+    use super::{Autoconnect, FIRST_RETRY, MAX_RETRY};
+
+    fn links() -> Vec<LinkBlock> {
+        vec![
+            LinkBlock {
+                servername: "server2".to_string(),
+                address: "127.0.0.1:8081".to_string(),
+                autoconnect: true,
+            },
+            LinkBlock {
+                servername: "server3".to_string(),
+                address: "127.0.0.1:8082".to_string(),
+                autoconnect: false,
+            },
+        ]
+    }
+
+    fn names(links: Vec<LinkBlock>) -> Vec<String> {
+        links.into_iter().map(|link| link.servername).collect()
+    }
+
+    #[test]
+    fn only_links_down_with_autoconnect_are_due() {
+        let mut autoconnect = Autoconnect::new();
+        let now = Instant::now();
+
+        assert_eq!(
+            names(autoconnect.due(&links(), |_| false, now)),
+            ["server2"]
+        );
+        assert!(autoconnect.due(&links(), |_| true, now).is_empty());
+    }
+
+    #[test]
+    fn wait_doubles_after_each_attempt() {
+        let mut autoconnect = Autoconnect::new();
+        let now = Instant::now();
+        autoconnect.attempted("server2", now);
+        assert!(autoconnect.due(&links(), |_| false, now).is_empty());
+
+        let now = now + FIRST_RETRY;
+        assert_eq!(autoconnect.due(&links(), |_| false, now).len(), 1);
+        autoconnect.attempted("server2", now);
+        assert!(autoconnect
+            .due(&links(), |_| false, now + FIRST_RETRY)
+            .is_empty());
+        assert_eq!(
+            autoconnect
+                .due(&links(), |_| false, now + FIRST_RETRY * 2)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn wait_is_limited() {
+        let mut autoconnect = Autoconnect::new();
+        let mut now = Instant::now();
+        for _ in 0..20 {
+            autoconnect.attempted("server2", now);
+            now += MAX_RETRY;
+        }
+        assert_eq!(autoconnect.due(&links(), |_| false, now).len(), 1);
+    }
+
+    #[test]
+    fn link_up_starts_the_wait_over() {
+        let mut autoconnect = Autoconnect::new();
+        let now = Instant::now();
+        autoconnect.attempted("server2", now);
+        autoconnect.attempted("server2", now);
+        autoconnect.due(&links(), |_| true, now);
+
+        autoconnect.attempted("server2", now);
+        let after_first_retry = now + FIRST_RETRY + Duration::from_millis(1);
+        assert_eq!(
+            autoconnect
+                .due(&links(), |_| false, after_first_retry)
+                .len(),
+            1
+        );
+    }
+}
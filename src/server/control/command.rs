@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use crate::error::error_server::ErrorServer;
+
+const DEFAULT_REASON: &str = "Requested by the administrator";
+
+///
+/// Commands accepted by the control socket, one per line:
+///
+/// * `connect <ip:port>` - links the server with another one.
+/// * `squit <server> [reason]` - splits a server from the network.
+/// * `links` - lists the links between the servers.
+/// * `users` - lists the users of the network.
+/// * `kill <nick>` - removes a user from the network.
+/// * `reload` - reads the configuration file again, as REHASH does.
+/// * `dump` - lists everything the server knows.
+/// * `die [reason]` and `restart [reason]` - stop the server.
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum ControlCommand {
+    Connect(String),
+    Squit(String, String),
+    Links,
+    Users,
+    Kill(String),
+    Reload,
+    Dump,
+    Die(String),
+    Restart(String),
+}
+
+impl FromStr for ControlCommand {
+    type Err = ErrorServer;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (name, arguments) = match line.split_once(char::is_whitespace) {
+            Some((name, arguments)) => (name, arguments.trim()),
+            None => (line, ""),
+        };
+        let (first, rest) = match arguments.split_once(char::is_whitespace) {
+            Some((first, rest)) => (first, rest.trim()),
+            None => (arguments, ""),
+        };
+        let reason = |text: &str| match text.is_empty() {
+            true => DEFAULT_REASON.to_string(),
+            false => text.to_string(),
+        };
+        let command = match (name.to_lowercase().as_str(), first.is_empty()) {
+            ("connect", false) => ControlCommand::Connect(first.to_string()),
+            ("squit", false) => ControlCommand::Squit(first.to_string(), reason(rest)),
+            ("kill", false) => ControlCommand::Kill(first.to_string()),
+            ("links", true) => ControlCommand::Links,
+            ("users", true) => ControlCommand::Users,
+            ("reload", true) => ControlCommand::Reload,
+            ("dump", true) => ControlCommand::Dump,
+            ("die", _) => ControlCommand::Die(reason(arguments)),
+            ("restart", _) => ControlCommand::Restart(reason(arguments)),
+            _ => return Err(ErrorServer::UnknownCommand),
+        };
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::{ControlCommand, DEFAULT_REASON};
+    use crate::error::error_server::ErrorServer;
+
+    #[test]
+    fn parses_connect() {
+        assert_eq!(
+            ControlCommand::from_str("connect 127.0.0.1:8081"),
+            Ok(ControlCommand::Connect("127.0.0.1:8081".to_string()))
+        );
+    }
+
+    #[test]
+    fn squit_keeps_the_whole_reason() {
+        assert_eq!(
+            ControlCommand::from_str("SQUIT leaf going  away"),
+            Ok(ControlCommand::Squit(
+                "leaf".to_string(),
+                "going  away".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn die_has_a_default_reason() {
+        assert_eq!(
+            ControlCommand::from_str("die"),
+            Ok(ControlCommand::Die(DEFAULT_REASON.to_string()))
+        );
+    }
+
+    #[test]
+    fn missing_argument_is_err() {
+        assert_eq!(
+            ControlCommand::from_str("kill"),
+            Err(ErrorServer::UnknownCommand)
+        );
+        assert_eq!(
+            ControlCommand::from_str("links now"),
+            Err(ErrorServer::UnknownCommand)
+        );
+    }
+}
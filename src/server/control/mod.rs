@@ -0,0 +1,369 @@
+pub mod command;
+
+use std::{
+    fs::{self, Permissions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    str::FromStr,
+    sync::{Arc, Weak},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    ban::ban_kind::BanKind,
+    command::{kill::Kill, squit::SquitMsg},
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::code::Code,
+    repository::traits::operations::Operations,
+    server_comunication::info_sender::inform_all_servers,
+    utils::{unix_timestamp, write_message_to},
+};
+
+use self::command::ControlCommand;
+
+use super::{shutdown::ShutdownRequest, MainServer};
+
+const FIELD_SEPARATOR: &str = "\t";
+// only the user that runs the server can administer it
+const SOCKET_MODE: u32 = 0o600;
+
+///
+/// Local administration socket of the [`MainServer`].
+/// Every line received is a [`ControlCommand`], answered with one
+/// record per line, made of tab separated fields, and a last
+/// line with `OK` or `ERR <reason>`.
+///
+pub struct ControlSocket {
+    // it doesn't keep the server alive, so the repositories can be stopped
+    server: Weak<MainServer>,
+}
+
+impl ControlSocket {
+    pub fn new(server: &Arc<MainServer>) -> Self {
+        ControlSocket {
+            server: Arc::downgrade(server),
+        }
+    }
+
+    ///
+    /// Binds the socket at the given path, readable and writable only
+    /// by the owner of the server, and attends its clients in a new
+    /// thread. Any other file at the path is kept, and refused.
+    ///
+    pub fn run(self, path: &str) -> Result<JoinHandle<()>, ErrorServer> {
+        // a socket left by a previous run would make the bind fail
+        Self::remove(path)?;
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, Permissions::from_mode(SOCKET_MODE))?;
+        let control = Arc::new(self);
+        Ok(thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let control = control.clone();
+                thread::spawn(move || {
+                    if let Err(e) = control.attend(stream) {
+                        println!("control socket: {e:?}");
+                    }
+                });
+            }
+        }))
+    }
+
+    ///
+    /// removes the socket at the path, if there is one,
+    /// once the server doesn't attend it anymore
+    ///
+    pub fn remove(path: &str) -> Result<(), ErrorServer> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => Ok(fs::remove_file(path)?),
+            Ok(_) => Err(ErrorServer::TcpStreamError(ErrorKind::AlreadyExists)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn attend(&self, stream: UnixStream) -> Result<(), ErrorServer> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            writer.write_all(self.execute(&line?).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Executes a command line and returns
+    /// the response that is sent to the client.
+    ///
+    pub fn execute(&self, line: &str) -> String {
+        let result = ControlCommand::from_str(line).and_then(|c| self.run_command(c));
+        match result {
+            Ok(records) => records
+                .iter()
+                .map(|record| format!("{record}\n"))
+                .chain(std::iter::once("OK\n".to_string()))
+                .collect(),
+            Err(e) => format!("ERR {e}\n"),
+        }
+    }
+
+    fn run_command(&self, command: ControlCommand) -> Result<Vec<String>, ErrorServer> {
+        let server = match self.server.upgrade() {
+            Some(server) => server,
+            None => return Err(ErrorServer::ServerClosed),
+        };
+        match command {
+            ControlCommand::Connect(address) => {
+                server.build_server_comunication()?.connect_to(&address)?;
+                Ok(vec![])
+            }
+            ControlCommand::Squit(target, reason) => Self::squit(&server, &target, &reason),
+            ControlCommand::Kill(nickname) => Self::kill(&server, &nickname),
+            ControlCommand::Links => Self::links(&server),
+            ControlCommand::Users => Self::users(&server),
+            ControlCommand::Reload => {
+                // `changed <setting>`
+                let changes = server.state.rehash()?;
+                Ok(changes.iter().map(|c| record(&["changed", c])).collect())
+            }
+            ControlCommand::Dump => Self::dump(&server),
+            ControlCommand::Die(reason) => Self::stop(&server, ShutdownRequest::die(&reason)),
+            ControlCommand::Restart(reason) => {
+                Self::stop(&server, ShutdownRequest::restart(&reason))
+            }
+        }
+    }
+
+    // Splits the server from the network. When it is a neighbour,
+    // it is notified and its connection is closed.
+    fn squit(server: &MainServer, target: &str, reason: &str) -> Result<Vec<String>, ErrorServer> {
+        let servername = server.get_servername()?;
+        if target == servername {
+            return Err(ErrorServer::UnexpectedCommand);
+        }
+        let neighbour = match server.spanning_tree.lock() {
+            Ok(st) => st
+                .search(target.to_string())
+                .and_then(|s| s.get_connection()),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let msg = Message::from_str(&format!(":{servername} SQUIT {target} :{reason}"))?;
+        let nick_sender = server.get_nick_repository_channels();
+        let replies = SquitMsg::new(&msg)?.response(
+            &nick_sender,
+            &server.get_channels_repository_channels(),
+            &server.spanning_tree,
+        )?;
+        if replies.iter().any(|r| r.code() == Code::ErrNosuchserver) {
+            return Err(ErrorServer::UnreachableClient);
+        }
+        inform_all_servers(&server.spanning_tree, &nick_sender, &servername, msg)?;
+        if let Some(mut connection) = neighbour {
+            let squit = format!(":{servername} SQUIT {servername} :{reason}");
+            let _ = write_message_to(&squit, &mut connection);
+            let _ = connection.shutdown();
+        }
+        Ok(vec![])
+    }
+
+    fn kill(server: &MainServer, nickname: &str) -> Result<Vec<String>, ErrorServer> {
+        let msg = Message::from_str(&format!("KILL {nickname}"))?;
+        let replies = Kill::new(msg, true)?
+            .response(server.get_nick_repository_channels(), server.get_servers())?;
+        match replies.iter().any(|r| r.code() == Code::ErrNoSuchNickname) {
+            true => Err(ErrorServer::UnreachableClient),
+            false => Ok(vec![]),
+        }
+    }
+
+    fn stop(server: &MainServer, request: ShutdownRequest) -> Result<Vec<String>, ErrorServer> {
+        match server.state.shutdown.request(request)? {
+            true => Ok(vec![]),
+            false => Err(ErrorServer::ServerClosed),
+        }
+    }
+
+    // `link <source> <destination> <hopcount> <tree|standby>`
+    fn links(server: &MainServer) -> Result<Vec<String>, ErrorServer> {
+        match server.spanning_tree.lock() {
+            Ok(st) => {
+                let tree = st.get_tree_edges();
+                Ok(st
+                    .get_edges()
+                    .iter()
+                    .map(|e| {
+                        let source = e.source.get_element().servername;
+                        let destination = e.destination.get_element().servername;
+                        let state = match tree.iter().any(|t| t.connects(&source, &destination)) {
+                            true => "tree",
+                            false => "standby",
+                        };
+                        record(&["link", &source, &destination, &e.cost.to_string(), state])
+                    })
+                    .collect())
+            }
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
+    }
+
+    // `user <nickname> <username> <hostname> <server> <oper>`
+    fn users(server: &MainServer) -> Result<Vec<String>, ErrorServer> {
+        Ok(server
+            .get_nick_repository_channels()
+            .find_all()?
+            .iter()
+            .map(|c| {
+                record(&[
+                    "user",
+                    &c.get_nickname(),
+                    c.get_username().unwrap_or_default(),
+                    c.get_hostname().unwrap_or_default(),
+                    c.get_servername().unwrap_or_default(),
+                    if c.is_op_connection() { "1" } else { "0" },
+                ])
+            })
+            .collect())
+    }
+
+    // `channel <name> <members> <flags>`
+    fn channels(server: &MainServer) -> Result<Vec<String>, ErrorServer> {
+        Ok(server
+            .get_channels_repository_channels()
+            .find_all()?
+            .iter()
+            .map(|c| {
+                record(&[
+                    "channel",
+                    &c.get_name(),
+                    &c.member_amount().to_string(),
+                    &c.return_channel_flags_str(),
+                ])
+            })
+            .collect())
+    }
+
+    // `ban <kind> <mask> <remaining seconds> <setter> <reason>`
+    fn bans(server: &MainServer) -> Result<Vec<String>, ErrorServer> {
+        let now = unix_timestamp();
+        let bans = match server.state.bans.lock() {
+            Ok(list) => [BanKind::K, BanKind::G, BanKind::Z]
+                .into_iter()
+                .flat_map(|kind| list.list(kind, now))
+                .collect::<Vec<_>>(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        Ok(bans
+            .iter()
+            .map(|ban| {
+                record(&[
+                    "ban",
+                    &ban.kind.to_string(),
+                    &ban.mask,
+                    &ban.remaining(now).to_string(),
+                    &ban.setter,
+                    &ban.reason,
+                ])
+            })
+            .collect())
+    }
+
+    // `server <name> <local connections>` followed by every other record.
+    fn dump(server: &MainServer) -> Result<Vec<String>, ErrorServer> {
+        let connections = match server.state.admission.lock() {
+            Ok(admission) => admission.total(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let mut records = vec![record(&[
+            "server",
+            &server.get_servername()?,
+            &connections.to_string(),
+        ])];
+        records.append(&mut Self::links(server)?);
+        records.append(&mut Self::users(server)?);
+        records.append(&mut Self::channels(server)?);
+        records.append(&mut Self::bans(server)?);
+        Ok(records)
+    }
+}
+
+fn record(fields: &[&str]) -> String {
+    fields.join(FIELD_SEPARATOR)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, os::unix::fs::PermissionsExt, sync::Arc};
+
+    use crate::{
+        config::ServerConfig,
+        server::{shutdown::ShutdownKind, MainServer},
+    };
+
+    use super::ControlSocket;
+
+    fn server() -> Arc<MainServer> {
+        Arc::new(MainServer::new("server".to_string(), &ServerConfig::default()).unwrap())
+    }
+
+    #[test]
+    fn socket_is_private_and_removed() {
+        let path = env::temp_dir().join(format!("irc_control_{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        let server = server();
+        ControlSocket::new(&server).run(path).unwrap();
+        let mode = fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        ControlSocket::remove(path).unwrap();
+        assert!(fs::metadata(path).is_err());
+    }
+
+    #[test]
+    fn other_file_at_the_path_is_kept() {
+        let path = env::temp_dir().join(format!("irc_control_{}.conf", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "keep me").unwrap();
+        let server = server();
+        assert!(ControlSocket::new(&server).run(path).is_err());
+        assert_eq!(fs::read_to_string(path).unwrap(), "keep me");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn dump_starts_with_the_server_record() {
+        let server = server();
+        let control = ControlSocket::new(&server);
+        assert_eq!(control.execute("dump"), "server\tserver\t0\nOK\n");
+    }
+
+    #[test]
+    fn unknown_command_is_err() {
+        let server = server();
+        let control = ControlSocket::new(&server);
+        assert_eq!(control.execute("fly"), "ERR UNKNOWNCOMMAND\n");
+    }
+
+    #[test]
+    fn squit_of_unknown_server_is_err() {
+        let server = server();
+        let control = ControlSocket::new(&server);
+        assert_eq!(control.execute("squit nowhere"), "ERR UNREACHABLECLIENT\n");
+    }
+
+    #[test]
+    fn restart_is_requested_once() {
+        let server = server();
+        let control = ControlSocket::new(&server);
+        assert_eq!(control.execute("restart"), "OK\n");
+        assert_eq!(control.execute("die"), "ERR SERVERCLOSED\n");
+        assert_eq!(server.state.shutdown.wait().unwrap().kind, ShutdownKind::Restart);
+    }
+
+    #[test]
+    fn stopped_server_is_err() {
+        let control = ControlSocket::new(&server());
+        assert_eq!(control.execute("links"), "ERR SERVERCLOSED\n");
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    command::ping::build_ping,
+    repository::{connection::Connection, repository_channel::client_channel::ClientChannel},
+    server_comunication::{link_health::notify_lag_change, spanning_tree::SpanningTree},
+    utils::write_message_to,
+};
+
+use super::state::ServerState;
+
+// time between two checks of the links
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+///
+/// Starts a thread that sends PINGs through the links with the
+/// neighbour servers, so their lag is known. The operators are
+/// told when a link lags more than the threshold, and when it
+/// recovers. The thread ends when the server shuts down.
+///
+pub fn handle_link_monitor(
+    nick_sender: ClientChannel<String, Connection>,
+    spanning_tree: Arc<Mutex<SpanningTree>>,
+    state: ServerState,
+) {
+    thread::spawn(move || {
+        let mut pings: u64 = 0;
+        while !state.shutdown.is_requested() {
+            let (servername, links) = match spanning_tree.lock() {
+                Ok(st) => (st.get_root_name(), st.get_links()),
+                Err(_) => return,
+            };
+            let now = Instant::now();
+            for link in links {
+                let mut connection = match link.get_connection() {
+                    Some(connection) => connection,
+                    None => continue,
+                };
+                let health = connection.health();
+                let (change, ping_due) = match health.lock() {
+                    Ok(mut health) => (health.waiting(now), health.ping_due(now)),
+                    Err(_) => return,
+                };
+                if let Some(change) = change {
+                    let _ = notify_lag_change(&nick_sender, &servername, &link.servername, change);
+                }
+                if !ping_due {
+                    continue;
+                }
+                pings += 1;
+                let token = pings.to_string();
+                if write_message_to(&build_ping(&servername, &token), &mut connection).is_ok() {
+                    if let Ok(mut health) = health.lock() {
+                        health.ping_sent(&token, now);
+                    }
+                }
+            }
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
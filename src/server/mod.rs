@@ -1,22 +1,42 @@
+pub mod admission;
+pub mod autoconnect;
+pub mod control;
+pub mod link_monitor;
+pub mod nick_enforcer;
+pub mod shutdown;
+pub mod signals;
+pub mod state;
+
 use crate::channel::Channel;
 use crate::command::traits::Runnable;
 use crate::command::Command;
+use crate::config::ServerConfig;
 use crate::database::RepositoryHandler;
 use crate::error::error_server::ErrorServer;
 use crate::parser::message::Message;
 use crate::repository::connection::Connection;
 use crate::repository::repository_channel::client_channel::ClientChannel;
+use crate::repository::traits::operations::Operations;
 use crate::server_comunication::server::Server;
 use crate::server_comunication::spanning_tree::node::Node;
 use crate::server_comunication::spanning_tree::SpanningTree;
 use crate::server_comunication::ServerComunicationHandler;
 use crate::user::user_handler::UserHandler;
-use crate::utils::{read_message_from, write_message_to};
-use std::net::{SocketAddr, TcpListener, TcpStream};
+use crate::utils::{read_message_from, unix_timestamp, write_message_to};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 
+use self::admission::AdmissionTicket;
+use self::autoconnect::handle_autoconnect;
+use self::control::ControlSocket;
+use self::link_monitor::handle_link_monitor;
+use self::nick_enforcer::handle_nick_enforcer;
+use self::shutdown::ShutdownRequest;
+use self::signals::handle_signals;
+use self::state::ServerState;
+
 /// Struct that encapsulates the information of a server needs to handle, in order
 /// to be part of a irc connections. Is in charge to stores information about
 /// [`Connection`] from users, servers with [`SpanningTree`] and [`Channel`] that
@@ -25,6 +45,7 @@ pub struct MainServer {
     nicknames: RepositoryHandler<String, Connection>,
     channels: RepositoryHandler<String, Channel>,
     spanning_tree: Arc<Mutex<SpanningTree>>,
+    state: ServerState,
 }
 
 impl MainServer {
@@ -32,12 +53,14 @@ impl MainServer {
     ///
     /// # Arguments
     /// * `server_name` - Name of the server
+    /// * `config` - Configuration of the server, used for the connection limits
+    ///   and the file where the bans are persisted.
     ///
     /// # Returns
     ///  If the [`RepositoryHandler`] entities, in charge of the communication with
     /// persistion entities, are created succesfully, returns the MainServer entity
     /// with a new [`SpanningTree`] with his own as a root.
-    pub fn new(server_name: String) -> Result<Self, ErrorServer> {
+    pub fn new(server_name: String, config: &ServerConfig) -> Result<Self, ErrorServer> {
         // create spanning tree with the server
         let root = Node::new(Server::new(server_name, None));
         let st = SpanningTree::new(root, vec![]);
@@ -47,6 +70,7 @@ impl MainServer {
             nicknames: RepositoryHandler::new()?,
             channels: RepositoryHandler::new()?,
             spanning_tree,
+            state: ServerState::new(config.clone())?,
         })
     }
 
@@ -57,6 +81,7 @@ impl MainServer {
             self.get_servers(),
             self.get_nick_repository_channels(),
             self.get_channels_repository_channels(),
+            self.state.clone(),
         ))
     }
 
@@ -66,6 +91,7 @@ impl MainServer {
             self.get_nick_repository_channels(),
             self.get_channels_repository_channels(),
             self.get_servers(),
+            self.state.clone(),
         )
     }
 
@@ -92,11 +118,83 @@ impl MainServer {
         self.channels.get_channels()
     }
 
+    //Notifies the users of this server and the neighbour servers that the server
+    //is stopping, and closes their connections.
+    fn close_connections(&self, request: &ShutdownRequest) -> Result<(), ErrorServer> {
+        let servername = self.get_servername()?;
+        let servers = match self.spanning_tree.lock() {
+            Ok(st) => st.get_links(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let squit = format!(":{servername} SQUIT {servername} :{}", request.reason);
+        for server in servers {
+            if let Some(mut connection) = server.get_connection() {
+                // the neighbour may already be gone
+                let _ = write_message_to(&squit, &mut connection);
+                let _ = connection.shutdown();
+            }
+        }
+        for connection in self.get_nick_repository_channels().find_all()? {
+            if !connection.is_on_server(&servername) {
+                continue;
+            }
+            if let Some(mut stream) = connection.see_if_clonable() {
+                let nickname = connection.get_nickname();
+                let notice = format!(
+                    ":{servername} NOTICE {nickname} :*** Server shutting down ({})",
+                    request.reason
+                );
+                let error = format!("ERROR :Closing Link: {nickname} ({})", request.reason);
+                let _ = write_message_to(&notice, &mut stream);
+                let _ = write_message_to(&error, &mut stream);
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+        Ok(())
+    }
+
+    //Stops the repositories, waiting for their threads to finish.
+    fn stop(self) -> Result<(), ErrorServer> {
+        self.nicknames.stop()?;
+        self.channels.stop()
+    }
+
+    //Checks the Z-lines and the connection limits before handling a new connection.
+    //A refused connection is notified with an ERROR message and closed, as far
+    //as it can be: the peer may already be gone.
+    fn accept_connection(
+        &self,
+        mut stream: TcpStream,
+        addr: SocketAddr,
+    ) -> Result<(), ErrorServer> {
+        let zline = match self.state.bans.lock() {
+            Ok(bans) => bans.find_ip_ban(addr.ip(), unix_timestamp()),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        if let Some(ban) = zline {
+            let _ = write_message_to(&ban.closing_message(&addr.ip().to_string()), &mut stream);
+            let _ = stream.shutdown(Shutdown::Both);
+            return Ok(());
+        }
+        match AdmissionTicket::request(&self.state.admission, addr.ip()) {
+            Ok(ticket) => {
+                self.handle_incoming_connection(stream, addr, ticket)?;
+            }
+            Err(reason) => {
+                let error = format!("ERROR :Closing Link: {} ({reason})", addr.ip());
+                let _ = write_message_to(&error, &mut stream);
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+        Ok(())
+    }
+
     //Attempts to register a client or a server, depending on the message received by the main server.
     fn handle_incoming_connection(
         &self,
         mut stream: TcpStream,
         addr: SocketAddr,
+        ticket: AdmissionTicket,
     ) -> Result<JoinHandle<Result<(), ErrorServer>>, ErrorServer> {
         let servername = self.get_servername()?;
         let user_handler = self.build_user_handler();
@@ -104,6 +202,8 @@ impl MainServer {
         //REGISTRATION
         let th = thread::Builder::new().name(addr.to_string()).spawn(
             move || -> Result<(), ErrorServer> {
+                // the connection is released once the thread ends.
+                let _ticket = ticket;
                 let mut element = Self::registration(
                     user_handler,
                     server_communication,
@@ -130,7 +230,7 @@ impl MainServer {
             match Message::from_str(&msg) {
                 Ok(message) => {
                     return match message.command() {
-                        Command::User | Command::Nick | Command::Pass => {
+                        Command::User | Command::Nick | Command::Pass | Command::Cap => {
                             //println!("Registro de un Usuario: user_registration( msg, servername, socket )");
                             user_handler.user_registration(message, servername, socket)
                         }
@@ -151,22 +251,92 @@ impl MainServer {
 /// the irc network and opens for new connections to handle.
 /// Also allows CLI communication in orden to commands server-to-server connections.
 pub fn init_server(ip: String, name: String) -> Result<(), ErrorServer> {
+    init_server_with_config(ip, name, ServerConfig::default())?;
+    Ok(())
+}
+
+/// Same as [`init_server`], applying the given [`ServerConfig`].
+///
+/// # Returns
+/// Runs until a DIE or RESTART command, or a SIGTERM or SIGINT signal, is received.
+/// Then closes the connections, stops the repositories and returns the
+/// [`ShutdownRequest`] so the caller can decide if the server is restarted.
+pub fn init_server_with_config(
+    ip: String,
+    name: String,
+    config: ServerConfig,
+) -> Result<ShutdownRequest, ErrorServer> {
     // Create the connection of the server, ready to accept connections.
-    let server = MainServer::new(name.clone())?;
+    let server = Arc::new(MainServer::new(name, &config)?);
+    let shutdown = server.state.shutdown.clone();
+    handle_signals(server.state.clone())?;
 
     //Starts command line communication
-    let cli_thread = server.build_server_comunication()?.run_cli();
+    let _cli_thread = server.build_server_comunication()?.run_cli();
+    if let Some(path) = &config.control_socket {
+        ControlSocket::new(&server).run(path)?;
+    }
 
     let listener = TcpListener::bind(ip.as_str())?;
-    let _msg_thread = thread::spawn(move || -> Result<(), ErrorServer> {
+    let local_addr = listener.local_addr()?;
+    handle_autoconnect(
+        server.build_server_comunication()?,
+        server.get_servers(),
+        server.state.clone(),
+    );
+    handle_link_monitor(
+        server.get_nick_repository_channels(),
+        server.get_servers(),
+        server.state.clone(),
+    );
+    handle_nick_enforcer(
+        server.get_nick_repository_channels(),
+        server.get_channels_repository_channels(),
+        server.get_servers(),
+        server.state.clone(),
+    );
+    let accepting = server.clone();
+    let stop = shutdown.clone();
+    let msg_thread = thread::spawn(move || -> Result<(), ErrorServer> {
         loop {
             if let Ok((socket, addr)) = listener.accept() {
-                server.handle_incoming_connection(socket, addr)?;
+                if stop.is_requested() {
+                    return Ok(());
+                }
+                // a failed connection must not stop the server from accepting others
+                if let Err(e) = accepting.accept_connection(socket, addr) {
+                    println!("init_server(): couldn't accept {addr} ({e:?})");
+                }
             }
         }
     });
-    match cli_thread.join() {
-        Ok(r) => r,
-        Err(_) => Err(ErrorServer::PoisonedThread),
+
+    let request = shutdown.wait()?;
+    server.close_connections(&request)?;
+    if let Some(path) = &config.control_socket {
+        // a socket left behind is removed by the next run anyway
+        let _ = ControlSocket::remove(path);
+    }
+    // wakes up the thread blocked on accept()
+    let _ = TcpStream::connect(wake_up_addr(local_addr));
+    match msg_thread.join() {
+        Ok(Err(e)) => println!("init_server(): connections stopped with {e:?}"),
+        Ok(Ok(())) => {}
+        Err(_) => return Err(ErrorServer::PoisonedThread),
+    }
+    match Arc::try_unwrap(server) {
+        Ok(server) => server.stop()?,
+        Err(_) => return Err(ErrorServer::PoisonedThread),
+    }
+    Ok(request)
+}
+
+// Address to connect to the listener, that may be bound to every interface.
+fn wake_up_addr(mut addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => addr.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        IpAddr::V6(ip) if ip.is_unspecified() => addr.set_ip(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        _ => {}
     }
+    addr
 }
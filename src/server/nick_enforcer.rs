@@ -0,0 +1,54 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    channel::Channel,
+    repository::{connection::Connection, repository_channel::client_channel::ClientChannel},
+    server_comunication::spanning_tree::SpanningTree,
+    services::nickserv::enforce,
+    utils::unix_timestamp,
+};
+
+use super::state::ServerState;
+
+// time between two checks of the pending nicknames
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+///
+/// Starts a thread that changes the nickname of the users that
+/// didn't identify in time for the registered nickname they use.
+/// The thread ends when the server shuts down.
+///
+pub fn handle_nick_enforcer(
+    nick_sender: ClientChannel<String, Connection>,
+    channel_sender: ClientChannel<String, Channel>,
+    spanning_tree: Arc<Mutex<SpanningTree>>,
+    state: ServerState,
+) {
+    thread::spawn(move || {
+        while !state.shutdown.is_requested() {
+            let due = match state.enforcement.lock() {
+                Ok(mut enforcement) => enforcement.due(unix_timestamp()),
+                Err(_) => return,
+            };
+            for pending in due {
+                if let Err(e) = enforce(
+                    &pending,
+                    &nick_sender,
+                    &channel_sender,
+                    &spanning_tree,
+                    &state.accounts,
+                ) {
+                    println!(
+                        "nick enforcer: {} kept its nickname, {e:?}",
+                        pending.nickname
+                    );
+                }
+            }
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
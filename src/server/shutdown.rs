@@ -0,0 +1,126 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::error::error_server::ErrorServer;
+
+///
+/// What the server does once it finished closing its connections.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ShutdownKind {
+    Die,
+    Restart,
+}
+
+///
+/// Struct that represents the request to stop
+/// the server, with the reason shown to the users.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ShutdownRequest {
+    pub kind: ShutdownKind,
+    pub reason: String,
+}
+
+impl ShutdownRequest {
+    pub fn die(reason: &str) -> Self {
+        ShutdownRequest {
+            kind: ShutdownKind::Die,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn restart(reason: &str) -> Self {
+        ShutdownRequest {
+            kind: ShutdownKind::Restart,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+///
+/// Struct shared between the threads of the server, used to ask
+/// for a shutdown and to wait for it. Only the first request is kept.
+///
+#[derive(Clone, Default)]
+pub struct ShutdownHandle {
+    state: Arc<(Mutex<Option<ShutdownRequest>>, Condvar)>,
+}
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        ShutdownHandle::default()
+    }
+
+    ///
+    /// asks the server to stop, returns false
+    /// if a shutdown was already requested
+    ///
+    pub fn request(&self, request: ShutdownRequest) -> Result<bool, ErrorServer> {
+        let (lock, condvar) = &*self.state;
+        let mut current = match lock.lock() {
+            Ok(c) => c,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        if current.is_some() {
+            return Ok(false);
+        }
+        *current = Some(request);
+        condvar.notify_all();
+        Ok(true)
+    }
+
+    pub fn is_requested(&self) -> bool {
+        match self.state.0.lock() {
+            Ok(current) => current.is_some(),
+            Err(_) => true,
+        }
+    }
+
+    ///
+    /// blocks the thread until a
+    /// shutdown is requested
+    ///
+    pub fn wait(&self) -> Result<ShutdownRequest, ErrorServer> {
+        let (lock, condvar) = &*self.state;
+        let mut current = match lock.lock() {
+            Ok(c) => c,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        loop {
+            if let Some(request) = current.as_ref() {
+                return Ok(request.clone());
+            }
+            current = match condvar.wait(current) {
+                Ok(c) => c,
+                Err(_) => return Err(ErrorServer::LockedResource),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::{ShutdownHandle, ShutdownKind, ShutdownRequest};
+
+    #[test]
+    fn only_the_first_request_is_kept() {
+        let shutdown = ShutdownHandle::new();
+        assert!(shutdown.request(ShutdownRequest::restart("first")).unwrap());
+        assert!(!shutdown.request(ShutdownRequest::die("second")).unwrap());
+        let request = shutdown.wait().unwrap();
+        assert_eq!(request.kind, ShutdownKind::Restart);
+        assert_eq!(request.reason, "first");
+    }
+
+    #[test]
+    fn wait_blocks_until_requested() {
+        let shutdown = ShutdownHandle::new();
+        let waiter = shutdown.clone();
+        let handle = thread::spawn(move || waiter.wait().unwrap());
+        assert!(!shutdown.is_requested());
+        shutdown.request(ShutdownRequest::die("bye")).unwrap();
+        assert_eq!(handle.join().unwrap(), ShutdownRequest::die("bye"));
+    }
+}
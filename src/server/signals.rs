@@ -0,0 +1,41 @@
+use std::{process, thread};
+
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
+
+use crate::error::error_server::ErrorServer;
+
+use super::{shutdown::ShutdownRequest, state::ServerState};
+
+///
+/// Starts a thread that attends the signals sent to the server.
+/// SIGHUP reloads the configuration, SIGTERM and SIGINT request
+/// the shutdown. A second stop signal while the server is closing
+/// forces the exit.
+///
+pub fn handle_signals(state: ServerState) -> Result<(), ErrorServer> {
+    let mut signals = Signals::new([SIGHUP, SIGTERM, SIGINT])?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal == SIGHUP {
+                match state.rehash() {
+                    Ok(changes) => println!("SIGHUP: rehashed, changed {changes:?}"),
+                    Err(e) => println!("SIGHUP: configuration not reloaded, {e:?}"),
+                }
+                continue;
+            }
+            let name = if signal == SIGTERM {
+                "SIGTERM"
+            } else {
+                "SIGINT"
+            };
+            let request = ShutdownRequest::die(&format!("Received {name}"));
+            if !matches!(state.shutdown.request(request), Ok(true)) {
+                process::exit(1);
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,262 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    ban::BanList,
+    channel::history::{ChannelHistory, DEFAULT_HISTORY_LENGTH},
+    command::{invite::DEFAULT_INVITE_EXPIRY, knock::KnockLimiter},
+    config::ServerConfig,
+    error::error_server::ErrorServer,
+    server_comunication::uid::{default_server_id, UidGenerator, UidIndex},
+    services::{
+        accounts::AccountRegistry,
+        nickserv::{NickEnforcement, DEFAULT_NICK_ENFORCE_DELAY},
+        registry::ChannelRegistry,
+    },
+    utils::unix_timestamp,
+};
+
+use super::{admission::AdmissionControl, shutdown::ShutdownHandle};
+
+///
+/// State of the server shared by the threads that attend the
+/// connections: the configuration and everything built from it.
+///
+#[derive(Clone)]
+pub struct ServerState {
+    pub config: Arc<Mutex<ServerConfig>>,
+    pub admission: Arc<Mutex<AdmissionControl>>,
+    pub bans: Arc<Mutex<BanList>>,
+    pub shutdown: ShutdownHandle,
+    pub uids: Arc<Mutex<UidGenerator>>,
+    /// nicknames of the users of the network by their ids
+    pub uid_index: UidIndex,
+    pub peaks: Arc<Mutex<UserPeaks>>,
+    pub history: Arc<Mutex<ChannelHistory>>,
+    pub registry: Arc<Mutex<ChannelRegistry>>,
+    pub accounts: Arc<Mutex<AccountRegistry>>,
+    pub enforcement: Arc<Mutex<NickEnforcement>>,
+    pub knocks: Arc<Mutex<KnockLimiter>>,
+    /// unix timestamp of the start of the server
+    pub started: u64,
+}
+
+///
+/// Greatest amount of users seen at the same
+/// time in this server and in the whole network.
+///
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct UserPeaks {
+    pub local: usize,
+    pub global: usize,
+}
+
+impl ServerState {
+    pub fn new(config: ServerConfig) -> Result<Self, ErrorServer> {
+        let bans = match &config.ban_file {
+            Some(path) => BanList::load(path)?,
+            None => BanList::new(),
+        };
+        let length = config.history_length.unwrap_or(DEFAULT_HISTORY_LENGTH);
+        let history = match &config.history_file {
+            Some(path) => ChannelHistory::load(path, length)?,
+            None => ChannelHistory::new(length),
+        };
+        let registry = match &config.chanserv_file {
+            Some(path) => ChannelRegistry::load(path)?,
+            None => ChannelRegistry::new(),
+        };
+        let accounts = match &config.nickserv_file {
+            Some(path) => AccountRegistry::load(path)?,
+            None => AccountRegistry::new(),
+        };
+        Ok(ServerState {
+            admission: Arc::new(Mutex::new(AdmissionControl::new(config.limits.clone()))),
+            bans: Arc::new(Mutex::new(bans)),
+            config: Arc::new(Mutex::new(config)),
+            shutdown: ShutdownHandle::new(),
+            uids: Arc::new(Mutex::new(UidGenerator::new())),
+            uid_index: UidIndex::new(),
+            peaks: Arc::new(Mutex::new(UserPeaks::default())),
+            history: Arc::new(Mutex::new(history)),
+            registry: Arc::new(Mutex::new(registry)),
+            accounts: Arc::new(Mutex::new(accounts)),
+            enforcement: Arc::new(Mutex::new(NickEnforcement::new())),
+            knocks: Arc::new(Mutex::new(KnockLimiter::new())),
+            started: unix_timestamp(),
+        })
+    }
+
+    ///
+    /// Reads the configuration file again and applies it, returning the
+    /// settings that changed. If the new configuration is invalid the
+    /// running one is kept.
+    ///
+    pub fn rehash(&self) -> Result<Vec<String>, ErrorServer> {
+        let mut config = match self.config.lock() {
+            Ok(config) => config,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let new_config = match &config.path {
+            Some(path) => ServerConfig::load(path)?,
+            None => return Err(ErrorServer::InvalidConfig),
+        };
+        let new_bans = match &new_config.ban_file {
+            Some(path) => Some(BanList::load(path)?),
+            None => None,
+        };
+        // everything was read, from here on nothing can fail halfway
+        let mut changes = config.changes(&new_config);
+        match self.admission.lock() {
+            Ok(mut admission) => admission.set_limits(new_config.limits.clone()),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        }
+        if let Some(new_bans) = new_bans {
+            match self.bans.lock() {
+                Ok(mut bans) if *bans != new_bans => {
+                    *bans = new_bans;
+                    changes.push("bans".to_string());
+                }
+                Ok(_) => {}
+                Err(_) => return Err(ErrorServer::LockedResource),
+            }
+        }
+        *config = new_config;
+        Ok(changes)
+    }
+
+    ///
+    /// returns the id of the server, the configured
+    /// one or else the one derived from its name
+    ///
+    pub fn server_id(&self, servername: &str) -> Result<String, ErrorServer> {
+        let configured = match self.config.lock() {
+            Ok(config) => config.sid.clone(),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        match self.uids.lock() {
+            Ok(mut uids) => {
+                Ok(uids.server_id(|| configured.unwrap_or_else(|| default_server_id(servername))))
+            }
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
+    }
+
+    ///
+    /// allocates the id of a new user of this server
+    ///
+    pub fn new_uid(&self, servername: &str) -> Result<String, ErrorServer> {
+        let sid = self.server_id(servername)?;
+        match self.uids.lock() {
+            Ok(mut uids) => Ok(uids.next_uid(&sid)),
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
+    }
+
+    ///
+    /// updates the peaks with the current amount of
+    /// users and returns them
+    ///
+    pub fn record_users(&self, local: usize, global: usize) -> Result<UserPeaks, ErrorServer> {
+        match self.peaks.lock() {
+            Ok(mut peaks) => {
+                peaks.local = peaks.local.max(local);
+                peaks.global = peaks.global.max(global);
+                Ok(*peaks)
+            }
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
+    }
+
+    ///
+    /// returns the seconds an unidentified user
+    /// can keep a registered nickname
+    ///
+    pub fn nick_enforce_delay(&self) -> Result<u64, ErrorServer> {
+        match self.config.lock() {
+            Ok(config) => Ok(config
+                .nick_enforce_delay
+                .unwrap_or(DEFAULT_NICK_ENFORCE_DELAY)),
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
+    }
+
+    ///
+    /// returns the seconds an invite
+    /// to a channel can be used
+    ///
+    pub fn invite_expiry(&self) -> Result<u64, ErrorServer> {
+        match self.config.lock() {
+            Ok(config) => Ok(config.invite_expiry.unwrap_or(DEFAULT_INVITE_EXPIRY)),
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
+    }
+
+    ///
+    /// returns the path of the configuration file,
+    /// if the server was started with one
+    ///
+    pub fn config_path(&self) -> Option<String> {
+        match self.config.lock() {
+            Ok(config) => config.path.clone(),
+            Err(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process, str::FromStr};
+
+    use crate::{config::ServerConfig, error::error_server::ErrorServer};
+
+    use super::ServerState;
+
+    fn config_file(name: &str, content: &str) -> String {
+        let path = env::temp_dir().join(format!("rehash-{}-{name}.conf", process::id()));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn rehash_applies_the_new_configuration() {
+        let path = config_file("apply", "max_connections = 1");
+        let state = ServerState::new(ServerConfig::load(&path).unwrap()).unwrap();
+        fs::write(&path, "max_connections = 2\noper = admin secret").unwrap();
+
+        assert_eq!(state.rehash().unwrap(), vec!["limits", "opers"]);
+        assert!(state.config.lock().unwrap().is_oper("admin", "secret"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_configuration_is_not_applied() {
+        let path = config_file("invalid", "max_connections = 1");
+        let state = ServerState::new(ServerConfig::load(&path).unwrap()).unwrap();
+        fs::write(&path, "oper = admin secret\nmax_connections = many").unwrap();
+
+        assert_eq!(state.rehash(), Err(ErrorServer::InvalidConfig));
+        assert!(state.config.lock().unwrap().opers.is_empty());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn uids_use_the_configured_server_id() {
+        let state = ServerState::new(ServerConfig::from_str("sid = 1XY").unwrap()).unwrap();
+        assert_eq!(state.new_uid("server1").unwrap(), "1XY000000");
+        assert_eq!(state.new_uid("server1").unwrap(), "1XY000001");
+    }
+
+    #[test]
+    fn rehash_without_file_is_err() {
+        let state = ServerState::new(ServerConfig::default()).unwrap();
+        assert_eq!(state.rehash(), Err(ErrorServer::InvalidConfig));
+    }
+
+    #[test]
+    fn peaks_keep_the_greatest_amount() {
+        let state = ServerState::new(ServerConfig::default()).unwrap();
+        state.record_users(3, 10).unwrap();
+        let peaks = state.record_users(1, 12).unwrap();
+        assert_eq!((peaks.local, peaks.global), (3, 12));
+    }
+}
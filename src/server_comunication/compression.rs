@@ -0,0 +1,124 @@
+use std::io::{self, Read};
+
+use flate2::{Decompress, FlushDecompress};
+
+use crate::utils::MESSAGE_LENGHT;
+
+///
+/// Reads the messages of a compressed link. Each read from the
+/// stream is decompressed at once, so a message is available as
+/// soon as it arrives, without waiting for the next one.
+///
+pub struct LinkDecoder<R: Read> {
+    reader: R,
+    decompress: Decompress,
+    pending: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> LinkDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        LinkDecoder {
+            reader,
+            decompress: Decompress::new(true),
+            pending: vec![],
+            position: 0,
+        }
+    }
+
+    // Decompresses the input until every byte was consumed
+    // and no output is left inside the decompressor.
+    fn decompress(&mut self, mut input: &[u8]) -> io::Result<()> {
+        self.pending.drain(..self.position);
+        self.position = 0;
+        loop {
+            self.pending.reserve(MESSAGE_LENGHT);
+            let (total_in, total_out) = (self.decompress.total_in(), self.decompress.total_out());
+            self.decompress
+                .decompress_vec(input, &mut self.pending, FlushDecompress::Sync)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let consumed = (self.decompress.total_in() - total_in) as usize;
+            let produced = self.decompress.total_out() - total_out;
+            input = &input[consumed..];
+            let spare = self.pending.len() < self.pending.capacity();
+            if input.is_empty() && (spare || produced == 0) {
+                return Ok(());
+            }
+            // data after the end of the compressed stream
+            if consumed == 0 && produced == 0 {
+                return Err(io::Error::from(io::ErrorKind::InvalidData));
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for LinkDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut input = [0; MESSAGE_LENGHT];
+        while self.position == self.pending.len() {
+            let read = self.reader.read(&mut input)?;
+            if read == 0 {
+                return Ok(0);
+            }
+            self.decompress(&input[..read])?;
+        }
+        let amount = buf.len().min(self.pending.len() - self.position);
+        buf[..amount].copy_from_slice(&self.pending[self.position..self.position + amount]);
+        self.position += amount;
+        Ok(amount)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use crate::{
+        error::error_server::ErrorServer,
+        utils::{read_frame_from, write_message_to},
+    };
+
+    use super::LinkDecoder;
+
+    // Reader that hands the data in pieces of a few bytes.
+    struct Chunked(Vec<u8>);
+
+    impl Read for Chunked {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let amount = buf.len().min(self.0.len()).min(7);
+            buf[..amount].copy_from_slice(&self.0[..amount]);
+            self.0.drain(..amount);
+            Ok(amount)
+        }
+    }
+
+    fn compress(messages: &[&str]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        for message in messages {
+            write_message_to(message, &mut encoder).unwrap();
+            encoder.flush().unwrap();
+        }
+        encoder.get_ref().clone()
+    }
+
+    #[test]
+    fn messages_are_read_as_they_arrive() {
+        let data = compress(&["NICK bob", "NICK alice"]);
+        let mut decoder = LinkDecoder::new(Chunked(data));
+
+        assert_eq!(read_frame_from(&mut decoder), Ok("NICK bob".to_string()));
+        assert_eq!(read_frame_from(&mut decoder), Ok("NICK alice".to_string()));
+        assert_eq!(
+            read_frame_from(&mut decoder),
+            Err(ErrorServer::UnreachableClient)
+        );
+    }
+
+    #[test]
+    fn corrupted_data_is_err() {
+        let mut decoder = LinkDecoder::new(Chunked(vec![0xff; 64]));
+        assert!(read_frame_from(&mut decoder).is_err());
+    }
+}
@@ -1,27 +1,29 @@
 use std::sync::MutexGuard;
 
 use super::spanning_tree::SpanningTree;
+use super::uid::to_uids;
 use crate::repository::traits::operations::Operations;
 use crate::server_comunication::ClientChannel;
 use crate::server_comunication::Connection;
 use crate::{error::error_server::ErrorServer, parser::message::Message, utils::write_message_to};
+use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+///
+/// writes the message to every server linked to this one,
+/// except the one toward the server it came from
+///
 pub fn send_to_all_servers(
     st: &MutexGuard<SpanningTree>,
     message: Message,
     origin_server: &str,
 ) -> Result<(), ErrorServer> {
-    let mut servers = st.get_servers();
-    //println!("----------------------------------------------");
-    //println!("SERVER ORIGEN {:?}", origin_server);
     let informer = match st.look_for_nearest_connection(origin_server.to_owned()) {
         Some(s) => s,
         None => return Ok(()), //porque no conozco el origen.
     };
-    //println!("SERVER PASAMSG {:?}", informer.servername);
-    for server in &mut servers {
+    for server in &mut st.get_neighbours() {
         if &informer != server {
             println!(
                 "{:?} --> {:?} : {:?}",
@@ -29,26 +31,41 @@ pub fn send_to_all_servers(
                 server.servername,
                 message.to_string()
             );
-            if write_message_to(&message, server).is_err() {
-                // No puede enviar informción porque no es vecino o el destinatario es él mismo
-                //println!(
-                  //" (!) - Server doesn't have a direct connection with server: {}",
-                    //server.servername
-                //)
-            };
+            // a broken link is split when its reader sees it dropped
+            let _ = write_message_to(&message, server);
         }
     }
-    //println!("----------------------------------------------");
     Ok(())
 }
 
-// GENERA LOOPS SI EL SERVIDOR NO SE CAE.
+///
+/// writes the message only toward the given servers, following
+/// the spanning tree: each link on the way gets it once and
+/// it never goes back toward the server it came from
+///
+pub fn send_to_servers(
+    st: &MutexGuard<SpanningTree>,
+    message: &dyn ToString,
+    servernames: &[String],
+    origin_server: &str,
+) -> Result<(), ErrorServer> {
+    for mut server in st.next_hops(servernames, origin_server) {
+        let _ = write_message_to(message, &mut server);
+    }
+    Ok(())
+}
+
+///
+/// writes the message to every server, except the one toward
+/// the server of the user, with the users named by their ids
+///
 pub fn inform_all_servers(
     spanning_tree: &Arc<Mutex<SpanningTree>>,
     nick_sender: &ClientChannel<String, Connection>,
     nickname: &str,
     msg: Message,
 ) -> Result<(), ErrorServer> {
+    let msg = Message::from_str(&to_uids(&msg.to_string(), nick_sender))?;
     match spanning_tree.lock() {
         Ok(st) => {
             if let Some(connection) = nick_sender.search(nickname.to_string())? {
@@ -67,6 +84,7 @@ pub fn inform_all_server_an_user_command(
     nickname: &str,
     msg: Message,
 ) -> Result<(), ErrorServer> {
+    let msg = Message::from_str(&to_uids(&msg.to_string(), nick_sender))?;
     match spanning_tree.lock() {
         Ok(st) => {
             if let Some(connection) = nick_sender.search(nickname.to_string())? {
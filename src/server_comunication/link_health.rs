@@ -0,0 +1,274 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    error::error_server::ErrorServer,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    utils::write_message_to,
+};
+
+/// time between two PINGs sent through a link
+pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// lag from which the operators are warned about a link
+pub const LAG_THRESHOLD: Duration = Duration::from_secs(10);
+
+///
+/// Health of the link with a neighbour server: the messages that
+/// went through it both ways, the bytes being written to it, that
+/// make its send queue, and the lag measured with PINGs.
+///
+#[derive(Debug)]
+pub struct LinkHealth {
+    opened: Instant,
+    sendq: usize,
+    sent: Traffic,
+    received: Traffic,
+    // token of the PING waiting for its PONG, and when it was sent
+    ping: Option<(String, Instant)>,
+    last_ping: Option<Instant>,
+    lag: Option<Duration>,
+    lagged: bool,
+}
+
+///
+/// Amount of messages, and their bytes,
+/// that went through a link in one way.
+///
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Traffic {
+    pub messages: usize,
+    pub bytes: usize,
+}
+
+///
+/// Change of the lag of a link that
+/// the operators have to be told about.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LagChange {
+    Lagged(Duration),
+    Recovered(Duration),
+}
+
+impl LinkHealth {
+    pub fn new(now: Instant) -> Self {
+        LinkHealth {
+            opened: now,
+            sendq: 0,
+            sent: Traffic::default(),
+            received: Traffic::default(),
+            ping: None,
+            last_ping: None,
+            lag: None,
+            lagged: false,
+        }
+    }
+
+    ///
+    /// records a message that starts being written
+    ///
+    pub fn queue(&mut self, bytes: usize) {
+        self.sendq += bytes;
+    }
+
+    ///
+    /// records the end of the writing of a message,
+    /// counting it if it was sent
+    ///
+    pub fn dequeue(&mut self, bytes: usize, sent: bool) {
+        self.sendq = self.sendq.saturating_sub(bytes);
+        if sent {
+            self.sent.messages += 1;
+            self.sent.bytes += bytes;
+        }
+    }
+
+    ///
+    /// records a message read from the link
+    ///
+    pub fn receive(&mut self, bytes: usize) {
+        self.received.messages += 1;
+        self.received.bytes += bytes;
+    }
+
+    ///
+    /// returns true if a new PING has to be sent. There is only
+    /// one PING at a time waiting for its PONG.
+    ///
+    pub fn ping_due(&self, now: Instant) -> bool {
+        self.ping.is_none()
+            && self
+                .last_ping
+                .is_none_or(|last| now.duration_since(last) >= PING_INTERVAL)
+    }
+
+    ///
+    /// records the PING sent with the given token
+    ///
+    pub fn ping_sent(&mut self, token: &str, now: Instant) {
+        self.ping = Some((token.to_string(), now));
+        self.last_ping = Some(now);
+    }
+
+    ///
+    /// takes the time the PONG took as the lag of the link.
+    /// A PONG that doesn't answer the last PING is ignored.
+    ///
+    pub fn pong(&mut self, token: &str, now: Instant) -> Option<LagChange> {
+        match &self.ping {
+            Some((sent_token, sent)) if sent_token == token => {
+                let lag = now.duration_since(*sent);
+                self.ping = None;
+                self.set_lag(lag)
+            }
+            _ => None,
+        }
+    }
+
+    ///
+    /// while a PING waits for its PONG, the lag
+    /// is at least the time it has been waiting
+    ///
+    pub fn waiting(&mut self, now: Instant) -> Option<LagChange> {
+        let waited = match &self.ping {
+            Some((_, sent)) => now.duration_since(*sent),
+            None => return None,
+        };
+        match self.lag {
+            Some(lag) if lag >= waited => None,
+            _ => self.set_lag(waited),
+        }
+    }
+
+    pub fn sendq(&self) -> usize {
+        self.sendq
+    }
+
+    pub fn sent(&self) -> Traffic {
+        self.sent
+    }
+
+    pub fn received(&self) -> Traffic {
+        self.received
+    }
+
+    pub fn lag(&self) -> Option<Duration> {
+        self.lag
+    }
+
+    pub fn open_for(&self, now: Instant) -> Duration {
+        now.duration_since(self.opened)
+    }
+
+    // Sets the lag, telling if it crossed the threshold.
+    fn set_lag(&mut self, lag: Duration) -> Option<LagChange> {
+        self.lag = Some(lag);
+        match (lag >= LAG_THRESHOLD, self.lagged) {
+            (true, false) => {
+                self.lagged = true;
+                Some(LagChange::Lagged(lag))
+            }
+            (false, true) => {
+                self.lagged = false;
+                Some(LagChange::Recovered(lag))
+            }
+            _ => None,
+        }
+    }
+}
+
+///
+/// Sends a server notice about the change of the
+/// lag of the link to the operators of this server.
+///
+pub fn notify_lag_change(
+    nick_sender: &ClientChannel<String, Connection>,
+    servername: &str,
+    neighbour: &str,
+    change: LagChange,
+) -> Result<(), ErrorServer> {
+    let text = match change {
+        LagChange::Lagged(lag) => format!("Link with {neighbour} is lagged ({}s)", lag.as_secs()),
+        LagChange::Recovered(lag) => {
+            format!("Link with {neighbour} recovered ({}ms)", lag.as_millis())
+        }
+    };
+    println!("{text}");
+    for mut oper in nick_sender
+        .find_all()?
+        .into_iter()
+        .filter(|c| c.is_local() && c.is_op_connection())
+    {
+        let notice = format!(
+            ":{servername} NOTICE {} :*** Notice -- {text}",
+            oper.get_nickname()
+        );
+        let _ = write_message_to(&notice, &mut oper);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{LagChange, LinkHealth, Traffic, LAG_THRESHOLD, PING_INTERVAL};
+
+    #[test]
+    fn written_messages_leave_the_send_queue() {
+        let mut health = LinkHealth::new(Instant::now());
+        health.queue(512);
+        health.queue(512);
+        assert_eq!(health.sendq(), 1024);
+
+        health.dequeue(512, true);
+        health.dequeue(512, false);
+        assert_eq!(health.sendq(), 0);
+        assert_eq!(
+            health.sent(),
+            Traffic {
+                messages: 1,
+                bytes: 512
+            }
+        );
+    }
+
+    #[test]
+    fn pong_sets_the_lag() {
+        let now = Instant::now();
+        let mut health = LinkHealth::new(now);
+        assert!(health.ping_due(now));
+        health.ping_sent("1", now);
+        assert!(!health.ping_due(now + PING_INTERVAL));
+
+        assert_eq!(health.pong("0", now + Duration::from_millis(40)), None);
+        assert_eq!(health.lag(), None);
+        assert_eq!(health.pong("1", now + Duration::from_millis(40)), None);
+        assert_eq!(health.lag(), Some(Duration::from_millis(40)));
+        assert!(!health.ping_due(now + Duration::from_secs(1)));
+        assert!(health.ping_due(now + PING_INTERVAL));
+    }
+
+    #[test]
+    fn unanswered_ping_lags_the_link() {
+        let now = Instant::now();
+        let mut health = LinkHealth::new(now);
+        health.ping_sent("1", now);
+
+        assert_eq!(health.waiting(now + Duration::from_secs(1)), None);
+        let late = now + LAG_THRESHOLD;
+        assert_eq!(health.waiting(late), Some(LagChange::Lagged(LAG_THRESHOLD)));
+        assert_eq!(health.waiting(late + Duration::from_secs(1)), None);
+
+        let pong = health.pong("1", late + Duration::from_secs(2));
+        assert_eq!(pong, None);
+        health.ping_sent("2", late);
+        let recovered = health.pong("2", late + Duration::from_millis(5));
+        assert_eq!(
+            recovered,
+            Some(LagChange::Recovered(Duration::from_millis(5)))
+        );
+    }
+}
@@ -1,43 +1,70 @@
+pub mod compression;
 pub mod info_sender;
+pub mod link_health;
+pub mod netsplit;
 pub mod server;
 pub mod server_connection;
 pub mod spanning_tree;
+pub mod uid;
 
 use crate::{
     channel::Channel,
     command::{
-        kill::Kill, nick_command::NickCommand, notice_msg::NoticeMsg, server_msg::ServerMsg,
-        squit::SquitMsg, traits::Runnable, user_msg::UserMsg, Command,
+        batch::{wrap_in_batch, BatchMsg, NETBURST},
+        kill::Kill,
+        lusers::record_user_peaks,
+        nick_command::NickCommand,
+        notice_msg::NoticeMsg,
+        ping::PingMsg,
+        reply_msg::ReplyMsg,
+        server_ban::{build_gline_msgs, ServerBanMsg},
+        server_msg::ServerMsg,
+        sjoin::SjoinMsg,
+        squit::SquitMsg,
+        topic_burst::TopicBurstMsg,
+        traits::Runnable,
+        unlink::UnlinkMsg,
+        user_msg::UserMsg,
+        Command,
     },
     dcc::dcc_handler::is_dcc_chat,
     error::{error_msg::ErrorMsg, error_server::ErrorServer},
-    parser::{add_prefix, dcc_message::DccMessage, message::Message},
+    parser::{add_prefix, convert_into_command_prefix, dcc_message::DccMessage, message::Message},
     reply::{code::Code, Reply},
     repository::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
+    server::state::ServerState,
+    services::nickserv::account_msg,
     user::{user_handler::UserHandler, User},
-    utils::{read_message_from, write_message_to, write_messages_to},
+    utils::{read_frame_from, unix_timestamp, write_message_to, write_messages_to, MESSAGE_LENGHT},
 };
 
 use self::{
+    compression::LinkDecoder,
     info_sender::{inform_all_servers, send_to_all_servers},
+    link_health::notify_lag_change,
+    netsplit::quit_lost_users,
     server::Server,
     server_connection::ConnectionServer,
     spanning_tree::SpanningTree,
+    uid::{to_nicknames, to_uids},
 };
 
 use std::{
-    io::{stdin, BufRead, BufReader, Write},
+    io::{sink, stdin, BufRead, BufReader, Read, Write},
     net::{Shutdown, TcpStream},
     str::FromStr,
     sync::{Arc, Mutex},
     thread::{self, JoinHandle},
-    time::Duration,
+    time::Instant,
 };
 
 const SERVER_IP_PORT_POSITION: usize = 0;
+// the handshake offers compression with this flag, after the hopcount
+const COMPRESSION_FLAG: &str = "+z";
+const COMPRESSION_POSITION: usize = 2;
 
 ///
 ///  Struct that is in charge to handle the communication
@@ -52,6 +79,13 @@ pub struct ServerComunicationHandler {
     spanning_tree: Arc<Mutex<SpanningTree>>,
     nick_sender: ClientChannel<String, Connection>,
     channel_sender: ClientChannel<String, Channel>,
+    state: ServerState,
+    // server at the other side of the link attended by this handler
+    neighbour: Option<String>,
+    // the messages of the link arrive compressed
+    compressed: bool,
+    // connection with the neighbour, to answer its PINGs
+    link: Option<ConnectionServer>,
 }
 
 impl ServerComunicationHandler {
@@ -63,12 +97,17 @@ impl ServerComunicationHandler {
         servers: Arc<Mutex<SpanningTree>>,
         nick_sender: ClientChannel<String, Connection>,
         channel_sender: ClientChannel<String, Channel>,
+        state: ServerState,
     ) -> Self {
         ServerComunicationHandler {
             servername,
             spanning_tree: servers,
             nick_sender,
             channel_sender,
+            state,
+            neighbour: None,
+            compressed: false,
+            link: None,
         }
     }
 
@@ -97,8 +136,9 @@ impl ServerComunicationHandler {
         msg: Message,
         socket: &mut TcpStream,
     ) -> Result<Box<dyn Runnable>, ErrorServer> {
-        self.send_handshake(socket)?;
-        self.handle_handshake(msg, socket)
+        let compression = self.link_compression()? && offers_compression(&msg);
+        self.send_handshake(socket, compression)?;
+        self.handle_handshake(msg, socket, compression)
     }
 
     ///
@@ -148,18 +188,24 @@ impl ServerComunicationHandler {
     // 3. The created Message is sent to the destination connection, which is the one that traveled on the command line.
     // 4. Waits for handshake's response.
     fn handle_server_message(&self, line: &str) -> Result<(), ErrorServer> {
-        match TcpStream::connect(get_destination_host_from_handshake(line)) {
+        match build_msg_from_handshake(line) {
+            Ok(_message) => self.connect_to(&get_destination_host_from_handshake(line)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    ///
+    /// Connects to the server listening at the given address, sending
+    /// the handshake and waiting for its response in a new thread.
+    ///
+    pub fn connect_to(&self, address: &str) -> Result<(), ErrorServer> {
+        match TcpStream::connect(address) {
             Err(e) => Err(ErrorServer::TcpStreamError(e.kind())),
-            Ok(mut destination) => match build_msg_from_handshake(line) {
-                Ok(_message) => {
-                    let _ = self.send_handshake(&mut destination);
-                    self.wait_for_handshake_response(destination)
-                }
-                Err(e) => {
-                    let _ = destination.shutdown(Shutdown::Both);
-                    Err(e.into())
-                }
-            },
+            Ok(mut destination) => {
+                let compression = self.link_compression()?;
+                let _ = self.send_handshake(&mut destination, compression);
+                self.wait_for_handshake_response(destination, compression)
+            }
         }
     }
 
@@ -167,13 +213,22 @@ impl ServerComunicationHandler {
     //
     // 1. A thread is created, listening for the connection that was created from the command line.
     // 2. We receive the handshake, so we validate that it is a SERVER message.
-    fn wait_for_handshake_response(&self, stream: TcpStream) -> Result<(), ErrorServer> {
+    //    The link is compressed if both ends offered it.
+    fn wait_for_handshake_response(
+        &self,
+        stream: TcpStream,
+        compression: bool,
+    ) -> Result<(), ErrorServer> {
         let sc = self.clone();
         thread::spawn(move || -> Result<(), ErrorServer> {
             let mut socket = stream.try_clone()?;
-            let new_communication = match read_message_from(&mut socket) {
+            // the compressed messages may follow the handshake in the same read
+            let new_communication = match read_frame_from(&mut socket) {
                 Ok(msg) => match Message::from_str(&msg) {
-                    Ok(message) => sc.handle_handshake(message, &mut socket),
+                    Ok(message) => {
+                        let compression = compression && offers_compression(&message);
+                        sc.handle_handshake(message, &mut socket, compression)
+                    }
                     Err(_) => Err(ErrorServer::UnknownCommand),
                 },
                 Err(_e) => {
@@ -213,29 +268,48 @@ impl ServerComunicationHandler {
     // server into the server's network and runs the listening of the messages from the newcome server.
     //
     // 1. If it is indeed a server message, we proceed to send the current information of the server.
+    //    A server that was already in the network gets only the links, it knows the rest.
     // 2. Announce the new server to the neighbours
     // 3. From the SERVER message, we have information of the server we wanted to connect, so we add it to the network (Spanning Tree).
     // 4. It executes a loop where listens the server's messages from the new connection.
+    // Once agreed, every message after the handshake is compressed, in both ways.
     fn handle_handshake(
         &self,
         msg: Message,
         new_server_stream: &mut TcpStream,
+        compression: bool,
     ) -> Result<Box<dyn Runnable>, ErrorServer> {
         if !msg.is_command(Command::Server) {
             new_server_stream.shutdown(Shutdown::Both)?;
             return Err(ErrorServer::UnexpectedCommand);
         }
 
+        let servername = ServerMsg::new(msg.clone())?.servername;
+        let redundant = match self.spanning_tree.lock() {
+            Ok(st) => st.contains(servername),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let mut connection = ConnectionServer::new(new_server_stream.try_clone()?);
+        if compression {
+            connection = connection.compressed()?;
+        }
         self.inform_neighbours_new_server(msg.clone())?;
-        self.send_server_information(new_server_stream)?;
-        self.add_server_into_network(&msg.to_string(), new_server_stream)?;
-        Ok(Box::new(self.clone()))
+        self.send_server_information(connection.clone(), redundant)?;
+        self.add_server_into_network(&msg.to_string(), connection.clone(), new_server_stream)?;
+        let mut handler = self.clone();
+        handler.neighbour = Some(ServerMsg::new(msg)?.servername);
+        handler.compressed = compression;
+        handler.link = Some(connection);
+        Ok(Box::new(handler))
     }
 
     // Prepares info and runs a new thread to send all the information to the new connection.
-    fn send_server_information(&self, destination: &mut TcpStream) -> Result<(), ErrorServer> {
-        let info = self.prepare_info()?;
-        let mut client = destination.try_clone()?;
+    fn send_server_information(
+        &self,
+        mut client: ConnectionServer,
+        redundant: bool,
+    ) -> Result<(), ErrorServer> {
+        let info = self.prepare_info(redundant)?;
         thread::spawn(move || {
             if let Err(_e) = write_messages_to(&mut info.iter(), &mut client) {
                 println!("ERROR SENDING SERVER INFORMATION.")
@@ -247,34 +321,32 @@ impl ServerComunicationHandler {
     fn add_server_into_network(
         &self,
         msg: &str,
+        connection: ConnectionServer,
         new_connection: &mut TcpStream,
     ) -> Result<(), ErrorServer> {
         // Agrego de prefijo el nombre del servidor
         //ya que los hijos deben saber de quien conocen el nuevo servidor.
         let c = Message::from_str(&add_prefix(Some(&self.get_servername()), msg))?;
         let cmd = ServerMsg::new(c.clone())?;
-        //Agrega sin conexion si no lo tiene.
-        //Manda el mensaje para sus hijos que poseen conexión.
-        let replies = cmd.response(self.get_servers())?;
-        if let Some(r) = replies.first() {
-            //Agrego el server con CONEXION al arbol
-            if Code::ErrAlreadyregistred == r.code() {
-                new_connection
-                    .shutdown(Shutdown::Read)
-                    .expect("shutdown call failed");
-                return Err(ErrorServer::ServerClosed);
-            }
+        let linked = match self.spanning_tree.lock() {
+            Ok(st) => st.is_linked_to(&cmd.servername),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        if linked {
+            new_connection
+                .shutdown(Shutdown::Read)
+                .expect("shutdown call failed");
+            return Err(ErrorServer::ServerClosed);
         }
-        self.add_server_connection(c, new_connection)
+        // the link may already be known, announced by the other end
+        self.add_server_connection(c, connection)
     }
 
     fn add_server_connection(
         &self,
         msg: Message,
-        socket: &mut TcpStream,
+        connection: ConnectionServer,
     ) -> Result<(), ErrorServer> {
-        //Creo la conexion del server que se dió conocer.
-        let connection = ConnectionServer::new(socket.try_clone()?);
         match self.spanning_tree.lock() {
             Ok(mut st) => {
                 let params = msg.parameters().unwrap();
@@ -288,41 +360,92 @@ impl ServerComunicationHandler {
     }
 
     // Send the Server's handshake to a neighbour, defined as direct connection server.
-    fn send_handshake(&self, new_connection: &mut TcpStream) -> Result<(), ErrorServer> {
+    // With compression, the handshake offers it to the other end.
+    fn send_handshake(
+        &self,
+        new_connection: &mut TcpStream,
+        compression: bool,
+    ) -> Result<(), ErrorServer> {
         let server = Server::new(self.servername.clone(), None);
         //HANDSHAKE
-        write_message_to(
-            &server.build_server_message_as_neighbour(&server),
-            new_connection,
-        )
+        let handshake = Message::from_str(&server.build_server_message_as_neighbour(&server))?;
+        if !compression {
+            return write_message_to(&handshake, new_connection);
+        }
+        let mut parameters = handshake.parameters().unwrap_or_default();
+        parameters.insert(COMPRESSION_POSITION, COMPRESSION_FLAG.to_string());
+        let handshake = Message::new(
+            Some(convert_into_command_prefix(&self.servername)),
+            Command::Server,
+            Some(parameters),
+        );
+        write_message_to(&handshake, new_connection)
+    }
+
+    // Tells if this server compresses its links.
+    fn link_compression(&self) -> Result<bool, ErrorServer> {
+        match self.state.config.lock() {
+            Ok(config) => Ok(config.link_compression),
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
     }
 
     //Prepares data from de server communication handler to be sended
-    //via IRC commands as Strings.
-    fn prepare_info(&self) -> Result<Vec<String>, ErrorServer> {
+    //via IRC commands as Strings. A redundant link only gets the server links.
+    //The messages go in a batch, so the other end knows where the burst ends.
+    fn prepare_info(&self, redundant: bool) -> Result<Vec<String>, ErrorServer> {
         let mut messages = vec![];
         messages.append(&mut self.get_server_creation_msg_from_repository()?);
-        messages.append(&mut self.get_user_creation_msg_from_repository()?);
-        messages.append(&mut self.get_channels_creation_msg_from_repository()?);
-        Ok(messages)
+        if !redundant {
+            messages.append(&mut self.get_user_creation_msg_from_repository()?);
+            messages.append(&mut self.get_channels_creation_msg_from_repository()?);
+            messages.append(&mut self.get_gline_msg_from_repository()?);
+        }
+        let reference = format!("{:x}", unix_timestamp());
+        Ok(wrap_in_batch(
+            &self.get_servername(),
+            &reference,
+            NETBURST,
+            messages,
+        ))
+    }
+
+    // Create a collection of String with the commands that share the active G-lines.
+    fn get_gline_msg_from_repository(&self) -> Result<Vec<String>, ErrorServer> {
+        match self.state.bans.lock() {
+            Ok(bans) => Ok(build_gline_msgs(
+                &bans,
+                &self.get_servername(),
+                unix_timestamp(),
+            )),
+            Err(_) => Err(ErrorServer::LockedResource),
+        }
     }
 
-    // Create a collection of String with the commands that adds users of the current nickname's repository
+    // Create a collection of String with the commands that adds users of the current nickname's repository,
+    // each one followed by the account it is logged in, if any.
     fn get_user_creation_msg_from_repository(&self) -> Result<Vec<String>, ErrorServer> {
-        let connections = self.nick_sender.find_all()?;
-        Ok(connections
-            .iter()
-            .map(|c| c.get_user().build_user_msg())
-            .collect())
+        let mut messages = vec![];
+        for connection in self.nick_sender.find_all()? {
+            messages.push(connection.get_user().build_user_msg());
+            if let Some(account) = connection.get_account() {
+                let nickname = connection.get_nickname();
+                let uid = connection.get_uid().unwrap_or(&nickname);
+                messages.push(account_msg(uid, Some(account))?.to_string());
+            }
+        }
+        Ok(messages)
     }
 
-    // Create a collection of String with the commands that generates channels from the current channel's repository
+    // Create a collection of String with the commands that generates channels from the current channel's repository,
+    // with the members named by their ids.
     fn get_channels_creation_msg_from_repository(&self) -> Result<Vec<String>, ErrorServer> {
         //Chequear colision de nombres, especialmente cuando alguno tiene una restricción al respecto.
         let channels = self.channel_sender.find_all()?;
         Ok(channels
             .iter()
-            .flat_map(|c| c.build_channel_msg())
+            .flat_map(|c| c.build_channel_msg(&self.servername))
+            .map(|line| to_uids(&line, &self.nick_sender))
             .collect())
     }
 
@@ -346,24 +469,63 @@ impl ServerComunicationHandler {
 
     // Method in charge of reading receiving messages from a socket
     // and handle it to a functionto be treated as a Command or DccCommand.
-    fn listen_to_server(&self, socket: &mut TcpStream) -> Result<(), ErrorServer> {
+    fn listen_to_server(&self, socket: &mut dyn Read) -> Result<(), ErrorServer> {
         loop {
-            match read_message_from(socket) {
+            match read_frame_from(socket) {
                 Err(err) => return self.handle_dropped_connection(&Message::from(err).to_string()),
-                Ok(msg) => match self.handle_received_message(&msg) {
-                    Ok(replies) => Self::handle_user_replies(replies, socket)?,
+                Ok(msg) => match self.count_received(&msg) {
+                    Ok(replies) => Self::handle_user_replies(replies, &mut sink())?,
                     Err(e) => println!("An error ocurred at execute message: \n - {msg} : \n Handled error at handle_received_message() {:?}", e)
                 }
             }
         }
     }
 
+    // Counts the message read from the link in its health, and handles it.
+    fn count_received(&self, msg: &str) -> Result<Vec<Reply>, ErrorServer> {
+        if let Some(link) = &self.link {
+            match link.health().lock() {
+                Ok(mut health) => health.receive(MESSAGE_LENGHT),
+                Err(_) => return Err(ErrorServer::LockedResource),
+            }
+        }
+        self.handle_received_message(msg)
+    }
+
     fn handle_dropped_connection(&self, msg: &str) -> Result<(), ErrorServer> {
-        // Eliminar server SQUIT
         println!("listen_to_server(): {msg}");
+        if let Some(neighbour) = &self.neighbour {
+            self.netsplit(neighbour)?;
+        }
         Err(ErrorServer::ServerClosed)
     }
 
+    // Removes the lost link, and the servers only reachable through it with
+    // their users, and informs the UNLINK to the remaining neighbours. If a
+    // link on standby reaches the neighbour, the network stays joined through
+    // it. Nothing is done if the server already left the network with a SQUIT.
+    fn netsplit(&self, neighbour: &str) -> Result<(), ErrorServer> {
+        let servername = self.get_servername();
+        let reason = format!("{servername} {neighbour}");
+        let lost_servers = match self.spanning_tree.lock() {
+            Ok(mut st) => match st.remove_link(&servername, neighbour) {
+                Some(lost_servers) => {
+                    let unlink = format!(":{servername} UNLINK {neighbour} :{reason}");
+                    send_to_all_servers(&st, Message::from_str(&unlink)?, &servername)?;
+                    lost_servers
+                }
+                None => vec![],
+            },
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        quit_lost_users(
+            &lost_servers,
+            &reason,
+            &self.nick_sender,
+            &self.channel_sender,
+        )
+    }
+
     fn handle_user_replies(
         _replies: Vec<Reply>,
         client: &mut dyn Write,
@@ -374,13 +536,15 @@ impl ServerComunicationHandler {
     }
 
     // Function that receives a message on string format.
+    // The ids of the users are replaced with their nicknames first.
     // Validates if it's a DccComand or a IRC command, and execute it.
     // Then handle the Result, if it's a Reply ignores it, and if it's an error
     // logs it.
     fn handle_received_message(&self, msg: &str) -> Result<Vec<Reply>, ErrorServer> {
-        match is_dcc_chat(msg) {
-            true => self.execute_dcc_commands(msg),
-            false => self.execute_irc_commands(msg),
+        let msg = to_nicknames(msg, &self.nick_sender, &self.state.uid_index);
+        match is_dcc_chat(&msg) {
+            true => self.execute_dcc_commands(&msg),
+            false => self.execute_irc_commands(&msg),
         }
     }
 
@@ -418,6 +582,15 @@ impl ServerComunicationHandler {
                 | Command::Notice
                 | Command::Nick
                 | Command::Kill
+                | Command::Gline
+                | Command::Ungline
+                | Command::Sjoin
+                | Command::Tb
+                | Command::Unlink
+                | Command::Batch
+                | Command::Ping
+                | Command::Pong
+                | Command::Reply
         )
     }
 
@@ -432,7 +605,9 @@ impl ServerComunicationHandler {
                     "POST-SQUIT | SPANNING TREE: {:?}",
                     self.spanning_tree.lock().unwrap()
                 );
-                if let Some(prefix) = c.prefix() {
+                // an unknown server was already removed, forwarding it again would loop
+                let known = !result.iter().any(|r| r.code() == Code::ErrNosuchserver);
+                if let (true, Some(prefix)) = (known, c.prefix()) {
                     inform_all_servers(&self.spanning_tree, &self.nick_sender, &prefix, c)?;
                 }
                 Ok(result)
@@ -440,7 +615,6 @@ impl ServerComunicationHandler {
             Command::Server => {
                 let msg = ServerMsg::new(c)?;
                 msg.response(self.get_servers())?;
-                thread::sleep(Duration::from_millis(150));
                 print!(
                     "POST-SERVER | SPANNING TREE: {:?}",
                     self.spanning_tree.lock().unwrap()
@@ -449,7 +623,8 @@ impl ServerComunicationHandler {
             }
             Command::User => {
                 let msg = UserMsg::new(c);
-                msg.response(self.nick_sender.clone(), self.get_servers());
+                msg.response(&self.nick_sender, &self.channel_sender, &self.spanning_tree)?;
+                record_user_peaks(&self.nick_sender, &self.servername, &self.state)?;
                 Ok(vec![Reply::rpl_none()])
             }
 
@@ -477,10 +652,104 @@ impl ServerComunicationHandler {
                 msg.response(self.nick_sender.clone(), self.get_servers())?;
                 Ok(vec![Reply::rpl_none()])
             }
+            Command::Gline | Command::Ungline => {
+                // The operator was validated by the server that received the command.
+                let mut ban = ServerBanMsg::new(&c, true)?;
+                ban.response(&self.nick_sender, &self.spanning_tree, &self.state.bans)?;
+                if ban.must_be_propagated() {
+                    let origin = self.origin_of(&c.prefix().unwrap_or_default())?;
+                    let c = Message::from_str(&to_uids(&c.to_string(), &self.nick_sender))?;
+                    match self.spanning_tree.lock() {
+                        Ok(st) => send_to_all_servers(&st, c, &origin)?,
+                        Err(_) => return Err(ErrorServer::LockedResource),
+                    }
+                }
+                Ok(vec![Reply::rpl_none()])
+            }
+            Command::Batch => BatchMsg::new(&c)?.response(),
+            Command::Ping => {
+                let ping = PingMsg::new(&c)?;
+                if let Some(link) = &self.link {
+                    write_message_to(&ping.pong(&self.servername), &mut link.clone())?;
+                }
+                Ok(vec![Reply::rpl_none()])
+            }
+            Command::Pong => {
+                let pong = PingMsg::new(&c)?;
+                let change = match self.link.as_ref().map(|link| link.health()) {
+                    Some(health) => match health.lock() {
+                        Ok(mut health) => health.pong(pong.token(), Instant::now()),
+                        Err(_) => return Err(ErrorServer::LockedResource),
+                    },
+                    None => None,
+                };
+                if let Some(change) = change {
+                    notify_lag_change(&self.nick_sender, &self.servername, pong.server(), change)?;
+                }
+                Ok(vec![Reply::rpl_none()])
+            }
+            Command::Reply => {
+                ReplyMsg::new(&c)?.response(&self.nick_sender, &self.spanning_tree)
+            }
+            Command::Unlink => {
+                let msg = UnlinkMsg::new(&c)?;
+                msg.response(&self.nick_sender, &self.channel_sender, &self.spanning_tree)
+            }
+            Command::Sjoin => {
+                let origin = c.prefix().unwrap_or_default();
+                let mut sjoin = SjoinMsg::new(&c)?;
+                sjoin.retain_members(|nickname| self.comes_from(nickname, &origin));
+                sjoin.response(&self.channel_sender)?;
+                // every server resolves the channel the same way
+                let c = Message::from_str(&to_uids(&c.to_string(), &self.nick_sender))?;
+                match self.spanning_tree.lock() {
+                    Ok(st) => send_to_all_servers(&st, c, &origin)?,
+                    Err(_) => return Err(ErrorServer::LockedResource),
+                }
+                Ok(vec![Reply::rpl_none()])
+            }
+            Command::Tb => {
+                let origin = c.prefix().unwrap_or_default();
+                TopicBurstMsg::new(&c)?.response(&self.nick_sender, &self.channel_sender)?;
+                match self.spanning_tree.lock() {
+                    Ok(st) => send_to_all_servers(&st, c, &origin)?,
+                    Err(_) => return Err(ErrorServer::LockedResource),
+                }
+                Ok(vec![Reply::rpl_none()])
+            }
             _ => Err(ErrorServer::UnexpectedCommand),
         }
     }
 
+    // Returns the server the message came from: the neighbour of the link,
+    // or else the server of the user or the server that prefixes it.
+    fn origin_of(&self, prefix: &str) -> Result<String, ErrorServer> {
+        if let Some(neighbour) = &self.neighbour {
+            return Ok(neighbour.clone());
+        }
+        Ok(match self.nick_sender.search(prefix.to_string())? {
+            Some(connection) => connection.get_servername().unwrap_or(prefix).to_string(),
+            None => prefix.to_string(),
+        })
+    }
+
+    // Tells if the user is connected to a server reached through the same link
+    // as the given one. A user killed in a nick collision may appear in a burst
+    // prepared before, while its nickname belongs now to a user of this side.
+    fn comes_from(&self, nickname: &str, servername: &str) -> bool {
+        let user_server = match self.nick_sender.search(nickname.to_string()) {
+            Ok(Some(connection)) => connection.get_servername().unwrap_or_default().to_string(),
+            _ => return false,
+        };
+        match self.spanning_tree.lock() {
+            Ok(st) => {
+                let link = st.look_for_nearest_connection(servername.to_string());
+                link.is_some() && link == st.look_for_nearest_connection(user_server)
+            }
+            Err(_) => false,
+        }
+    }
+
     // Method that delegates the execution of an IRC command to the UserHandler.
     fn execute_irc_command(&self, command: Message) -> Result<Vec<Reply>, ErrorServer> {
         UserHandler::execute_irc_command_from(
@@ -489,6 +758,7 @@ impl ServerComunicationHandler {
             &self.get_servers(),
             &self.nick_sender,
             &self.channel_sender,
+            &self.state,
         )
     }
 
@@ -506,10 +776,21 @@ impl ServerComunicationHandler {
 
 impl Runnable for ServerComunicationHandler {
     fn run(&mut self, socket: &mut TcpStream) -> Result<(), ErrorServer> {
-        self.listen_to_server(socket)
+        match self.compressed {
+            true => self.listen_to_server(&mut LinkDecoder::new(socket.try_clone()?)),
+            false => self.listen_to_server(socket),
+        }
     }
 }
 
+// Tells if the handshake offers to compress the link.
+fn offers_compression(handshake: &Message) -> bool {
+    handshake
+        .get_param_from_msg(COMPRESSION_POSITION)
+        .as_deref()
+        == Some(COMPRESSION_FLAG)
+}
+
 // It's expected that server's ip:port travels in the SERVER_IP_PORT_POSITION place.
 fn get_destination_host_from_handshake(server_msg: &str) -> String {
     let lines: Vec<String> = server_msg.split_whitespace().map(str::to_string).collect();
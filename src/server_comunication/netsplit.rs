@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+use crate::{
+    channel::Channel,
+    command::quit::QuitMsg,
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    utils::write_message_to,
+};
+
+///
+/// Removes the users of the lost servers. Their QUIT, with the
+/// `<near server> <far server>` reason, is sent to the members of
+/// their channels connected to this server.
+///
+pub fn quit_lost_users(
+    lost_servers: &[String],
+    reason: &str,
+    nick_sender: &ClientChannel<String, Connection>,
+    channel_sender: &ClientChannel<String, Channel>,
+) -> Result<(), ErrorServer> {
+    let lost_users: Vec<String> = nick_sender
+        .find_all()?
+        .iter()
+        .filter(|c| {
+            c.get_servername()
+                .is_some_and(|s| lost_servers.iter().any(|lost| lost == s))
+        })
+        .map(|c| c.get_nickname())
+        .collect();
+    quit_users(&lost_users, reason, nick_sender, channel_sender)
+}
+
+///
+/// Removes the given users from the network, sending their QUIT
+/// to the members of their channels connected to this server.
+///
+pub fn quit_users(
+    nicknames: &[String],
+    reason: &str,
+    nick_sender: &ClientChannel<String, Connection>,
+    channel_sender: &ClientChannel<String, Channel>,
+) -> Result<(), ErrorServer> {
+    let channels = channel_sender.find_all()?;
+
+    for nickname in nicknames {
+        let quit = Message::from_str(&format!(":{nickname} QUIT :{reason}"))?;
+        let mut informed: Vec<String> = vec![];
+        for channel in channels.iter().filter(|c| c.has_member(nickname)) {
+            for member in channel.return_members() {
+                if nicknames.contains(&member) || informed.contains(&member) {
+                    continue;
+                }
+                if let Some(mut connection) = nick_sender.search(member.clone())? {
+                    // only the users of this server have a stream to write to
+                    let _ = write_message_to(&quit, &mut connection);
+                }
+                informed.push(member);
+            }
+        }
+        QuitMsg::new(&quit)?.response(nick_sender, channel_sender)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        channel::Channel,
+        command::fixtures::add_user,
+        database::RepositoryHandler,
+        repository::{connection::Connection, traits::operations::Operations},
+        user::User,
+    };
+
+    use super::quit_lost_users;
+
+    fn remote_user(nickname: &str, servername: &str) -> Connection {
+        Connection::connection_away_server(User::new(
+            nickname, "user", "host", servername, "real", "",
+        ))
+    }
+
+    #[test]
+    fn lost_users_are_removed_from_their_channels() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let channels = RepositoryHandler::new().unwrap();
+        let mut client = add_user(&nicks, "local", false);
+        nicks
+            .get_channels()
+            .add("far".to_string(), remote_user("far", "server3"))
+            .unwrap();
+        let mut channel = Channel::new("#c".to_string(), "local".to_string());
+        channel.add_member("far", &None).unwrap();
+        channels
+            .get_channels()
+            .add("#c".to_string(), channel)
+            .unwrap();
+
+        quit_lost_users(
+            &["server2".to_string(), "server3".to_string()],
+            "server1 server2",
+            &nicks.get_channels(),
+            &channels.get_channels(),
+        )
+        .unwrap();
+
+        assert!(nicks
+            .get_channels()
+            .search("far".to_string())
+            .unwrap()
+            .is_none());
+        let channel = channels.get_channels().search("#c".to_string()).unwrap();
+        assert!(!channel.unwrap().has_member("far"));
+        let mut received = [0; 512];
+        std::io::Read::read(&mut client, &mut received).unwrap();
+        assert!(String::from_utf8_lossy(&received).starts_with(":far QUIT :server1 server2"));
+        nicks.stop().unwrap();
+        channels.stop().unwrap();
+    }
+}
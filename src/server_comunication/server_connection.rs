@@ -1,16 +1,25 @@
+use super::link_health::LinkHealth;
 use crate::error::error_server::ErrorServer;
+use flate2::{write::ZlibEncoder, Compression};
+use std::fmt;
 use std::io::Write;
-use std::net::TcpStream;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
 
 ///
 /// struct that implements a connection
 /// a connection between servers. it has
 /// a tcp stream and makes it clonable so it
 /// can be used in the repositories and spanning trees.
+/// On a compressed link the clones share the
+/// compressor, as the stream they write to.
+/// The clones share the health of the link too.
 ///
-#[derive(Debug)]
 pub struct ConnectionServer {
     stream: Result<TcpStream, ErrorServer>,
+    compressor: Option<Arc<Mutex<ZlibEncoder<TcpStream>>>>,
+    health: Arc<Mutex<LinkHealth>>,
 }
 
 impl ConnectionServer {
@@ -18,8 +27,41 @@ impl ConnectionServer {
     /// function that creates a new connection
     ///
     pub fn new(stream: TcpStream) -> Self {
-        Self { stream: Ok(stream) }
+        Self {
+            stream: Ok(stream),
+            compressor: None,
+            health: Arc::new(Mutex::new(LinkHealth::new(Instant::now()))),
+        }
+    }
+
+    ///
+    /// function that returns the health
+    /// of the link, shared by the clones
+    ///
+    pub fn health(&self) -> Arc<Mutex<LinkHealth>> {
+        self.health.clone()
+    }
+
+    ///
+    /// function that compresses the messages written
+    /// from now on through the connection and its clones
+    ///
+    pub fn compressed(mut self) -> Result<Self, ErrorServer> {
+        let encoder = ZlibEncoder::new(self.see_if_clonable()?, Compression::default());
+        self.compressor = Some(Arc::new(Mutex::new(encoder)));
+        Ok(self)
+    }
+    ///
+    /// function that closes
+    /// the tcp stream
+    ///
+    pub fn shutdown(&self) -> Result<(), ErrorServer> {
+        match &self.stream {
+            Ok(s) => Ok(s.shutdown(Shutdown::Both)?),
+            Err(_) => Err(ErrorServer::TcpFail),
+        }
     }
+
     ///
     /// function that tries to clone
     /// the tcp stream into the connection
@@ -42,10 +84,21 @@ impl Clone for ConnectionServer {
     fn clone(&self) -> Self {
         Self {
             stream: self.see_if_clonable(),
+            compressor: self.compressor.clone(),
+            health: self.health.clone(),
         }
     }
 }
 
+impl fmt::Debug for ConnectionServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionServer")
+            .field("stream", &self.stream)
+            .field("compressed", &self.compressor.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
 ///
 /// implementation of the trait
 /// write for connection so it
@@ -55,21 +108,108 @@ impl Clone for ConnectionServer {
 ///
 impl Write for ConnectionServer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(compressor) = &self.compressor {
+            return lock_compressor(compressor)?.write(buf);
+        }
         match &mut self.stream {
             Ok(s) => s.write(buf),
             Err(_) => Err(std::io::Error::from(std::io::ErrorKind::WriteZero)),
         }
     }
     fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(compressor) = &self.compressor {
+            return lock_compressor(compressor)?.flush();
+        }
         match &mut self.stream {
             Ok(s) => s.flush(),
             Err(_) => Err(std::io::Error::from(std::io::ErrorKind::WriteZero)),
         }
     }
     fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        if let Ok(mut health) = self.health.lock() {
+            health.queue(buf.len());
+        }
+        let result = self.write_message(buf);
+        if let Ok(mut health) = self.health.lock() {
+            health.dequeue(buf.len(), result.is_ok());
+        }
+        result
+    }
+}
+
+impl ConnectionServer {
+    // Writes the whole message to the stream. Each message is
+    // flushed, so the other end can decompress it on arrival.
+    fn write_message(&mut self, message: &[u8]) -> std::io::Result<()> {
+        if let Some(compressor) = &self.compressor {
+            let mut compressor = lock_compressor(compressor)?;
+            compressor.write_all(message)?;
+            return compressor.flush();
+        }
         match &mut self.stream {
-            Ok(s) => s.write_all(buf),
+            Ok(s) => s.write_all(message),
             Err(_) => Err(std::io::Error::from(std::io::ErrorKind::WriteZero)),
         }
     }
 }
+
+fn lock_compressor(
+    compressor: &Mutex<ZlibEncoder<TcpStream>>,
+) -> std::io::Result<MutexGuard<'_, ZlibEncoder<TcpStream>>> {
+    compressor
+        .lock()
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::Other))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Write,
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::utils::{read_frame_from, write_message_to, MESSAGE_LENGHT};
+
+    use super::{super::compression::LinkDecoder, ConnectionServer};
+
+    // Returns a connection and the other end of its stream.
+    fn link() -> (ConnectionServer, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let connection = ConnectionServer::new(stream);
+        (connection, listener.accept().unwrap().0)
+    }
+
+    #[test]
+    fn written_messages_are_counted_in_the_link_health() {
+        let (connection, _other_end) = link();
+        let mut clone = connection.clone();
+        write_message_to(&":server1 PING server1 :1", &mut clone).unwrap();
+
+        let health = connection.health();
+        let health = health.lock().unwrap();
+        assert_eq!(health.sent().messages, 1);
+        assert_eq!(health.sent().bytes, MESSAGE_LENGHT);
+        assert_eq!(health.sendq(), 0);
+    }
+
+    #[test]
+    fn clones_write_to_the_same_compressed_stream() {
+        let (connection, other_end) = link();
+        let mut connection = connection.compressed().unwrap();
+        let mut clone = connection.clone();
+        write_message_to(&":server1 SERVER server2 2 :info", &mut connection).unwrap();
+        write_message_to(&":server1 SQUIT server2 :bye", &mut clone).unwrap();
+        connection.flush().unwrap();
+
+        let mut reader = LinkDecoder::new(other_end);
+        assert_eq!(
+            read_frame_from(&mut reader).unwrap(),
+            ":server1 SERVER server2 2 :info"
+        );
+        assert_eq!(
+            read_frame_from(&mut reader).unwrap(),
+            ":server1 SQUIT server2 :bye"
+        );
+    }
+}
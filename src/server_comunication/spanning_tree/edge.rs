@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::server_comunication::server::Server;
 
 use super::node::Node;
@@ -29,23 +31,68 @@ impl Edge {
             cost,
         }
     }
+
+    ///
+    /// returns true if the edge links both
+    /// servers, in any direction
+    ///
+    pub fn connects(&self, server: &str, other: &str) -> bool {
+        let (source, destination) = self.names();
+        (source == server && destination == other) || (source == other && destination == server)
+    }
+
+    ///
+    /// returns true if one of the ends
+    /// of the edge is the server
+    ///
+    pub fn touches(&self, server: &str) -> bool {
+        let (source, destination) = self.names();
+        source == server || destination == server
+    }
+
+    fn names(&self) -> (&str, &str) {
+        (
+            &self.source.server.servername,
+            &self.destination.server.servername,
+        )
+    }
+
+    // The names of both ends in order, so an edge
+    // has the same key whatever its direction is.
+    fn key(&self) -> (usize, &str, &str) {
+        let (source, destination) = self.names();
+        (self.cost, source.min(destination), source.max(destination))
+    }
 }
 
 ///
 /// Kruscal algorithm
 /// that given a set of edges (a graph)
 /// finds the minimum tree.
+/// Edges of equal cost are taken in the order of the names
+/// of their servers, so every server builds the same tree.
 ///
 pub fn kruskal(mut edges: Vec<Edge>, number_of_nodes: usize) -> Option<Vec<Edge>> {
-    let mut visited_nodes: Vec<Node> = Vec::new();
+    // component of each node, the edges that join a component
+    // with itself would close a loop
+    let mut components: HashMap<String, usize> = HashMap::new();
     let mut tree: Vec<Edge> = Vec::new();
-    edges.sort_by(|a, b| a.cost.cmp(&b.cost));
+    edges.sort_by(|a, b| a.key().cmp(&b.key()));
     for edge in edges {
-        if !visited_nodes.contains(&edge.source) || !visited_nodes.contains(&edge.destination) {
-            visited_nodes.push(edge.source.clone());
-            visited_nodes.push(edge.destination.clone());
-            tree.push(edge);
+        let (source, destination) = edge.names();
+        let count = components.len();
+        let source_component = *components.entry(source.to_string()).or_insert(count);
+        let count = components.len();
+        let destination_component = *components.entry(destination.to_string()).or_insert(count);
+        if source_component == destination_component {
+            continue;
+        }
+        for component in components.values_mut() {
+            if *component == destination_component {
+                *component = source_component;
+            }
         }
+        tree.push(edge);
         if number_of_nodes - 1 == tree.len() {
             return Some(tree);
         }
@@ -53,6 +100,33 @@ pub fn kruskal(mut edges: Vec<Edge>, number_of_nodes: usize) -> Option<Vec<Edge>
     None
 }
 
+///
+/// orients the edges of a tree so they
+/// go away from the given node
+///
+pub fn orient(tree: Vec<Edge>, root: &Node) -> Vec<Edge> {
+    let mut reached = vec![root.server.servername.clone()];
+    let mut oriented = vec![];
+    let mut pending = tree;
+    let mut index = 0;
+    while index < reached.len() {
+        let current = reached[index].clone();
+        let (linked, rest): (Vec<Edge>, Vec<Edge>) =
+            pending.into_iter().partition(|e| e.touches(&current));
+        pending = rest;
+        for edge in linked {
+            let edge = match edge.source.server.servername == current {
+                true => edge,
+                false => Edge::new(edge.destination, edge.source, edge.cost),
+            };
+            reached.push(edge.destination.server.servername.clone());
+            oriented.push(edge);
+        }
+        index += 1;
+    }
+    oriented
+}
+
 ///
 /// given a graph, calculates the distance
 /// between two nodes belonging to the graph
@@ -124,7 +198,7 @@ pub fn search_node(
 ) -> Option<Node> {
     let tree = kruskal(edges, number_of_nodes);
     match tree {
-        Some(c) => look_for_nearest_connection(c, initial, searched),
+        Some(c) => look_for_nearest_connection(orient(c, &initial), initial, searched),
         None => None,
     }
 }
@@ -306,4 +380,57 @@ mod test {
         let result = distance_between_servers(edges, 6, node1, searched).unwrap();
         assert_eq!(result, 2);
     }
+
+    fn link(source: &str, destination: &str) -> Edge {
+        Edge::new(
+            Node::new(Server::new(source.to_string(), None)),
+            Node::new(Server::new(destination.to_string(), None)),
+            1,
+        )
+    }
+
+    #[test]
+    fn kruskal_skips_the_links_that_close_a_loop() {
+        let edges = vec![
+            link("server1", "server2"),
+            link("server3", "server4"),
+            link("server2", "server3"),
+            link("server4", "server1"),
+        ];
+        let tree = kruskal(edges, 4).unwrap();
+        assert_eq!(tree.len(), 3);
+        assert!(!tree.iter().any(|e| e.connects("server3", "server4")));
+    }
+
+    #[test]
+    fn kruskal_builds_the_same_tree_in_every_server() {
+        let edges = vec![
+            link("server1", "server2"),
+            link("server2", "server3"),
+            link("server3", "server1"),
+        ];
+        let mut reversed: Vec<Edge> = edges
+            .iter()
+            .map(|e| Edge::new(e.destination.clone(), e.source.clone(), e.cost))
+            .collect();
+        reversed.reverse();
+
+        let tree = kruskal(edges, 3).unwrap();
+        let other = kruskal(reversed, 3).unwrap();
+        assert!(tree
+            .iter()
+            .zip(other.iter())
+            .all(|(a, b)| a.key() == b.key()));
+    }
+
+    #[test]
+    fn orient_points_the_edges_away_from_the_root() {
+        let tree = vec![link("server2", "server1"), link("server3", "server2")];
+        let root = Node::new(Server::new("server1".to_string(), None));
+        let oriented = orient(tree, &root);
+
+        assert_eq!(oriented[0].source.server.servername, "server1");
+        assert_eq!(oriented[1].source.server.servername, "server2");
+        assert_eq!(oriented[1].destination.server.servername, "server3");
+    }
 }
@@ -11,9 +11,15 @@ use super::server_connection::ConnectionServer;
 pub mod edge;
 pub mod node;
 
+/// cost of every link between two servers
+pub const LINK_COST: usize = 1;
+
 ///
 /// implementation of
 /// a spanning tree
+/// It keeps every link known in the network, the ones that
+/// aren't part of the minimum spanning tree are on standby
+/// and carry no messages until a link of the tree fails.
 ///
 pub struct SpanningTree {
     edges: Vec<Edge>,
@@ -85,24 +91,131 @@ impl SpanningTree {
 
     pub fn look_for_nearest_connection(&self, servername: String) -> Option<Server> {
         let server = Server::new(servername, None);
-        let node = edge::search_node(
-            self.edges.clone(),
-            self.get_number_of_nodes(),
-            self.root.clone(),
-            server,
-        );
+        let node =
+            edge::look_for_nearest_connection(self.get_tree_edges(), self.root.clone(), server);
         if let Some(result) = node {
             return Some(result.server);
         }
         None
     }
+
+    ///
+    /// returns the edges of the minimum spanning tree,
+    /// oriented away from this server
+    ///
+    pub fn get_tree_edges(&self) -> Vec<Edge> {
+        match edge::kruskal(self.edges.clone(), self.get_number_of_nodes()) {
+            Some(tree) => edge::orient(tree, &self.root),
+            None => vec![],
+        }
+    }
+
+    ///
+    /// returns every server of the network with the one it is
+    /// reached through and its distance to this server, in
+    /// hopcount order. This server is its own uplink.
+    ///
+    pub fn get_hopcounts(&self) -> Vec<(String, String, usize)> {
+        let root = self.get_root_name();
+        let mut hopcounts = vec![(root.clone(), root, 0)];
+        for edge in self.get_tree_edges() {
+            let uplink = edge.source.server.servername;
+            let hopcount = hopcounts
+                .iter()
+                .find(|(server, _, _)| *server == uplink)
+                .map_or(0, |(_, _, hopcount)| hopcount + 1);
+            hopcounts.push((edge.destination.server.servername, uplink, hopcount));
+        }
+        hopcounts
+    }
+
+    ///
+    /// returns true if both servers are linked
+    ///
+    pub fn has_link(&self, server: &str, other: &str) -> bool {
+        self.edges.iter().any(|e| e.connects(server, other))
+    }
+    ///
+    /// returns the servers linked directly
+    /// to this one
+    ///
+    pub fn get_neighbours(&self) -> Vec<Server> {
+        let root = self.get_root_name();
+        self.get_tree_edges()
+            .into_iter()
+            .filter(|e| e.source.server.servername == root)
+            .map(|e| e.destination.server)
+            .filter(|server| server.get_connection().is_some())
+            .collect()
+    }
+
+    ///
+    /// returns the servers linked directly to this
+    /// one, including the links on standby
+    ///
+    pub fn get_links(&self) -> Vec<Server> {
+        let root = self.get_root_name();
+        self.edges
+            .iter()
+            .filter(|e| e.source.server.servername == root)
+            .map(|e| e.destination.server.clone())
+            .filter(|server| server.get_connection().is_some())
+            .collect()
+    }
+
+    ///
+    /// returns true if this server already has
+    /// a connection with the given one
+    ///
+    pub fn is_linked_to(&self, servername: &str) -> bool {
+        self.get_links().iter().any(|s| s.servername == servername)
+    }
+
+    ///
+    /// returns the neighbours a message has to be written to
+    /// so it reaches the given servers, once each, without
+    /// going back toward the server it came from
+    ///
+    pub fn next_hops(&self, servernames: &[String], origin_server: &str) -> Vec<Server> {
+        let root = self.get_root_name();
+        let informer = self.look_for_nearest_connection(origin_server.to_string());
+        let mut hops: Vec<Server> = vec![];
+        for servername in servernames {
+            if let Some(hop) = self.look_for_nearest_connection(servername.clone()) {
+                if hop.servername != root && Some(&hop) != informer.as_ref() && !hops.contains(&hop)
+                {
+                    hops.push(hop);
+                }
+            }
+        }
+        hops
+    }
+
     ///
-    /// adds a new edge to the spanning tree
+    /// adds a new edge to the spanning tree,
+    /// replacing the one between the same servers
     ///
     pub fn add_edge(&mut self, new: Edge) {
+        let source = new.source.server.servername.clone();
+        let destination = new.destination.server.servername.clone();
+        self.edges.retain(|e| !e.connects(&source, &destination));
         self.edges.push(new);
     }
 
+    ///
+    /// removes the link between both servers and every server
+    /// that can't be reached anymore, returning the names of
+    /// the lost servers. Returns none if the link wasn't known.
+    ///
+    pub fn remove_link(&mut self, server: &str, other: &str) -> Option<Vec<String>> {
+        if !self.has_link(server, other) {
+            return None;
+        }
+        let known = self.get_server_names();
+        self.edges.retain(|e| !e.connects(server, other));
+        Some(self.prune(known, None))
+    }
+
     ///
     /// deletes an existing edge
     ///
@@ -118,7 +231,53 @@ impl SpanningTree {
         }
     }
 
-    //pub netsplit() {}
+    ///
+    /// removes the server and every server only reachable
+    /// through it, returning the names of the lost servers
+    ///
+    pub fn split(&mut self, servername: &str) -> Vec<String> {
+        let known = self.get_server_names();
+        self.prune(known, Some(servername))
+    }
+
+    // Removes the servers that can't be reached from this one without
+    // going through the excluded server, returning the ones that were
+    // known before.
+    fn prune(&mut self, known: Vec<String>, excluded: Option<&str>) -> Vec<String> {
+        let mut reachable = vec![self.get_root_name()];
+        let mut index = 0;
+        while index < reachable.len() {
+            let current = reachable[index].clone();
+            for edge in &self.edges {
+                let source = &edge.source.server.servername;
+                let destination = &edge.destination.server.servername;
+                let neighbour = match (*source == current, *destination == current) {
+                    (true, _) => destination,
+                    (_, true) => source,
+                    _ => continue,
+                };
+                if Some(neighbour.as_str()) != excluded && !reachable.contains(neighbour) {
+                    reachable.push(neighbour.clone());
+                }
+            }
+            index += 1;
+        }
+        self.edges.retain(|e| {
+            reachable.contains(&e.source.server.servername)
+                && reachable.contains(&e.destination.server.servername)
+        });
+        known
+            .into_iter()
+            .filter(|name| !reachable.contains(name))
+            .collect()
+    }
+
+    fn get_server_names(&self) -> Vec<String> {
+        self.get_servers()
+            .into_iter()
+            .map(|s| s.servername)
+            .collect()
+    }
 
     ///
     /// returns all the edges in the tree
@@ -149,22 +308,56 @@ impl SpanningTree {
         name: String,
         connection: ConnectionServer,
     ) -> Result<(), ErrorServer> {
-        let node = self.look_for_nearest_connection(name.clone());
-        let server = Server::new(name.clone(), Some(connection));
-        let edge = Edge::new(self.get_root(), Node::new(server), 1);
-        match node {
-            None => {}
-            Some(s) => {
-                if s.servername == name {
-                    self.delete_edge(self.get_root().server.servername, name);
-                }
-            }
-        }
-        self.add_edge(edge);
+        let server = Server::new(name, Some(connection));
+        self.add_edge(Edge::new(self.get_root(), Node::new(server), LINK_COST));
         Ok(())
     }
 }
 
+///
+/// draws the spanning tree as seen from this server,
+/// one server per line below the one it is reached through
+///
+impl fmt::Display for SpanningTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_branch(f, &self.get_tree_edges(), &self.get_root_name(), "", None)
+    }
+}
+
+// Writes the server and, below it, the servers reached through it.
+fn write_branch(
+    f: &mut fmt::Formatter,
+    tree: &[Edge],
+    server: &str,
+    indent: &str,
+    last: Option<bool>,
+) -> fmt::Result {
+    let indent = match last {
+        None => {
+            writeln!(f, "{server}")?;
+            String::new()
+        }
+        Some(true) => {
+            writeln!(f, "{indent}`- {server}")?;
+            format!("{indent}   ")
+        }
+        Some(false) => {
+            writeln!(f, "{indent}|- {server}")?;
+            format!("{indent}|  ")
+        }
+    };
+    let mut children: Vec<&str> = tree
+        .iter()
+        .filter(|e| e.source.server.servername == server)
+        .map(|e| e.destination.server.servername.as_str())
+        .collect();
+    children.sort_unstable();
+    for (i, child) in children.iter().enumerate() {
+        write_branch(f, tree, child, &indent, Some(i + 1 == children.len()))?;
+    }
+    Ok(())
+}
+
 impl fmt::Debug for SpanningTree {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "DEBUGGING SPANNING TREE")?;
@@ -313,4 +506,162 @@ mod test {
             .unwrap();
         assert_eq!(result, Server::new("server1".to_string(), None));
     }
+
+    #[test]
+    fn split_removes_the_servers_behind() {
+        let node1 = Node::new(Server::new("server1".to_string(), None));
+        let node2 = Node::new(Server::new("server2".to_string(), None));
+        let node3 = Node::new(Server::new("server3".to_string(), None));
+        let node4 = Node::new(Server::new("server4".to_string(), None));
+        let edges = vec![
+            Edge::new(node1.clone(), node2.clone(), 1),
+            Edge::new(node2.clone(), node3.clone(), 2),
+            Edge::new(node1.clone(), node4.clone(), 1),
+        ];
+        let mut st = SpanningTree::new(node1, edges);
+
+        let mut lost = st.split("server2");
+        lost.sort();
+        assert_eq!(lost, vec!["server2", "server3"]);
+        assert_eq!(st.get_edges().len(), 1);
+        assert!(st.contains("server4".to_string()));
+    }
+
+    #[test]
+    fn split_of_unknown_server_loses_nothing() {
+        let node1 = Node::new(Server::new("server1".to_string(), None));
+        let node2 = Node::new(Server::new("server2".to_string(), None));
+        let edges = vec![Edge::new(node1.clone(), node2, 1)];
+        let mut st = SpanningTree::new(node1, edges);
+
+        assert!(st.split("server9").is_empty());
+        assert_eq!(st.get_edges().len(), 1);
+    }
+
+    fn routed_tree() -> SpanningTree {
+        let node1 = Node::new(Server::new("server1".to_string(), None));
+        let node2 = Node::new(Server::new("server2".to_string(), None));
+        let node3 = Node::new(Server::new("server3".to_string(), None));
+        let node4 = Node::new(Server::new("server4".to_string(), None));
+        let node5 = Node::new(Server::new("server5".to_string(), None));
+        let edges = vec![
+            Edge::new(node1.clone(), node2.clone(), 1),
+            Edge::new(node2.clone(), node3.clone(), 2),
+            Edge::new(node2, node5, 2),
+            Edge::new(node1.clone(), node4, 1),
+        ];
+        SpanningTree::new(node1, edges)
+    }
+
+    fn names(servers: Vec<Server>) -> Vec<String> {
+        servers.into_iter().map(|s| s.servername).collect()
+    }
+
+    #[test]
+    fn next_hops_write_once_to_each_link() {
+        let st = routed_tree();
+        let targets = ["server3", "server5", "server4", "server1"].map(String::from);
+
+        assert_eq!(
+            names(st.next_hops(&targets, "server1")),
+            vec!["server2", "server4"]
+        );
+    }
+
+    #[test]
+    fn next_hops_dont_go_back_to_the_origin() {
+        let st = routed_tree();
+        let targets = ["server3", "server4"].map(String::from);
+
+        assert_eq!(names(st.next_hops(&targets, "server5")), vec!["server4"]);
+        assert!(st.next_hops(&targets[..1], "server3").is_empty());
+    }
+
+    #[test]
+    fn unknown_servers_have_no_route() {
+        let st = routed_tree();
+        assert!(st.next_hops(&["server9".to_string()], "server1").is_empty());
+    }
+
+    // server1 is linked to server2 and server3, and both of them to server4
+    fn mesh() -> SpanningTree {
+        let node1 = Node::new(Server::new("server1".to_string(), None));
+        let node2 = Node::new(Server::new("server2".to_string(), None));
+        let node3 = Node::new(Server::new("server3".to_string(), None));
+        let node4 = Node::new(Server::new("server4".to_string(), None));
+        let edges = vec![
+            Edge::new(node1.clone(), node2.clone(), 1),
+            Edge::new(node1.clone(), node3.clone(), 1),
+            Edge::new(node2, node4.clone(), 1),
+            Edge::new(node3, node4, 1),
+        ];
+        SpanningTree::new(node1, edges)
+    }
+
+    #[test]
+    fn tree_leaves_the_redundant_link_on_standby() {
+        let st = mesh();
+        let tree = st.get_tree_edges();
+
+        assert_eq!(tree.len(), 3);
+        assert!(!tree.iter().any(|e| e.connects("server3", "server4")));
+        assert_eq!(
+            names(st.next_hops(&["server4".to_string()], "server1")),
+            vec!["server2"]
+        );
+    }
+
+    #[test]
+    fn removed_link_fails_over_to_the_standby_one() {
+        let mut st = mesh();
+
+        assert_eq!(st.remove_link("server2", "server4"), Some(vec![]));
+        assert!(st.contains("server4".to_string()));
+        assert_eq!(
+            names(st.next_hops(&["server4".to_string()], "server1")),
+            vec!["server3"]
+        );
+    }
+
+    #[test]
+    fn removed_last_link_loses_the_servers() {
+        let mut st = mesh();
+        st.remove_link("server2", "server4");
+
+        let mut lost = st.remove_link("server1", "server3").unwrap();
+        lost.sort();
+        assert_eq!(lost, vec!["server3", "server4"]);
+        assert_eq!(st.get_edges().len(), 1);
+    }
+
+    #[test]
+    fn unknown_link_is_not_removed() {
+        let mut st = mesh();
+
+        assert_eq!(st.remove_link("server1", "server4"), None);
+        assert_eq!(st.get_edges().len(), 4);
+    }
+
+    #[test]
+    fn hopcounts_follow_the_tree() {
+        let st = routed_tree();
+        let hopcounts = st.get_hopcounts();
+
+        assert_eq!(hopcounts.len(), 5);
+        assert_eq!(
+            hopcounts[0],
+            ("server1".to_string(), "server1".to_string(), 0)
+        );
+        assert!(hopcounts.contains(&("server3".to_string(), "server2".to_string(), 2)));
+        assert!(hopcounts.contains(&("server4".to_string(), "server1".to_string(), 1)));
+    }
+
+    #[test]
+    fn display_draws_the_tree() {
+        let st = routed_tree();
+        assert_eq!(
+            st.to_string(),
+            "server1\n|- server2\n|  |- server3\n|  `- server5\n`- server4\n"
+        );
+    }
 }
@@ -0,0 +1,403 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    channel::{
+        member_status::{MemberStatus, Rank},
+        mode_parser::takes_parameter,
+    },
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+};
+
+const SID_LENGTH: usize = 3;
+const UID_LENGTH: usize = 9;
+const ID_CHARS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+///
+/// returns true if the text is a valid server id:
+/// a digit followed by two uppercase letters or digits
+///
+pub fn is_server_id(sid: &str) -> bool {
+    sid.len() == SID_LENGTH
+        && sid.starts_with(|c: char| c.is_ascii_digit())
+        && sid.bytes().all(|c| ID_CHARS.contains(&c))
+}
+
+///
+/// returns true if the text is a user id: the id
+/// of its server followed by six letters or digits
+///
+pub fn is_user_id(uid: &str) -> bool {
+    uid.len() == UID_LENGTH
+        && uid.is_char_boundary(SID_LENGTH)
+        && is_server_id(&uid[..SID_LENGTH])
+        && uid.bytes().all(|c| ID_CHARS.contains(&c))
+}
+
+///
+/// id of a server that has none configured,
+/// derived from its servername
+///
+pub fn default_server_id(servername: &str) -> String {
+    let hash = servername.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    });
+    let mut sid = String::with_capacity(SID_LENGTH);
+    sid.push(ID_CHARS[(hash % 10) as usize] as char);
+    sid.push(ID_CHARS[(hash / 10 % 36) as usize] as char);
+    sid.push(ID_CHARS[(hash / 360 % 36) as usize] as char);
+    sid
+}
+
+///
+/// struct that allocates the ids of the users
+/// registered in this server, in order
+///
+#[derive(Debug, Default)]
+pub struct UidGenerator {
+    sid: Option<String>,
+    next: u64,
+}
+
+impl UidGenerator {
+    pub fn new() -> Self {
+        UidGenerator { sid: None, next: 0 }
+    }
+
+    ///
+    /// returns the id of the server. It is fixed by the first
+    /// call, so the users keep the same one after a rehash
+    ///
+    pub fn server_id<F: FnOnce() -> String>(&mut self, sid: F) -> String {
+        self.sid.get_or_insert_with(sid).clone()
+    }
+
+    ///
+    /// returns a new user id for the server
+    /// with the given id
+    ///
+    pub fn next_uid(&mut self, sid: &str) -> String {
+        let mut id = self.next;
+        self.next = (self.next + 1) % 36u64.pow((UID_LENGTH - SID_LENGTH) as u32);
+        let mut suffix = [b'A'; UID_LENGTH - SID_LENGTH];
+        for c in suffix.iter_mut().rev() {
+            *c = ID_CHARS[(id % 36) as usize];
+            id /= 36;
+        }
+        format!("{sid}{}", String::from_utf8_lossy(&suffix))
+    }
+}
+
+///
+/// Index of the nicknames of the users by their ids. An entry
+/// that no longer names the user, after a nick change or a quit,
+/// is refreshed from the repository of users.
+///
+#[derive(Debug, Clone, Default)]
+pub struct UidIndex {
+    nicknames: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl UidIndex {
+    pub fn new() -> Self {
+        UidIndex {
+            nicknames: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    ///
+    /// returns the current nickname of the
+    /// user with the given id, if it is known
+    ///
+    pub fn nickname(
+        &self,
+        uid: &str,
+        nick_sender: &ClientChannel<String, Connection>,
+    ) -> Option<String> {
+        let mut nicknames = self
+            .nicknames
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(nickname) = nicknames.get(uid) {
+            if let Ok(Some(user)) = nick_sender.search(nickname.clone()) {
+                if user.get_uid() == Some(uid) {
+                    return Some(nickname.clone());
+                }
+            }
+        }
+        *nicknames = nick_sender
+            .find_all()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|user| Some((user.get_uid()?.to_string(), user.get_nickname())))
+            .collect();
+        nicknames.get(uid).cloned()
+    }
+}
+
+///
+/// Replaces the nicknames of a message that is sent to another
+/// server with the ids of the users. It is applied once, when
+/// the message is built. Users without an id keep their nickname.
+///
+pub fn to_uids(line: &str, nick_sender: &ClientChannel<String, Connection>) -> String {
+    translate(line, |nickname| {
+        match nick_sender.search(nickname.to_string()) {
+            Ok(Some(connection)) => connection.get_uid().map(str::to_string),
+            _ => None,
+        }
+    })
+}
+
+///
+/// Replaces the user ids of a message received from another
+/// server with the current nicknames of the users.
+///
+pub fn to_nicknames(
+    line: &str,
+    nick_sender: &ClientChannel<String, Connection>,
+    index: &UidIndex,
+) -> String {
+    translate(line, |uid| match is_user_id(uid) {
+        true => index.nickname(uid, nick_sender),
+        false => None,
+    })
+}
+
+// Applies the lookup to the prefix and to the parameters of the message
+// that name users. The text of the trailing parameter is never changed,
+// except for the members of a SJOIN.
+fn translate<F: FnMut(&str) -> Option<String>>(line: &str, mut lookup: F) -> String {
+    let line = line.trim();
+    let (head, trailing) = match line.get(1..).and_then(|rest| rest.find(" :")) {
+        Some(position) => line.split_at(position + 1),
+        None => (line, ""),
+    };
+    let mut words: Vec<String> = head.split_whitespace().map(String::from).collect();
+    let prefixed = words.first().is_some_and(|word| word.starts_with(':'));
+    let command = match words.get(usize::from(prefixed)) {
+        Some(command) => command.to_uppercase(),
+        None => return line.to_string(),
+    };
+    // a USER message introduces the nickname, it has no id to refer to yet
+    if prefixed && command != "USER" {
+        let (nickname, host) = match words[0][1..].split_once('!') {
+            Some((nickname, host)) => (nickname.to_string(), format!("!{host}")),
+            None => (words[0][1..].to_string(), String::new()),
+        };
+        if let Some(id) = lookup(&nickname) {
+            words[0] = format!(":{id}{host}");
+        }
+    }
+    let first = usize::from(prefixed) + 1;
+    for position in nickname_positions(&command, &words[first..]) {
+        let list = &words[first + position];
+        let list: Vec<String> = list
+            .split(',')
+            .map(|name| lookup(name).unwrap_or_else(|| name.to_string()))
+            .collect();
+        words[first + position] = list.join(",");
+    }
+    let mut line = words.join(" ");
+    match command.as_str() {
+        "SJOIN" if !trailing.is_empty() => {
+            let members: Vec<String> = trailing[2..]
+                .split_whitespace()
+                .map(|member| {
+                    let (_, nickname) = MemberStatus::parse(member);
+                    let status = &member[..member.len() - nickname.len()];
+                    match lookup(nickname) {
+                        Some(id) => format!("{status}{id}"),
+                        None => member.to_string(),
+                    }
+                })
+                .collect();
+            line.push_str(" :");
+            line.push_str(&members.join(" "));
+        }
+        _ => line.push_str(trailing),
+    }
+    line
+}
+
+// Positions of the parameters that hold nicknames.
+fn nickname_positions(command: &str, parameters: &[String]) -> Vec<usize> {
+    match command {
+        "PRIVMSG" | "NOTICE" | "INVITE" | "KILL" | "REPLY" => vec![0],
+        "KICK" => vec![1],
+        "MODE" => match parameters.first() {
+            Some(target) if target.starts_with(['#', '&']) => {
+                let flags = parameters.get(1).map(String::as_str).unwrap_or_default();
+                let mut positions = vec![];
+                let mut next = 2;
+                let mut add = true;
+                for mode in flags.chars() {
+                    match mode {
+                        '+' => add = true,
+                        '-' => add = false,
+                        _ if Rank::from_mode(mode).is_some() => {
+                            positions.push(next);
+                            next += 1;
+                        }
+                        _ if takes_parameter(add, mode) => next += 1,
+                        _ => {}
+                    }
+                }
+                positions.retain(|position| *position < parameters.len());
+                positions
+            }
+            Some(_) => vec![0],
+            None => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::{database::RepositoryHandler, user::User};
+
+    use super::*;
+
+    fn ids() -> HashMap<&'static str, &'static str> {
+        HashMap::from([("alice", "0ABAAAAAA"), ("bob", "0ABAAAAAB")])
+    }
+
+    fn outgoing(line: &str) -> String {
+        let ids = ids();
+        translate(line, |nickname| ids.get(nickname).map(|id| id.to_string()))
+    }
+
+    fn incoming(line: &str) -> String {
+        let ids = ids();
+        translate(line, |uid| {
+            ids.iter()
+                .find(|(_, id)| **id == uid)
+                .map(|(nickname, _)| nickname.to_string())
+        })
+    }
+
+    #[test]
+    fn ids_are_allocated_in_order() {
+        let mut uids = UidGenerator::new();
+        assert_eq!(uids.next_uid("0AB"), "0AB000000");
+        assert_eq!(uids.next_uid("0AB"), "0AB000001");
+        assert!(is_user_id(&uids.next_uid("0AB")));
+    }
+
+    #[test]
+    fn server_id_is_kept() {
+        let mut uids = UidGenerator::new();
+        assert_eq!(uids.server_id(|| "0AB".to_string()), "0AB");
+        assert_eq!(uids.server_id(|| "1CD".to_string()), "0AB");
+    }
+
+    #[test]
+    fn default_server_id_is_valid_and_stable() {
+        let sid = default_server_id("server1");
+        assert!(is_server_id(&sid));
+        assert_eq!(sid, default_server_id("server1"));
+        assert_ne!(sid, default_server_id("server2"));
+    }
+
+    #[test]
+    fn invalid_ids_are_rejected() {
+        assert!(!is_server_id("A01"));
+        assert!(!is_server_id("0a1"));
+        assert!(!is_user_id("alice"));
+        assert!(!is_user_id("0AB00000a"));
+    }
+
+    #[test]
+    fn prefix_and_targets_are_translated() {
+        let line = ":alice PRIVMSG bob,#c,carol :hi bob";
+        assert_eq!(
+            outgoing(line),
+            ":0ABAAAAAA PRIVMSG 0ABAAAAAB,#c,carol :hi bob"
+        );
+        assert_eq!(incoming(&outgoing(line)), line);
+    }
+
+    #[test]
+    fn kick_and_channel_modes_are_translated() {
+        assert_eq!(
+            outgoing(":alice KICK #c bob :bye alice"),
+            ":0ABAAAAAA KICK #c 0ABAAAAAB :bye alice"
+        );
+        assert_eq!(
+            outgoing(":alice MODE #c +kov bob alice bob"),
+            ":0ABAAAAAA MODE #c +kov bob 0ABAAAAAA 0ABAAAAAB"
+        );
+        assert_eq!(
+            outgoing(":alice MODE #c -l+h-b bob *!*@x"),
+            ":0ABAAAAAA MODE #c -l+h-b 0ABAAAAAB *!*@x"
+        );
+        assert_eq!(
+            outgoing(":alice MODE alice +i"),
+            ":0ABAAAAAA MODE 0ABAAAAAA +i"
+        );
+    }
+
+    #[test]
+    fn relayed_replies_are_translated() {
+        let line = ":server2 REPLY alice :391 server2 :bob's time";
+        assert_eq!(
+            outgoing(line),
+            ":server2 REPLY 0ABAAAAAA :391 server2 :bob's time"
+        );
+        assert_eq!(incoming(&outgoing(line)), line);
+    }
+
+    #[test]
+    fn sjoin_members_are_translated() {
+        let line = ":server1 SJOIN 10 #c +t :@alice +bob carol";
+        assert_eq!(
+            outgoing(line),
+            ":server1 SJOIN 10 #c +t :@0ABAAAAAA +0ABAAAAAB carol"
+        );
+        assert_eq!(incoming(&outgoing(line)), line);
+    }
+
+    #[test]
+    fn user_introduction_keeps_the_nickname() {
+        let line = ":alice USER alice host server1 10 0ABAAAAAA :Alice";
+        assert_eq!(outgoing(line), line);
+    }
+
+    #[test]
+    fn index_follows_nick_changes() {
+        let nicks = RepositoryHandler::new().unwrap();
+        let nick_sender = nicks.get_channels();
+        let mut user = User::new("alice", "alice", "host", "server2", "Alice", "");
+        user.set_uid("0ABAAAAAA");
+        let connection = Connection::connection_away_server(user);
+        nick_sender
+            .add("alice".to_string(), connection.clone())
+            .unwrap();
+        let index = UidIndex::new();
+        let line = ":0ABAAAAAA PRIVMSG #c :hi 0ABAAAAAA";
+        assert_eq!(
+            to_nicknames(line, &nick_sender, &index),
+            ":alice PRIVMSG #c :hi 0ABAAAAAA"
+        );
+        let mut renamed = connection;
+        renamed.set_nickname("ally");
+        nick_sender.add("ally".to_string(), renamed).unwrap();
+        nick_sender.delete("alice".to_string()).unwrap();
+        assert_eq!(
+            index.nickname("0ABAAAAAA", &nick_sender).as_deref(),
+            Some("ally")
+        );
+        assert_eq!(index.nickname("0ABAAAAAB", &nick_sender), None);
+        nicks.stop().unwrap();
+    }
+}
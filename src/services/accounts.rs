@@ -0,0 +1,281 @@
+use std::{collections::HashMap, fmt, fs, io::ErrorKind};
+
+use crypto::pbkdf2::{pbkdf2_check, pbkdf2_simple};
+
+use crate::error::{error_config::ErrorConfig, error_server::ErrorServer};
+
+// rounds of the hash of the passwords
+#[cfg(not(test))]
+const HASH_ITERATIONS: u32 = 10_000;
+// tests only need a hash, not a slow one
+#[cfg(test)]
+const HASH_ITERATIONS: u32 = 1;
+
+///
+/// Account registered with the services. It owns its name as a
+/// nickname, and the other nicknames grouped to it. Only the
+/// hash of its password is kept.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Account {
+    pub name: String,
+    password: String,
+    pub registered_at: u64,
+    nicks: Vec<String>,
+}
+
+impl Account {
+    pub fn new(name: &str, password: &str, registered_at: u64) -> Result<Self, ErrorServer> {
+        Ok(Account {
+            name: name.to_string(),
+            password: pbkdf2_simple(password, HASH_ITERATIONS)?,
+            registered_at,
+            nicks: vec![],
+        })
+    }
+
+    ///
+    /// returns true if the password
+    /// is the one of the account
+    ///
+    pub fn check_password(&self, password: &str) -> bool {
+        pbkdf2_check(password, &self.password).unwrap_or(false)
+    }
+
+    pub fn set_password(&mut self, password: &str) -> Result<(), ErrorServer> {
+        self.password = pbkdf2_simple(password, HASH_ITERATIONS)?;
+        Ok(())
+    }
+
+    ///
+    /// returns the nicknames grouped
+    /// to the account, besides its name
+    ///
+    pub fn nicks(&self) -> &[String] {
+        &self.nicks
+    }
+
+    pub fn group(&mut self, nickname: &str) {
+        if !self.owns(nickname) {
+            self.nicks.push(nickname.to_string());
+        }
+    }
+
+    ///
+    /// returns true if the nickname is the
+    /// name of the account or one grouped to it
+    ///
+    pub fn owns(&self, nickname: &str) -> bool {
+        self.name.eq_ignore_ascii_case(nickname)
+            || self.nicks.iter().any(|n| n.eq_ignore_ascii_case(nickname))
+    }
+}
+
+///
+/// converts the account into the lines in which it is persisted:
+/// `ACCOUNT <name> <registered_at> <hash>` followed by a
+/// `GROUP <name> <nickname>` for each grouped nickname
+///
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.name;
+        writeln!(f, "ACCOUNT {name} {} {}", self.registered_at, self.password)?;
+        for nickname in &self.nicks {
+            writeln!(f, "GROUP {name} {nickname}")?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Struct that stores the accounts registered with the services.
+/// When it is created with a file, every change is written back
+/// to it, so the accounts survive a restart.
+///
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct AccountRegistry {
+    accounts: HashMap<String, Account>,
+    path: Option<String>,
+}
+
+impl AccountRegistry {
+    ///
+    /// creates an empty registry,
+    /// kept only in memory
+    ///
+    pub fn new() -> Self {
+        AccountRegistry::default()
+    }
+
+    ///
+    /// Reads the accounts persisted on the file. If the file doesn't exist
+    /// yet, the registry starts empty and creates it on the first change.
+    ///
+    pub fn load(path: &str) -> Result<Self, ErrorConfig> {
+        let mut registry = AccountRegistry {
+            accounts: HashMap::new(),
+            path: Some(path.to_string()),
+        };
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(registry),
+            Err(e) => return Err(e.into()),
+        };
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if registry.read_line(line).is_none() {
+                return Err(ErrorConfig::MalformedLine(number + 1));
+            }
+        }
+        Ok(registry)
+    }
+
+    ///
+    /// returns the account with the name
+    ///
+    pub fn find(&self, name: &str) -> Option<Account> {
+        self.accounts.get(&name.to_lowercase()).cloned()
+    }
+
+    ///
+    /// returns the account that owns the nickname, either
+    /// as its name or as one of its grouped nicknames
+    ///
+    pub fn owner_of(&self, nickname: &str) -> Option<Account> {
+        self.accounts
+            .values()
+            .find(|account| account.owns(nickname))
+            .cloned()
+    }
+
+    ///
+    /// Returns the account that owns the nickname
+    /// if the password is the one of the account.
+    ///
+    pub fn authenticate(&self, nickname: &str, password: &str) -> Option<Account> {
+        self.owner_of(nickname)
+            .filter(|account| account.check_password(password))
+    }
+
+    ///
+    /// Registers the account.
+    ///
+    /// # Returns
+    /// false if its name is already owned by another account.
+    pub fn register(&mut self, account: Account) -> Result<bool, ErrorServer> {
+        if self.owner_of(&account.name).is_some() {
+            return Ok(false);
+        }
+        self.accounts.insert(account.name.to_lowercase(), account);
+        self.save()?;
+        Ok(true)
+    }
+
+    ///
+    /// replaces the account with the new one
+    ///
+    pub fn update(&mut self, account: Account) -> Result<(), ErrorServer> {
+        self.accounts.insert(account.name.to_lowercase(), account);
+        self.save()
+    }
+
+    ///
+    /// forgets the account, returns
+    /// false if it wasn't registered
+    ///
+    pub fn unregister(&mut self, name: &str) -> Result<bool, ErrorServer> {
+        if self.accounts.remove(&name.to_lowercase()).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    // Reads a persisted line, a group must be
+    // of an account registered in a previous line.
+    fn read_line(&mut self, line: &str) -> Option<()> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["ACCOUNT", name, registered_at, password] => {
+                let account = Account {
+                    name: name.to_string(),
+                    password: password.to_string(),
+                    registered_at: registered_at.parse().ok()?,
+                    nicks: vec![],
+                };
+                self.accounts.insert(name.to_lowercase(), account);
+            }
+            ["GROUP", name, nickname] => {
+                let account = self.accounts.get_mut(&name.to_lowercase())?;
+                account.group(nickname);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn save(&self) -> Result<(), ErrorServer> {
+        if let Some(path) = &self.path {
+            let mut accounts: Vec<&Account> = self.accounts.values().collect();
+            accounts.sort_by(|a, b| a.name.cmp(&b.name));
+            let content: String = accounts.iter().map(|a| a.to_string()).collect();
+            fs::write(path, content)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+
+    use super::{Account, AccountRegistry};
+
+    #[test]
+    fn only_the_hash_of_the_password_is_kept() {
+        let mut account = Account::new("ana", "secreta", 0).unwrap();
+        assert!(!account.to_string().contains("secreta"));
+        assert!(account.check_password("secreta"));
+        assert!(!account.check_password("otra"));
+
+        account.set_password("otra").unwrap();
+        assert!(account.check_password("otra"));
+    }
+
+    #[test]
+    fn grouped_nicknames_belong_to_the_account() {
+        let mut registry = AccountRegistry::new();
+        let mut account = Account::new("ana", "secreta", 0).unwrap();
+        account.group("ana_away");
+        assert!(registry.register(account).unwrap());
+
+        assert_eq!(registry.owner_of("ANA_away").unwrap().name, "ana");
+        assert!(registry.authenticate("ana_away", "secreta").is_some());
+        assert!(registry.authenticate("ana_away", "otra").is_none());
+        let taken = Account::new("Ana_Away", "clave", 0).unwrap();
+        assert!(!registry.register(taken).unwrap());
+    }
+
+    #[test]
+    fn accounts_survive_a_restart() {
+        let path = env::temp_dir().join(format!("nickserv-{}.db", process::id()));
+        let path = path.to_string_lossy().to_string();
+        let _ = fs::remove_file(&path);
+
+        let mut registry = AccountRegistry::load(&path).unwrap();
+        let mut account = Account::new("ana", "secreta", 100).unwrap();
+        account.group("anita");
+        registry.register(account.clone()).unwrap();
+        registry
+            .register(Account::new("juan", "clave", 200).unwrap())
+            .unwrap();
+        assert!(registry.unregister("JUAN").unwrap());
+
+        let loaded = AccountRegistry::load(&path).unwrap();
+        assert_eq!(loaded.find("ana"), Some(account));
+        assert!(loaded.find("juan").is_none());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,655 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    channel::{
+        member_status::MemberStatus,
+        mode_parser::{mode_line, mode_type, ModeChange, ModeType},
+        Channel,
+    },
+    command::{join::JoinMsg, mode::build_mode, topic::broadcast_topic, Command},
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    user::User,
+    utils::{format_utc, unix_timestamp, write_message_to},
+};
+
+use super::{
+    account_of,
+    registry::{AccessEntry, AccessLevel, ChannelRegistry, RegisteredChannel},
+    service_mask, service_notice, CHANSERV,
+};
+
+const OFF: &str = "OFF";
+
+const HELP: [&str; 6] = [
+    "ChanServ keeps the channels registered with it:",
+    "REGISTER <#channel>",
+    "DROP <#channel>",
+    "INFO <#channel>",
+    "ACCESS <#channel> LIST | ADD <mask> <OP|VOICE|AKICK> | DEL <mask>",
+    "SET <#channel> MLOCK <flags|OFF> | KEY <key|OFF> | TOPIC <topic|OFF>",
+];
+
+///
+/// struct that implements the requests to the ChanServ service,
+/// sent with the CHANSERV command, or its alias CS, or as a
+/// private message to ChanServ:
+///
+/// `CHANSERV <subcommand> <#channel> [parameters]`
+/// `PRIVMSG ChanServ :<subcommand> <#channel> [parameters]`
+///
+pub struct ChanServMsg {
+    subcommand: String,
+    parameters: Vec<String>,
+    forward: Vec<Message>,
+}
+
+impl ChanServMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let skip = match msg.command() {
+            Command::Privmsg => 1,
+            _ => 0,
+        };
+        let mut words = msg
+            .parameters()
+            .unwrap_or_default()
+            .into_iter()
+            .skip(skip)
+            .flat_map(|p| {
+                p.trim_start_matches(':')
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect::<Vec<String>>()
+            });
+        Ok(ChanServMsg {
+            subcommand: words.next().unwrap_or_default().to_uppercase(),
+            parameters: words.collect(),
+            forward: vec![],
+        })
+    }
+
+    ///
+    /// returns true if the private message
+    /// is addressed to ChanServ
+    ///
+    pub fn is_addressed(msg: &Message) -> bool {
+        msg.command() == Command::Privmsg
+            && msg
+                .get_param_from_msg(0)
+                .is_some_and(|target| target.eq_ignore_ascii_case(CHANSERV))
+    }
+
+    ///
+    /// returns the messages that announce to the other
+    /// servers the channel changed by the request
+    ///
+    pub fn forward(&self) -> Vec<Message> {
+        self.forward.clone()
+    }
+
+    ///
+    /// Answers the request of the user with the notices of ChanServ.
+    /// The operators of the server can drop and change any channel.
+    ///
+    pub fn response(
+        &mut self,
+        user: &User,
+        oper: bool,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        registry: &Arc<Mutex<ChannelRegistry>>,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let texts = match (self.subcommand.as_str(), self.parameters.first()) {
+            ("HELP" | "", _) => HELP.iter().map(|line| line.to_string()).collect(),
+            ("REGISTER" | "DROP" | "INFO" | "ACCESS" | "SET", None) => {
+                vec![format!("Insufficient parameters for {}.", self.subcommand)]
+            }
+            ("REGISTER", Some(name)) => self.register(user, name, channel_sender, registry)?,
+            (_, Some(name)) => {
+                let registered = match registry.lock() {
+                    Ok(registry) => registry.find(name),
+                    Err(_) => return Err(ErrorServer::LockedResource),
+                };
+                match registered {
+                    Some(registered) => self.manage(
+                        user,
+                        oper,
+                        registered,
+                        nick_sender,
+                        channel_sender,
+                        registry,
+                    )?,
+                    None if self.is_known() => vec![format!("{name} is not registered.")],
+                    None => vec![self.unknown()],
+                }
+            }
+            _ => vec![self.unknown()],
+        };
+        let servername = user.servername().unwrap_or_default();
+        let nickname = user.nickname().unwrap_or_default();
+        Ok(texts
+            .iter()
+            .map(|text| service_notice(CHANSERV, servername, nickname, text))
+            .collect())
+    }
+
+    // The channel is registered to the user, who must be one of its operators.
+    fn register(
+        &self,
+        user: &User,
+        name: &str,
+        channel_sender: &ClientChannel<String, Channel>,
+        registry: &Arc<Mutex<ChannelRegistry>>,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let nickname = user.nickname().unwrap_or_default();
+        match channel_sender.search(name.to_string())? {
+            Some(channel) if channel.is_channel_operator(nickname) => {}
+            _ => {
+                return Ok(vec![format!(
+                    "You must be an operator of {name} to register it."
+                )])
+            }
+        }
+        let account = account_of(user);
+        if account.is_empty() {
+            return Ok(vec![format!(
+                "You must identify with NickServ to register {name}."
+            )]);
+        }
+        let registered = RegisteredChannel::new(name, &account, unix_timestamp());
+        let new = match registry.lock() {
+            Ok(mut registry) => registry.register(registered)?,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        match new {
+            true => Ok(vec![format!("{name} is now registered to {account}.")]),
+            false => Ok(vec![format!("{name} is already registered.")]),
+        }
+    }
+
+    // The subcommands on a registered channel.
+    fn manage(
+        &mut self,
+        user: &User,
+        oper: bool,
+        mut registered: RegisteredChannel,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        registry: &Arc<Mutex<ChannelRegistry>>,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let name = registered.name.clone();
+        let level = registered.level_of(&account_of(user), &user.mask());
+        let founder = oper || registered.is_founder(&account_of(user));
+        let manager = founder || level == Some(AccessLevel::Op);
+        let parameters: Vec<&str> = self.parameters[1..].iter().map(String::as_str).collect();
+        let texts = match (self.subcommand.as_str(), parameters.as_slice()) {
+            ("INFO", _) => return Ok(info(&registered)),
+            ("DROP", _) if founder => {
+                match registry.lock() {
+                    Ok(mut registry) => registry.unregister(&name)?,
+                    Err(_) => return Err(ErrorServer::LockedResource),
+                };
+                return Ok(vec![format!("{name} was dropped.")]);
+            }
+            ("ACCESS", [list]) if manager && list.eq_ignore_ascii_case("LIST") => {
+                let mut texts: Vec<String> = registered
+                    .access()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| format!("{} {} {}", i + 1, entry.level, entry.mask))
+                    .collect();
+                texts.push(format!("End of the access list of {name}."));
+                return Ok(texts);
+            }
+            ("ACCESS", [add, mask, level]) if manager && add.eq_ignore_ascii_case("ADD") => {
+                let level = match AccessLevel::from_str(level) {
+                    Ok(level) => level,
+                    Err(_) => return Ok(vec![format!("Invalid access level {level}.")]),
+                };
+                let entry = AccessEntry::new(mask, level);
+                let text = format!("{} was added to {name} as {level}.", entry.mask);
+                registered.set_access(entry);
+                vec![text]
+            }
+            ("ACCESS", [del, mask]) if manager && del.eq_ignore_ascii_case("DEL") => {
+                match registered.remove_access(mask) {
+                    true => vec![format!("{mask} was removed from {name}.")],
+                    false => {
+                        return Ok(vec![format!("{mask} is not on the access list of {name}.")])
+                    }
+                }
+            }
+            ("SET", [setting, values @ ..]) if founder && !values.is_empty() => {
+                let value = values.join(" ");
+                let value = (!value.eq_ignore_ascii_case(OFF)).then_some(value);
+                match setting.to_uppercase().as_str() {
+                    "MLOCK" if value.as_ref().is_none_or(|mlock| is_mode_lock(mlock)) => {
+                        registered.mlock = value
+                    }
+                    "KEY" if values.len() == 1 => registered.key = value,
+                    "TOPIC" => registered.topic = value,
+                    _ => return Ok(vec![format!("Invalid setting {setting}.")]),
+                }
+                vec![format!("{} of {name} was changed.", setting.to_uppercase())]
+            }
+            ("ACCESS", _) if manager => {
+                return Ok(vec!["Invalid parameters for ACCESS.".to_string()])
+            }
+            ("SET", _) if founder => return Ok(vec!["Invalid parameters for SET.".to_string()]),
+            ("DROP" | "ACCESS" | "SET", _) => return Ok(vec!["Access denied.".to_string()]),
+            _ => return Ok(vec![self.unknown()]),
+        };
+        match registry.lock() {
+            Ok(mut registry) => registry.update(registered.clone())?,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        }
+        if let Some(mut channel) = channel_sender.search(name)? {
+            let servername = user.servername().unwrap_or_default();
+            if restore(&registered, &mut channel, servername, nick_sender, vec![])? {
+                channel_sender.update(channel.name.clone(), channel.clone())?;
+                self.forward = announce(&channel, servername);
+            }
+        }
+        Ok(texts)
+    }
+
+    fn is_known(&self) -> bool {
+        HELP.iter()
+            .skip(1)
+            .any(|line| line.starts_with(&format!("{} ", self.subcommand)))
+    }
+
+    fn unknown(&self) -> String {
+        format!("Unknown command {}. Use HELP.", self.subcommand)
+    }
+}
+
+///
+/// Leaves out of the JOIN of a user of this server the registered
+/// channels where it is autokicked, and answers that it is banned.
+///
+pub fn refuse_autokicked(
+    user: &User,
+    join: &mut JoinMsg,
+    registry: &Arc<Mutex<ChannelRegistry>>,
+) -> Result<Vec<Reply>, ErrorServer> {
+    let registry = match registry.lock() {
+        Ok(registry) => registry,
+        Err(_) => return Err(ErrorServer::LockedResource),
+    };
+    let (account, mask) = (account_of(user), user.mask());
+    let mut replies = vec![];
+    join.retain_channels(|name| {
+        let level = registry
+            .find(name)
+            .and_then(|registered| registered.level_of(&account, &mask));
+        if level == Some(AccessLevel::AutoKick) {
+            replies.push(Reply::err_banned_from_ch(vec![name.to_string()]));
+            return false;
+        }
+        true
+    });
+    Ok(replies)
+}
+
+///
+/// Gives the user that joined the registered channels the status of
+/// its access. A channel created by the join gets back its settings,
+/// and its creator loses the operator status if it has no access.
+/// Returns the messages that announce the changed channels to the
+/// other servers.
+///
+pub fn on_join(
+    user: &User,
+    channels: &[String],
+    nick_sender: &ClientChannel<String, Connection>,
+    channel_sender: &ClientChannel<String, Channel>,
+    registry: &Arc<Mutex<ChannelRegistry>>,
+) -> Result<Vec<Message>, ErrorServer> {
+    let nickname = user.nickname().unwrap_or_default();
+    let servername = user.servername().unwrap_or_default();
+    let mut forward = vec![];
+    for name in channels {
+        let registered = match registry.lock() {
+            Ok(registry) => registry.find(name),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        let (registered, mut channel) = match (registered, channel_sender.search(name.clone())?) {
+            (Some(registered), Some(channel)) if channel.has_member(nickname) => {
+                (registered, channel)
+            }
+            _ => continue,
+        };
+        let created = channel.member_amount() == 1;
+        let status = |add: bool, mode: char| ModeChange {
+            add,
+            mode,
+            param: Some(nickname.to_string()),
+        };
+        let level = registered.level_of(&account_of(user), &user.mask());
+        let mut changes = vec![];
+        if created && level != Some(AccessLevel::Op) {
+            channel.join_with_status(nickname, MemberStatus::default());
+            changes.push(status(false, 'o'));
+        }
+        let mode = match level {
+            Some(AccessLevel::Op) => Some('o'),
+            Some(AccessLevel::Voice) => Some('v'),
+            _ => None,
+        };
+        if let Some(mode) = mode {
+            changes.append(&mut channel.apply_modes(&[status(true, mode)]));
+        }
+        let changed = match created {
+            true => restore(&registered, &mut channel, servername, nick_sender, changes)?,
+            false => tell_modes(&channel, servername, &changes, nick_sender)?,
+        };
+        if changed {
+            channel_sender.update(channel.name.clone(), channel.clone())?;
+            forward.append(&mut announce(&channel, servername));
+        }
+    }
+    Ok(forward)
+}
+
+// Sets the kept modes and topic on the channel, along with the given
+// changes, telling them to its local members. Returns true if changed.
+fn restore(
+    registered: &RegisteredChannel,
+    channel: &mut Channel,
+    servername: &str,
+    nick_sender: &ClientChannel<String, Connection>,
+    mut changes: Vec<ModeChange>,
+) -> Result<bool, ErrorServer> {
+    let mut restored = registered.restore_modes(channel);
+    restored.append(&mut changes);
+    let mut changed = tell_modes(channel, servername, &restored, nick_sender)?;
+    let mask = service_mask(CHANSERV, servername);
+    if let Some(topic) = registered.restore_topic(channel, &mask, unix_timestamp()) {
+        broadcast_topic(channel, &mask, &topic.text, nick_sender)?;
+        changed = true;
+    }
+    Ok(changed)
+}
+
+// Writes the MODE with the changes to the local members
+// of the channel. Returns true if there was any.
+fn tell_modes(
+    channel: &Channel,
+    servername: &str,
+    changes: &[ModeChange],
+    nick_sender: &ClientChannel<String, Connection>,
+) -> Result<bool, ErrorServer> {
+    if changes.is_empty() {
+        return Ok(false);
+    }
+    let mut parameters = vec![channel.name.clone()];
+    parameters.append(&mut mode_line(changes));
+    let line = build_mode(&service_mask(CHANSERV, servername), &parameters);
+    for member in channel.return_members() {
+        if let Some(mut connection) = nick_sender.search(member)? {
+            if connection.is_local() {
+                let _ = write_message_to(&line, &mut connection);
+            }
+        }
+    }
+    Ok(true)
+}
+
+// The SJOIN and topic of the channel, so the other servers take its
+// modes and the status of its members.
+fn announce(channel: &Channel, servername: &str) -> Vec<Message> {
+    channel
+        .build_channel_msg(servername)
+        .iter()
+        .filter_map(|msg| Message::from_str(msg).ok())
+        .collect()
+}
+
+// The registration of the channel, as told by INFO.
+fn info(registered: &RegisteredChannel) -> Vec<String> {
+    let mut texts = vec![
+        format!("Information on {}:", registered.name),
+        format!("Founder: {}", registered.founder),
+        format!("Registered: {}", format_utc(registered.registered_at)),
+    ];
+    if let Some(mlock) = &registered.mlock {
+        texts.push(format!("Mode lock: {mlock}"));
+    }
+    if let Some(topic) = &registered.topic {
+        texts.push(format!("Topic: {topic}"));
+    }
+    texts
+}
+
+// A mode lock only sets flags without parameter.
+fn is_mode_lock(mlock: &str) -> bool {
+    match mlock.strip_prefix('+') {
+        Some(flags) if !flags.is_empty() => flags
+            .chars()
+            .all(|mode| mode_type(mode) == Some(ModeType::D)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        channel::{channel_flag::ChannelFlag, Channel},
+        database::RepositoryHandler,
+        parser::message::Message,
+        repository::traits::operations::Operations,
+        services::registry::{AccessLevel, ChannelRegistry},
+        user::User,
+    };
+
+    use super::{on_join, ChanServMsg};
+
+    fn request(
+        line: &str,
+        user: &User,
+        handler: &RepositoryHandler<String, Channel>,
+        registry: &Arc<Mutex<ChannelRegistry>>,
+    ) -> Vec<String> {
+        let nicks = RepositoryHandler::new().unwrap();
+        let msg = Message::from_str(line).unwrap();
+        let lines = ChanServMsg::new(&msg)
+            .unwrap()
+            .response(
+                user,
+                false,
+                &nicks.get_channels(),
+                &handler.get_channels(),
+                registry,
+            )
+            .unwrap();
+        nicks.stop().unwrap();
+        lines
+    }
+
+    fn identified(nickname: &str) -> User {
+        let mut user = User::new(nickname, nickname, "host", "server", nickname, "");
+        user.set_account(Some(nickname.to_string()));
+        user
+    }
+
+    fn setup() -> (
+        RepositoryHandler<String, Channel>,
+        Arc<Mutex<ChannelRegistry>>,
+    ) {
+        let channels = RepositoryHandler::new().unwrap();
+        let channel = Channel::new("#canal".to_string(), "ana".to_string());
+        channels
+            .get_channels()
+            .add("#canal".to_string(), channel)
+            .unwrap();
+        (channels, Arc::new(Mutex::new(ChannelRegistry::new())))
+    }
+
+    #[test]
+    fn only_an_operator_registers_the_channel() {
+        let (channels, registry) = setup();
+        let ana = identified("ana");
+        let juan = identified("juan");
+
+        let unidentified = User::new("ana", "ana", "host", "server", "Ana", "");
+        let lines = request(
+            ":ana CHANSERV REGISTER #canal",
+            &unidentified,
+            &channels,
+            &registry,
+        );
+        assert!(lines[0].ends_with(":You must identify with NickServ to register #canal."));
+        let lines = request(
+            ":juan CHANSERV REGISTER #canal",
+            &juan,
+            &channels,
+            &registry,
+        );
+        assert_eq!(
+            lines,
+            vec![":ChanServ!ChanServ@server NOTICE juan :You must be an operator of #canal to register it."]
+        );
+        let lines = request(
+            ":ana PRIVMSG ChanServ :register #canal",
+            &ana,
+            &channels,
+            &registry,
+        );
+        assert!(lines[0].ends_with(":#canal is now registered to ana."));
+        assert_eq!(
+            registry.lock().unwrap().find("#canal").unwrap().founder,
+            "ana"
+        );
+        channels.stop().unwrap();
+    }
+
+    #[test]
+    fn only_the_managers_change_the_access_list() {
+        let (channels, registry) = setup();
+        let ana = identified("ana");
+        let juan = identified("juan");
+        request(":ana CS REGISTER #canal", &ana, &channels, &registry);
+
+        let lines = request(
+            ":juan CS ACCESS #canal ADD juan OP",
+            &juan,
+            &channels,
+            &registry,
+        );
+        assert!(lines[0].ends_with(":Access denied."));
+        request(
+            ":ana CS ACCESS #canal ADD juan OP",
+            &ana,
+            &channels,
+            &registry,
+        );
+        let lines = request(
+            ":juan CS ACCESS #canal ADD *!*@bad AKICK",
+            &juan,
+            &channels,
+            &registry,
+        );
+        assert!(lines[0].ends_with(":*!*@bad was added to #canal as AKICK."));
+
+        let registered = registry.lock().unwrap().find("#canal").unwrap();
+        assert_eq!(
+            registered.level_of("luis", "luis!luis@bad"),
+            Some(AccessLevel::AutoKick)
+        );
+        let lines = request(
+            ":juan CS SET #canal TOPIC hola",
+            &juan,
+            &channels,
+            &registry,
+        );
+        assert!(lines[0].ends_with(":Access denied."));
+        channels.stop().unwrap();
+    }
+
+    #[test]
+    fn settings_are_applied_to_the_live_channel() {
+        let (channels, registry) = setup();
+        let ana = identified("ana");
+        request(":ana CS REGISTER #canal", &ana, &channels, &registry);
+
+        let lines = request(":ana CS SET #canal MLOCK +kt", &ana, &channels, &registry);
+        assert!(lines[0].ends_with(":Invalid setting MLOCK."));
+        let nicks = RepositoryHandler::new().unwrap();
+        let msg = Message::from_str(":ana CS SET #canal TOPIC hola a todos").unwrap();
+        let mut set = ChanServMsg::new(&msg).unwrap();
+        set.response(
+            &ana,
+            false,
+            &nicks.get_channels(),
+            &channels.get_channels(),
+            &registry,
+        )
+        .unwrap();
+
+        let channel = channels
+            .get_channels()
+            .search("#canal".to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(channel.get_topic(), Some("hola a todos".to_string()));
+        assert_eq!(set.forward().len(), 2);
+        nicks.stop().unwrap();
+        channels.stop().unwrap();
+    }
+
+    #[test]
+    fn creator_without_access_loses_the_operator_status() {
+        let (channels, registry) = setup();
+        let ana = identified("ana");
+        request(":ana CS REGISTER #canal", &ana, &channels, &registry);
+        request(":ana CS SET #canal MLOCK +nt", &ana, &channels, &registry);
+        channels
+            .get_channels()
+            .delete("#canal".to_string())
+            .unwrap();
+
+        let nicks = RepositoryHandler::new().unwrap();
+        let juan = identified("juan");
+        let channel = Channel::new("#canal".to_string(), "juan".to_string());
+        channels
+            .get_channels()
+            .add("#canal".to_string(), channel)
+            .unwrap();
+        let forward = on_join(
+            &juan,
+            &["#canal".to_string()],
+            &nicks.get_channels(),
+            &channels.get_channels(),
+            &registry,
+        )
+        .unwrap();
+
+        let channel = channels
+            .get_channels()
+            .search("#canal".to_string())
+            .unwrap()
+            .unwrap();
+        assert!(!channel.is_channel_operator("juan"));
+        assert_eq!(
+            channel.return_channel_flags(),
+            vec![ChannelFlag::N, ChannelFlag::T]
+        );
+        assert!(forward[0].to_string().contains(" SJOIN "));
+        nicks.stop().unwrap();
+        channels.stop().unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+pub mod accounts;
+pub mod chanserv;
+pub mod nickserv;
+pub mod registry;
+
+use crate::user::User;
+
+/// nickname of the service that keeps the registered channels
+pub const CHANSERV: &str = "ChanServ";
+
+/// nickname of the service that keeps the registered nicknames
+pub const NICKSERV: &str = "NickServ";
+
+// the nicknames no user can take
+const SERVICES: [&str; 2] = [CHANSERV, NICKSERV];
+
+///
+/// returns true if the nickname belongs to
+/// one of the services of the server
+///
+pub fn is_service(nickname: &str) -> bool {
+    SERVICES
+        .iter()
+        .any(|service| service.eq_ignore_ascii_case(nickname))
+}
+
+///
+/// returns the account by which the services know the user,
+/// empty while it hasn't identified with NickServ
+///
+pub fn account_of(user: &User) -> String {
+    user.account().unwrap_or_default().to_string()
+}
+
+///
+/// returns the mask with which the service
+/// sends its messages from the server
+///
+pub fn service_mask(service: &str, servername: &str) -> String {
+    format!("{service}!{service}@{servername}")
+}
+
+///
+/// builds the NOTICE with which the service answers the user
+///
+pub fn service_notice(service: &str, servername: &str, nickname: &str, text: &str) -> String {
+    format!(
+        ":{} NOTICE {nickname} :{text}",
+        service_mask(service, servername)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_service, service_notice};
+
+    #[test]
+    fn service_nicknames_are_reserved() {
+        assert!(is_service("chanserv"));
+        assert!(is_service("NICKSERV"));
+        assert!(!is_service("chanserver"));
+    }
+
+    #[test]
+    fn notice_comes_from_the_service() {
+        assert_eq!(
+            service_notice("ChanServ", "server1", "ana", "hola"),
+            ":ChanServ!ChanServ@server1 NOTICE ana :hola"
+        );
+    }
+}
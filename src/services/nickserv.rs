@@ -0,0 +1,608 @@
+use std::{
+    net::Shutdown,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{
+    channel::Channel,
+    command::{kill::Kill, nick_command::NickCommand, quit::QuitMsg, Command},
+    error::error_server::ErrorServer,
+    parser::message::Message,
+    reply::Reply,
+    repository::{
+        connection::Connection, repository_channel::client_channel::ClientChannel,
+        traits::operations::Operations,
+    },
+    server::state::ServerState,
+    server_comunication::{info_sender::inform_all_servers, spanning_tree::SpanningTree},
+    user::User,
+    utils::{unix_timestamp, write_message_to, write_messages_to},
+};
+
+use super::{
+    accounts::{Account, AccountRegistry},
+    service_notice, NICKSERV,
+};
+
+/// seconds an unidentified user can keep a registered nickname
+pub const DEFAULT_NICK_ENFORCE_DELAY: u64 = 60;
+
+const GUEST_PREFIX: &str = "Guest";
+const LOGGED_OUT: &str = "*";
+
+const HELP: [&str; 7] = [
+    "NickServ keeps the nicknames registered with it:",
+    "REGISTER <password>",
+    "IDENTIFY [account] <password>",
+    "GROUP",
+    "GHOST <nickname> [password]",
+    "DROP",
+    "SET PASSWORD <password>",
+];
+
+///
+/// struct that implements the requests to the NickServ service,
+/// sent with the NICKSERV command, or its alias NS, or as a
+/// private message to NickServ:
+///
+/// `NICKSERV <subcommand> [parameters]`
+/// `PRIVMSG NickServ :<subcommand> [parameters]`
+///
+pub struct NickServMsg {
+    subcommand: String,
+    parameters: Vec<String>,
+    forward: Vec<Message>,
+}
+
+impl NickServMsg {
+    pub fn new(msg: &Message) -> Result<Self, ErrorServer> {
+        let skip = match msg.command() {
+            Command::Privmsg => 1,
+            _ => 0,
+        };
+        let mut words = msg
+            .parameters()
+            .unwrap_or_default()
+            .into_iter()
+            .skip(skip)
+            .flat_map(|p| {
+                p.trim_start_matches(':')
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect::<Vec<String>>()
+            });
+        Ok(NickServMsg {
+            subcommand: words.next().unwrap_or_default().to_uppercase(),
+            parameters: words.collect(),
+            forward: vec![],
+        })
+    }
+
+    ///
+    /// returns true if the private message
+    /// is addressed to NickServ
+    ///
+    pub fn is_addressed(msg: &Message) -> bool {
+        msg.command() == Command::Privmsg
+            && msg
+                .get_param_from_msg(0)
+                .is_some_and(|target| target.eq_ignore_ascii_case(NICKSERV))
+    }
+
+    ///
+    /// returns the messages that announce to the other
+    /// servers the account the user logged in or out
+    ///
+    pub fn forward(&self) -> Vec<Message> {
+        self.forward.clone()
+    }
+
+    ///
+    /// Answers the request of the user with the notices of NickServ.
+    /// Logging in or out changes the account of the user and of its
+    /// connection.
+    ///
+    pub fn response(
+        &mut self,
+        user: &mut User,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        state: &ServerState,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let nickname = user.nickname().unwrap_or_default().to_string();
+        let identified = user.account().map(str::to_string);
+        let parameters: Vec<String> = self.parameters.clone();
+        let parameters: Vec<&str> = parameters.iter().map(String::as_str).collect();
+        let texts = match (self.subcommand.as_str(), parameters.as_slice(), identified) {
+            ("HELP" | "", _, _) => HELP.iter().map(|line| line.to_string()).collect(),
+            ("REGISTER", [password], None) => {
+                let account = Account::new(&nickname, password, unix_timestamp())?;
+                let registered = lock(&state.accounts)?.register(account)?;
+                match registered {
+                    true => {
+                        let mut texts = vec![format!("{nickname} is now registered.")];
+                        let account = Some(nickname.clone());
+                        texts.append(&mut self.log_in(user, account, nick_sender, state)?);
+                        texts
+                    }
+                    false => vec![format!("{nickname} is already registered.")],
+                }
+            }
+            ("IDENTIFY", [password], _) => {
+                self.identify(user, &nickname, password, nick_sender, state)?
+            }
+            ("IDENTIFY", [account, password], _) => {
+                self.identify(user, account, password, nick_sender, state)?
+            }
+            ("GHOST", [ghost, password @ ..], identified) if password.len() <= 1 => {
+                let owner = lock(&state.accounts)?.owner_of(ghost);
+                let allowed = owner.as_ref().is_some_and(|owner| {
+                    identified.is_some_and(|account| account.eq_ignore_ascii_case(&owner.name))
+                        || password
+                            .first()
+                            .is_some_and(|password| owner.check_password(password))
+                });
+                match nick_sender.search(ghost.to_string())? {
+                    _ if ghost.eq_ignore_ascii_case(&nickname) => {
+                        vec!["You can't ghost yourself.".to_string()]
+                    }
+                    _ if owner.is_none() => vec![format!("{ghost} is not registered.")],
+                    _ if !allowed => vec!["Access denied.".to_string()],
+                    Some(connection) => {
+                        let reason = format!("GHOST command used by {nickname}");
+                        disconnect(
+                            connection,
+                            &reason,
+                            nick_sender,
+                            channel_sender,
+                            spanning_tree,
+                        )?;
+                        vec![format!("{ghost} was disconnected.")]
+                    }
+                    None => vec![format!("{ghost} is not online.")],
+                }
+            }
+            ("GROUP", [], Some(account)) => {
+                let mut accounts = lock(&state.accounts)?;
+                match (accounts.owner_of(&nickname), accounts.find(&account)) {
+                    (Some(owner), _) if owner.name == account => {
+                        vec![format!("{nickname} is already grouped to {account}.")]
+                    }
+                    (Some(_), _) => vec![format!("{nickname} is already registered.")],
+                    (None, Some(mut owner)) => {
+                        owner.group(&nickname);
+                        accounts.update(owner)?;
+                        vec![format!("{nickname} is now grouped to {account}.")]
+                    }
+                    (None, None) => vec![format!("{account} is not registered.")],
+                }
+            }
+            ("DROP", [], Some(account)) => {
+                lock(&state.accounts)?.unregister(&account)?;
+                let mut texts = vec![format!("{account} was dropped.")];
+                texts.append(&mut self.log_in(user, None, nick_sender, state)?);
+                texts
+            }
+            ("SET", [setting, password], Some(account))
+                if setting.eq_ignore_ascii_case("PASSWORD") =>
+            {
+                let mut accounts = lock(&state.accounts)?;
+                match accounts.find(&account) {
+                    Some(mut owner) => {
+                        owner.set_password(password)?;
+                        accounts.update(owner)?;
+                        vec![format!("Password of {account} was changed.")]
+                    }
+                    None => vec![format!("{account} is not registered.")],
+                }
+            }
+            ("REGISTER", [_], Some(account)) => {
+                vec![format!("You are already identified as {account}.")]
+            }
+            ("GROUP" | "DROP" | "SET", _, None) => vec!["You are not identified.".to_string()],
+            ("REGISTER" | "IDENTIFY" | "GHOST" | "SET", _, _) => {
+                vec![format!("Invalid parameters for {}.", self.subcommand)]
+            }
+            _ => vec![format!("Unknown command {}. Use HELP.", self.subcommand)],
+        };
+        let servername = user.servername().unwrap_or_default();
+        Ok(texts
+            .iter()
+            .map(|text| service_notice(NICKSERV, servername, &nickname, text))
+            .collect())
+    }
+
+    // Logs in the account that owns the name if the password is right.
+    fn identify(
+        &mut self,
+        user: &mut User,
+        name: &str,
+        password: &str,
+        nick_sender: &ClientChannel<String, Connection>,
+        state: &ServerState,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let account = lock(&state.accounts)?.authenticate(name, password);
+        match account {
+            Some(account) => {
+                let mut texts = vec![format!("You are now identified for {}.", account.name)];
+                texts.append(&mut self.log_in(user, Some(account.name), nick_sender, state)?);
+                Ok(texts)
+            }
+            None if lock(&state.accounts)?.owner_of(name).is_some() => {
+                Ok(vec![format!("Invalid password for {name}.")])
+            }
+            None => Ok(vec![format!("{name} is not registered.")]),
+        }
+    }
+
+    // Changes the account of the user, and tells if it
+    // must still identify for its nickname.
+    fn log_in(
+        &mut self,
+        user: &mut User,
+        account: Option<String>,
+        nick_sender: &ClientChannel<String, Connection>,
+        state: &ServerState,
+    ) -> Result<Vec<String>, ErrorServer> {
+        let nickname = user.nickname().unwrap_or_default().to_string();
+        user.set_account(account.clone());
+        if let Some(mut connection) = nick_sender.search(nickname.clone())? {
+            connection.set_account(account.clone());
+            nick_sender.update(nickname.clone(), connection)?;
+        }
+        self.forward
+            .push(account_msg(&nickname, account.as_deref())?);
+        check_nickname(user, state)
+    }
+}
+
+///
+/// builds the message with which the servers tell each
+/// other the account the user logged in, or `*` if it
+/// logged out: `:<nickname> ACCOUNT <account>`
+///
+pub fn account_msg(nickname: &str, account: Option<&str>) -> Result<Message, ErrorServer> {
+    let account = account.unwrap_or(LOGGED_OUT);
+    Ok(Message::from_str(&format!(
+        ":{nickname} ACCOUNT {account}"
+    ))?)
+}
+
+///
+/// Applies the ACCOUNT message of another server
+/// to the connection of the user.
+///
+pub fn account_change(
+    msg: &Message,
+    nick_sender: &ClientChannel<String, Connection>,
+) -> Result<(), ErrorServer> {
+    let (nickname, account) = match (msg.prefix(), msg.get_param_from_msg(0)) {
+        (Some(nickname), Some(account)) => (nickname, account),
+        _ => return Err(ErrorServer::BadQuery),
+    };
+    if let Some(mut connection) = nick_sender.search(nickname.clone())? {
+        connection.set_account((account != LOGGED_OUT).then_some(account));
+        nick_sender.update(nickname, connection)?;
+    }
+    Ok(())
+}
+
+///
+/// Checks the nickname of a local user against the accounts. If it is
+/// registered to an account the user isn't identified to, it has
+/// until the delay ends to identify, and is told so.
+///
+pub fn check_nickname(user: &User, state: &ServerState) -> Result<Vec<String>, ErrorServer> {
+    let nickname = user.nickname().unwrap_or_default();
+    let uid = user.uid().unwrap_or_default();
+    let owner = lock(&state.accounts)?.owner_of(nickname);
+    let delay = state.nick_enforce_delay()?;
+    let mut enforcement = lock(&state.enforcement)?;
+    match owner {
+        Some(owner)
+            if !user
+                .account()
+                .is_some_and(|a| a.eq_ignore_ascii_case(&owner.name)) =>
+        {
+            if enforcement.watch(nickname, uid, unix_timestamp() + delay) {
+                return Ok(vec![format!(
+                    "{nickname} is registered. Identify with IDENTIFY <password> \
+                     within {delay} seconds or your nickname will be changed."
+                )]);
+            }
+        }
+        _ => enforcement.release(uid),
+    }
+    Ok(vec![])
+}
+
+///
+/// returns the notices of NickServ that
+/// check_nickname has for the user
+///
+pub fn check_nickname_notices(
+    user: &User,
+    state: &ServerState,
+) -> Result<Vec<String>, ErrorServer> {
+    let servername = user.servername().unwrap_or_default();
+    let nickname = user.nickname().unwrap_or_default();
+    Ok(check_nickname(user, state)?
+        .iter()
+        .map(|text| service_notice(NICKSERV, servername, nickname, text))
+        .collect())
+}
+
+///
+/// Changes the nickname of the user that didn't identify in time
+/// for it to a guest one. If it can't be changed, the user is
+/// disconnected.
+///
+pub fn enforce(
+    pending: &PendingNick,
+    nick_sender: &ClientChannel<String, Connection>,
+    channel_sender: &ClientChannel<String, Channel>,
+    spanning_tree: &Arc<Mutex<SpanningTree>>,
+    accounts: &Arc<Mutex<AccountRegistry>>,
+) -> Result<(), ErrorServer> {
+    let connection = match nick_sender.search(pending.nickname.clone())? {
+        Some(c) if c.is_local() && c.get_uid() == Some(pending.uid.as_str()) => c,
+        _ => return Ok(()),
+    };
+    let owner = lock(accounts)?.owner_of(&pending.nickname);
+    match owner {
+        Some(owner)
+            if !connection
+                .get_account()
+                .is_some_and(|a| a.eq_ignore_ascii_case(&owner.name)) => {}
+        _ => return Ok(()),
+    }
+    let guest = format!("{GUEST_PREFIX}{}", pending.uid);
+    let nick = Message::from_str(&format!(":{} NICK {guest}", pending.nickname))?;
+    let replies = match NickCommand::new(nick) {
+        Ok(nick) => nick.response(
+            nick_sender.clone(),
+            channel_sender.clone(),
+            spanning_tree.clone(),
+        )?,
+        Err(reply) => vec![reply],
+    };
+    if replies.first() != Some(&Reply::rpl_none()) {
+        let reason = "Nickname enforcement";
+        return disconnect(
+            connection,
+            reason,
+            nick_sender,
+            channel_sender,
+            spanning_tree,
+        );
+    }
+    if let Some(mut stream) = connection.see_if_clonable() {
+        let servername = connection.get_servername().unwrap_or_default();
+        let text = format!("Your nickname was changed to {guest}, it is registered.");
+        let lines = [
+            format!(":{} NICK {guest}", connection.get_mask()),
+            service_notice(NICKSERV, servername, &guest, &text),
+        ];
+        write_messages_to(&mut lines.iter(), &mut stream)?;
+    }
+    Ok(())
+}
+
+// A local user quits with the reason and its connection is closed,
+// a user of another server is killed.
+fn disconnect(
+    connection: Connection,
+    reason: &str,
+    nick_sender: &ClientChannel<String, Connection>,
+    channel_sender: &ClientChannel<String, Channel>,
+    spanning_tree: &Arc<Mutex<SpanningTree>>,
+) -> Result<(), ErrorServer> {
+    let nickname = connection.get_nickname();
+    match connection.see_if_clonable() {
+        Some(mut stream) => {
+            let quit = Message::from_str(&format!(":{nickname} QUIT :{reason}"))?;
+            inform_all_servers(spanning_tree, nick_sender, &nickname, quit.clone())?;
+            QuitMsg::new(&quit)?.response(nick_sender, channel_sender)?;
+            let error = format!("ERROR :Closing Link: {nickname} ({reason})");
+            let _ = write_message_to(&error, &mut stream);
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        None => {
+            let kill = Message::new(None, Command::Kill, Some(vec![nickname]));
+            Kill::new(kill, true)?.response(nick_sender.clone(), spanning_tree.clone())?;
+        }
+    }
+    Ok(())
+}
+
+// Locks the shared resource, failing if another thread panicked with it.
+fn lock<T>(resource: &Arc<Mutex<T>>) -> Result<MutexGuard<'_, T>, ErrorServer> {
+    resource.lock().map_err(|_| ErrorServer::LockedResource)
+}
+
+///
+/// Registered nickname used by a user that has not identified
+/// for it yet, and the time it has to do it.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PendingNick {
+    pub nickname: String,
+    pub uid: String,
+    pub deadline: u64,
+}
+
+///
+/// Struct that keeps the users that must identify
+/// for their nicknames before their deadline.
+///
+#[derive(Debug, Default)]
+pub struct NickEnforcement {
+    pending: Vec<PendingNick>,
+}
+
+impl NickEnforcement {
+    pub fn new() -> Self {
+        NickEnforcement::default()
+    }
+
+    ///
+    /// Starts counting the time of the user on the nickname.
+    ///
+    /// # Returns
+    /// false if the user was already counting it.
+    pub fn watch(&mut self, nickname: &str, uid: &str, deadline: u64) -> bool {
+        let watched = self
+            .pending
+            .iter()
+            .any(|p| p.uid == uid && p.nickname.eq_ignore_ascii_case(nickname));
+        if watched {
+            return false;
+        }
+        self.release(uid);
+        self.pending.push(PendingNick {
+            nickname: nickname.to_string(),
+            uid: uid.to_string(),
+            deadline,
+        });
+        true
+    }
+
+    ///
+    /// forgets the user, that identified or
+    /// changed to an unregistered nickname
+    ///
+    pub fn release(&mut self, uid: &str) {
+        self.pending.retain(|p| p.uid != uid);
+    }
+
+    ///
+    /// removes and returns the users whose time is over
+    ///
+    pub fn due(&mut self, now: u64) -> Vec<PendingNick> {
+        let (due, pending) = self.pending.drain(..).partition(|p| p.deadline <= now);
+        self.pending = pending;
+        due
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        channel::Channel,
+        config::ServerConfig,
+        database::RepositoryHandler,
+        parser::message::Message,
+        repository::connection::Connection,
+        server::state::ServerState,
+        server_comunication::{
+            server::Server,
+            spanning_tree::{node::Node, SpanningTree},
+        },
+        user::User,
+    };
+
+    use super::{check_nickname, NickEnforcement, NickServMsg};
+
+    struct Setup {
+        nicks: RepositoryHandler<String, Connection>,
+        channels: RepositoryHandler<String, Channel>,
+        spanning_tree: Arc<Mutex<SpanningTree>>,
+        state: ServerState,
+    }
+
+    fn setup() -> Setup {
+        let root = Node::new(Server::new("server".to_string(), None));
+        Setup {
+            nicks: RepositoryHandler::new().unwrap(),
+            channels: RepositoryHandler::new().unwrap(),
+            spanning_tree: Arc::new(Mutex::new(SpanningTree::new(root, vec![]))),
+            state: ServerState::new(ServerConfig::default()).unwrap(),
+        }
+    }
+
+    impl Setup {
+        // Ends the threads of the repositories.
+        fn stop(self) {
+            self.nicks.stop().unwrap();
+            self.channels.stop().unwrap();
+        }
+    }
+
+    fn request(line: &str, user: &mut User, setup: &Setup) -> Vec<String> {
+        let msg = Message::from_str(line).unwrap();
+        NickServMsg::new(&msg)
+            .unwrap()
+            .response(
+                user,
+                &setup.nicks.get_channels(),
+                &setup.channels.get_channels(),
+                &setup.spanning_tree,
+                &setup.state,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn registering_identifies_the_user() {
+        let setup = setup();
+        let mut ana = User::new("ana", "ana", "host", "server", "Ana", "");
+        let lines = request(":ana NICKSERV REGISTER secreta", &mut ana, &setup);
+        assert_eq!(
+            lines,
+            vec![":NickServ!NickServ@server NOTICE ana :ana is now registered."]
+        );
+        assert_eq!(ana.account(), Some("ana"));
+
+        let mut impostor = User::new("ana", "ana", "other", "server", "Ana", "");
+        let lines = request(":ana NS IDENTIFY otra", &mut impostor, &setup);
+        assert!(lines[0].ends_with(":Invalid password for ana."));
+        let lines = request(
+            ":ana PRIVMSG NickServ :identify ana secreta",
+            &mut impostor,
+            &setup,
+        );
+        assert!(lines[0].ends_with(":You are now identified for ana."));
+        assert_eq!(impostor.account(), Some("ana"));
+        setup.stop();
+    }
+
+    #[test]
+    fn grouped_nickname_needs_no_other_identification() {
+        let setup = setup();
+        let mut ana = User::new("ana", "ana", "host", "server", "Ana", "");
+        request(":ana NS REGISTER secreta", &mut ana, &setup);
+        ana.set_nickname("anita");
+        let lines = request(":anita NS GROUP", &mut ana, &setup);
+        assert!(lines[0].ends_with(":anita is now grouped to ana."));
+
+        assert!(check_nickname(&ana, &setup.state).unwrap().is_empty());
+        let mut juan = User::new("anita", "juan", "host", "server", "Juan", "");
+        juan.set_uid("1AA000001");
+        assert_eq!(check_nickname(&juan, &setup.state).unwrap().len(), 1);
+        let lines = request(":anita NS DROP", &mut juan, &setup);
+        assert!(lines[0].ends_with(":You are not identified."));
+        setup.stop();
+    }
+
+    #[test]
+    fn pending_nicknames_are_due_after_their_deadline() {
+        let mut enforcement = NickEnforcement::new();
+        assert!(enforcement.watch("ana", "1AA000001", 10));
+        assert!(!enforcement.watch("ANA", "1AA000001", 20));
+        assert!(enforcement.watch("juan", "1AA000002", 30));
+        assert!(enforcement.due(5).is_empty());
+
+        let due = enforcement.due(10);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].nickname, "ana");
+        enforcement.release("1AA000002");
+        assert!(enforcement.due(40).is_empty());
+    }
+}
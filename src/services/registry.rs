@@ -0,0 +1,458 @@
+use std::{collections::HashMap, fmt, fs, io::ErrorKind, str::FromStr};
+
+use crate::{
+    channel::{
+        full_mask,
+        mode_parser::{parse_modes, ModeChange},
+        topic::Topic,
+        Channel,
+    },
+    error::{error_config::ErrorConfig, error_server::ErrorServer},
+    utils::mask_matches,
+};
+
+const COLON_CHAR: char = ':';
+
+///
+/// Access that an entry of the list of a registered
+/// channel gives to the users that match it.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AccessLevel {
+    /// gets operator on join
+    Op,
+    /// gets voice on join
+    Voice,
+    /// can't join the channel
+    AutoKick,
+}
+
+impl fmt::Display for AccessLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            AccessLevel::Op => "OP",
+            AccessLevel::Voice => "VOICE",
+            AccessLevel::AutoKick => "AKICK",
+        };
+        write!(f, "{level}")
+    }
+}
+
+impl FromStr for AccessLevel {
+    type Err = ErrorConfig;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "OP" => Ok(AccessLevel::Op),
+            "VOICE" => Ok(AccessLevel::Voice),
+            "AKICK" => Ok(AccessLevel::AutoKick),
+            _ => Err(ErrorConfig::InvalidValue("access".to_string())),
+        }
+    }
+}
+
+///
+/// Entry of the access list of a registered channel. Its mask is
+/// an account, or a `nick!user@host` mask if it has `!` or `@`.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AccessEntry {
+    pub mask: String,
+    pub level: AccessLevel,
+}
+
+impl AccessEntry {
+    pub fn new(mask: &str, level: AccessLevel) -> Self {
+        let mask = match is_account(mask) {
+            true => mask.to_string(),
+            false => full_mask(mask),
+        };
+        AccessEntry { mask, level }
+    }
+
+    ///
+    /// returns true if the entry names the account
+    /// or its mask matches the one of the user
+    ///
+    pub fn matches(&self, account: &str, mask: &str) -> bool {
+        match is_account(&self.mask) {
+            true => self.mask.eq_ignore_ascii_case(account),
+            false => mask_matches(&self.mask, mask),
+        }
+    }
+}
+
+///
+/// Channel registered with the services: its founder, its access
+/// list and the settings it gets back each time it is created.
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RegisteredChannel {
+    pub name: String,
+    pub founder: String,
+    pub registered_at: u64,
+    access: Vec<AccessEntry>,
+    /// flags set on the channel, like `+nt`
+    pub mlock: Option<String>,
+    pub key: Option<String>,
+    pub topic: Option<String>,
+}
+
+impl RegisteredChannel {
+    pub fn new(name: &str, founder: &str, registered_at: u64) -> Self {
+        RegisteredChannel {
+            name: name.to_string(),
+            founder: founder.to_string(),
+            registered_at,
+            access: vec![],
+            mlock: None,
+            key: None,
+            topic: None,
+        }
+    }
+
+    pub fn is_founder(&self, account: &str) -> bool {
+        self.founder.eq_ignore_ascii_case(account)
+    }
+
+    ///
+    /// Returns the access of the user with the account and mask. The
+    /// founder is always an operator, for the rest an autokick
+    /// wins over the other entries that match them.
+    ///
+    pub fn level_of(&self, account: &str, mask: &str) -> Option<AccessLevel> {
+        if self.is_founder(account) {
+            return Some(AccessLevel::Op);
+        }
+        let levels: Vec<AccessLevel> = self
+            .access
+            .iter()
+            .filter(|entry| entry.matches(account, mask))
+            .map(|entry| entry.level)
+            .collect();
+        [AccessLevel::AutoKick, AccessLevel::Op, AccessLevel::Voice]
+            .into_iter()
+            .find(|level| levels.contains(level))
+    }
+
+    pub fn access(&self) -> &[AccessEntry] {
+        &self.access
+    }
+
+    ///
+    /// adds the entry, replacing the one with the same mask
+    ///
+    pub fn set_access(&mut self, entry: AccessEntry) {
+        self.access
+            .retain(|e| !e.mask.eq_ignore_ascii_case(&entry.mask));
+        self.access.push(entry);
+    }
+
+    ///
+    /// removes the entry with the mask,
+    /// returns false if there wasn't one
+    ///
+    pub fn remove_access(&mut self, mask: &str) -> bool {
+        let mask = AccessEntry::new(mask, AccessLevel::Voice).mask;
+        let amount = self.access.len();
+        self.access.retain(|e| !e.mask.eq_ignore_ascii_case(&mask));
+        amount != self.access.len()
+    }
+
+    ///
+    /// Sets the locked flags and the key on the channel, and
+    /// returns the changes that it didn't have yet.
+    ///
+    pub fn restore_modes(&self, channel: &mut Channel) -> Vec<ModeChange> {
+        let mut changes = match &self.mlock {
+            Some(mlock) => parse_modes(mlock, &[]).unwrap_or_default(),
+            None => vec![],
+        };
+        if let Some(key) = &self.key {
+            changes.push(ModeChange {
+                add: true,
+                mode: 'k',
+                param: Some(key.clone()),
+            });
+        }
+        channel.apply_modes(&changes)
+    }
+
+    ///
+    /// sets the kept topic on the channel, if it has another one,
+    /// and returns it
+    ///
+    pub fn restore_topic(&self, channel: &mut Channel, setter: &str, now: u64) -> Option<Topic> {
+        let text = self.topic.as_ref()?;
+        if channel.get_topic().as_ref() == Some(text) {
+            return None;
+        }
+        let topic = Topic::new(text, setter, now);
+        channel.set_topic(topic.clone());
+        Some(topic)
+    }
+
+    // Reads a persisted line into the channel.
+    fn read_line(&mut self, kind: &str, fields: &[&str], text: Option<&str>) -> Option<()> {
+        match (kind, fields, text) {
+            ("ACCESS", [level, mask], None) => {
+                let level = AccessLevel::from_str(level).ok()?;
+                self.access.push(AccessEntry {
+                    mask: mask.to_string(),
+                    level,
+                })
+            }
+            ("MLOCK", [mlock], None) => self.mlock = Some(mlock.to_string()),
+            ("KEY", [key], None) => self.key = Some(key.to_string()),
+            ("TOPIC", [], Some(text)) => self.topic = Some(text.to_string()),
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+///
+/// converts the channel into the lines in which it is persisted:
+/// `REGISTER <channel> <founder> <registered_at>` followed by
+/// `ACCESS <channel> <level> <mask>`, `MLOCK <channel> <flags>`,
+/// `KEY <channel> <key>` and `TOPIC <channel> :<topic>`
+///
+impl fmt::Display for RegisteredChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.name;
+        writeln!(f, "REGISTER {name} {} {}", self.founder, self.registered_at)?;
+        for entry in &self.access {
+            writeln!(f, "ACCESS {name} {} {}", entry.level, entry.mask)?;
+        }
+        if let Some(mlock) = &self.mlock {
+            writeln!(f, "MLOCK {name} {mlock}")?;
+        }
+        if let Some(key) = &self.key {
+            writeln!(f, "KEY {name} {key}")?;
+        }
+        if let Some(topic) = &self.topic {
+            writeln!(f, "TOPIC {name} {COLON_CHAR}{topic}")?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Struct that stores the channels registered with the services.
+/// When it is created with a file, every change is written back
+/// to it, so the registrations survive a restart.
+///
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct ChannelRegistry {
+    channels: HashMap<String, RegisteredChannel>,
+    path: Option<String>,
+}
+
+impl ChannelRegistry {
+    ///
+    /// creates an empty registry,
+    /// kept only in memory
+    ///
+    pub fn new() -> Self {
+        ChannelRegistry::default()
+    }
+
+    ///
+    /// Reads the channels persisted on the file. If the file doesn't exist
+    /// yet, the registry starts empty and creates it on the first change.
+    ///
+    pub fn load(path: &str) -> Result<Self, ErrorConfig> {
+        let mut registry = ChannelRegistry {
+            channels: HashMap::new(),
+            path: Some(path.to_string()),
+        };
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(registry),
+            Err(e) => return Err(e.into()),
+        };
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if registry.read_line(line).is_none() {
+                return Err(ErrorConfig::MalformedLine(number + 1));
+            }
+        }
+        Ok(registry)
+    }
+
+    ///
+    /// returns the registration of the channel, if it has one
+    ///
+    pub fn find(&self, name: &str) -> Option<RegisteredChannel> {
+        self.channels.get(name).cloned()
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.channels.contains_key(name)
+    }
+
+    ///
+    /// Registers the channel.
+    ///
+    /// # Returns
+    /// false if the channel was already registered.
+    pub fn register(&mut self, channel: RegisteredChannel) -> Result<bool, ErrorServer> {
+        if self.is_registered(&channel.name) {
+            return Ok(false);
+        }
+        self.channels.insert(channel.name.clone(), channel);
+        self.save()?;
+        Ok(true)
+    }
+
+    ///
+    /// replaces the registration of the channel with the new one
+    ///
+    pub fn update(&mut self, channel: RegisteredChannel) -> Result<(), ErrorServer> {
+        self.channels.insert(channel.name.clone(), channel);
+        self.save()
+    }
+
+    ///
+    /// forgets the registration of the channel,
+    /// returns false if it wasn't registered
+    ///
+    pub fn unregister(&mut self, name: &str) -> Result<bool, ErrorServer> {
+        if self.channels.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    // Reads a persisted line, that must be of
+    // a channel registered in a previous line.
+    fn read_line(&mut self, line: &str) -> Option<()> {
+        let (fields, text) = match line.split_once(COLON_CHAR) {
+            Some((fields, text)) => (fields, Some(text)),
+            None => (line, None),
+        };
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        match (fields.as_slice(), text) {
+            (["REGISTER", name, founder, registered_at], None) => {
+                let registered_at = registered_at.parse().ok()?;
+                let channel = RegisteredChannel::new(name, founder, registered_at);
+                self.channels.insert(name.to_string(), channel);
+                Some(())
+            }
+            ([kind, name, fields @ ..], text) => {
+                let channel = self.channels.get_mut(*name)?;
+                channel.read_line(kind, fields, text)
+            }
+            _ => None,
+        }
+    }
+
+    fn save(&self) -> Result<(), ErrorServer> {
+        if let Some(path) = &self.path {
+            let mut channels: Vec<&RegisteredChannel> = self.channels.values().collect();
+            channels.sort_by(|a, b| a.name.cmp(&b.name));
+            let content: String = channels.iter().map(|c| c.to_string()).collect();
+            fs::write(path, content)?;
+        }
+        Ok(())
+    }
+}
+
+// A mask without `!` nor `@` names an account.
+fn is_account(mask: &str) -> bool {
+    !mask.contains(['!', '@'])
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+
+    use crate::channel::Channel;
+
+    use super::{AccessEntry, AccessLevel, ChannelRegistry, RegisteredChannel};
+
+    #[test]
+    fn autokick_wins_over_other_entries_but_not_the_founder() {
+        let mut channel = RegisteredChannel::new("#canal", "ana", 0);
+        channel.set_access(AccessEntry::new("*!*@bad.host", AccessLevel::AutoKick));
+        channel.set_access(AccessEntry::new("juan", AccessLevel::Op));
+        channel.set_access(AccessEntry::new("pedro", AccessLevel::Voice));
+
+        assert_eq!(
+            channel.level_of("juan", "juan!juan@good.host"),
+            Some(AccessLevel::Op)
+        );
+        assert_eq!(
+            channel.level_of("juan", "juan!juan@bad.host"),
+            Some(AccessLevel::AutoKick)
+        );
+        assert_eq!(
+            channel.level_of("ana", "ana!ana@bad.host"),
+            Some(AccessLevel::Op)
+        );
+        assert_eq!(channel.level_of("luis", "luis!luis@good.host"), None);
+
+        assert!(channel.remove_access("PEDRO"));
+        assert_eq!(channel.access().len(), 2);
+    }
+
+    #[test]
+    fn settings_are_restored_on_the_channel() {
+        let mut registered = RegisteredChannel::new("#canal", "ana", 0);
+        registered.mlock = Some("+nt".to_string());
+        registered.key = Some("clave".to_string());
+        registered.topic = Some("bienvenidos".to_string());
+        let mut channel = Channel::new("#canal".to_string(), "ana".to_string());
+
+        let flags: Vec<String> = registered
+            .restore_modes(&mut channel)
+            .iter()
+            .map(|change| change.flag())
+            .collect();
+        assert_eq!(flags, vec!["+n", "+t", "+k"]);
+        assert!(channel.key_is_correct(&Some("clave".to_string())));
+        assert!(registered
+            .restore_topic(&mut channel, "ChanServ", 10)
+            .is_some());
+        assert!(registered
+            .restore_topic(&mut channel, "ChanServ", 20)
+            .is_none());
+        assert_eq!(channel.get_topic(), Some("bienvenidos".to_string()));
+    }
+
+    #[test]
+    fn registrations_survive_a_restart() {
+        let path = env::temp_dir().join(format!("chanserv-{}.db", process::id()));
+        let path = path.to_string_lossy().to_string();
+        let _ = fs::remove_file(&path);
+
+        let mut registry = ChannelRegistry::load(&path).unwrap();
+        let mut channel = RegisteredChannel::new("#canal", "ana", 100);
+        channel.set_access(AccessEntry::new("juan!*@*", AccessLevel::Voice));
+        channel.mlock = Some("+nt".to_string());
+        channel.topic = Some("hola: mundo".to_string());
+        assert!(registry.register(channel.clone()).unwrap());
+        assert!(!registry.register(channel.clone()).unwrap());
+        registry
+            .register(RegisteredChannel::new("#otro", "juan", 200))
+            .unwrap();
+        assert!(registry.unregister("#otro").unwrap());
+
+        let loaded = ChannelRegistry::load(&path).unwrap();
+        assert_eq!(loaded.find("#canal"), Some(channel));
+        assert!(!loaded.is_registered("#otro"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn line_of_an_unregistered_channel_is_malformed() {
+        let path = env::temp_dir().join(format!("chanserv-bad-{}.db", process::id()));
+        fs::write(&path, "MLOCK #canal +nt\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+        assert!(ChannelRegistry::load(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -378,6 +378,10 @@ mod test {
             password: None,
             oper: false,
             user_flags: Vec::new(),
+            created_at: 0,
+            uid: String::new(),
+            capabilities: Vec::new(),
+            account: None,
         };
         let mut user = UserBuilder::new();
         assert!(user.build().is_err());
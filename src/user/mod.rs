@@ -27,6 +27,10 @@ pub struct User {
     password: Option<String>,
     oper: bool,
     user_flags: Vec<UserFlag>,
+    created_at: u64,
+    uid: String,
+    capabilities: Vec<String>,
+    account: Option<String>,
 }
 
 impl User {
@@ -56,6 +60,10 @@ impl User {
             password: pwd,
             oper: false,
             user_flags: Vec::new(),
+            created_at: 0,
+            uid: String::new(),
+            capabilities: Vec::new(),
+            account: None,
         }
     }
 
@@ -111,6 +119,19 @@ impl User {
         None
     }
 
+    ///
+    /// function that returns the mask of the user,
+    /// `nick!user@host`, matched by the channel bans
+    ///
+    pub fn mask(&self) -> String {
+        format!(
+            "{}!{}@{}",
+            self.nickname,
+            self.username().unwrap_or("*"),
+            self.hostname().unwrap_or("*")
+        )
+    }
+
     ///
     /// function that returns the servername
     /// of the user
@@ -133,6 +154,61 @@ impl User {
         None
     }
 
+    ///
+    /// returns the timestamp of the registration of the
+    /// nickname, the older one wins a nick collision
+    ///
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn set_created_at(&mut self, created_at: u64) {
+        self.created_at = created_at;
+    }
+
+    ///
+    /// returns the id of the user inside the network,
+    /// used to refer to it between servers
+    ///
+    pub fn uid(&self) -> Option<&str> {
+        if !self.uid.is_empty() {
+            return Some(&self.uid);
+        }
+        None
+    }
+
+    pub fn set_uid(&mut self, uid: &str) {
+        self.uid = uid.to_string();
+    }
+
+    ///
+    /// returns true if the client enabled
+    /// the capability with CAP REQ
+    ///
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    pub fn capabilities(&self) -> &[String] {
+        &self.capabilities
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Vec<String>) {
+        self.capabilities = capabilities;
+    }
+
+    ///
+    /// returns the account the user identified to,
+    /// none while it is not identified
+    ///
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
+    pub fn set_account(&mut self, account: Option<String>) {
+        self.account = account;
+    }
+
     pub fn is_op(&self) -> bool {
         self.user_flags.contains(&UserFlag::O)
     }
@@ -219,16 +295,18 @@ impl User {
                     if let Some(realname) = self.realname() {
                         if let Some(username) = self.username() {
                             let prefix = convert_into_command_prefix(nick);
-                            let msg = Message::new(
-                                Some(prefix),
-                                Command::User,
-                                Some(vec![
-                                    username.to_string(),
-                                    host.to_string(),
-                                    server.to_string(),
-                                    realname.to_string(),
-                                ]),
-                            );
+                            let mut parameters = vec![
+                                username.to_string(),
+                                host.to_string(),
+                                server.to_string(),
+                                self.created_at.to_string(),
+                            ];
+                            // users of servers that don't send an id have none
+                            if let Some(uid) = self.uid() {
+                                parameters.push(uid.to_string());
+                            }
+                            parameters.push(realname.to_string());
+                            let msg = Message::new(Some(prefix), Command::User, Some(parameters));
                             //println!("Builded message {}", msg.to_string());
                             return msg.to_string();
                         }
@@ -313,6 +391,10 @@ mod test {
             password: Some(String::from("secretpasswordhere")),
             oper: false,
             user_flags: Vec::new(),
+            created_at: 0,
+            uid: String::new(),
+            capabilities: Vec::new(),
+            account: None,
         };
         let result = User::new(
             "Wiz",
@@ -326,6 +408,12 @@ mod test {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn mask_joins_nickname_username_and_hostname() {
+        let user = User::new("Wiz", "guest", "tolmoon", "tolsun", "Ronnie Reagan", "");
+        assert_eq!(user.mask(), "Wiz!guest@tolmoon");
+    }
+
     #[test]
     fn user_to_string() {
         let user = User::new(
@@ -1,6 +1,6 @@
 use std::{
     io::Write,
-    net::TcpStream,
+    net::{Shutdown, TcpStream},
     str::FromStr,
     sync::{Arc, Mutex},
     thread,
@@ -8,20 +8,32 @@ use std::{
 };
 
 use crate::{
-    channel::Channel,
+    channel::{isupport_tokens, Channel},
     command::{
+        admin::AdminMsg,
+        authenticate::{AuthenticateMsg, SaslSession},
         away::AwayMsg,
+        cap::CapMsg,
+        chathistory::{replay_on_join, ChatHistoryMsg},
+        connect::ConnectMsg,
         dcc_accept::DccAcceptMessage,
         dcc_chat::DccChatMessage,
         dcc_pause::DccPauseMessage,
         dcc_resume::DccResumeMessage,
         dcc_send::DccSendMessage,
+        die::DieMsg,
+        info::InfoMsg,
         invite::InviteMsg,
         join::JoinMsg,
         kick::KickMsg,
         kill::Kill,
+        knock::KnockMsg,
+        links::LinksMsg,
         list::ListMsg,
+        lusers::{record_user_peaks, LusersMsg},
+        map::MapMsg,
         mode::Mode,
+        motd::MotdMsg,
         names::NamesMsg,
         nick_command::NickCommand,
         notice_msg::NoticeMsg,
@@ -30,10 +42,16 @@ use crate::{
         pass_command::PassCommand,
         private_msg::PrivMsg,
         quit::QuitMsg,
+        rehash::RehashMsg,
+        server_ban::ServerBanMsg,
+        server_query::ServerQuery,
         squit::SquitMsg,
+        stats::StatsMsg,
+        time::TimeMsg,
         topic::TopicMsg,
         traits::{RegistrationCommand, Runnable},
         user_command::*,
+        version::VersionMsg,
         who::Who,
         whois::Whois,
         Command,
@@ -51,12 +69,22 @@ use crate::{
         connection::Connection, repository_channel::client_channel::ClientChannel,
         traits::operations::Operations,
     },
+    server::state::ServerState,
     server_comunication::{
-        info_sender::{inform_all_server_an_user_command, inform_all_servers},
-        server::Server,
+        info_sender::{
+            inform_all_server_an_user_command, inform_all_servers, send_to_all_servers,
+            send_to_servers,
+        },
         spanning_tree::SpanningTree,
+        uid::to_uids,
+        ServerComunicationHandler,
+    },
+    services::{
+        chanserv::{on_join, refuse_autokicked, ChanServMsg},
+        is_service,
+        nickserv::{account_change, account_msg, check_nickname_notices, NickServMsg},
     },
-    utils::{read_message_from, write_message_to},
+    utils::{read_message_from, unix_timestamp, write_message_to, write_messages_to},
 };
 
 use super::{builder::UserBuilder, User};
@@ -73,6 +101,15 @@ pub struct UserHandler {
     channel_sender: ClientChannel<String, Channel>,
     user: Option<User>,
     spanning_tree: Arc<Mutex<SpanningTree>>,
+    state: ServerState,
+}
+
+// What the client negotiates before completing its registration.
+#[derive(Default)]
+struct Negotiation {
+    capabilities: Vec<String>,
+    negotiating: bool,
+    sasl: SaslSession,
 }
 
 impl UserHandler {
@@ -84,12 +121,14 @@ impl UserHandler {
         nick_sender: ClientChannel<String, Connection>,
         channel_sender: ClientChannel<String, Channel>,
         spanning_tree: Arc<Mutex<SpanningTree>>,
+        state: ServerState,
     ) -> Self {
         UserHandler {
             nick_sender,
             channel_sender,
             user: None,
             spanning_tree,
+            state,
         }
     }
 
@@ -114,19 +153,48 @@ impl UserHandler {
         socket: &mut TcpStream,
     ) -> Result<Box<dyn Runnable>, ErrorServer> {
         //println!("Registrando un nuevo cliente");
-        let builder = match Self::create_user_builder(msg, &UserBuilder::new()) {
-            Some(b) => b,
-            None => UserBuilder::new(),
+        let mut negotiation = Negotiation::default();
+        let builder = match self.negotiate(&msg, &mut negotiation, socket)? {
+            true => UserBuilder::new(),
+            false => Self::create_user_builder(msg, &UserBuilder::new()).unwrap_or_default(),
         };
-        let mut new_user = self.register_user(servername, socket, builder)?;
+        let mut new_user = self.register_user(servername, socket, builder, &mut negotiation)?;
         loop {
             new_user = match self.add_user(new_user, socket)? {
-                None => self.register_user(servername, socket, UserBuilder::new())?,
+                None => {
+                    self.register_user(servername, socket, UserBuilder::new(), &mut negotiation)?
+                }
                 Some(u) => return Ok(u),
             }
         }
     }
 
+    // Answers a CAP or an AUTHENTICATE sent while registering, keeping
+    // the capabilities the client enables and the account it logs in.
+    // Returns false if the message is neither of them.
+    fn negotiate(
+        &self,
+        msg: &Message,
+        negotiation: &mut Negotiation,
+        socket: &mut TcpStream,
+    ) -> Result<bool, ErrorServer> {
+        let lines = match msg.command() {
+            Command::Cap => {
+                let cap = CapMsg::new(msg)?;
+                negotiation.negotiating = !cap.ends_negotiation();
+                cap.response("*", &mut negotiation.capabilities)
+            }
+            Command::Authenticate => AuthenticateMsg::new(msg)?.response(
+                "*",
+                &mut negotiation.sasl,
+                &self.state.accounts,
+            )?,
+            _ => return Ok(false),
+        };
+        write_messages_to(&mut lines.iter(), socket)?;
+        Ok(true)
+    }
+
     fn create_user_builder(msg: Message, new_user: &UserBuilder) -> Option<UserBuilder> {
         println!("UserHandler: {:?}", msg);
         // Por ahora chequeamos manualmente que tipo de comando tiene el msg.
@@ -153,6 +221,7 @@ impl UserHandler {
         servername: &str,
         socket: &TcpStream,
         mut new_user: UserBuilder,
+        negotiation: &mut Negotiation,
     ) -> Result<User, ErrorServer> {
         let mut client = socket.try_clone()?;
         // Loopea hasta que se completa el UserBuilder.
@@ -160,16 +229,23 @@ impl UserHandler {
             let msg = read_message_from(&mut client)?;
             match Message::from_str(&msg) {
                 Ok(msg) => {
-                    let mut builder = match Self::create_user_builder(msg, &new_user) {
-                        Some(b) => b,
-                        None => continue,
-                    };
+                    // the registration waits for the end of the negotiation
+                    if !self.negotiate(&msg, negotiation, &mut client)? {
+                        match Self::create_user_builder(msg, &new_user) {
+                            Some(b) => new_user = b,
+                            None => continue,
+                        }
+                    }
                     //Agrego el servername con el nombre del server. Habría que hacer algo parecido con el hostname.
-                    builder.servername(servername);
+                    new_user.servername(servername);
+                    if negotiation.negotiating {
+                        continue;
+                    }
                     // Si es capaz de construir, es porque tiene los atributos completos.
-                    match builder.build() {
-                        Ok(u) => return Ok(u),
-                        Err(_) => new_user = builder,
+                    if let Ok(mut u) = new_user.build() {
+                        u.set_capabilities(negotiation.capabilities.clone());
+                        u.set_account(negotiation.sasl.account());
+                        return Ok(u);
                     }
                 }
                 Err(e) => write_message_to(&format!("{e:}"), &mut client)?,
@@ -199,34 +275,61 @@ impl UserHandler {
 
     fn add_user(
         &mut self,
-        user: User,
+        mut user: User,
         socket: &mut TcpStream,
     ) -> Result<Option<Box<dyn Runnable>>, ErrorServer> {
+        self.reject_if_banned(&user, socket)?;
+        user.set_created_at(unix_timestamp());
+        user.set_uid(&self.state.new_uid(user.servername().unwrap_or_default())?);
         let connection = Connection::new(socket.try_clone()?, user.clone());
 
-        if !self.nick_sender.add(user.nickname.clone(), connection)? {
+        if is_service(&user.nickname) || !self.nick_sender.add(user.nickname.clone(), connection)? {
             let reply = Reply::err_nickname_in_use(None, vec![user.nickname]);
             write_message_to(&reply, socket)?;
             return Ok(None);
         } else {
-            match self.spanning_tree.lock() {
-                Ok(st) => {
-                    let servers = st.get_servers();
-                    for server in servers {
-                        if let Some(mut connection) = server.get_connection() {
-                            let msg = user.build_user_msg();
-                            connection.write_all(msg.as_bytes())?;
-                        }
-                    }
-                }
-                Err(_) => return Err(ErrorServer::LockedResource),
+            let mut messages = vec![Message::from_str(&user.build_user_msg())?];
+            if let Some(account) = user.account() {
+                let uid = user.uid().unwrap_or(&user.nickname);
+                messages.push(account_msg(uid, Some(account))?);
             }
+            Self::send_from_server(&self.spanning_tree, &user, messages)?;
+            record_user_peaks(
+                &self.nick_sender,
+                user.servername().unwrap_or_default(),
+                &self.state,
+            )?;
+            let reply = Reply::rpl_isupport(&user.nickname, &isupport_tokens());
+            write_message_to(&reply, socket)?;
+            let notices = check_nickname_notices(&user, &self.state)?;
+            write_messages_to(&mut notices.iter(), socket)?;
             println!("User: Successfully connected to: {}", socket.local_addr()?);
         }
         self.set_user(user);
         Ok(Some(Box::new(self.clone())))
     }
 
+    // Closes the connection of a user affected by a K-line or G-line.
+    fn reject_if_banned(&self, user: &User, socket: &mut TcpStream) -> Result<(), ErrorServer> {
+        let ip = socket.peer_addr().ok().map(|addr| addr.ip());
+        let ban = match self.state.bans.lock() {
+            Ok(bans) => bans.find_user_ban(
+                user.username().unwrap_or_default(),
+                user.hostname().unwrap_or_default(),
+                ip,
+                unix_timestamp(),
+            ),
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        if let Some(ban) = ban {
+            let error = ban.closing_message(user.nickname().unwrap_or_default());
+            write_message_to(&error, socket)?;
+            socket.shutdown(Shutdown::Both)?;
+            return Err(ErrorServer::UnacceptedClient);
+        }
+        Ok(())
+    }
+
     fn set_user(&mut self, user: User) {
         self.user = Some(user);
     }
@@ -244,8 +347,82 @@ impl UserHandler {
         spanning_tree: &Arc<Mutex<SpanningTree>>,
         nick_sender: &ClientChannel<String, Connection>,
         channel_sender: &ClientChannel<String, Channel>,
+        state: &ServerState,
     ) -> Result<Vec<Reply>, ErrorServer> {
         match msg.command() {
+            Command::Kline
+            | Command::Unkline
+            | Command::Gline
+            | Command::Ungline
+            | Command::Zline
+            | Command::Unzline => {
+                let oper = Self::is_oper(nick_sender, &msg)?;
+                let mut ban = ServerBanMsg::new(&msg, oper)?;
+                let replies = ban.response(nick_sender, spanning_tree, &state.bans)?;
+                if ban.must_be_propagated() {
+                    inform_all_servers(spanning_tree, nick_sender, &msg.prefix().unwrap(), msg)?;
+                }
+                Ok(replies)
+            }
+            Command::Stats => {
+                let oper = Self::is_oper(nick_sender, &msg)?;
+                let stats = StatsMsg::new(&msg)?;
+                ServerQuery::new(&msg, 1)?.response(nick_sender, spanning_tree, |_| {
+                    stats.response(oper, &state.bans, spanning_tree)
+                })
+            }
+            Command::Version => {
+                ServerQuery::new(&msg, 0)?.response(nick_sender, spanning_tree, |servername| {
+                    VersionMsg::response(servername, &state.config)
+                })
+            }
+            Command::Time => {
+                ServerQuery::new(&msg, 0)?.response(nick_sender, spanning_tree, |servername| {
+                    Ok(TimeMsg::response(servername))
+                })
+            }
+            Command::Admin => {
+                ServerQuery::new(&msg, 0)?.response(nick_sender, spanning_tree, |servername| {
+                    AdminMsg::response(servername, &state.config)
+                })
+            }
+            Command::Info => {
+                ServerQuery::new(&msg, 0)?
+                    .response(nick_sender, spanning_tree, |_| Ok(InfoMsg::response(state)))
+            }
+            Command::Motd => {
+                ServerQuery::new(&msg, 0)?.response(nick_sender, spanning_tree, |servername| {
+                    MotdMsg::response(servername, &state.config)
+                })
+            }
+            Command::Rehash => {
+                RehashMsg::new(&msg)?.response(nick_sender, spanning_tree, state)
+            }
+            Command::Connect => {
+                let connect = ConnectMsg::new(&msg)?;
+                connect.response(nick_sender, spanning_tree, &state.config, |address| {
+                    let servername = match spanning_tree.lock() {
+                        Ok(st) => st.get_root_name(),
+                        Err(_) => return Err(ErrorServer::LockedResource),
+                    };
+                    ServerComunicationHandler::new(
+                        servername,
+                        spanning_tree.clone(),
+                        nick_sender.clone(),
+                        channel_sender.clone(),
+                        state.clone(),
+                    )
+                    .connect_to(address)
+                })
+            }
+            Command::Links => LinksMsg::new(&msg)?.response(spanning_tree),
+            Command::Map => MapMsg::response(spanning_tree),
+            Command::Lusers => {
+                LusersMsg::response(nick_sender, channel_sender, spanning_tree, state)
+            }
+            Command::Die | Command::Restart => {
+                DieMsg::new(&msg)?.response(nick_sender, spanning_tree, &state.shutdown)
+            }
             Command::Kill => {
                 let oper = match nick_sender.search(user.clone().nickname)? {
                     Some(oper) => oper.is_op_connection(),
@@ -277,13 +454,40 @@ impl UserHandler {
             Command::Mode => {
                 let mut mode = Mode::new(&msg)?;
                 let replies = mode.response(nick_sender.to_owned(), channel_sender.to_owned());
-                inform_all_servers(spanning_tree, nick_sender, &msg.prefix().unwrap(), msg)?;
+                if let Some(forward) = mode.forward() {
+                    inform_all_servers(
+                        spanning_tree,
+                        nick_sender,
+                        &msg.prefix().unwrap(),
+                        forward,
+                    )?;
+                }
                 replies
             }
+            Command::Invite if msg.get_param_from_msg(0).is_none() => {
+                InviteMsg::invite_list(&msg.prefix().unwrap_or_default(), channel_sender)
+            }
             Command::Invite => {
                 inform_all_server_an_user_command(spanning_tree, nick_sender, &msg.prefix().unwrap(), msg.clone())?;
                 let mut msg = InviteMsg::new(msg)?;
-                msg.response(nick_sender.to_owned(), channel_sender.to_owned())
+                let expiry = state.invite_expiry()?;
+                msg.response(nick_sender.to_owned(), channel_sender.to_owned(), expiry)
+            }
+            Command::Knock => {
+                let mut knock = KnockMsg::new(&msg)?;
+                if Self::is_local(nick_sender, &msg)? {
+                    let replies = knock.response(nick_sender, channel_sender, &state.knocks)?;
+                    if let Some(forward) = knock.forward() {
+                        let nickname = msg.prefix().unwrap_or_default();
+                        inform_all_servers(spanning_tree, nick_sender, &nickname, forward)?;
+                    }
+                    Ok(replies)
+                } else {
+                    // the server of the user already accepted the KNOCK
+                    knock.notify_operators(nick_sender, channel_sender)?;
+                    inform_all_servers(spanning_tree, nick_sender, &msg.prefix().unwrap(), msg)?;
+                    Ok(vec![Reply::rpl_none()])
+                }
             }
             Command::Whois => {
                 println!("WHOIS");
@@ -296,19 +500,44 @@ impl UserHandler {
                 msg.response(nick_sender.to_owned(), channel_sender.to_owned())
             }
             Command::Join => {
-                let mut r = vec![];
-                let join = JoinMsg::new(&msg)?;
-                r.append(&mut join.response(channel_sender.clone())?);
+                let mut join = JoinMsg::new(&msg)?;
+                let local = Self::is_local(nick_sender, &msg)?;
+                let mut r = match local {
+                    true => refuse_autokicked(user, &mut join, &state.registry)?,
+                    false => vec![],
+                };
+                if join.channels().is_empty() {
+                    return Ok(r);
+                }
+                r.append(&mut join.response(nick_sender, channel_sender.clone())?);
                 inform_all_servers(
                     spanning_tree,
                     nick_sender,
                     &msg.prefix().unwrap(),
-                    msg.clone(),
+                    join.message(),
                 )?;
+                if local {
+                    let forward = on_join(
+                        user,
+                        join.channels(),
+                        nick_sender,
+                        channel_sender,
+                        &state.registry,
+                    )?;
+                    Self::send_from_server(spanning_tree, user, forward)?;
+                }
+                r.append(&mut join.topic_replies(nick_sender, channel_sender)?);
                 //RPL_NAMREPLY
 
-                let names = NamesMsg::new(msg)?;
+                let names = NamesMsg::new(join.message())?;
                 r.append(&mut names.response(channel_sender.to_owned())?);
+                replay_on_join(
+                    user,
+                    join.channels(),
+                    nick_sender,
+                    channel_sender,
+                    &state.history,
+                )?;
                 Ok(r)
             }
             Command::Part => {
@@ -316,6 +545,35 @@ impl UserHandler {
                 inform_all_servers(spanning_tree, nick_sender, &msg.prefix().unwrap(), msg)?;
                 part.response(channel_sender)
             }
+            Command::Privmsg if ChanServMsg::is_addressed(&msg) => {
+                Self::execute_chanserv(msg, user, spanning_tree, nick_sender, channel_sender, state)
+            }
+            Command::Chanserv => {
+                Self::execute_chanserv(msg, user, spanning_tree, nick_sender, channel_sender, state)
+            }
+            Command::Privmsg if NickServMsg::is_addressed(&msg) => {
+                Self::execute_nickserv(msg, user, spanning_tree, nick_sender, channel_sender, state)
+            }
+            Command::Nickserv => {
+                Self::execute_nickserv(msg, user, spanning_tree, nick_sender, channel_sender, state)
+            }
+            Command::Account => {
+                // only the servers tell the accounts of their users
+                if !Self::is_local(nick_sender, &msg)? {
+                    account_change(&msg, nick_sender)?;
+                    inform_all_servers(spanning_tree, nick_sender, &msg.prefix().unwrap(), msg)?;
+                }
+                Ok(vec![Reply::rpl_none()])
+            }
+            Command::Authenticate => {
+                let nickname = user.nickname().unwrap_or_default().to_string();
+                let line = match user.account() {
+                    Some(_) => format!("907 {nickname} :You have already authenticated using SASL"),
+                    None => format!("904 {nickname} :SASL authentication failed"),
+                };
+                Self::write_lines_to(nick_sender, &nickname, &[line])?;
+                Ok(vec![Reply::rpl_none()])
+            }
             Command::Privmsg => {
                 let privmsgs = PrivMsg::new(msg)?;
                 let mut replies = vec![];
@@ -325,34 +583,16 @@ impl UserHandler {
                 };
                 for msg in privmsgs {
                     let servers = msg.get_interested_servers(nick_sender, channel_sender)?;
-                    let mut servers: Vec<Server> = servers
-                        .into_iter()
-                        .filter_map(|s| st.look_for_nearest_connection(s))
-                        .collect();
-
-                    let informer =
-                        st.look_for_nearest_connection(msg.get_origin_server(nick_sender)?);
-
-                    servers.sort_by(|a, b| a.servername.cmp(&b.servername));
-                    servers.dedup();
-
-                    //Filtrar el informador
-                    if let Some(server) = informer {
-                        servers.retain(|s| {
-                            //println!("{} vs INFORMADOR {}", s.servername, server.servername);
-                            server.servername != s.servername
-                                && s.servername != st.get_root().server.servername
-                        });
-                    }
-
-                    servers.into_iter().for_each(|mut s| {
-                        println!("Le envio al servidor: {:?}", s);
-                        if write_message_to(&msg, &mut s).is_err() {
-                            println!("error - send msg server -> sever")
-                        };
-                    });
+                    let origin = msg.get_origin_server(nick_sender)?;
+                    let relayed = to_uids(&msg.to_string(), nick_sender);
+                    send_to_servers(&st, &relayed, &servers, &origin)?;
 
-                    replies.append(&mut msg.response(nick_sender, channel_sender)?);
+                    replies.append(&mut msg.response(
+                        nick_sender,
+                        channel_sender,
+                        &state.history,
+                        &st.get_root_name(),
+                    )?);
                 }
 
                 Ok(replies)
@@ -369,6 +609,9 @@ impl UserHandler {
                         if let Some(r) = replies.first() {
                             if Reply::rpl_none().eq(r) {
                                 user.set_nickname(cmd.get_new_nickname());
+                                let notices = check_nickname_notices(user, state)?;
+                                let nickname = cmd.get_new_nickname();
+                                Self::write_lines_to(nick_sender, nickname, &notices)?;
                             }
                         }
                     }
@@ -383,7 +626,7 @@ impl UserHandler {
             Command::Oper => {
                 let oper = OperMsg::new(&msg)?;
                 inform_all_servers(spanning_tree, nick_sender, &msg.prefix().unwrap(), msg)?;
-                oper.response(nick_sender.to_owned())
+                oper.response(nick_sender.to_owned(), &state.config)
             }
             Command::Notice => {
                 let msg = NoticeMsg::new(msg, spanning_tree.clone())?;
@@ -402,10 +645,41 @@ impl UserHandler {
             }
             Command::Topic => {
                 let mut topic = TopicMsg::new(&msg)?;
-                let replies = topic.response(channel_sender.to_owned())?;
-                inform_all_servers(spanning_tree, nick_sender, &msg.prefix().unwrap(), msg)?;
+                let replies = topic.response(nick_sender, channel_sender.to_owned())?;
+                if let Some(forward) = topic.forward() {
+                    inform_all_servers(
+                        spanning_tree,
+                        nick_sender,
+                        &msg.prefix().unwrap(),
+                        forward,
+                    )?;
+                }
                 Ok(replies)
             }
+            Command::Cap => {
+                let mut capabilities = user.capabilities().to_vec();
+                let nickname = user.nickname().unwrap_or_default().to_string();
+                let lines = CapMsg::new(&msg)?.response(&nickname, &mut capabilities);
+                user.set_capabilities(capabilities.clone());
+                // the deliveries to the user read them from its connection
+                if let Some(mut connection) = nick_sender.search(nickname.clone())? {
+                    connection.set_capabilities(capabilities);
+                    nick_sender.update(nickname.clone(), connection)?;
+                }
+                Self::write_lines_to(nick_sender, &nickname, &lines)?;
+                Ok(vec![Reply::rpl_none()])
+            }
+            Command::Chathistory => {
+                let lines =
+                    ChatHistoryMsg::new(&msg)?.response(user, channel_sender, &state.history)?;
+                let nickname = user.nickname().unwrap_or_default();
+                Self::write_lines_to(nick_sender, nickname, &lines)?;
+                Ok(vec![Reply::rpl_none()])
+            }
+            Command::Topichistory => {
+                let oper = Self::is_oper(nick_sender, &msg)?;
+                TopicMsg::new(&msg)?.history(oper, channel_sender.to_owned())
+            }
             Command::Squit => {
                 let squit = SquitMsg::new(&msg)?;
                 let result = if !squit.get_operator(nick_sender)?.is_op_connection() {
@@ -427,6 +701,98 @@ impl UserHandler {
         }
     }
 
+    // Checks if the sender of the message is an operator.
+    fn is_oper(
+        nick_sender: &ClientChannel<String, Connection>,
+        msg: &Message,
+    ) -> Result<bool, ErrorServer> {
+        let nickname = match msg.prefix() {
+            Some(n) => n,
+            None => return Err(ErrorServer::UnknownCommand),
+        };
+        match nick_sender.search(nickname)? {
+            Some(connection) => Ok(connection.is_op_connection()),
+            None => Err(ErrorServer::UnreachableClient),
+        }
+    }
+
+    // Writes the lines straight to the connection of a local user.
+    // Checks if the sender of the message is connected to this server.
+    fn is_local(
+        nick_sender: &ClientChannel<String, Connection>,
+        msg: &Message,
+    ) -> Result<bool, ErrorServer> {
+        let nickname = msg.prefix().unwrap_or_default();
+        match nick_sender.search(nickname)? {
+            Some(connection) => Ok(connection.is_local()),
+            None => Ok(false),
+        }
+    }
+
+    // Answers the request to ChanServ and announces to the
+    // other servers the channel it changed.
+    fn execute_chanserv(
+        msg: Message,
+        user: &User,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        state: &ServerState,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let oper = Self::is_oper(nick_sender, &msg)?;
+        let mut chanserv = ChanServMsg::new(&msg)?;
+        let lines = chanserv.response(user, oper, nick_sender, channel_sender, &state.registry)?;
+        Self::write_lines_to(nick_sender, user.nickname().unwrap_or_default(), &lines)?;
+        Self::send_from_server(spanning_tree, user, chanserv.forward())?;
+        Ok(vec![Reply::rpl_none()])
+    }
+
+    // Answers the request to NickServ and announces to the
+    // other servers the account the user logged in or out.
+    fn execute_nickserv(
+        msg: Message,
+        user: &mut User,
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        nick_sender: &ClientChannel<String, Connection>,
+        channel_sender: &ClientChannel<String, Channel>,
+        state: &ServerState,
+    ) -> Result<Vec<Reply>, ErrorServer> {
+        let mut nickserv = NickServMsg::new(&msg)?;
+        let lines = nickserv.response(user, nick_sender, channel_sender, spanning_tree, state)?;
+        Self::write_lines_to(nick_sender, user.nickname().unwrap_or_default(), &lines)?;
+        Self::send_from_server(spanning_tree, user, nickserv.forward())?;
+        Ok(vec![Reply::rpl_none()])
+    }
+
+    // Sends the messages of this server to all the others.
+    fn send_from_server(
+        spanning_tree: &Arc<Mutex<SpanningTree>>,
+        user: &User,
+        messages: Vec<Message>,
+    ) -> Result<(), ErrorServer> {
+        let st = match spanning_tree.lock() {
+            Ok(st) => st,
+            Err(_) => return Err(ErrorServer::LockedResource),
+        };
+        for message in messages {
+            send_to_all_servers(&st, message, user.servername().unwrap_or_default())?;
+        }
+        Ok(())
+    }
+
+    fn write_lines_to(
+        nick_sender: &ClientChannel<String, Connection>,
+        nickname: &str,
+        lines: &[String],
+    ) -> Result<(), ErrorServer> {
+        match nick_sender.search(nickname.to_string())? {
+            Some(mut connection) if connection.is_local() => {
+                write_messages_to(&mut lines.iter(), &mut connection)
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn handle_user_replies(replies: Vec<Reply>, client: &mut dyn Write) -> Result<(), ErrorServer> {
         for r in replies {
             thread::sleep(Duration::from_millis(150));
@@ -451,181 +817,71 @@ impl UserHandler {
             crate::dcc::command::DccCommand::Chat => {
                 let dcc_chat = DccChatMessage::new(&msg)?;
                 println!("{:?}", dcc_chat);
-                let st = match spanning_tree.lock() {
-                    Ok(st) => st,
-                    Err(_) => return Err(ErrorServer::LockedResource),
-                };
                 let servers = dcc_chat.get_interested_servers(nick_sender, channel_sender)?;
-                let mut servers: Vec<Server> = servers
-                    .into_iter()
-                    .filter_map(|s| st.look_for_nearest_connection(s))
-                    .collect();
-
-                let informer =
-                    st.look_for_nearest_connection(dcc_chat.get_origin_server(nick_sender)?);
-
-                servers.sort_by(|a, b| a.servername.cmp(&b.servername));
-                servers.dedup();
-                if let Some(server) = informer {
-                    servers.retain(|s| {
-                        //println!("{} vs INFORMADOR {}", s.servername, server.servername);
-                        server.servername != s.servername
-                            && s.servername != st.get_root().server.servername
-                    });
+                let origin = dcc_chat.get_origin_server(nick_sender)?;
+                let format = dcc_chat.format_message(DccChat::new(msg.clone()).unwrap());
+                let format = to_uids(&format, nick_sender);
+                match spanning_tree.lock() {
+                    Ok(st) => send_to_servers(&st, &format, &servers, &origin)?,
+                    Err(_) => return Err(ErrorServer::LockedResource),
                 }
 
-                servers.into_iter().for_each(|mut s| {
-                    println!("Le envio al servidor: {:?}", s);
-                    let format = dcc_chat.format_message(DccChat::new(msg.clone()).unwrap());
-                    if write_message_to(&format, &mut s).is_err() {
-                        println!("error - send msg server -> sever")
-                    };
-                });
-
                 replies = dcc_chat.response(nick_sender, channel_sender)?;
             }
             crate::dcc::command::DccCommand::Send => {
                 let dcc_chat = DccSendMessage::new(&msg)?;
                 println!("{:?}", dcc_chat);
-                let st = match spanning_tree.lock() {
-                    Ok(st) => st,
-                    Err(_) => return Err(ErrorServer::LockedResource),
-                };
                 let servers = dcc_chat.get_interested_servers(nick_sender, channel_sender)?;
-                let mut servers: Vec<Server> = servers
-                    .into_iter()
-                    .filter_map(|s| st.look_for_nearest_connection(s))
-                    .collect();
-
-                let informer =
-                    st.look_for_nearest_connection(dcc_chat.get_origin_server(nick_sender)?);
-
-                servers.sort_by(|a, b| a.servername.cmp(&b.servername));
-                servers.dedup();
-                if let Some(server) = informer {
-                    servers.retain(|s| {
-                        //println!("{} vs INFORMADOR {}", s.servername, server.servername);
-                        server.servername != s.servername
-                            && s.servername != st.get_root().server.servername
-                    });
+                let origin = dcc_chat.get_origin_server(nick_sender)?;
+                let format = dcc_chat.format_message(DccSend::new(msg.clone()).unwrap());
+                let format = to_uids(&format, nick_sender);
+                match spanning_tree.lock() {
+                    Ok(st) => send_to_servers(&st, &format, &servers, &origin)?,
+                    Err(_) => return Err(ErrorServer::LockedResource),
                 }
 
-                servers.into_iter().for_each(|mut s| {
-                    println!("Le envio al servidor: {:?}", s);
-                    let format = dcc_chat.format_message(DccSend::new(msg.clone()).unwrap());
-                    if write_message_to(&format, &mut s).is_err() {
-                        println!("error - send msg server -> sever")
-                    };
-                });
-
                 let _ = &mut dcc_chat.response(nick_sender, channel_sender)?;
             }
             crate::dcc::command::DccCommand::Pause => {
                 let dcc_chat = DccPauseMessage::new(&msg)?;
                 println!("{:?}", dcc_chat);
-                let st = match spanning_tree.lock() {
-                    Ok(st) => st,
-                    Err(_) => return Err(ErrorServer::LockedResource),
-                };
                 let servers = dcc_chat.get_interested_servers(nick_sender, channel_sender)?;
-                let mut servers: Vec<Server> = servers
-                    .into_iter()
-                    .filter_map(|s| st.look_for_nearest_connection(s))
-                    .collect();
-
-                let informer =
-                    st.look_for_nearest_connection(dcc_chat.get_origin_server(nick_sender)?);
-
-                servers.sort_by(|a, b| a.servername.cmp(&b.servername));
-                servers.dedup();
-                if let Some(server) = informer {
-                    servers.retain(|s| {
-                        //println!("{} vs INFORMADOR {}", s.servername, server.servername);
-                        server.servername != s.servername
-                            && s.servername != st.get_root().server.servername
-                    });
+                let origin = dcc_chat.get_origin_server(nick_sender)?;
+                let format = dcc_chat.format_message(DccPause::new(msg.clone()).unwrap());
+                let format = to_uids(&format, nick_sender);
+                match spanning_tree.lock() {
+                    Ok(st) => send_to_servers(&st, &format, &servers, &origin)?,
+                    Err(_) => return Err(ErrorServer::LockedResource),
                 }
 
-                servers.into_iter().for_each(|mut s| {
-                    println!("Le envio al servidor: {:?}", s);
-                    let format = dcc_chat.format_message(DccPause::new(msg.clone()).unwrap());
-                    if write_message_to(&format, &mut s).is_err() {
-                        println!("error - send msg server -> sever")
-                    };
-                });
-
                 let _ = &mut dcc_chat.response(nick_sender, channel_sender)?;
             }
             crate::dcc::command::DccCommand::Resume => {
                 let dcc_chat = DccResumeMessage::new(&msg)?;
                 println!("{:?}", dcc_chat);
-                let st = match spanning_tree.lock() {
-                    Ok(st) => st,
-                    Err(_) => return Err(ErrorServer::LockedResource),
-                };
                 let servers = dcc_chat.get_interested_servers(nick_sender, channel_sender)?;
-                let mut servers: Vec<Server> = servers
-                    .into_iter()
-                    .filter_map(|s| st.look_for_nearest_connection(s))
-                    .collect();
-
-                let informer =
-                    st.look_for_nearest_connection(dcc_chat.get_origin_server(nick_sender)?);
-
-                servers.sort_by(|a, b| a.servername.cmp(&b.servername));
-                servers.dedup();
-                if let Some(server) = informer {
-                    servers.retain(|s| {
-                        //println!("{} vs INFORMADOR {}", s.servername, server.servername);
-                        server.servername != s.servername
-                            && s.servername != st.get_root().server.servername
-                    });
+                let origin = dcc_chat.get_origin_server(nick_sender)?;
+                let format = dcc_chat.format_message(DccResume::new(msg.clone()).unwrap());
+                let format = to_uids(&format, nick_sender);
+                match spanning_tree.lock() {
+                    Ok(st) => send_to_servers(&st, &format, &servers, &origin)?,
+                    Err(_) => return Err(ErrorServer::LockedResource),
                 }
 
-                servers.into_iter().for_each(|mut s| {
-                    println!("Le envio al servidor: {:?}", s);
-                    let format = dcc_chat.format_message(DccResume::new(msg.clone()).unwrap());
-                    if write_message_to(&format, &mut s).is_err() {
-                        println!("error - send msg server -> sever")
-                    };
-                });
-
                 let _ = &mut dcc_chat.response(nick_sender, channel_sender)?;
             }
             crate::dcc::command::DccCommand::Accept => {
                 let dcc_chat = DccAcceptMessage::new(&msg)?;
                 println!("{:?}", dcc_chat);
-                let st = match spanning_tree.lock() {
-                    Ok(st) => st,
-                    Err(_) => return Err(ErrorServer::LockedResource),
-                };
                 let servers = dcc_chat.get_interested_servers(nick_sender, channel_sender)?;
-                let mut servers: Vec<Server> = servers
-                    .into_iter()
-                    .filter_map(|s| st.look_for_nearest_connection(s))
-                    .collect();
-
-                let informer =
-                    st.look_for_nearest_connection(dcc_chat.get_origin_server(nick_sender)?);
-
-                servers.sort_by(|a, b| a.servername.cmp(&b.servername));
-                servers.dedup();
-                if let Some(server) = informer {
-                    servers.retain(|s| {
-                        //println!("{} vs INFORMADOR {}", s.servername, server.servername);
-                        server.servername != s.servername
-                            && s.servername != st.get_root().server.servername
-                    });
+                let origin = dcc_chat.get_origin_server(nick_sender)?;
+                let format = dcc_chat.format_message(DccAccept::new(msg.clone()).unwrap());
+                let format = to_uids(&format, nick_sender);
+                match spanning_tree.lock() {
+                    Ok(st) => send_to_servers(&st, &format, &servers, &origin)?,
+                    Err(_) => return Err(ErrorServer::LockedResource),
                 }
 
-                servers.into_iter().for_each(|mut s| {
-                    println!("Le envio al servidor: {:?}", s);
-                    let format = dcc_chat.format_message(DccAccept::new(msg.clone()).unwrap());
-                    if write_message_to(&format, &mut s).is_err() {
-                        println!("error - send msg server -> sever")
-                    };
-                });
-
                 let _ = &mut dcc_chat.response(nick_sender, channel_sender)?;
             }
 
@@ -649,11 +905,20 @@ impl UserHandler {
         }
     }
 
-    fn handle_dropped_connection(&self, msg: &str) -> Result<(), ErrorServer> {
+    fn handle_dropped_connection(&mut self, msg: &str) -> Result<(), ErrorServer> {
+        self.refresh_nickname()?;
         let msg = match &self.user {
             Some(u) => add_prefix(u.nickname(), msg),
             None => msg.to_string(),
         };
+        if let Some(user) = &self.user {
+            // a user removed by a KILL, a GHOST or a nick collision already
+            // left, the nickname may belong now to another user
+            let connection = self.nick_sender.search(user.nickname.clone())?;
+            if !connection.is_some_and(|c| c.is_local() && c.get_uid() == user.uid()) {
+                return Ok(());
+            }
+        }
         let msg = Message::from_str(&msg)?;
         let quit = QuitMsg::new(&msg)?;
         inform_all_servers(
@@ -669,6 +934,7 @@ impl UserHandler {
     // Function that receives a message on string format.
     // Validates if it's a DccComand or a IRC command, and execute it.
     fn handle_received_message(&mut self, msg: &str) -> Result<Vec<Reply>, ErrorServer> {
+        self.refresh_nickname()?;
         let msg = match &self.user {
             Some(u) => add_prefix(u.nickname(), msg),
             None => msg.to_string(),
@@ -679,6 +945,32 @@ impl UserHandler {
         }
     }
 
+    // The server may have changed the nickname of the user, as NickServ
+    // does when it doesn't identify in time. Its id still finds it.
+    fn refresh_nickname(&mut self) -> Result<(), ErrorServer> {
+        let user = match &mut self.user {
+            Some(u) => u,
+            None => return Ok(()),
+        };
+        let uid = match user.uid() {
+            Some(uid) => uid.to_string(),
+            None => return Ok(()),
+        };
+        match self.nick_sender.search(user.nickname.clone())? {
+            Some(connection) if connection.get_uid() == Some(uid.as_str()) => return Ok(()),
+            _ => {}
+        }
+        let renamed = self
+            .nick_sender
+            .find_all()?
+            .into_iter()
+            .find(|connection| connection.get_uid() == Some(uid.as_str()));
+        if let Some(connection) = renamed {
+            user.set_nickname(&connection.get_nickname());
+        }
+        Ok(())
+    }
+
     // Function that receives an DCC message formatted as a
     // string and decides how to handle it according to the
     // command that travels in it.
@@ -726,6 +1018,7 @@ impl UserHandler {
             &self.spanning_tree,
             &self.nick_sender,
             &self.channel_sender,
+            &self.state,
         )
     }
 }
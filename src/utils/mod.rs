@@ -2,10 +2,9 @@ use crate::error::error_server::ErrorServer;
 
 use std::io::Read;
 use std::io::Write;
-use std::thread;
-use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-const MESSAGE_LENGHT: usize = 512;
+pub const MESSAGE_LENGHT: usize = 512;
 
 pub fn read_message_from(client: &mut dyn Read) -> Result<String, ErrorServer> {
     // whitespaces are removed by the parser.
@@ -21,6 +20,21 @@ pub fn read_message_from(client: &mut dyn Read) -> Result<String, ErrorServer> {
     }
 }
 
+///
+/// reads a whole padded message, that may
+/// arrive split in several reads.
+///
+pub fn read_frame_from(client: &mut dyn Read) -> Result<String, ErrorServer> {
+    let mut buff: [u8; 512] = [b' '; MESSAGE_LENGHT];
+    match client.read_exact(&mut buff) {
+        Ok(()) => Ok(String::from_utf8_lossy(&buff).trim().to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Err(ErrorServer::UnreachableClient)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub fn write_message_to(message: &dyn ToString, client: &mut dyn Write) -> Result<(), ErrorServer> {
     let mut buff: [u8; 512] = [b' '; MESSAGE_LENGHT];
     if !message.to_string().is_empty() {
@@ -39,7 +53,6 @@ pub fn write_messages_to(
     //println!("----------------------------------------------");
     //println!("START SENDING MESSAGES: write_messages_to()");
     messages.for_each(|msg| {
-        thread::sleep(Duration::from_millis(500));
         //println!("send_info(): {}", msg);
         if let Err(err) = write_message_to(&msg, client) {
             println!("cant send info {}: {:?}", msg, err);
@@ -49,3 +62,259 @@ pub fn write_messages_to(
     //println!("----------------------------------------------");
     Ok(())
 }
+
+///
+/// returns the seconds elapsed since the unix epoch
+///
+pub fn unix_timestamp() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+///
+/// formats the seconds since the unix epoch as a
+/// UTC date: `Tuesday, 2 January 2024 -- 15:04:05 UTC`
+///
+pub fn format_utc(timestamp: u64) -> String {
+    const DAYS: [&str; 7] = [
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+    ];
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+    let (days, seconds) = (timestamp / 86400, timestamp % 86400);
+    let (year, month, day) = civil_date(days);
+    format!(
+        "{}, {day} {} {year} -- {:02}:{:02}:{:02} UTC",
+        DAYS[(days % 7) as usize],
+        MONTHS[(month - 1) as usize],
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+///
+/// formats the seconds since the unix epoch as in the
+/// IRCv3 `server-time` tag: `2024-01-02T15:04:05.000Z`
+///
+pub fn format_iso8601(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / 86400, timestamp % 86400);
+    let (year, month, day) = civil_date(days);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.000Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+///
+/// parses a UTC date formatted as in the `server-time` tag into
+/// the seconds since the unix epoch. The fraction of second is ignored.
+///
+pub fn parse_iso8601(date: &str) -> Option<u64> {
+    let (date, time) = date.strip_suffix('Z')?.split_once('T')?;
+    let time = time.split('.').next()?;
+    let numbers = |s: &str, separator| -> Option<Vec<u64>> {
+        s.split(separator).map(|n| n.parse().ok()).collect()
+    };
+    match (&numbers(date, '-')?[..], &numbers(time, ':')?[..]) {
+        (&[year, month, day], &[hours, minutes, seconds])
+            if (1..=12).contains(&month) && (1..=31).contains(&day) && year >= 1970 =>
+        {
+            let days = days_from_civil(year, month, day);
+            Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
+        }
+        _ => None,
+    }
+}
+
+// Civil date of the day, counting eras of 400 years from March 1st.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let shifted = days + 719468;
+    let era = shifted / 146097;
+    let day_of_era = shifted % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+// Days since the unix epoch of the civil date, the inverse of civil_date.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146097 + day_of_era).saturating_sub(719468)
+}
+
+///
+/// decodes the standard base64 of the text, as the SASL
+/// payloads travel. Returns None if it isn't valid base64.
+///
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let text = text.trim_end_matches('=');
+    let mut bytes = vec![];
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = ALPHABET.iter().position(|a| *a == c)? as u32;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+///
+/// Checks if the text matches the mask, ignoring case.
+/// The mask may contain `*`, matching any amount of characters,
+/// and `?`, matching exactly one.
+///
+pub fn mask_matches(mask: &str, text: &str) -> bool {
+    let mask: Vec<char> = mask.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut m, mut t) = (0, 0);
+    // last '*' seen in the mask, with the text position it was tried on.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if m < mask.len() && (mask[m] == '?' || mask[m] == text[t]) {
+            m += 1;
+            t += 1;
+        } else if m < mask.len() && mask[m] == '*' {
+            star = Some((m, t));
+            m += 1;
+        } else if let Some((star_m, star_t)) = star {
+            m = star_m + 1;
+            t = star_t + 1;
+            star = Some((star_m, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    mask[m..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use crate::error::error_server::ErrorServer;
+
+    use super::{
+        decode_base64, format_iso8601, format_utc, mask_matches, parse_iso8601, read_frame_from,
+        MESSAGE_LENGHT,
+    };
+
+    #[test]
+    fn mask_without_wildcards_matches_exact_text() {
+        assert!(mask_matches("nick", "NICK"));
+        assert!(!mask_matches("nick", "nick2"));
+    }
+
+    #[test]
+    fn star_matches_any_amount_of_chars() {
+        assert!(mask_matches("*@host", "user@host"));
+        assert!(mask_matches("u*r@*", "user@host"));
+        assert!(mask_matches("*", ""));
+        assert!(!mask_matches("*@other", "user@host"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_char() {
+        assert!(mask_matches("10.0.0.?", "10.0.0.1"));
+        assert!(!mask_matches("10.0.0.?", "10.0.0.12"));
+    }
+
+    #[test]
+    fn base64_is_decoded() {
+        assert_eq!(
+            decode_base64("AGFuYQBzZWNyZXRh"),
+            Some(b"\0ana\0secreta".to_vec())
+        );
+        assert_eq!(decode_base64("aG9sYQ=="), Some(b"hola".to_vec()));
+        assert_eq!(decode_base64("no es base64"), None);
+    }
+
+    // Reader that hands the data in small pieces, as a busy socket does.
+    struct Chunked(Vec<u8>);
+
+    impl Read for Chunked {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let amount = buf.len().min(self.0.len()).min(100);
+            buf[..amount].copy_from_slice(&self.0[..amount]);
+            self.0.drain(..amount);
+            Ok(amount)
+        }
+    }
+
+    #[test]
+    fn frames_split_in_several_reads_are_joined() {
+        let mut data = b"NICK bob".to_vec();
+        data.resize(MESSAGE_LENGHT, b' ');
+        data.extend(b"NICK alice");
+        data.resize(2 * MESSAGE_LENGHT, b' ');
+        let mut reader = Chunked(data);
+
+        assert_eq!(read_frame_from(&mut reader), Ok("NICK bob".to_string()));
+        assert_eq!(read_frame_from(&mut reader), Ok("NICK alice".to_string()));
+        assert_eq!(
+            read_frame_from(&mut reader),
+            Err(ErrorServer::UnreachableClient)
+        );
+    }
+
+    #[test]
+    fn formats_the_utc_date() {
+        assert_eq!(format_utc(0), "Thursday, 1 January 1970 -- 00:00:00 UTC");
+        assert_eq!(
+            format_utc(1709210096),
+            "Thursday, 29 February 2024 -- 12:34:56 UTC"
+        );
+        assert_eq!(
+            format_utc(951868799),
+            "Tuesday, 29 February 2000 -- 23:59:59 UTC"
+        );
+    }
+
+    #[test]
+    fn iso_date_is_parsed_back() {
+        assert_eq!(format_iso8601(1709210096), "2024-02-29T12:34:56.000Z");
+        assert_eq!(parse_iso8601("2024-02-29T12:34:56.789Z"), Some(1709210096));
+        assert_eq!(parse_iso8601(&format_iso8601(951868799)), Some(951868799));
+        assert_eq!(parse_iso8601("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_iso8601("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_iso8601("yesterday"), None);
+    }
+}
@@ -0,0 +1,91 @@
+//! Time taken to send the burst of a server with many users to a new link,
+//! plain and compressed. Run it with
+//! `cargo test --release --test burst_benchmark -- --ignored --nocapture`.
+
+use std::{
+    io::Read,
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use irc_project::{
+    command::batch::{wrap_in_batch, NETBURST},
+    server_comunication::{compression::LinkDecoder, server_connection::ConnectionServer},
+    user::User,
+    utils::{read_frame_from, write_messages_to},
+};
+
+const USERS: usize = 5000;
+
+// Counts the bytes that went through the link.
+struct CountingReader {
+    stream: TcpStream,
+    bytes: Arc<AtomicUsize>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.stream.read(buf)?;
+        self.bytes.fetch_add(read, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+fn burst() -> Vec<String> {
+    let users = (0..USERS)
+        .map(|i| {
+            let nickname = format!("nick{i}");
+            User::new(&nickname, "user", "host", "server1", "Real Name", "").build_user_msg()
+        })
+        .collect();
+    wrap_in_batch("server1", "1", NETBURST, users)
+}
+
+// Returns the time until the other end read the whole burst, and the bytes sent.
+fn send_burst(compressed: bool) -> (Duration, usize) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let other_end = listener.accept().unwrap().0;
+    let mut connection = ConnectionServer::new(stream);
+    if compressed {
+        connection = connection.compressed().unwrap();
+    }
+    let messages = burst();
+    let amount = messages.len();
+    let bytes = Arc::new(AtomicUsize::new(0));
+    let counter = CountingReader {
+        stream: other_end,
+        bytes: bytes.clone(),
+    };
+
+    let start = Instant::now();
+    let reader = thread::spawn(move || {
+        let mut reader: Box<dyn Read> = match compressed {
+            true => Box::new(LinkDecoder::new(counter)),
+            false => Box::new(counter),
+        };
+        for _ in 0..amount {
+            read_frame_from(&mut reader).unwrap();
+        }
+    });
+    write_messages_to(&mut messages.iter(), &mut connection).unwrap();
+    reader.join().unwrap();
+    (start.elapsed(), bytes.load(Ordering::Relaxed))
+}
+
+#[test]
+#[ignore]
+fn burst_time() {
+    let (plain_time, plain_bytes) = send_burst(false);
+    let (compressed_time, compressed_bytes) = send_burst(true);
+
+    println!("burst of {USERS} users");
+    println!("plain:      {plain_time:?}, {plain_bytes} bytes");
+    println!("compressed: {compressed_time:?}, {compressed_bytes} bytes");
+    assert!(compressed_bytes < plain_bytes);
+}